@@ -0,0 +1,856 @@
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::receivables_service::ReceivablesService;
+use crate::student_service::{PaymentPlanConfig, Student, StudentService};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Longest series one request may ask for, e.g. five years of weeks
+const MAX_PERIODS: usize = 260;
+
+/// Furthest a cash-flow forecast looks ahead
+const MAX_FORECAST_MONTHS: u32 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesInterval {
+    /// Monday to Sunday
+    Week,
+    Month,
+}
+
+impl SeriesInterval {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeriesInterval::Week => "week",
+            SeriesInterval::Month => "month",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "week" => Ok(SeriesInterval::Week),
+            "month" => Ok(SeriesInterval::Month),
+            _ => Err(format!("Invalid series interval: {}", s)),
+        }
+    }
+}
+
+/// What a series can be split by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesBreakdown {
+    /// The group the student was in on the payment date
+    Group,
+    /// The student's payment plan
+    Plan,
+    /// The payment method; there is no expected amount per method
+    Method,
+}
+
+impl SeriesBreakdown {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SeriesBreakdown::Group => "group",
+            SeriesBreakdown::Plan => "plan",
+            SeriesBreakdown::Method => "method",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "group" => Ok(SeriesBreakdown::Group),
+            "plan" => Ok(SeriesBreakdown::Plan),
+            "method" => Ok(SeriesBreakdown::Method),
+            _ => Err(format!("Invalid series breakdown: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueSeriesRequest {
+    pub start_date: String,
+    pub end_date: String,
+    pub interval: SeriesInterval,
+    pub breakdown: Option<SeriesBreakdown>,
+}
+
+/// One point on the x axis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeriesPeriod {
+    /// `2024-03` for months, `2024-W09` (ISO week) for weeks
+    pub label: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+/// Amounts for one breakdown value, one entry per period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesLine {
+    pub key: String,
+    pub collected: Vec<i64>,
+    /// `None` for the method breakdown
+    pub expected: Option<Vec<i64>>,
+    pub previous_year_collected: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SeriesTotals {
+    pub collected: i64,
+    pub expected: i64,
+    pub collection_rate: Option<f64>,
+    pub previous_year_collected: i64,
+    pub change_from_previous_year: Option<f64>,
+}
+
+/// Collected and expected amounts per period as parallel arrays, ready to plot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueSeries {
+    pub interval: SeriesInterval,
+    pub breakdown: Option<SeriesBreakdown>,
+    pub periods: Vec<SeriesPeriod>,
+    pub collected: Vec<i64>,
+    /// What fell due in the period under each student's plan
+    pub expected: Vec<i64>,
+    /// Collected as a percentage of expected; `None` when nothing fell due
+    pub collection_rate: Vec<Option<f64>>,
+    /// Collected over the same dates a year earlier
+    pub previous_year_collected: Vec<i64>,
+    /// Percentage change from a year earlier; `None` when nothing was collected then
+    pub change_from_previous_year: Vec<Option<f64>>,
+    /// One line per breakdown value, largest total collected first
+    pub lines: Vec<SeriesLine>,
+    pub totals: SeriesTotals,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashFlowForecastRequest {
+    /// How many months ahead of today to project
+    pub months: u32,
+    pub interval: SeriesInterval,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ForecastTotals {
+    pub scheduled: i64,
+    pub best: i64,
+    pub expected: i64,
+    pub worst: i64,
+}
+
+/// Projected inflows per period under three scenarios, as parallel arrays ready to plot.
+///
+/// - best: every due date is paid on time and all arrears come in during the first period
+/// - expected: each due date is paid on time at the student's on-time rate, the rest one period
+///   late; arrears come in during the first period at the same rate
+/// - worst: only the on-time share is collected and no arrears are recovered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashFlowForecast {
+    pub as_of: String,
+    pub interval: SeriesInterval,
+    pub periods: Vec<SeriesPeriod>,
+    /// Unpaid amounts falling due in each period
+    pub scheduled: Vec<i64>,
+    pub best: Vec<i64>,
+    pub expected: Vec<i64>,
+    pub worst: Vec<i64>,
+    /// Unpaid amounts already due before `as_of`
+    pub arrears: i64,
+    /// Percentage of past due dates paid in full by the due date, across all students;
+    /// `None` when nothing has fallen due yet
+    pub on_time_rate: Option<f64>,
+    pub totals: ForecastTotals,
+}
+
+/// A payment with the values it can be broken down by
+struct PaymentPoint {
+    date: NaiveDate,
+    amount: i64,
+    group: String,
+    plan: String,
+    method: String,
+}
+
+pub struct AnalyticsService;
+
+impl AnalyticsService {
+    fn parse_date(field: &'static str, value: &str) -> DatabaseResult<NaiveDate> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+            DatabaseError::validation(
+                field,
+                format!(
+                    "Invalid date format '{}'. Expected format: YYYY-MM-DD",
+                    value
+                ),
+            )
+        })
+    }
+
+    /// Whole weeks or months covering `start..=end`, first and last ones included in full
+    fn build_periods(
+        start: NaiveDate,
+        end: NaiveDate,
+        interval: SeriesInterval,
+    ) -> DatabaseResult<Vec<(NaiveDate, NaiveDate)>> {
+        let mut periods = Vec::new();
+        let mut period_start = match interval {
+            SeriesInterval::Week => {
+                start - Duration::days(start.weekday().num_days_from_monday() as i64)
+            }
+            SeriesInterval::Month => start.with_day(1).unwrap_or(start),
+        };
+
+        while period_start <= end {
+            let next = match interval {
+                SeriesInterval::Week => period_start + Duration::days(7),
+                SeriesInterval::Month => period_start
+                    .checked_add_months(Months::new(1))
+                    .ok_or_else(|| DatabaseError::validation("end_date", "Date out of range"))?,
+            };
+            periods.push((period_start, next - Duration::days(1)));
+            if periods.len() > MAX_PERIODS {
+                return Err(DatabaseError::validation(
+                    "end_date",
+                    format!(
+                        "A series can have at most {} periods; choose a shorter range or a longer interval",
+                        MAX_PERIODS
+                    ),
+                ));
+            }
+            period_start = next;
+        }
+
+        Ok(periods)
+    }
+
+    fn label(start: NaiveDate, interval: SeriesInterval) -> String {
+        match interval {
+            SeriesInterval::Week => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            SeriesInterval::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// Index of the period containing `date`, if any
+    fn period_index(periods: &[(NaiveDate, NaiveDate)], date: NaiveDate) -> Option<usize> {
+        let index = periods.partition_point(|(_, end)| *end < date);
+        periods
+            .get(index)
+            .filter(|(start, _)| *start <= date)
+            .map(|_| index)
+    }
+
+    fn load_payments(
+        db: &Database,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> DatabaseResult<Vec<PaymentPoint>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT p.payment_date, p.amount, COALESCE(v.group_name, ''), s.payment_plan,
+                    COALESCE(p.payment_method, 'cash')
+             FROM payment_transactions p
+             JOIN students s ON s.id = p.student_id
+             LEFT JOIN payment_groups_view v ON v.id = p.id
+             WHERE p.payment_date BETWEEN ?1 AND ?2",
+        )?;
+        let rows = stmt
+            .query_map(
+                params![
+                    start.format("%Y-%m-%d").to_string(),
+                    end.format("%Y-%m-%d").to_string()
+                ],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                },
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(date, amount, group, plan, method)| {
+                let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?;
+                Some(PaymentPoint {
+                    date,
+                    amount,
+                    group,
+                    plan,
+                    method,
+                })
+            })
+            .collect())
+    }
+
+    /// The breakdown line for `key`, added with zeroes on first use
+    fn line(
+        lines: &mut BTreeMap<String, SeriesLine>,
+        key: String,
+        count: usize,
+        with_expected: bool,
+    ) -> &mut SeriesLine {
+        lines.entry(key.clone()).or_insert_with(|| SeriesLine {
+            key,
+            collected: vec![0; count],
+            expected: with_expected.then(|| vec![0; count]),
+            previous_year_collected: vec![0; count],
+        })
+    }
+
+    fn percentage(part: i64, whole: i64) -> Option<f64> {
+        (whole != 0).then(|| (part as f64 / whole as f64 * 1000.0).round() / 10.0)
+    }
+
+    fn change(current: i64, previous: i64) -> Option<f64> {
+        Self::percentage(current - previous, previous)
+    }
+
+    /// Collected against expected per week or month, optionally split by group, plan or method,
+    /// next to what was collected over the same dates a year earlier. Expected amounts are the
+    /// dues of each student's current plan, counted in their current group.
+    pub fn get_revenue_series(
+        db: &Database,
+        request: &RevenueSeriesRequest,
+    ) -> DatabaseResult<RevenueSeries> {
+        let start = Self::parse_date("start_date", &request.start_date)?;
+        let end = Self::parse_date("end_date", &request.end_date)?;
+        if end < start {
+            return Err(DatabaseError::validation(
+                "end_date",
+                "End date must not be before the start date",
+            ));
+        }
+
+        let periods = Self::build_periods(start, end, request.interval)?;
+        // Weeks compare with the same weekday 52 weeks back, months with the same month
+        let year_ago = |date: NaiveDate| match request.interval {
+            SeriesInterval::Week => date - Duration::weeks(52),
+            SeriesInterval::Month => date.checked_sub_months(Months::new(12)).unwrap_or(date),
+        };
+        let previous_periods: Vec<_> = periods
+            .iter()
+            .map(|(start, end)| (year_ago(*start), year_ago(*end)))
+            .collect();
+        let count = periods.len();
+        let (first, last) = (periods[0].0, periods[count - 1].1);
+
+        let breakdown = request.breakdown;
+        let with_expected = breakdown != Some(SeriesBreakdown::Method);
+        let mut lines: BTreeMap<String, SeriesLine> = BTreeMap::new();
+        let key_of = |payment: &PaymentPoint| {
+            breakdown.map(|breakdown| match breakdown {
+                SeriesBreakdown::Group => payment.group.clone(),
+                SeriesBreakdown::Plan => payment.plan.clone(),
+                SeriesBreakdown::Method => payment.method.clone(),
+            })
+        };
+
+        let mut collected = vec![0i64; count];
+        for payment in Self::load_payments(db, first, last)? {
+            if let Some(index) = Self::period_index(&periods, payment.date) {
+                collected[index] += payment.amount;
+                if let Some(key) = key_of(&payment) {
+                    Self::line(&mut lines, key, count, with_expected).collected[index] +=
+                        payment.amount;
+                }
+            }
+        }
+
+        let mut previous_year_collected = vec![0i64; count];
+        let (previous_first, previous_last) =
+            (previous_periods[0].0, previous_periods[count - 1].1);
+        for payment in Self::load_payments(db, previous_first, previous_last)? {
+            if let Some(index) = Self::period_index(&previous_periods, payment.date) {
+                previous_year_collected[index] += payment.amount;
+                if let Some(key) = key_of(&payment) {
+                    Self::line(&mut lines, key, count, with_expected).previous_year_collected
+                        [index] += payment.amount;
+                }
+            }
+        }
+
+        let mut expected = vec![0i64; count];
+        let config = StudentService::get_payment_plan_config(db)?;
+        for student in StudentService::get_all_students(db)? {
+            let key = match breakdown {
+                Some(SeriesBreakdown::Group) => Some(student.group_name.clone()),
+                Some(SeriesBreakdown::Plan) => Some(student.payment_plan.as_str().to_string()),
+                Some(SeriesBreakdown::Method) | None => None,
+            };
+            for (due_date, amount) in ReceivablesService::plan_dues(db, &student, &config, last)? {
+                if let Some(index) = Self::period_index(&periods, due_date) {
+                    expected[index] += amount;
+                    if let Some(key) = &key {
+                        if let Some(line_expected) =
+                            Self::line(&mut lines, key.clone(), count, with_expected)
+                                .expected
+                                .as_mut()
+                        {
+                            line_expected[index] += amount;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut lines: Vec<SeriesLine> = lines.into_values().collect();
+        lines.sort_by_key(|line| std::cmp::Reverse(line.collected.iter().sum::<i64>()));
+
+        let total_collected: i64 = collected.iter().sum();
+        let total_expected: i64 = expected.iter().sum();
+        let total_previous: i64 = previous_year_collected.iter().sum();
+
+        Ok(RevenueSeries {
+            interval: request.interval,
+            breakdown,
+            periods: periods
+                .iter()
+                .map(|(start, end)| SeriesPeriod {
+                    label: Self::label(*start, request.interval),
+                    start_date: start.format("%Y-%m-%d").to_string(),
+                    end_date: end.format("%Y-%m-%d").to_string(),
+                })
+                .collect(),
+            collection_rate: collected
+                .iter()
+                .zip(&expected)
+                .map(|(collected, expected)| Self::percentage(*collected, *expected))
+                .collect(),
+            change_from_previous_year: collected
+                .iter()
+                .zip(&previous_year_collected)
+                .map(|(current, previous)| Self::change(*current, *previous))
+                .collect(),
+            collected,
+            expected,
+            previous_year_collected,
+            lines,
+            totals: SeriesTotals {
+                collected: total_collected,
+                expected: total_expected,
+                collection_rate: Self::percentage(total_collected, total_expected),
+                previous_year_collected: total_previous,
+                change_from_previous_year: Self::change(total_collected, total_previous),
+            },
+        })
+    }
+
+    /// Every payment each student made, oldest first. Paid amounts recorded without a
+    /// transaction, such as an opening balance, count as paid on the enrollment date.
+    fn payment_history(
+        db: &Database,
+        students: &[Student],
+    ) -> DatabaseResult<HashMap<String, Vec<(NaiveDate, i64)>>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT student_id, payment_date, amount FROM payment_transactions
+             ORDER BY payment_date, id",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut history: HashMap<String, Vec<(NaiveDate, i64)>> = HashMap::new();
+        for (student_id, date, amount) in rows {
+            if let Ok(date) = NaiveDate::parse_from_str(&date, "%Y-%m-%d") {
+                history.entry(student_id).or_default().push((date, amount));
+            }
+        }
+
+        for student in students {
+            let payments = history.entry(student.id.clone()).or_default();
+            let recorded: i64 = payments.iter().map(|(_, amount)| amount).sum();
+            let opening = student.paid_amount as i64 - recorded;
+            if opening > 0 {
+                let enrollment = Self::parse_date("enrollment_date", &student.enrollment_date)?;
+                payments.insert(0, (enrollment, opening));
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// How many of the student's due dates before `today` were covered, together with every
+    /// earlier due date, by what had been paid by the end of that day
+    fn on_time_counts(
+        db: &Database,
+        student: &Student,
+        config: &PaymentPlanConfig,
+        payments: &[(NaiveDate, i64)],
+        today: NaiveDate,
+    ) -> DatabaseResult<(usize, usize)> {
+        let until = today - Duration::days(1);
+        let mut due_so_far = 0;
+        let (mut on_time, mut total) = (0, 0);
+
+        for (due_date, amount) in ReceivablesService::plan_dues(db, student, config, until)? {
+            due_so_far += amount;
+            let paid_by_then: i64 = payments
+                .iter()
+                .take_while(|(date, _)| *date <= due_date)
+                .map(|(_, amount)| amount)
+                .sum();
+            total += 1;
+            if paid_by_then >= due_so_far {
+                on_time += 1;
+            }
+        }
+
+        Ok((on_time, total))
+    }
+
+    /// Inflows expected over the next `months` months from each student's unpaid due dates,
+    /// weighted by how reliably the student has paid on time so far. Students with no past
+    /// due dates are assumed to pay at the school-wide rate.
+    pub fn get_cash_flow_forecast(
+        db: &Database,
+        request: &CashFlowForecastRequest,
+        today: NaiveDate,
+    ) -> DatabaseResult<CashFlowForecast> {
+        if request.months == 0 || request.months > MAX_FORECAST_MONTHS {
+            return Err(DatabaseError::validation(
+                "months",
+                format!(
+                    "A forecast covers between 1 and {} months",
+                    MAX_FORECAST_MONTHS
+                ),
+            ));
+        }
+
+        let horizon = today
+            .checked_add_months(Months::new(request.months))
+            .ok_or_else(|| DatabaseError::validation("months", "Date out of range"))?
+            - Duration::days(1);
+        let periods = Self::build_periods(today, horizon, request.interval)?;
+        let count = periods.len();
+        let last = periods[count - 1].1;
+
+        let config = StudentService::get_payment_plan_config(db)?;
+        let students = StudentService::get_all_students(db)?;
+        let history = Self::payment_history(db, &students)?;
+
+        let mut student_rates = Vec::with_capacity(students.len());
+        let (mut on_time, mut total) = (0, 0);
+        for student in &students {
+            let payments = history.get(&student.id).map_or(&[][..], Vec::as_slice);
+            let (student_on_time, student_total) =
+                Self::on_time_counts(db, student, &config, payments, today)?;
+            on_time += student_on_time;
+            total += student_total;
+            student_rates
+                .push((student_total > 0).then(|| student_on_time as f64 / student_total as f64));
+        }
+        let school_rate = (total > 0).then(|| on_time as f64 / total as f64);
+
+        let mut scheduled = vec![0i64; count];
+        let mut arrears = 0i64;
+        let (mut best, mut expected, mut worst) =
+            (vec![0f64; count], vec![0f64; count], vec![0f64; count]);
+        for (student, rate) in students.iter().zip(student_rates) {
+            let rate = rate.or(school_rate).unwrap_or(1.0);
+            for due in ReceivablesService::outstanding_dues(db, student, &config, last)? {
+                let due_date = Self::parse_date("due_date", &due.due_date)?;
+                let amount = due.amount as f64;
+                if due_date < today {
+                    arrears += due.amount;
+                    best[0] += amount;
+                    expected[0] += amount * rate;
+                } else if let Some(index) = Self::period_index(&periods, due_date) {
+                    scheduled[index] += due.amount;
+                    best[index] += amount;
+                    expected[index] += amount * rate;
+                    worst[index] += amount * rate;
+                    if let Some(next) = expected.get_mut(index + 1) {
+                        *next += amount * (1.0 - rate);
+                    }
+                }
+            }
+        }
+
+        let round = |values: Vec<f64>| -> Vec<i64> {
+            values.into_iter().map(|v| v.round() as i64).collect()
+        };
+        let (best, expected, worst) = (round(best), round(expected), round(worst));
+
+        Ok(CashFlowForecast {
+            as_of: today.format("%Y-%m-%d").to_string(),
+            interval: request.interval,
+            periods: periods
+                .iter()
+                .map(|(start, end)| SeriesPeriod {
+                    label: Self::label(*start, request.interval),
+                    start_date: start.format("%Y-%m-%d").to_string(),
+                    end_date: end.format("%Y-%m-%d").to_string(),
+                })
+                .collect(),
+            totals: ForecastTotals {
+                scheduled: scheduled.iter().sum(),
+                best: best.iter().sum(),
+                expected: expected.iter().sum(),
+                worst: worst.iter().sum(),
+            },
+            scheduled,
+            best,
+            expected,
+            worst,
+            arrears,
+            on_time_rate: school_rate.map(|rate| (rate * 1000.0).round() / 10.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment_service::{PaymentMethod, PaymentService, RecordPaymentRequest};
+    use crate::student_service::{CreateStudentRequest, PaymentPlan};
+    use tempfile::TempDir;
+
+    fn setup_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(
+        db: &Database,
+        group: &str,
+        payment_plan: PaymentPlan,
+        plan_amount: i32,
+        enrollment_date: &str,
+    ) -> Student {
+        StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: format!("{} student", group),
+                group_name: group.to_string(),
+                payment_plan,
+                plan_amount,
+                installment_count: None,
+                paid_amount: None,
+                enrollment_date: Some(enrollment_date.to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    fn pay(db: &Database, student: &Student, amount: i32, date: &str, method: PaymentMethod) {
+        PaymentService::record_payment(
+            db,
+            RecordPaymentRequest {
+                student_id: student.id.clone(),
+                enrollment_id: None,
+                amount,
+                payment_date: date.to_string(),
+                payment_method: method,
+                notes: None,
+            },
+        )
+        .unwrap();
+    }
+
+    fn request(
+        start_date: &str,
+        end_date: &str,
+        interval: SeriesInterval,
+        breakdown: Option<SeriesBreakdown>,
+    ) -> RevenueSeriesRequest {
+        RevenueSeriesRequest {
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+            interval,
+            breakdown,
+        }
+    }
+
+    #[test]
+    fn test_monthly_series_with_previous_year_and_breakdowns() {
+        let (db, _temp_dir) = setup_test_db();
+        // 500 due on the 10th of each month, and 3000 due once on Feb 5
+        let monthly = create_student(&db, "Group A", PaymentPlan::Monthly, 500, "2024-01-10");
+        let one_time = create_student(&db, "Group B", PaymentPlan::OneTime, 3000, "2024-02-05");
+        pay(&db, &monthly, 500, "2024-01-12", PaymentMethod::Cash);
+        pay(
+            &db,
+            &monthly,
+            500,
+            "2024-03-02",
+            PaymentMethod::BankTransfer,
+        );
+        pay(&db, &one_time, 1000, "2024-02-20", PaymentMethod::Cash);
+        pay(&db, &one_time, 400, "2023-02-10", PaymentMethod::Check);
+        // Outside the requested months
+        pay(&db, &one_time, 200, "2024-04-01", PaymentMethod::Cash);
+
+        let series = AnalyticsService::get_revenue_series(
+            &db,
+            &request("2024-01-15", "2024-03-31", SeriesInterval::Month, None),
+        )
+        .unwrap();
+        let labels: Vec<_> = series.periods.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["2024-01", "2024-02", "2024-03"]);
+        assert_eq!(series.periods[1].start_date, "2024-02-01");
+        assert_eq!(series.periods[1].end_date, "2024-02-29");
+        assert_eq!(series.collected, vec![500, 1000, 500]);
+        assert_eq!(series.expected, vec![500, 3500, 500]);
+        assert_eq!(
+            series.collection_rate,
+            vec![Some(100.0), Some(28.6), Some(100.0)]
+        );
+        assert_eq!(series.previous_year_collected, vec![0, 400, 0]);
+        assert_eq!(
+            series.change_from_previous_year,
+            vec![None, Some(150.0), None]
+        );
+        assert!(series.lines.is_empty());
+        assert_eq!(
+            series.totals,
+            SeriesTotals {
+                collected: 2000,
+                expected: 4500,
+                collection_rate: Some(44.4),
+                previous_year_collected: 400,
+                change_from_previous_year: Some(400.0),
+            }
+        );
+
+        let by_method = AnalyticsService::get_revenue_series(
+            &db,
+            &request(
+                "2024-01-01",
+                "2024-03-31",
+                SeriesInterval::Month,
+                Some(SeriesBreakdown::Method),
+            ),
+        )
+        .unwrap();
+        let keys: Vec<_> = by_method.lines.iter().map(|l| l.key.as_str()).collect();
+        assert_eq!(keys, vec!["cash", "bank_transfer", "check"]);
+        assert_eq!(by_method.lines[0].collected, vec![500, 1000, 0]);
+        assert_eq!(by_method.lines[2].previous_year_collected, vec![0, 400, 0]);
+        assert!(by_method.lines.iter().all(|l| l.expected.is_none()));
+
+        let by_group = AnalyticsService::get_revenue_series(
+            &db,
+            &request(
+                "2024-01-01",
+                "2024-03-31",
+                SeriesInterval::Month,
+                Some(SeriesBreakdown::Group),
+            ),
+        )
+        .unwrap();
+        let group_a = by_group.lines.iter().find(|l| l.key == "Group A").unwrap();
+        assert_eq!(group_a.collected, vec![500, 0, 500]);
+        assert_eq!(group_a.expected, Some(vec![500, 500, 500]));
+        let group_b = by_group.lines.iter().find(|l| l.key == "Group B").unwrap();
+        assert_eq!(group_b.expected, Some(vec![0, 3000, 0]));
+    }
+
+    #[test]
+    fn test_weekly_periods_and_validation() {
+        let (db, _temp_dir) = setup_test_db();
+
+        // Wednesday to the following Tuesday spans two ISO weeks
+        let series = AnalyticsService::get_revenue_series(
+            &db,
+            &request("2024-03-06", "2024-03-12", SeriesInterval::Week, None),
+        )
+        .unwrap();
+        let periods: Vec<_> = series
+            .periods
+            .iter()
+            .map(|p| (p.label.as_str(), p.start_date.as_str(), p.end_date.as_str()))
+            .collect();
+        assert_eq!(
+            periods,
+            vec![
+                ("2024-W10", "2024-03-04", "2024-03-10"),
+                ("2024-W11", "2024-03-11", "2024-03-17"),
+            ]
+        );
+        assert_eq!(series.collection_rate, vec![None, None]);
+
+        assert!(AnalyticsService::get_revenue_series(
+            &db,
+            &request("2024-03-12", "2024-03-06", SeriesInterval::Week, None)
+        )
+        .is_err());
+        assert!(AnalyticsService::get_revenue_series(
+            &db,
+            &request("2000-01-01", "2024-12-31", SeriesInterval::Week, None)
+        )
+        .is_err());
+        assert!(AnalyticsService::get_revenue_series(
+            &db,
+            &request("2024/01/01", "2024-12-31", SeriesInterval::Month, None)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_cash_flow_forecast_scenarios() {
+        let (db, _temp_dir) = setup_test_db();
+        // Due on the 10th from December; December and January paid on time, February late,
+        // March not at all
+        let monthly = create_student(&db, "Group A", PaymentPlan::Monthly, 500, "2023-12-10");
+        pay(&db, &monthly, 500, "2023-12-10", PaymentMethod::Cash);
+        pay(&db, &monthly, 500, "2024-01-05", PaymentMethod::Cash);
+        pay(&db, &monthly, 500, "2024-02-25", PaymentMethod::Cash);
+        // No history yet, so the school-wide rate applies
+        create_student(&db, "Group B", PaymentPlan::OneTime, 2000, "2024-04-20");
+
+        let today = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let forecast = AnalyticsService::get_cash_flow_forecast(
+            &db,
+            &CashFlowForecastRequest {
+                months: 2,
+                interval: SeriesInterval::Month,
+            },
+            today,
+        )
+        .unwrap();
+        let labels: Vec<_> = forecast.periods.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["2024-03", "2024-04", "2024-05"]);
+        assert_eq!(forecast.on_time_rate, Some(50.0));
+        assert_eq!(forecast.arrears, 500);
+        assert_eq!(forecast.scheduled, vec![0, 2500, 500]);
+        assert_eq!(forecast.best, vec![500, 2500, 500]);
+        // Half of April's dues slip into May; half of May's slip past the forecast
+        assert_eq!(forecast.expected, vec![250, 1250, 1500]);
+        assert_eq!(forecast.worst, vec![0, 1250, 250]);
+        assert_eq!(
+            forecast.totals,
+            ForecastTotals {
+                scheduled: 3000,
+                best: 3500,
+                expected: 3000,
+                worst: 1500,
+            }
+        );
+
+        for months in [0, 25] {
+            assert!(AnalyticsService::get_cash_flow_forecast(
+                &db,
+                &CashFlowForecastRequest {
+                    months,
+                    interval: SeriesInterval::Week,
+                },
+                today,
+            )
+            .is_err());
+        }
+    }
+}
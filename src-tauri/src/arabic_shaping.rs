@@ -0,0 +1,215 @@
+//! Arabic text for PDF output. PDF viewers draw glyphs exactly as given, so letters have to be
+//! replaced by their joined presentation forms and laid out right to left before drawing.
+
+/// How a letter connects to its neighbours
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Joining {
+    /// Never connects, e.g. hamza
+    None,
+    /// Connects only to the letter before it, e.g. alef or dal
+    Right,
+    /// Connects on both sides
+    Dual,
+}
+
+/// Isolated presentation form and joining type of U+0621..=U+064A. The final, initial and
+/// medial forms follow the isolated one; 0 marks code points that are left as they are.
+#[rustfmt::skip]
+const LETTERS: [(u16, Joining); 42] = [
+    (0xFE80, Joining::None),  // ء
+    (0xFE81, Joining::Right), // آ
+    (0xFE83, Joining::Right), // أ
+    (0xFE85, Joining::Right), // ؤ
+    (0xFE87, Joining::Right), // إ
+    (0xFE89, Joining::Dual),  // ئ
+    (0xFE8D, Joining::Right), // ا
+    (0xFE8F, Joining::Dual),  // ب
+    (0xFE93, Joining::Right), // ة
+    (0xFE95, Joining::Dual),  // ت
+    (0xFE99, Joining::Dual),  // ث
+    (0xFE9D, Joining::Dual),  // ج
+    (0xFEA1, Joining::Dual),  // ح
+    (0xFEA5, Joining::Dual),  // خ
+    (0xFEA9, Joining::Right), // د
+    (0xFEAB, Joining::Right), // ذ
+    (0xFEAD, Joining::Right), // ر
+    (0xFEAF, Joining::Right), // ز
+    (0xFEB1, Joining::Dual),  // س
+    (0xFEB5, Joining::Dual),  // ش
+    (0xFEB9, Joining::Dual),  // ص
+    (0xFEBD, Joining::Dual),  // ض
+    (0xFEC1, Joining::Dual),  // ط
+    (0xFEC5, Joining::Dual),  // ظ
+    (0xFEC9, Joining::Dual),  // ع
+    (0xFECD, Joining::Dual),  // غ
+    (0, Joining::None),
+    (0, Joining::None),
+    (0, Joining::None),
+    (0, Joining::None),
+    (0, Joining::None),
+    (0, Joining::Dual),       // ـ tatweel
+    (0xFED1, Joining::Dual),  // ف
+    (0xFED5, Joining::Dual),  // ق
+    (0xFED9, Joining::Dual),  // ك
+    (0xFEDD, Joining::Dual),  // ل
+    (0xFEE1, Joining::Dual),  // م
+    (0xFEE5, Joining::Dual),  // ن
+    (0xFEE9, Joining::Dual),  // ه
+    (0xFEED, Joining::Right), // و
+    (0xFEEF, Joining::Right), // ى
+    (0xFEF1, Joining::Dual),  // ي
+];
+
+const LAM: char = '\u{0644}';
+
+fn letter(c: char) -> Option<(u16, Joining)> {
+    match c {
+        '\u{0621}'..='\u{064A}' => Some(LETTERS[c as usize - 0x0621]),
+        _ => None,
+    }
+}
+
+fn joining(c: char) -> Joining {
+    letter(c).map_or(Joining::None, |(_, joining)| joining)
+}
+
+/// Harakat and other marks sit on a letter without breaking its connections
+fn is_transparent(c: char) -> bool {
+    matches!(c, '\u{064B}'..='\u{065F}' | '\u{0670}')
+}
+
+fn is_arabic(c: char) -> bool {
+    matches!(c, '\u{0600}'..='\u{06FF}' | '\u{FB50}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}')
+}
+
+/// Isolated form of the lam-alef ligature; the final form follows it
+fn lam_alef(alef: char) -> Option<u16> {
+    match alef {
+        '\u{0622}' => Some(0xFEF5),
+        '\u{0623}' => Some(0xFEF7),
+        '\u{0625}' => Some(0xFEF9),
+        '\u{0627}' => Some(0xFEFB),
+        _ => None,
+    }
+}
+
+fn presentation_form(base: u16, offset: u16) -> char {
+    char::from_u32(u32::from(base + offset)).unwrap_or('\u{FFFD}')
+}
+
+/// Replace Arabic letters by the presentation form matching their position in the word
+fn shape(chars: &[char]) -> Vec<char> {
+    let neighbour = |mut index: usize, forward: bool| -> Option<char> {
+        loop {
+            index = if forward {
+                index.checked_add(1).filter(|i| *i < chars.len())?
+            } else {
+                index.checked_sub(1)?
+            };
+            if !is_transparent(chars[index]) {
+                return Some(chars[index]);
+            }
+        }
+    };
+
+    let mut shaped = Vec::with_capacity(chars.len());
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let Some((base, own)) = letter(c) else {
+            shaped.push(c);
+            index += 1;
+            continue;
+        };
+
+        let joins_previous =
+            own != Joining::None && neighbour(index, false).map(joining) == Some(Joining::Dual);
+
+        if c == LAM {
+            let next = chars.get(index + 1).copied();
+            if let Some(ligature) = next.and_then(lam_alef) {
+                shaped.push(presentation_form(ligature, u16::from(joins_previous)));
+                index += 2;
+                continue;
+            }
+        }
+
+        let joins_next = own == Joining::Dual
+            && matches!(
+                neighbour(index, true).map(joining),
+                Some(Joining::Right | Joining::Dual)
+            );
+
+        if base == 0 {
+            shaped.push(c);
+        } else {
+            let offset = match (joins_previous, joins_next) {
+                (false, false) => 0,
+                (true, false) => 1,
+                (false, true) => 2,
+                (true, true) => 3,
+            };
+            shaped.push(presentation_form(base, offset));
+        }
+        index += 1;
+    }
+    shaped
+}
+
+/// Text in the order its glyphs are drawn from left to right. Text without Arabic is returned
+/// as it is; otherwise the line is read right to left, keeping runs of Latin letters and
+/// digits in their own order.
+pub fn visual_order(text: &str) -> String {
+    if !text.chars().any(is_arabic) {
+        return text.to_string();
+    }
+
+    let shaped = shape(&text.chars().collect::<Vec<_>>());
+    let is_ltr = |c: char| c.is_ascii_alphanumeric();
+
+    let mut runs: Vec<(bool, Vec<char>)> = Vec::new();
+    for (index, &c) in shaped.iter().enumerate() {
+        // Separators such as the dots of a date stay inside the run they split
+        let inside_ltr = !is_ltr(c)
+            && matches!(c, '.' | ',' | ':' | '/' | '-')
+            && index > 0
+            && is_ltr(shaped[index - 1])
+            && shaped.get(index + 1).copied().is_some_and(is_ltr);
+        let ltr = is_ltr(c) || inside_ltr;
+        match runs.last_mut() {
+            Some((run_ltr, run)) if *run_ltr == ltr => run.push(c),
+            _ => runs.push((ltr, vec![c])),
+        }
+    }
+
+    runs.into_iter()
+        .rev()
+        .flat_map(|(ltr, mut run)| {
+            if !ltr {
+                run.reverse();
+            }
+            run
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letters_take_joined_forms() {
+        // سمر: initial seen, medial meem, final reh, drawn right to left
+        assert_eq!(visual_order("سمر"), "\u{FEAE}\u{FEE4}\u{FEB3}");
+        // دار: dal and alef only join to the letter before them, so nothing connects
+        assert_eq!(visual_order("دار"), "\u{FEAD}\u{FE8D}\u{FEA9}");
+        // سلام: lam and alef become one ligature
+        assert_eq!(visual_order("سلام"), "\u{FEE1}\u{FEFC}\u{FEB3}");
+    }
+
+    #[test]
+    fn test_latin_runs_keep_their_order() {
+        assert_eq!(visual_order("Group A"), "Group A");
+        assert_eq!(visual_order("ب 2024-01-15"), "2024-01-15 \u{FE8F}");
+    }
+}
@@ -1,15 +1,75 @@
 use crate::audit_service::AuditService;
 use crate::database::{Database, DatabaseResult};
+use crate::enrollment_service::EnrollmentService;
+use crate::term_service::TermService;
 use chrono::{DateTime, Local, NaiveDate, Utc};
-use rusqlite::params;
+use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Matches a student's mark (`?1`) in an enrollment (`?2`) on a date (`?3`). A mark without an
+/// enrollment belongs to the student's primary one.
+pub(crate) const ENROLLMENT_DAY_FILTER: &str = "student_id = ?1 AND date = ?3
+    AND COALESCE(enrollment_id, (SELECT id FROM enrollments WHERE student_id = ?1 AND is_primary = 1)) = ?2";
+
+/// How a student attended a session. Only `Present` and `Late` count as attendance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttendanceStatus {
+    #[default]
+    Present,
+    Absent,
+    Late,
+    Excused,
+}
+
+impl AttendanceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttendanceStatus::Present => "present",
+            AttendanceStatus::Absent => "absent",
+            AttendanceStatus::Late => "late",
+            AttendanceStatus::Excused => "excused",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "present" => Ok(AttendanceStatus::Present),
+            "absent" => Ok(AttendanceStatus::Absent),
+            "late" => Ok(AttendanceStatus::Late),
+            "excused" => Ok(AttendanceStatus::Excused),
+            _ => Err(format!("Invalid attendance status: {}", s)),
+        }
+    }
+
+    pub fn is_present(&self) -> bool {
+        matches!(self, AttendanceStatus::Present | AttendanceStatus::Late)
+    }
+
+    /// Which mark wins when a student has several for the same session
+    fn rank(&self) -> u8 {
+        match self {
+            AttendanceStatus::Absent => 0,
+            AttendanceStatus::Excused => 1,
+            AttendanceStatus::Late => 2,
+            AttendanceStatus::Present => 3,
+        }
+    }
+}
+
+/// SQL condition for marks that count as attendance, for queries aliasing `attendance` as `a`
+pub(crate) const PRESENT_CONDITION: &str = "a.status IN ('present', 'late')";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttendanceRecord {
     pub id: i32,
     pub student_id: String,
+    pub enrollment_id: Option<i32>,
     pub date: String,
     pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub status: AttendanceStatus,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,6 +89,58 @@ pub struct DailyAttendanceSummary {
     pub present_student_ids: Vec<String>,
 }
 
+/// First and last day in a group, open-ended when missing
+type MembershipPeriod = (Option<String>, Option<String>);
+
+/// Counts of each status over a row or column of the attendance matrix
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttendanceTally {
+    pub present: i32,
+    pub late: i32,
+    pub absent: i32,
+    pub excused: i32,
+    /// Sessions the student was expected at
+    pub sessions: i32,
+    /// Percentage of sessions attended, late arrivals included
+    pub attendance_rate: f64,
+}
+
+impl AttendanceTally {
+    fn record(&mut self, status: AttendanceStatus) {
+        match status {
+            AttendanceStatus::Present => self.present += 1,
+            AttendanceStatus::Late => self.late += 1,
+            AttendanceStatus::Absent => self.absent += 1,
+            AttendanceStatus::Excused => self.excused += 1,
+        }
+        self.sessions += 1;
+        self.attendance_rate = (self.present + self.late) as f64 / self.sessions as f64 * 100.0;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttendanceMatrixRow {
+    pub student_id: String,
+    pub student_name: String,
+    /// One cell per entry of `AttendanceMatrix::dates`; `None` when the student was not in the
+    /// group on that date
+    pub cells: Vec<Option<AttendanceStatus>>,
+    pub totals: AttendanceTally,
+}
+
+/// A group's register for a date range: one row per student and one column per session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttendanceMatrix {
+    pub group_name: String,
+    pub start_date: String,
+    pub end_date: String,
+    /// Dates on which any student of the group has a mark
+    pub dates: Vec<String>,
+    pub rows: Vec<AttendanceMatrixRow>,
+    pub date_totals: Vec<AttendanceTally>,
+    pub totals: AttendanceTally,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttendanceHistoryFilter {
     pub student_id: Option<String>,
@@ -40,83 +152,99 @@ pub struct AttendanceHistoryFilter {
 pub struct AttendanceService;
 
 impl AttendanceService {
+    /// Helper function to map database row to AttendanceRecord struct
+    fn map_row_to_attendance(row: &rusqlite::Row) -> rusqlite::Result<AttendanceRecord> {
+        Ok(AttendanceRecord {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            enrollment_id: row.get(2)?,
+            date: row.get(3)?,
+            created_at: row.get(4)?,
+            status: AttendanceStatus::from_str(&row.get::<_, String>(5)?).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, e.into())
+            })?,
+        })
+    }
+
     /// Mark attendance for a student on a specific date
-    /// Prevents duplicate entries for the same student on the same date
+    /// Attendance is recorded against an enrollment (the primary one when none is given)
+    /// Prevents duplicate entries for the same enrollment on the same date
     pub fn mark_attendance(
         db: &Database,
         student_id: &str,
+        enrollment_id: Option<i32>,
         date: &str,
     ) -> DatabaseResult<AttendanceRecord> {
-        // Validate date format
-        Self::validate_date_format(date)?;
-
-        // Check if student exists
-        let student_exists: bool = db
-            .connection()
-            .query_row(
-                "SELECT COUNT(*) > 0 FROM students WHERE id = ?1",
-                params![student_id],
-                |row| row.get(0),
-            )
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+        AuditService::audited(db, |audit| {
+            // Validate date format
+            Self::validate_date_format(date)?;
+
+            // Check if student exists
+            let student_exists: bool = db
+                .connection()
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM students WHERE id = ?1",
+                    params![student_id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+
+            if !student_exists {
+                return Err(crate::database::DatabaseError::not_found("Student", student_id));
+            }
 
-        if !student_exists {
-            return Err(crate::database::DatabaseError::Migration(format!(
-                "Student with ID '{}' does not exist",
-                student_id
-            )));
-        }
+            // Only an explicitly chosen enrollment has to be active; the primary one follows the student
+            let enrollment = EnrollmentService::resolve_enrollment(
+                db,
+                student_id,
+                enrollment_id,
+                enrollment_id.is_some(),
+            )?;
+
+            // Check if attendance already exists for this enrollment on this date
+            let existing_count: i32 = db
+                .connection()
+                .query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM attendance WHERE {}",
+                        ENROLLMENT_DAY_FILTER
+                    ),
+                    params![student_id, enrollment.id, date],
+                    |row| row.get(0),
+                )
+                .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+
+            if existing_count > 0 {
+                return Err(crate::database::DatabaseError::conflict(
+                    "attendance_already_recorded",
+                    format!(
+                        "Attendance already recorded for student '{}' in '{}' on date '{}'",
+                        student_id, enrollment.group_name, date
+                    ),
+                ));
+            }
 
-        // Check if attendance already exists for this student on this date
-        let existing_count: i32 = db
-            .connection()
-            .query_row(
-                "SELECT COUNT(*) FROM attendance WHERE student_id = ?1 AND date = ?2",
-                params![student_id, date],
-                |row| row.get(0),
-            )
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+            // Insert attendance record
+            let mut stmt = db.connection().prepare(
+                "INSERT INTO attendance (student_id, enrollment_id, date) VALUES (?1, ?2, ?3) RETURNING id, student_id, enrollment_id, date, created_at, status"
+            ).map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
 
-        if existing_count > 0 {
-            return Err(crate::database::DatabaseError::Migration(format!(
-                "Attendance already recorded for student '{}' on date '{}'",
-                student_id, date
-            )));
-        }
+            let attendance_record = stmt
+                .query_row(
+                    params![student_id, enrollment.id, date],
+                    Self::map_row_to_attendance,
+                )
+                .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
 
-        // Insert attendance record
-        let mut stmt = db.connection().prepare(
-            "INSERT INTO attendance (student_id, date) VALUES (?1, ?2) RETURNING id, student_id, date, created_at"
-        ).map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        let attendance_record = stmt
-            .query_row(params![student_id, date], |row| {
-                Ok(AttendanceRecord {
-                    id: row.get(0)?,
-                    student_id: row.get(1)?,
-                    date: row.get(2)?,
-                    created_at: row.get(3)?,
-                })
-            })
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+            audit.create("attendance", &attendance_record.id.to_string(), &attendance_record, None);
 
-        // Log audit entry for attendance creation
-        if let Ok(serialized_data) = AuditService::serialize_data(&attendance_record) {
-            let _ = AuditService::log_create(
-                db,
-                "attendance",
-                &attendance_record.id.to_string(),
-                &serialized_data,
-                None,
+            log::info!(
+                "Marked attendance for student '{}' on date '{}'",
+                student_id,
+                date
             );
-        }
-
-        log::info!(
-            "Marked attendance for student '{}' on date '{}'",
-            student_id,
-            date
-        );
-        Ok(attendance_record)
+            Ok(attendance_record)
+        })
     }
 
     /// Check if attendance is already recorded for a student today
@@ -151,7 +279,9 @@ impl AttendanceService {
         db: &Database,
         filter: Option<AttendanceHistoryFilter>,
     ) -> DatabaseResult<Vec<AttendanceRecord>> {
-        let mut query = "SELECT id, student_id, date, created_at FROM attendance".to_string();
+        let mut query =
+            "SELECT id, student_id, enrollment_id, date, created_at, status FROM attendance"
+                .to_string();
         let mut conditions = Vec::new();
         let mut params_vec = Vec::new();
 
@@ -174,8 +304,9 @@ impl AttendanceService {
             }
 
             if let Some(group_name) = filter.group_name {
+                // Attendance belongs to the group the student was in on that date
                 conditions.push(
-                    "student_id IN (SELECT id FROM students WHERE group_name = ?)".to_string(),
+                    "id IN (SELECT id FROM attendance_groups_view WHERE group_name = ?)".to_string(),
                 );
                 params_vec.push(group_name);
             }
@@ -199,14 +330,7 @@ impl AttendanceService {
             .collect();
 
         let attendance_iter = stmt
-            .query_map(&params_refs[..], |row| {
-                Ok(AttendanceRecord {
-                    id: row.get(0)?,
-                    student_id: row.get(1)?,
-                    date: row.get(2)?,
-                    created_at: row.get(3)?,
-                })
-            })
+            .query_map(&params_refs[..], Self::map_row_to_attendance)
             .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
 
         attendance_iter
@@ -229,13 +353,23 @@ impl AttendanceService {
         Self::get_attendance_history(db, Some(filter))
     }
 
-    /// Get attendance statistics for a student
+    /// Get attendance statistics for a student, optionally limited to a term
     pub fn get_student_attendance_stats(
         db: &Database,
         student_id: &str,
         start_date: Option<&str>,
         end_date: Option<&str>,
+        term_id: Option<i32>,
     ) -> DatabaseResult<AttendanceStats> {
+        let (start_date, end_date) = TermService::clamp_to_term(db, term_id, start_date, end_date)?;
+        let start_date = start_date.as_deref();
+        // A term still in progress is only counted up to today
+        let today = Self::get_current_date();
+        let end_date = match end_date.as_deref() {
+            Some(end) if term_id.is_some() && end > today.as_str() => Some(today.as_str()),
+            end => end,
+        };
+
         let mut date_conditions = Vec::new();
         let mut params_vec = vec![student_id.to_string()];
 
@@ -257,10 +391,10 @@ impl AttendanceService {
             format!(" AND {}", date_conditions.join(" AND "))
         };
 
-        // Get present days count
+        // Get present days count (a day attended in several enrollments counts once)
         let present_query = format!(
-            "SELECT COUNT(*) FROM attendance WHERE student_id = ?1{}",
-            date_filter
+            "SELECT COUNT(DISTINCT date) FROM attendance a WHERE student_id = ?1 AND {}{}",
+            PRESENT_CONDITION, date_filter
         );
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec
@@ -332,7 +466,7 @@ impl AttendanceService {
         let mut params_vec = vec![date.to_string()];
 
         if let Some(group) = group_name {
-            group_filter = " AND s.group_name = ?".to_string();
+            group_filter = " AND v.group_name = ?".to_string();
             params_vec.push(group.to_string());
         }
 
@@ -341,12 +475,18 @@ impl AttendanceService {
             .map(|p| p as &dyn rusqlite::ToSql)
             .collect();
 
-        // Get total students count
-        let total_query = format!("SELECT COUNT(*) FROM students s WHERE 1=1{}", group_filter);
+        // Get total students count: students who belonged to the group on that date, students with
+        // an active additional enrollment in it, and students without any history whose own group matches
+        let total_query = "SELECT COUNT(*) FROM students_view s
+             WHERE s.id IN (
+                    SELECT m.student_id FROM group_memberships m LEFT JOIN groups g ON g.id = m.group_id
+                    WHERE COALESCE(g.name, m.group_name) = ?2 AND m.start_date <= ?1 AND (m.end_date IS NULL OR m.end_date >= ?1))
+                OR s.id IN (SELECT student_id FROM enrollments_view WHERE status = 'active' AND is_primary = 0 AND group_name = ?2)
+                OR (s.group_name = ?2 AND NOT EXISTS (SELECT 1 FROM group_memberships WHERE student_id = s.id))";
 
         let total_students: i32 = if group_name.is_some() {
             db.connection()
-                .query_row(&total_query, &params_refs[1..], |row| row.get(0))
+                .query_row(total_query, &params_refs[..], |row| row.get(0))
                 .map_err(|e| crate::database::DatabaseError::Sqlite(e))?
         } else {
             db.connection()
@@ -356,11 +496,11 @@ impl AttendanceService {
 
         // Get present students
         let present_query = format!(
-            "SELECT COUNT(*), GROUP_CONCAT(a.student_id) 
+            "SELECT COUNT(DISTINCT a.student_id), GROUP_CONCAT(DISTINCT a.student_id) 
              FROM attendance a 
-             JOIN students s ON a.student_id = s.id 
-             WHERE a.date = ?1{}",
-            group_filter
+             JOIN attendance_groups_view v ON v.id = a.id 
+             WHERE a.date = ?1 AND {}{}",
+            PRESENT_CONDITION, group_filter
         );
 
         let (present_students, present_ids_str): (i32, Option<String>) = db
@@ -414,54 +554,254 @@ impl AttendanceService {
     }
 
     /// Delete attendance record (for corrections)
-    pub fn delete_attendance(db: &Database, student_id: &str, date: &str) -> DatabaseResult<bool> {
-        Self::validate_date_format(date)?;
+    /// Removes the mark of the given enrollment, or of the primary enrollment when none is given
+    pub fn delete_attendance(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: Option<i32>,
+        date: &str,
+    ) -> DatabaseResult<bool> {
+        AuditService::audited(db, |audit| {
+            Self::validate_date_format(date)?;
+
+            let enrollment_id = match enrollment_id {
+                Some(id) => id,
+                None => EnrollmentService::get_primary_enrollment_id(db, student_id)?,
+            };
+
+            // Get the attendance record before deleting for audit log
+            let attendance_record = db.connection().query_row(
+                &format!("SELECT id, student_id, enrollment_id, date, created_at, status FROM attendance WHERE {}", ENROLLMENT_DAY_FILTER),
+                params![student_id, enrollment_id, date],
+                Self::map_row_to_attendance,
+            ).ok();
+
+            let rows_affected = db
+                .connection()
+                .execute(
+                    &format!("DELETE FROM attendance WHERE {}", ENROLLMENT_DAY_FILTER),
+                    params![student_id, enrollment_id, date],
+                )
+                .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+
+            if rows_affected > 0 {
+                if let Some(record) = attendance_record {
+                    audit.delete("attendance", &record.id.to_string(), &record, None);
+                }
 
-        // Get the attendance record before deleting for audit log
-        let attendance_record = db.connection().query_row(
-            "SELECT id, student_id, date, created_at FROM attendance WHERE student_id = ?1 AND date = ?2",
-            params![student_id, date],
-            |row| {
-                Ok(AttendanceRecord {
-                    id: row.get(0)?,
-                    student_id: row.get(1)?,
-                    date: row.get(2)?,
-                    created_at: row.get(3)?,
-                })
+                log::info!(
+                    "Deleted attendance record for student '{}' on date '{}'",
+                    student_id,
+                    date
+                );
+                Ok(true)
+            } else {
+                Ok(false)
             }
-        ).ok();
+        })
+    }
 
-        let rows_affected = db
-            .connection()
-            .execute(
-                "DELETE FROM attendance WHERE student_id = ?1 AND date = ?2",
-                params![student_id, date],
-            )
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+    /// Record how a student attended a session, replacing any mark already made for
+    /// that enrollment and date. Unlike `mark_attendance` this also records absences.
+    pub fn set_attendance_status(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: Option<i32>,
+        date: &str,
+        status: AttendanceStatus,
+    ) -> DatabaseResult<AttendanceRecord> {
+        AuditService::audited(db, |audit| {
+            Self::validate_date_format(date)?;
 
-        if rows_affected > 0 {
-            // Log audit entry for attendance deletion
-            if let Some(record) = attendance_record {
-                if let Ok(serialized_data) = AuditService::serialize_data(&record) {
-                    let _ = AuditService::log_delete(
-                        db,
-                        "attendance",
-                        &record.id.to_string(),
-                        &serialized_data,
-                        None,
-                    );
+            let enrollment = EnrollmentService::resolve_enrollment(
+                db,
+                student_id,
+                enrollment_id,
+                enrollment_id.is_some(),
+            )?;
+
+            let existing = db
+                .connection()
+                .query_row(
+                    &format!("SELECT id, student_id, enrollment_id, date, created_at, status FROM attendance WHERE {}", ENROLLMENT_DAY_FILTER),
+                    params![student_id, enrollment.id, date],
+                    Self::map_row_to_attendance,
+                )
+                .optional()?;
+
+            let record = match existing {
+                Some(old) => {
+                    let record = db.connection().query_row(
+                        "UPDATE attendance SET status = ?1 WHERE id = ?2 RETURNING id, student_id, enrollment_id, date, created_at, status",
+                        params![status.as_str(), old.id],
+                        Self::map_row_to_attendance,
+                    )?;
+                    audit.update("attendance", &record.id.to_string(), &old, &record, None);
+                    record
                 }
-            }
+                None => {
+                    let record = db.connection().query_row(
+                        "INSERT INTO attendance (student_id, enrollment_id, date, status) VALUES (?1, ?2, ?3, ?4) RETURNING id, student_id, enrollment_id, date, created_at, status",
+                        params![student_id, enrollment.id, date, status.as_str()],
+                        Self::map_row_to_attendance,
+                    )?;
+                    audit.create("attendance", &record.id.to_string(), &record, None);
+                    record
+                }
+            };
 
             log::info!(
-                "Deleted attendance record for student '{}' on date '{}'",
+                "Recorded student '{}' as {} on date '{}'",
                 student_id,
+                status.as_str(),
                 date
             );
-            Ok(true)
-        } else {
-            Ok(false)
+            Ok(record)
+        })
+    }
+
+    /// Build the register of a group between two dates. Columns are the dates on which anyone
+    /// in the group has a mark; a student who was in the group on such a date but has no mark
+    /// counts as absent.
+    pub fn get_attendance_matrix(
+        db: &Database,
+        group_name: &str,
+        start_date: &str,
+        end_date: &str,
+    ) -> DatabaseResult<AttendanceMatrix> {
+        Self::validate_date_format(start_date)?;
+        Self::validate_date_format(end_date)?;
+        if start_date > end_date {
+            return Err(crate::database::DatabaseError::validation(
+                "end_date",
+                format!(
+                    "End date '{}' is before start date '{}'",
+                    end_date, start_date
+                ),
+            ));
         }
+
+        let mut marks: HashMap<(String, String), AttendanceStatus> = HashMap::new();
+        let mut names: HashMap<String, String> = HashMap::new();
+        let mut dates = BTreeSet::new();
+        {
+            let mut stmt = db.connection().prepare(
+                "SELECT a.student_id, s.name, a.date, a.status
+                 FROM attendance a
+                 JOIN attendance_groups_view v ON v.id = a.id
+                 JOIN students s ON s.id = a.student_id
+                 WHERE v.group_name = ?1 AND a.date BETWEEN ?2 AND ?3",
+            )?;
+            let rows = stmt.query_map(params![group_name, start_date, end_date], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                ))
+            })?;
+            for row in rows {
+                let (student_id, name, date, status) = row?;
+                let status = AttendanceStatus::from_str(&status)
+                    .map_err(|e| crate::database::DatabaseError::validation("status", e))?;
+                dates.insert(date.clone());
+                names.insert(student_id.clone(), name);
+                let mark = marks.entry((student_id, date)).or_insert(status);
+                if status.rank() > mark.rank() {
+                    *mark = status;
+                }
+            }
+        }
+
+        // Periods in which each student belonged to the group: membership history, additional
+        // enrollments, and the own group of students who have no history yet
+        let mut periods: HashMap<String, Vec<MembershipPeriod>> = HashMap::new();
+        {
+            let mut stmt = db.connection().prepare(
+                "SELECT m.student_id, s.name, m.start_date, m.end_date
+                 FROM group_memberships m
+                 JOIN students s ON s.id = m.student_id
+                 LEFT JOIN groups g ON g.id = m.group_id
+                 WHERE COALESCE(g.name, m.group_name) = ?1
+                   AND m.start_date <= ?3 AND (m.end_date IS NULL OR m.end_date >= ?2)
+                 UNION ALL
+                 SELECT e.student_id, s.name, e.start_date, e.end_date
+                 FROM enrollments_view e
+                 JOIN students s ON s.id = e.student_id
+                 WHERE e.is_primary = 0 AND e.status = 'active' AND e.group_name = ?1
+                   AND e.start_date <= ?3 AND (e.end_date IS NULL OR e.end_date >= ?2)
+                 UNION ALL
+                 SELECT s.id, s.name, NULL, NULL
+                 FROM students_view s
+                 WHERE s.group_name = ?1
+                   AND NOT EXISTS (SELECT 1 FROM group_memberships WHERE student_id = s.id)",
+            )?;
+            let rows = stmt.query_map(params![group_name, start_date, end_date], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?;
+            for row in rows {
+                let (student_id, name, start, end) = row?;
+                names.insert(student_id.clone(), name);
+                periods.entry(student_id).or_default().push((start, end));
+            }
+        }
+
+        let dates: Vec<String> = dates.into_iter().collect();
+        let mut students: Vec<(String, String)> = names.into_iter().collect();
+        students.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut date_totals = vec![AttendanceTally::default(); dates.len()];
+        let mut totals = AttendanceTally::default();
+        let mut rows = Vec::with_capacity(students.len());
+
+        for (student_id, student_name) in students {
+            let in_group = |date: &str| {
+                periods.get(&student_id).is_some_and(|periods| {
+                    periods.iter().any(|(start, end)| {
+                        start.as_deref().map_or(true, |s| s <= date)
+                            && end.as_deref().map_or(true, |e| e >= date)
+                    })
+                })
+            };
+
+            let mut row_totals = AttendanceTally::default();
+            let mut cells = Vec::with_capacity(dates.len());
+            for (index, date) in dates.iter().enumerate() {
+                let cell = match marks.get(&(student_id.clone(), date.clone())) {
+                    Some(status) => Some(*status),
+                    None if in_group(date) => Some(AttendanceStatus::Absent),
+                    None => None,
+                };
+                if let Some(status) = cell {
+                    row_totals.record(status);
+                    date_totals[index].record(status);
+                    totals.record(status);
+                }
+                cells.push(cell);
+            }
+
+            rows.push(AttendanceMatrixRow {
+                student_id,
+                student_name,
+                cells,
+                totals: row_totals,
+            });
+        }
+
+        Ok(AttendanceMatrix {
+            group_name: group_name.to_string(),
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+            dates,
+            rows,
+            date_totals,
+            totals,
+        })
     }
 
     /// Get current date in YYYY-MM-DD format
@@ -473,10 +813,10 @@ impl AttendanceService {
     pub fn validate_date_format(date: &str) -> DatabaseResult<()> {
         match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
             Ok(_) => Ok(()),
-            Err(_) => Err(crate::database::DatabaseError::Migration(format!(
-                "Invalid date format '{}'. Expected format: YYYY-MM-DD",
-                date
-            ))),
+            Err(_) => Err(crate::database::DatabaseError::validation(
+                "date",
+                format!("Invalid date format '{}'. Expected format: YYYY-MM-DD", date),
+            )),
         }
     }
 
@@ -497,7 +837,8 @@ impl AttendanceService {
             }
         }
 
-        Err(crate::database::DatabaseError::Migration(
+        Err(crate::database::DatabaseError::validation(
+            "date",
             format!("Unable to parse date '{}'. Supported formats: YYYY-MM-DD, DD/MM/YYYY, MM/DD/YYYY, DD-MM-YYYY, YYYY/MM/DD", date_str)
         ))
     }
@@ -505,17 +846,17 @@ impl AttendanceService {
     /// Calculate number of days between two dates
     fn calculate_days_between(start_date: &str, end_date: &str) -> DatabaseResult<i32> {
         let start = NaiveDate::parse_from_str(start_date, "%Y-%m-%d").map_err(|_| {
-            crate::database::DatabaseError::Migration(format!(
-                "Invalid start date format: {}",
-                start_date
-            ))
+            crate::database::DatabaseError::validation(
+                "start_date",
+                format!("Invalid start date format: {}", start_date),
+            )
         })?;
 
         let end = NaiveDate::parse_from_str(end_date, "%Y-%m-%d").map_err(|_| {
-            crate::database::DatabaseError::Migration(format!(
-                "Invalid end date format: {}",
-                end_date
-            ))
+            crate::database::DatabaseError::validation(
+                "end_date",
+                format!("Invalid end date format: {}", end_date),
+            )
         })?;
 
         let duration = end.signed_duration_since(start);
@@ -525,17 +866,17 @@ impl AttendanceService {
     /// Generate a list of dates between start and end date (inclusive)
     fn get_date_range(start_date: &str, end_date: &str) -> DatabaseResult<Vec<String>> {
         let start = NaiveDate::parse_from_str(start_date, "%Y-%m-%d").map_err(|_| {
-            crate::database::DatabaseError::Migration(format!(
-                "Invalid start date format: {}",
-                start_date
-            ))
+            crate::database::DatabaseError::validation(
+                "start_date",
+                format!("Invalid start date format: {}", start_date),
+            )
         })?;
 
         let end = NaiveDate::parse_from_str(end_date, "%Y-%m-%d").map_err(|_| {
-            crate::database::DatabaseError::Migration(format!(
-                "Invalid end date format: {}",
-                end_date
-            ))
+            crate::database::DatabaseError::validation(
+                "end_date",
+                format!("Invalid end date format: {}", end_date),
+            )
         })?;
 
         let mut dates = Vec::new();
@@ -544,7 +885,7 @@ impl AttendanceService {
         while current <= end {
             dates.push(current.format("%Y-%m-%d").to_string());
             current = current.succ_opt().ok_or_else(|| {
-                crate::database::DatabaseError::Migration("Date overflow".to_string())
+                crate::database::DatabaseError::validation("end_date", "Date overflow")
             })?;
         }
 
@@ -577,7 +918,7 @@ mod tests {
         let (db, _temp_dir) = setup_test_db();
         create_test_student(&db, "student1", "Test Student", "Group A");
 
-        let result = AttendanceService::mark_attendance(&db, "student1", "2024-01-15");
+        let result = AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15");
         assert!(result.is_ok());
 
         let record = result.unwrap();
@@ -591,25 +932,33 @@ mod tests {
         create_test_student(&db, "student1", "Test Student", "Group A");
 
         // First attendance should succeed
-        let result1 = AttendanceService::mark_attendance(&db, "student1", "2024-01-15");
+        let result1 = AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15");
         assert!(result1.is_ok());
 
         // Second attendance on same date should fail
-        let result2 = AttendanceService::mark_attendance(&db, "student1", "2024-01-15");
+        let result2 = AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15");
         assert!(result2.is_err());
-        assert!(result2
-            .unwrap_err()
-            .to_string()
-            .contains("already recorded"));
+        let error = result2.unwrap_err();
+        assert!(error.to_string().contains("already recorded"));
+        assert!(matches!(
+            error,
+            crate::database::DatabaseError::Conflict {
+                reason: "attendance_already_recorded",
+                ..
+            }
+        ));
     }
 
     #[test]
     fn test_mark_attendance_nonexistent_student() {
         let (db, _temp_dir) = setup_test_db();
 
-        let result = AttendanceService::mark_attendance(&db, "nonexistent", "2024-01-15");
+        let result = AttendanceService::mark_attendance(&db, "nonexistent", None, "2024-01-15");
         assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("does not exist"));
+        assert!(matches!(
+            result.unwrap_err(),
+            crate::database::DatabaseError::NotFound { entity: "Student", .. }
+        ));
     }
 
     #[test]
@@ -625,7 +974,7 @@ mod tests {
         assert!(!result.unwrap());
 
         // Mark attendance for today
-        AttendanceService::mark_attendance(&db, "student1", &today).unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, &today).unwrap();
 
         // Now should return true
         let result = AttendanceService::check_attendance_today(&db, "student1");
@@ -640,9 +989,9 @@ mod tests {
         create_test_student(&db, "student2", "Test Student 2", "Group B");
 
         // Mark attendance for different dates
-        AttendanceService::mark_attendance(&db, "student1", "2024-01-15").unwrap();
-        AttendanceService::mark_attendance(&db, "student1", "2024-01-16").unwrap();
-        AttendanceService::mark_attendance(&db, "student2", "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-16").unwrap();
+        AttendanceService::mark_attendance(&db, "student2", None, "2024-01-15").unwrap();
 
         // Get all attendance
         let all_attendance = AttendanceService::get_attendance_history(&db, None).unwrap();
@@ -669,14 +1018,15 @@ mod tests {
         create_test_student(&db, "student1", "Test Student", "Group A");
 
         // Mark attendance for some days
-        AttendanceService::mark_attendance(&db, "student1", "2024-01-15").unwrap();
-        AttendanceService::mark_attendance(&db, "student1", "2024-01-16").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-16").unwrap();
 
         let stats = AttendanceService::get_student_attendance_stats(
             &db,
             "student1",
             Some("2024-01-15"),
             Some("2024-01-20"),
+            None,
         )
         .unwrap();
 
@@ -734,8 +1084,8 @@ mod tests {
         create_test_student(&db, "student3", "Test Student 3", "Group B");
 
         // Mark attendance for some students
-        AttendanceService::mark_attendance(&db, "student1", "2024-01-15").unwrap();
-        AttendanceService::mark_attendance(&db, "student2", "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student2", None, "2024-01-15").unwrap();
 
         let summary =
             AttendanceService::get_daily_attendance_summary(&db, "2024-01-15", None).unwrap();
@@ -759,7 +1109,7 @@ mod tests {
         create_test_student(&db, "student1", "Test Student", "Group A");
 
         // Mark attendance
-        AttendanceService::mark_attendance(&db, "student1", "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
 
         // Verify attendance exists
         assert!(
@@ -767,7 +1117,8 @@ mod tests {
         );
 
         // Delete attendance
-        let deleted = AttendanceService::delete_attendance(&db, "student1", "2024-01-15").unwrap();
+        let deleted =
+            AttendanceService::delete_attendance(&db, "student1", None, "2024-01-15").unwrap();
         assert!(deleted);
 
         // Verify attendance is gone
@@ -777,7 +1128,99 @@ mod tests {
 
         // Try to delete non-existent attendance
         let not_deleted =
-            AttendanceService::delete_attendance(&db, "student1", "2024-01-15").unwrap();
+            AttendanceService::delete_attendance(&db, "student1", None, "2024-01-15").unwrap();
         assert!(!not_deleted);
     }
+
+    #[test]
+    fn test_set_attendance_status_replaces_mark() {
+        let (db, _temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Test Student", "Group A");
+
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
+        let record = AttendanceService::set_attendance_status(
+            &db,
+            "student1",
+            None,
+            "2024-01-15",
+            AttendanceStatus::Absent,
+        )
+        .unwrap();
+        assert_eq!(record.status, AttendanceStatus::Absent);
+
+        // Still one mark, and an absence is not attendance
+        let history = AttendanceService::get_student_attendance_history(&db, "student1").unwrap();
+        assert_eq!(history.len(), 1);
+        let stats = AttendanceService::get_student_attendance_stats(
+            &db,
+            "student1",
+            Some("2024-01-15"),
+            Some("2024-01-15"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(stats.present_days, 0);
+    }
+
+    #[test]
+    fn test_attendance_matrix() {
+        let (db, _temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Amal", "Group A");
+        create_test_student(&db, "student2", "Basel", "Group A");
+        create_test_student(&db, "student3", "Karim", "Group A");
+        create_test_student(&db, "student4", "Other", "Group B");
+
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-08").unwrap();
+        AttendanceService::set_attendance_status(
+            &db,
+            "student2",
+            None,
+            "2024-01-08",
+            AttendanceStatus::Late,
+        )
+        .unwrap();
+        AttendanceService::set_attendance_status(
+            &db,
+            "student1",
+            None,
+            "2024-01-15",
+            AttendanceStatus::Excused,
+        )
+        .unwrap();
+        AttendanceService::mark_attendance(&db, "student2", None, "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student4", None, "2024-01-22").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-02-05").unwrap();
+
+        let matrix =
+            AttendanceService::get_attendance_matrix(&db, "Group A", "2024-01-01", "2024-01-31")
+                .unwrap();
+
+        assert_eq!(matrix.dates, vec!["2024-01-08", "2024-01-15"]);
+        let names: Vec<&str> = matrix.rows.iter().map(|r| r.student_name.as_str()).collect();
+        assert_eq!(names, vec!["Amal", "Basel", "Karim"]);
+
+        assert_eq!(
+            matrix.rows[0].cells,
+            vec![
+                Some(AttendanceStatus::Present),
+                Some(AttendanceStatus::Excused)
+            ]
+        );
+        assert_eq!(matrix.rows[0].totals.attendance_rate, 50.0);
+        assert_eq!(matrix.rows[1].totals.late, 1);
+        assert_eq!(matrix.rows[1].totals.attendance_rate, 100.0);
+        // No mark on a session date means absent
+        assert_eq!(matrix.rows[2].totals.absent, 2);
+        assert_eq!(matrix.rows[2].totals.attendance_rate, 0.0);
+
+        assert_eq!(matrix.date_totals[0].sessions, 3);
+        assert_eq!(matrix.date_totals[0].present + matrix.date_totals[0].late, 2);
+        assert_eq!(matrix.totals.sessions, 6);
+        assert_eq!(matrix.totals.attendance_rate, 50.0);
+
+        assert!(
+            AttendanceService::get_attendance_matrix(&db, "Group A", "2024-02-01", "2024-01-01")
+                .is_err()
+        );
+    }
 }
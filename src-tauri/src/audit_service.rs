@@ -1,7 +1,24 @@
-use crate::database::Database;
-use rusqlite::{params, Result as SqliteResult};
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::settings_service::SettingsService;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension, Result as SqliteResult};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+
+const AUDIT_COLUMNS: &str = "id, action_type, table_name, record_id, old_values, new_values, user_id, timestamp, prev_hash, entry_hash";
+
+/// Action of the entry `cleanup_old_entries` leaves in place of the entries it removes
+pub const CHECKPOINT_ACTION: &str = "CHECKPOINT";
+
+/// File next to the database holding the key checkpoints are signed with. Keeping it out of the
+/// database means a copy of the database alone is not enough to forge a checkpoint.
+const SIGNING_KEY_FILE: &str = "audit_signing.key";
+
+type HmacSha256 = Hmac<Sha256>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditLogEntry {
@@ -13,6 +30,55 @@ pub struct AuditLogEntry {
     pub new_values: Option<String>,
     pub user_id: Option<String>,
     pub timestamp: String,
+    /// `entry_hash` of the entry before this one; empty for the first entry of the chain
+    #[serde(default)]
+    pub prev_hash: Option<String>,
+    /// Hash over this entry's contents and `prev_hash`; `None` on entries written before chaining
+    #[serde(default)]
+    pub entry_hash: Option<String>,
+}
+
+/// What a CHECKPOINT entry records about the entries removed before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditCheckpoint {
+    /// Highest ID removed
+    pub through_id: i32,
+    /// `entry_hash` of that entry, which the oldest remaining entry points back to
+    pub through_hash: String,
+    pub deleted_count: i32,
+    /// HMAC-SHA256 of the fields above
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainBreakKind {
+    /// The entry's contents no longer match its hash
+    Modified,
+    /// The entry does not point back at the one before it: entries were removed or reordered
+    Unlinked,
+    /// A checkpoint whose signature does not match
+    BadCheckpoint,
+    /// An entry without a hash after the chain has started
+    Unsealed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainBreak {
+    pub entry_id: i32,
+    pub kind: ChainBreakKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditChainReport {
+    pub valid: bool,
+    pub entries_checked: i32,
+    /// Entries written before the log was chained, which cannot be verified
+    pub legacy_entries: i32,
+    pub checkpoints: i32,
+    /// The first problem found, walking from the oldest entry
+    pub first_break: Option<AuditChainBreak>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +99,156 @@ pub struct AuditStatistics {
     pub recent_activity_count: i32, // Last 24 hours
 }
 
+/// One field whose value an entry changed; a missing value is `None`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    /// Empty when the values are not JSON objects and are compared whole
+    pub field: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntryDiff {
+    pub entry_id: i32,
+    pub action_type: String,
+    pub user_id: Option<String>,
+    pub timestamp: String,
+    /// Changed fields in name order
+    pub changes: Vec<FieldChange>,
+}
+
+/// A record as the audit log shows it at a past moment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordSnapshot {
+    pub table_name: String,
+    pub record_id: String,
+    /// `YYYY-MM-DD HH:MM:SS` in UTC, like audit timestamps
+    pub as_of: String,
+    /// False when the record had not been created yet or had been deleted
+    pub exists: bool,
+    pub values: Option<serde_json::Value>,
+    /// The last entry replayed, if any
+    pub last_entry_id: Option<i32>,
+    pub last_changed_at: Option<String>,
+}
+
+/// Whether changes are audited, and whether a change whose audit entry fails is undone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditPolicy {
+    pub enabled: bool,
+    pub strict: bool,
+}
+
+impl AuditPolicy {
+    /// Read from the `enable_audit_log` and `audit_strict_mode` settings
+    pub fn load(db: &Database) -> DatabaseResult<Self> {
+        let flag = |key: &str, default: bool| -> DatabaseResult<bool> {
+            Ok(SettingsService::get_setting(db, key)?.map_or(default, |value| value == "true"))
+        };
+        Ok(Self {
+            enabled: flag("enable_audit_log", true)?,
+            strict: flag("audit_strict_mode", false)?,
+        })
+    }
+}
+
+struct PendingEntry {
+    action_type: String,
+    table_name: String,
+    record_id: String,
+    /// Old and new values, or why they could not be serialized
+    values: Result<(Option<String>, Option<String>), serde_json::Error>,
+    user_id: Option<String>,
+}
+
+/// Audit entries a change records as it runs; `AuditService::audited` writes them before committing
+#[derive(Default)]
+pub struct AuditTrail {
+    entries: Vec<PendingEntry>,
+}
+
+impl AuditTrail {
+    /// Record an entry with values already in their stored form
+    pub fn record(
+        &mut self,
+        action_type: &str,
+        table_name: &str,
+        record_id: &str,
+        old_values: Option<String>,
+        new_values: Option<String>,
+        user_id: Option<&str>,
+    ) {
+        self.push(action_type, table_name, record_id, Ok((old_values, new_values)), user_id);
+    }
+
+    pub fn create<T: Serialize>(
+        &mut self,
+        table_name: &str,
+        record_id: &str,
+        new_data: &T,
+        user_id: Option<&str>,
+    ) {
+        let values = AuditService::serialize_data(new_data).map(|new| (None, Some(new)));
+        self.push("CREATE", table_name, record_id, values, user_id);
+    }
+
+    pub fn update<T: Serialize, U: Serialize>(
+        &mut self,
+        table_name: &str,
+        record_id: &str,
+        old_data: &T,
+        new_data: &U,
+        user_id: Option<&str>,
+    ) {
+        self.change("UPDATE", table_name, record_id, old_data, new_data, user_id);
+    }
+
+    pub fn delete<T: Serialize>(
+        &mut self,
+        table_name: &str,
+        record_id: &str,
+        old_data: &T,
+        user_id: Option<&str>,
+    ) {
+        let values = AuditService::serialize_data(old_data).map(|old| (Some(old), None));
+        self.push("DELETE", table_name, record_id, values, user_id);
+    }
+
+    /// Record an entry of any action from old and new data
+    pub fn change<T: Serialize, U: Serialize>(
+        &mut self,
+        action_type: &str,
+        table_name: &str,
+        record_id: &str,
+        old_data: &T,
+        new_data: &U,
+        user_id: Option<&str>,
+    ) {
+        let values = AuditService::serialize_data(old_data).and_then(|old| {
+            AuditService::serialize_data(new_data).map(|new| (Some(old), Some(new)))
+        });
+        self.push(action_type, table_name, record_id, values, user_id);
+    }
+
+    fn push(
+        &mut self,
+        action_type: &str,
+        table_name: &str,
+        record_id: &str,
+        values: Result<(Option<String>, Option<String>), serde_json::Error>,
+        user_id: Option<&str>,
+    ) {
+        self.entries.push(PendingEntry {
+            action_type: action_type.to_string(),
+            table_name: table_name.to_string(),
+            record_id: record_id.to_string(),
+            values,
+            user_id: user_id.map(str::to_string),
+        });
+    }
+}
+
 pub struct AuditService;
 
 impl AuditService {
@@ -46,34 +262,101 @@ impl AuditService {
         new_values: Option<&str>,
         user_id: Option<&str>,
     ) -> SqliteResult<AuditLogEntry> {
-        let mut stmt = db.connection().prepare(
-            "INSERT INTO audit_log (action_type, table_name, record_id, old_values, new_values, user_id)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
-             RETURNING id, action_type, table_name, record_id, old_values, new_values, user_id, timestamp"
-        )?;
+        // Entries are chained in ID order; an entry from before chaining starts a new chain
+        let prev_hash: String = db
+            .connection()
+            .query_row(
+                "SELECT entry_hash FROM audit_log ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?
+            .flatten()
+            .unwrap_or_default();
+        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let entry_hash = Self::compute_hash(
+            &prev_hash,
+            [
+                Some(action_type),
+                Some(table_name),
+                Some(record_id),
+                old_values,
+                new_values,
+                user_id,
+                Some(timestamp.as_str()),
+            ],
+        );
+
+        let mut stmt = db.connection().prepare(&format!(
+            "INSERT INTO audit_log (action_type, table_name, record_id, old_values, new_values, user_id, timestamp, prev_hash, entry_hash)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             RETURNING {}",
+            AUDIT_COLUMNS
+        ))?;
+
+        stmt.query_row(
+            params![
+                action_type,
+                table_name,
+                record_id,
+                old_values,
+                new_values,
+                user_id,
+                timestamp,
+                prev_hash,
+                entry_hash
+            ],
+            Self::map_row_to_entry,
+        )
+    }
 
-        let entry = stmt.query_row(
-            params![action_type, table_name, record_id, old_values, new_values, user_id],
-            |row| {
-                Ok(AuditLogEntry {
-                    id: row.get(0)?,
-                    action_type: row.get(1)?,
-                    table_name: row.get(2)?,
-                    record_id: row.get(3)?,
-                    old_values: row.get(4)?,
-                    new_values: row.get(5)?,
-                    user_id: row.get(6)?,
-                    timestamp: row.get(7)?,
-                })
+    fn map_row_to_entry(row: &rusqlite::Row) -> SqliteResult<AuditLogEntry> {
+        Ok(AuditLogEntry {
+            id: row.get(0)?,
+            action_type: row.get(1)?,
+            table_name: row.get(2)?,
+            record_id: row.get(3)?,
+            old_values: row.get(4)?,
+            new_values: row.get(5)?,
+            user_id: row.get(6)?,
+            timestamp: row.get(7)?,
+            prev_hash: row.get(8)?,
+            entry_hash: row.get(9)?,
+        })
+    }
+
+    /// SHA-256 over the previous hash and each field. Fields are length-prefixed so that moving
+    /// text from one field to the next changes the hash.
+    fn compute_hash(prev_hash: &str, fields: [Option<&str>; 7]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash.as_bytes());
+        for field in fields {
+            match field {
+                Some(value) => hasher.update(format!("|{}:{}", value.len(), value)),
+                None => hasher.update("|-"),
             }
-        )?;
+        }
+        format!("{:x}", hasher.finalize())
+    }
 
-        Ok(entry)
+    fn hash_of(entry: &AuditLogEntry) -> String {
+        Self::compute_hash(
+            entry.prev_hash.as_deref().unwrap_or_default(),
+            [
+                Some(entry.action_type.as_str()),
+                Some(entry.table_name.as_str()),
+                Some(entry.record_id.as_str()),
+                entry.old_values.as_deref(),
+                entry.new_values.as_deref(),
+                entry.user_id.as_deref(),
+                Some(entry.timestamp.as_str()),
+            ],
+        )
     }
 
     /// Get audit log entries with optional filtering
     pub fn get_audit_log(db: &Database, filter: Option<AuditLogFilter>) -> SqliteResult<Vec<AuditLogEntry>> {
-        let mut query = "SELECT id, action_type, table_name, record_id, old_values, new_values, user_id, timestamp FROM audit_log".to_string();
+        let mut query = format!("SELECT {} FROM audit_log", AUDIT_COLUMNS);
         let mut conditions = Vec::new();
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
@@ -119,18 +402,7 @@ impl AuditService {
         let mut stmt = db.connection().prepare(&query)?;
         let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         
-        let entries = stmt.query_map(&param_refs[..], |row| {
-            Ok(AuditLogEntry {
-                id: row.get(0)?,
-                action_type: row.get(1)?,
-                table_name: row.get(2)?,
-                record_id: row.get(3)?,
-                old_values: row.get(4)?,
-                new_values: row.get(5)?,
-                user_id: row.get(6)?,
-                timestamp: row.get(7)?,
-            })
-        })?;
+        let entries = stmt.query_map(&param_refs[..], Self::map_row_to_entry)?;
 
         let mut result = Vec::new();
         for entry in entries {
@@ -154,27 +426,195 @@ impl AuditService {
         Self::get_audit_log(db, Some(filter))
     }
 
+    /// Get a single audit entry by ID
+    pub fn get_entry(db: &Database, id: i32) -> SqliteResult<Option<AuditLogEntry>> {
+        db.connection()
+            .query_row(
+                &format!("SELECT {} FROM audit_log WHERE id = ?1", AUDIT_COLUMNS),
+                [id],
+                Self::map_row_to_entry,
+            )
+            .optional()
+    }
+
+    /// Stored values as JSON; text that is not JSON is kept as a string
+    fn parse_values(values: Option<&str>) -> Option<serde_json::Value> {
+        values.map(|text| {
+            serde_json::from_str(text).unwrap_or_else(|_| serde_json::Value::String(text.to_string()))
+        })
+    }
+
+    /// The fields an entry changed, with their values before and after
+    pub fn diff_entry(entry: &AuditLogEntry) -> AuditEntryDiff {
+        let before = Self::parse_values(entry.old_values.as_deref());
+        let after = Self::parse_values(entry.new_values.as_deref());
+        let empty = serde_json::Map::new();
+
+        let as_object = |value: &Option<serde_json::Value>| match value {
+            Some(value) => value.as_object(),
+            None => Some(&empty),
+        };
+        let changes = match (as_object(&before), as_object(&after)) {
+            (Some(old), Some(new)) => old
+                .keys()
+                .chain(new.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .filter(|field| old.get(*field) != new.get(*field))
+                .map(|field| FieldChange {
+                    field: field.clone(),
+                    before: old.get(field).cloned(),
+                    after: new.get(field).cloned(),
+                })
+                .collect(),
+            _ if before != after => vec![FieldChange {
+                field: String::new(),
+                before,
+                after,
+            }],
+            _ => Vec::new(),
+        };
+
+        AuditEntryDiff {
+            entry_id: entry.id,
+            action_type: entry.action_type.clone(),
+            user_id: entry.user_id.clone(),
+            timestamp: entry.timestamp.clone(),
+            changes,
+        }
+    }
+
+    /// Entries for one record, oldest first
+    fn record_entries(
+        db: &Database,
+        table_name: &str,
+        record_id: &str,
+        until: Option<&str>,
+    ) -> SqliteResult<Vec<AuditLogEntry>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM audit_log
+             WHERE table_name = ?1 AND record_id = ?2 AND (?3 IS NULL OR timestamp <= ?3)
+             ORDER BY id",
+            AUDIT_COLUMNS
+        ))?;
+        let entries = stmt
+            .query_map(params![table_name, record_id, until], Self::map_row_to_entry)?
+            .collect();
+        entries
+    }
+
+    /// What each entry for a record changed, oldest first
+    pub fn get_record_changes(
+        db: &Database,
+        table_name: &str,
+        record_id: &str,
+    ) -> SqliteResult<Vec<AuditEntryDiff>> {
+        Ok(Self::record_entries(db, table_name, record_id, None)?
+            .iter()
+            .map(Self::diff_entry)
+            .collect())
+    }
+
+    /// Accepts `YYYY-MM-DD HH:MM:SS` in UTC, RFC 3339, or a date meaning the end of that day
+    fn parse_point_in_time(value: &str) -> DatabaseResult<String> {
+        let value = value.trim();
+        let parsed = DateTime::parse_from_rfc3339(value)
+            .map(|moment| moment.with_timezone(&Utc).naive_utc())
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S"))
+            .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+            .or_else(|_| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map(|date| date.and_hms_opt(23, 59, 59).unwrap_or_default())
+            })
+            .map_err(|_| {
+                DatabaseError::validation(
+                    "timestamp",
+                    format!(
+                        "Invalid timestamp '{}'. Expected YYYY-MM-DD HH:MM:SS (UTC) or YYYY-MM-DD",
+                        value
+                    ),
+                )
+            })?;
+        Ok(parsed.format("%Y-%m-%d %H:%M:%S").to_string())
+    }
+
+    /// Apply one entry to a record's values. CREATE sets them and DELETE clears them. Other
+    /// entries overwrite the fields they carry, except fields neither the record nor the entry's
+    /// old values have, such as the reason given for a transfer.
+    fn replay(state: Option<serde_json::Value>, entry: &AuditLogEntry) -> Option<serde_json::Value> {
+        let old = Self::parse_values(entry.old_values.as_deref());
+        let mut new = Self::parse_values(entry.new_values.as_deref());
+        if entry.action_type == "REVERT" {
+            // A REVERT names the entry it undid alongside the values it restored
+            if let Some(serde_json::Value::Object(fields)) = &mut new {
+                fields.remove("reverted_entry_id");
+                if fields.is_empty() {
+                    new = None;
+                }
+            }
+        }
+        match entry.action_type.as_str() {
+            "DELETE" => None,
+            "CREATE" => new,
+            // Reverting a create removes the record
+            "REVERT" if new.is_none() => None,
+            _ => {
+                let Some(new) = new else { return state };
+                let mut base = match state.or_else(|| old.clone()) {
+                    Some(base) => base,
+                    None => return Some(new),
+                };
+                match (base.as_object_mut(), new) {
+                    (Some(fields), serde_json::Value::Object(changes)) => {
+                        let old_fields = old.as_ref().and_then(|old| old.as_object());
+                        for (field, value) in changes {
+                            let known = fields.contains_key(&field)
+                                || old_fields.is_some_and(|old| old.contains_key(&field));
+                            if known {
+                                fields.insert(field, value);
+                            }
+                        }
+                        Some(base)
+                    }
+                    (_, new) => Some(new),
+                }
+            }
+        }
+    }
+
+    /// Replay a record's audit entries up to `timestamp` to show its values at that moment
+    pub fn reconstruct_record(
+        db: &Database,
+        table_name: &str,
+        record_id: &str,
+        timestamp: &str,
+    ) -> DatabaseResult<RecordSnapshot> {
+        let as_of = Self::parse_point_in_time(timestamp)?;
+        let entries = Self::record_entries(db, table_name, record_id, Some(&as_of))?;
+        let values = entries.iter().fold(None, Self::replay);
+        let last = entries.last();
+
+        Ok(RecordSnapshot {
+            table_name: table_name.to_string(),
+            record_id: record_id.to_string(),
+            as_of,
+            exists: values.is_some(),
+            values,
+            last_entry_id: last.map(|entry| entry.id),
+            last_changed_at: last.map(|entry| entry.timestamp.clone()),
+        })
+    }
+
     /// Get recent audit activity (last 24 hours)
     pub fn get_recent_activity(db: &Database) -> SqliteResult<Vec<AuditLogEntry>> {
-        let mut stmt = db.connection().prepare(
-            "SELECT id, action_type, table_name, record_id, old_values, new_values, user_id, timestamp 
-             FROM audit_log 
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM audit_log
              WHERE timestamp >= datetime('now', '-1 day')
-             ORDER BY timestamp DESC"
-        )?;
+             ORDER BY timestamp DESC",
+            AUDIT_COLUMNS
+        ))?;
 
-        let entries = stmt.query_map([], |row| {
-            Ok(AuditLogEntry {
-                id: row.get(0)?,
-                action_type: row.get(1)?,
-                table_name: row.get(2)?,
-                record_id: row.get(3)?,
-                old_values: row.get(4)?,
-                new_values: row.get(5)?,
-                user_id: row.get(6)?,
-                timestamp: row.get(7)?,
-            })
-        })?;
+        let entries = stmt.query_map([], Self::map_row_to_entry)?;
 
         let mut result = Vec::new();
         for entry in entries {
@@ -234,14 +674,252 @@ impl AuditService {
         })
     }
 
-    /// Delete old audit entries (for maintenance)
-    pub fn cleanup_old_entries(db: &Database, days_to_keep: i32) -> SqliteResult<i32> {
-        let mut stmt = db.connection().prepare(
-            "DELETE FROM audit_log WHERE timestamp < datetime('now', '-' || ? || ' days')"
+    /// Delete audit entries older than `days_to_keep` days (for maintenance). The oldest entries
+    /// are removed as one run up to the newest expired one, and a signed CHECKPOINT entry records
+    /// where the chain now starts so the gap can be told apart from tampering.
+    pub fn cleanup_old_entries(db: &Database, days_to_keep: i32) -> DatabaseResult<i32> {
+        let through: Option<(i32, Option<String>)> = db
+            .connection()
+            .query_row(
+                "SELECT id, entry_hash FROM audit_log
+                 WHERE id = (SELECT MAX(id) FROM audit_log
+                             WHERE timestamp < datetime('now', '-' || ?1 || ' days'))",
+                params![days_to_keep],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let (through_id, through_hash) = match through {
+            Some((id, hash)) => (id, hash.unwrap_or_default()),
+            None => return Ok(0),
+        };
+
+        let key = Self::signing_key(db, true)?.unwrap_or_default();
+        let tx = db.connection().unchecked_transaction()?;
+        let deleted_count: i32 = tx.query_row(
+            "SELECT COUNT(*) FROM audit_log WHERE id <= ?1",
+            params![through_id],
+            |row| row.get(0),
         )?;
-        
-        let deleted_count = stmt.execute(params![days_to_keep])?;
-        Ok(deleted_count as i32)
+        let checkpoint = AuditCheckpoint {
+            signature: Self::sign(&key, through_id, &through_hash, deleted_count),
+            through_id,
+            through_hash,
+            deleted_count,
+        };
+        let json = Self::serialize_data(&checkpoint)
+            .map_err(|e| DatabaseError::Storage(format!("Failed to record checkpoint: {}", e)))?;
+        // Logged before the delete so it chains onto the newest entry even when all go
+        Self::log_action(
+            db,
+            CHECKPOINT_ACTION,
+            "audit_log",
+            &through_id.to_string(),
+            None,
+            Some(&json),
+            None,
+        )?;
+        tx.execute("DELETE FROM audit_log WHERE id <= ?1", params![through_id])?;
+        tx.commit()?;
+
+        log::info!(
+            "Removed {} audit entries through #{}",
+            deleted_count,
+            through_id
+        );
+        Ok(deleted_count)
+    }
+
+    /// The checkpoint signing key, created on first use when `create` is set
+    fn signing_key(db: &Database, create: bool) -> DatabaseResult<Option<Vec<u8>>> {
+        let path = db.db_path().with_file_name(SIGNING_KEY_FILE);
+        match fs::read(&path) {
+            Ok(key) if !key.is_empty() => Ok(Some(key)),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if !create {
+                    return Ok(None);
+                }
+                let mut key = vec![0u8; 32];
+                rand::thread_rng().fill_bytes(&mut key);
+                fs::write(&path, &key).map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write audit signing key: {}", e))
+                })?;
+                Ok(Some(key))
+            }
+            Err(e) => Err(DatabaseError::Storage(format!(
+                "Failed to read audit signing key: {}",
+                e
+            ))),
+        }
+    }
+
+    fn sign(key: &[u8], through_id: i32, through_hash: &str, deleted_count: i32) -> String {
+        let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(format!("{}|{}|{}", through_id, through_hash, deleted_count).as_bytes());
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+
+    /// The checkpoint a CHECKPOINT entry records, if its signature matches
+    fn verified_checkpoint(entry: &AuditLogEntry, key: Option<&[u8]>) -> Option<AuditCheckpoint> {
+        let checkpoint: AuditCheckpoint = serde_json::from_str(entry.new_values.as_deref()?).ok()?;
+        let expected = Self::sign(
+            key?,
+            checkpoint.through_id,
+            &checkpoint.through_hash,
+            checkpoint.deleted_count,
+        );
+        (expected == checkpoint.signature).then_some(checkpoint)
+    }
+
+    /// Walk the log from the oldest entry, checking each entry's hash and its link to the one
+    /// before. The oldest entry may only point back at nothing or at a checkpoint's last removed
+    /// entry. Stops at the first problem.
+    pub fn verify_chain(db: &Database) -> DatabaseResult<AuditChainReport> {
+        let mut stmt = db
+            .connection()
+            .prepare(&format!("SELECT {} FROM audit_log ORDER BY id", AUDIT_COLUMNS))?;
+        let entries = stmt
+            .query_map([], Self::map_row_to_entry)?
+            .collect::<Result<Vec<_>, _>>()?;
+        let key = Self::signing_key(db, false)?;
+
+        // Where the chain may start: at nothing, or right after a checkpoint's removed entries
+        let mut starts: HashSet<String> = HashSet::from([String::new()]);
+        let mut checkpoints = 0;
+        for entry in entries.iter().filter(|e| e.action_type == CHECKPOINT_ACTION) {
+            checkpoints += 1;
+            if let Some(checkpoint) = Self::verified_checkpoint(entry, key.as_deref()) {
+                starts.insert(checkpoint.through_hash);
+            }
+        }
+
+        let mut report = AuditChainReport {
+            valid: true,
+            entries_checked: 0,
+            legacy_entries: 0,
+            checkpoints,
+            first_break: None,
+        };
+        let mut last_hash: Option<&str> = None;
+        for entry in &entries {
+            let broken = |kind: ChainBreakKind, message: String| AuditChainBreak {
+                entry_id: entry.id,
+                kind,
+                message,
+            };
+
+            let hash = match (&entry.entry_hash, last_hash) {
+                (Some(hash), _) => hash,
+                (None, None) => {
+                    report.legacy_entries += 1;
+                    continue;
+                }
+                (None, Some(_)) => {
+                    report.first_break = Some(broken(
+                        ChainBreakKind::Unsealed,
+                        format!("Entry #{} has no hash", entry.id),
+                    ));
+                    break;
+                }
+            };
+            report.entries_checked += 1;
+
+            if Self::hash_of(entry) != *hash {
+                report.first_break = Some(broken(
+                    ChainBreakKind::Modified,
+                    format!("Entry #{} was changed after it was written", entry.id),
+                ));
+                break;
+            }
+
+            let prev_hash = entry.prev_hash.as_deref().unwrap_or_default();
+            let linked = match last_hash {
+                Some(last) => prev_hash == last,
+                // Entries from before chaining are always older, so nothing was removed after them
+                None if report.legacy_entries > 0 => prev_hash.is_empty(),
+                None => starts.contains(prev_hash),
+            };
+            if !linked {
+                let message = if last_hash.is_some() {
+                    format!("The entry before #{} is missing or out of order", entry.id)
+                } else {
+                    format!(
+                        "Entries before #{} were removed without a checkpoint",
+                        entry.id
+                    )
+                };
+                report.first_break = Some(broken(ChainBreakKind::Unlinked, message));
+                break;
+            }
+
+            if entry.action_type == CHECKPOINT_ACTION
+                && Self::verified_checkpoint(entry, key.as_deref()).is_none()
+            {
+                report.first_break = Some(broken(
+                    ChainBreakKind::BadCheckpoint,
+                    format!("Checkpoint #{} does not carry a valid signature", entry.id),
+                ));
+                break;
+            }
+
+            last_hash = Some(hash.as_str());
+        }
+
+        report.valid = report.first_break.is_none();
+        Ok(report)
+    }
+
+    /// Run `change` and write the audit entries it records in the same transaction, so a change
+    /// is never committed without its entries. Nothing is written while audit logging is
+    /// disabled. A failed audit write undoes the change in strict mode and is only logged
+    /// otherwise.
+    pub fn audited<T>(
+        db: &Database,
+        change: impl FnOnce(&mut AuditTrail) -> DatabaseResult<T>,
+    ) -> DatabaseResult<T> {
+        let policy = AuditPolicy::load(db)?;
+        let tx = db.savepoint()?;
+
+        let mut trail = AuditTrail::default();
+        let value = change(&mut trail)?;
+
+        if policy.enabled {
+            for entry in trail.entries {
+                let written = entry
+                    .values
+                    .map_err(|e| {
+                        DatabaseError::Storage(format!("Failed to serialize audit values: {}", e))
+                    })
+                    .and_then(|(old_values, new_values)| {
+                        Self::log_action(
+                            db,
+                            &entry.action_type,
+                            &entry.table_name,
+                            &entry.record_id,
+                            old_values.as_deref(),
+                            new_values.as_deref(),
+                            entry.user_id.as_deref(),
+                        )
+                        .map_err(DatabaseError::from)
+                    });
+
+                if let Err(error) = written {
+                    if policy.strict {
+                        return Err(error);
+                    }
+                    log::warn!(
+                        "Kept {} on {} {} without its audit entry: {}",
+                        entry.action_type,
+                        entry.table_name,
+                        entry.record_id,
+                        error
+                    );
+                }
+            }
+        }
+
+        tx.commit()?;
+        Ok(value)
     }
 
     /// Helper function to serialize data for audit logging
@@ -502,4 +1180,221 @@ mod tests {
         assert!(entry.old_values.is_some());
         assert!(entry.new_values.is_none());
     }
+
+    fn log_three(db: &Database) {
+        AuditService::log_create(db, "students", "student_001", r#"{"name": "John"}"#, Some("admin")).unwrap();
+        AuditService::log_update(db, "students", "student_001", r#"{"name": "John"}"#, r#"{"name": "Jane"}"#, None).unwrap();
+        AuditService::log_delete(db, "payment_transactions", "7", r#"{"amount": 500}"#, None).unwrap();
+    }
+
+    #[test]
+    fn test_verify_chain_detects_changes_and_removals() {
+        let (db, _temp_dir) = setup_test_db();
+        log_three(&db);
+
+        let report = AuditService::verify_chain(&db).unwrap();
+        assert!(report.valid);
+        assert_eq!(report.entries_checked, 3);
+
+        db.connection()
+            .execute(r#"UPDATE audit_log SET old_values = '{"amount": 5}' WHERE id = 3"#, [])
+            .unwrap();
+        let report = AuditService::verify_chain(&db).unwrap();
+        assert!(!report.valid);
+        let broken = report.first_break.unwrap();
+        assert_eq!((broken.entry_id, broken.kind), (3, ChainBreakKind::Modified));
+
+        // Erasing an entry outright breaks the link from the next one
+        let (db, _temp_dir) = setup_test_db();
+        log_three(&db);
+        db.connection().execute("DELETE FROM audit_log WHERE id = 2", []).unwrap();
+        let broken = AuditService::verify_chain(&db).unwrap().first_break.unwrap();
+        assert_eq!((broken.entry_id, broken.kind), (3, ChainBreakKind::Unlinked));
+
+        // And so does erasing the oldest entries without a checkpoint
+        db.connection().execute("DELETE FROM audit_log WHERE id = 1", []).unwrap();
+        let broken = AuditService::verify_chain(&db).unwrap().first_break.unwrap();
+        assert_eq!((broken.entry_id, broken.kind), (3, ChainBreakKind::Unlinked));
+    }
+
+    #[test]
+    fn test_verify_chain_accepts_legacy_entries() {
+        let (db, _temp_dir) = setup_test_db();
+        db.connection()
+            .execute("INSERT INTO audit_log (action_type, table_name, record_id) VALUES ('CREATE', 'students', 'old')", [])
+            .unwrap();
+        log_three(&db);
+
+        let report = AuditService::verify_chain(&db).unwrap();
+        assert!(report.valid);
+        assert_eq!((report.legacy_entries, report.entries_checked), (1, 3));
+    }
+
+    #[test]
+    fn test_cleanup_leaves_signed_checkpoint() {
+        let (db, _temp_dir) = setup_test_db();
+        log_three(&db);
+        // Age the first two entries; their hashes no longer match but they are about to go
+        db.connection()
+            .execute("UPDATE audit_log SET timestamp = '2000-01-01 00:00:00' WHERE id <= 2", [])
+            .unwrap();
+
+        assert_eq!(AuditService::cleanup_old_entries(&db, 30).unwrap(), 2);
+        let report = AuditService::verify_chain(&db).unwrap();
+        assert!(report.valid, "{:?}", report.first_break);
+        assert_eq!((report.entries_checked, report.checkpoints), (2, 1));
+
+        let checkpoint = AuditService::get_audit_log(&db, None)
+            .unwrap()
+            .into_iter()
+            .find(|e| e.action_type == CHECKPOINT_ACTION)
+            .unwrap();
+        let recorded: AuditCheckpoint = serde_json::from_str(checkpoint.new_values.as_deref().unwrap()).unwrap();
+        assert_eq!((recorded.through_id, recorded.deleted_count), (2, 2));
+
+        // A forged checkpoint does not cover the gap
+        let forged = AuditCheckpoint { signature: "0".repeat(64), ..recorded };
+        db.connection()
+            .execute(
+                "UPDATE audit_log SET new_values = ?1 WHERE id = ?2",
+                params![serde_json::to_string(&forged).unwrap(), checkpoint.id],
+            )
+            .unwrap();
+        let broken = AuditService::verify_chain(&db).unwrap().first_break.unwrap();
+        assert_eq!((broken.entry_id, broken.kind), (3, ChainBreakKind::Unlinked));
+    }
+
+    fn log_student_history(db: &Database) {
+        AuditService::log_create(db, "students", "1", r#"{"name":"John","plan_amount":500,"group_name":"A"}"#, None).unwrap();
+        AuditService::log_update(
+            db,
+            "students",
+            "1",
+            r#"{"name":"John","plan_amount":500,"group_name":"A"}"#,
+            r#"{"name":"John","plan_amount":300,"group_name":"A"}"#,
+            None,
+        )
+        .unwrap();
+        AuditService::log_action(
+            db,
+            "TRANSFER",
+            "students",
+            "1",
+            Some(r#"{"group_name":"A"}"#),
+            Some(r#"{"group_name":"B","reason":"schedule"}"#),
+            None,
+        )
+        .unwrap();
+        AuditService::log_delete(db, "students", "1", r#"{"name":"John","plan_amount":300,"group_name":"B"}"#, None).unwrap();
+        for (id, timestamp) in [(1, "2024-01-01 10:00:00"), (2, "2024-02-01 10:00:00"), (3, "2024-02-15 10:00:00"), (4, "2024-03-01 10:00:00")] {
+            db.connection()
+                .execute("UPDATE audit_log SET timestamp = ?1 WHERE id = ?2", params![timestamp, id])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_get_record_changes() {
+        let (db, _temp_dir) = setup_test_db();
+        log_student_history(&db);
+
+        let diffs = AuditService::get_record_changes(&db, "students", "1").unwrap();
+        assert_eq!(diffs.len(), 4);
+        assert_eq!(diffs[0].changes.len(), 3);
+        assert!(diffs[0].changes.iter().all(|change| change.before.is_none()));
+        assert_eq!(
+            diffs[1].changes,
+            vec![FieldChange {
+                field: "plan_amount".to_string(),
+                before: Some(serde_json::json!(500)),
+                after: Some(serde_json::json!(300)),
+            }]
+        );
+        let fields: Vec<&str> = diffs[2].changes.iter().map(|change| change.field.as_str()).collect();
+        assert_eq!(fields, vec!["group_name", "reason"]);
+        assert!(diffs[3].changes.iter().all(|change| change.after.is_none()));
+    }
+
+    #[test]
+    fn test_reconstruct_record() {
+        let (db, _temp_dir) = setup_test_db();
+        log_student_history(&db);
+
+        let before = AuditService::reconstruct_record(&db, "students", "1", "2023-12-31").unwrap();
+        assert!(!before.exists);
+        assert_eq!(before.last_entry_id, None);
+
+        let created = AuditService::reconstruct_record(&db, "students", "1", "2024-01-15 00:00:00").unwrap();
+        assert_eq!(created.values.unwrap()["plan_amount"], 500);
+
+        let transferred = AuditService::reconstruct_record(&db, "students", "1", "2024-02-20").unwrap();
+        let values = transferred.values.unwrap();
+        assert_eq!((values["plan_amount"].clone(), values["group_name"].clone()), (serde_json::json!(300), serde_json::json!("B")));
+        assert!(values.get("reason").is_none());
+        assert_eq!(transferred.last_changed_at.as_deref(), Some("2024-02-15 10:00:00"));
+
+        let deleted = AuditService::reconstruct_record(&db, "students", "1", "2024-03-01T12:00:00+02:00").unwrap();
+        assert_eq!(deleted.as_of, "2024-03-01 10:00:00");
+        assert!(!deleted.exists);
+        assert_eq!(deleted.last_entry_id, Some(4));
+
+        assert!(AuditService::reconstruct_record(&db, "students", "1", "last week").is_err());
+    }
+
+    fn audited_note(db: &Database) -> DatabaseResult<()> {
+        AuditService::audited(db, |audit| {
+            db.connection().execute("INSERT INTO notes (id, body) VALUES (1, 'hello')", [])?;
+            audit.create("notes", "1", &serde_json::json!({ "body": "hello" }), None);
+            Ok(())
+        })
+    }
+
+    fn count(db: &Database, sql: &str) -> i64 {
+        db.connection().query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_audited_writes_change_and_entry_together() {
+        let (db, _temp_dir) = setup_test_db();
+        db.connection().execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)", []).unwrap();
+
+        audited_note(&db).unwrap();
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes"), 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM audit_log WHERE table_name = 'notes'"), 1);
+
+        // A failed change leaves neither the change nor its entry behind
+        let result: DatabaseResult<()> = AuditService::audited(&db, |audit| {
+            db.connection().execute("UPDATE notes SET body = 'changed' WHERE id = 1", [])?;
+            audit.record("UPDATE", "notes", "1", None, None, None);
+            Err(DatabaseError::validation("body", "rejected"))
+        });
+        assert!(result.is_err());
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes WHERE body = 'hello'"), 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM audit_log WHERE table_name = 'notes'"), 1);
+
+        SettingsService::set_setting(&db, "enable_audit_log", "false").unwrap();
+        db.connection().execute("DELETE FROM notes", []).unwrap();
+        audited_note(&db).unwrap();
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes"), 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM audit_log WHERE table_name = 'notes'"), 1);
+    }
+
+    #[test]
+    fn test_audited_strict_mode_rolls_back_unlogged_change() {
+        let (db, _temp_dir) = setup_test_db();
+        db.connection().execute("CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)", []).unwrap();
+        db.connection()
+            .execute("CREATE TRIGGER reject_audit BEFORE INSERT ON audit_log BEGIN SELECT RAISE(ABORT, 'audit log is full'); END", [])
+            .unwrap();
+
+        // By default the change is kept without its entry
+        audited_note(&db).unwrap();
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes"), 1);
+
+        db.connection().execute("DELETE FROM notes", []).unwrap();
+        SettingsService::set_setting(&db, "audit_strict_mode", "true").unwrap();
+        assert!(audited_note(&db).is_err());
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes"), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM audit_log WHERE table_name = 'notes'"), 0);
+    }
 }
\ No newline at end of file
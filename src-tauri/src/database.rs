@@ -1,8 +1,12 @@
 use rusqlite::{Connection, params};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,6 +19,38 @@ pub enum DatabaseError {
     Migration(String),
     #[error("Connection error: {0}")]
     Connection(String),
+    #[error("{entity} with ID {id} not found")]
+    NotFound { entity: &'static str, id: String },
+    /// Input that fails validation; `field` names the offending input
+    #[error("{message}")]
+    Validation { field: &'static str, message: String },
+    /// The request clashes with existing data, e.g. a duplicate or a full group
+    #[error("{message}")]
+    Conflict { reason: &'static str, message: String },
+    /// The request is valid but not allowed in the record's current state
+    #[error("{message}")]
+    Forbidden { reason: &'static str, message: String },
+    /// Writing a file outside the database failed, e.g. an export
+    #[error("{0}")]
+    Storage(String),
+}
+
+impl DatabaseError {
+    pub fn not_found(entity: &'static str, id: impl ToString) -> Self {
+        DatabaseError::NotFound { entity, id: id.to_string() }
+    }
+    
+    pub fn validation(field: &'static str, message: impl Into<String>) -> Self {
+        DatabaseError::Validation { field, message: message.into() }
+    }
+    
+    pub fn conflict(reason: &'static str, message: impl Into<String>) -> Self {
+        DatabaseError::Conflict { reason, message: message.into() }
+    }
+    
+    pub fn forbidden(reason: &'static str, message: impl Into<String>) -> Self {
+        DatabaseError::Forbidden { reason, message: message.into() }
+    }
 }
 
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
@@ -24,57 +60,129 @@ pub struct Migration {
     pub version: i32,
     pub description: String,
     pub sql: String,
+    /// Reverses `sql`; `None` when the migration cannot be rolled back automatically
+    pub down_sql: Option<String>,
     pub applied_at: Option<DateTime<Utc>>,
 }
 
+impl Migration {
+    /// SHA-256 of the migration SQL, recorded when it is applied
+    pub fn checksum(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.sql.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// How long a connection waits for another connection's write lock before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Rows sampled per index by ANALYZE, which keeps it quick on large tables
+const ANALYSIS_LIMIT: i32 = 1000;
+
 pub struct Database {
     connection: Connection,
     db_path: PathBuf,
+    startup: StartupReport,
+    /// Set when migrations ran on this connection, so the planner statistics predate the schema
+    stats_stale: bool,
+    /// SQLite's `total_changes()` when last polled by [`Database::idle_for`], and when it last moved
+    total_changes: i64,
+    last_change: Instant,
 }
 
 impl Database {
     /// Create a new database instance and initialize it
     pub fn new(app_data_dir: PathBuf) -> DatabaseResult<Self> {
+        Self::open(app_data_dir, false)
+    }
+    
+    /// Like `new`, but `allow_checksum_mismatch` lets startup continue when applied
+    /// migrations were edited afterwards. Meant for recovery only.
+    pub fn open(app_data_dir: PathBuf, allow_checksum_mismatch: bool) -> DatabaseResult<Self> {
         // Ensure the app data directory exists
         fs::create_dir_all(&app_data_dir)?;
         
         let db_path = app_data_dir.join("student_management.db");
-        let connection = Connection::open(&db_path)?;
+        let started = Instant::now();
+        let mut db = Self::open_existing(&db_path)?;
+        let opened = started.elapsed();
+        
+        // Initialize the database schema
+        let migrations_applied = db.initialize_schema(allow_checksum_mismatch)?;
+        let total = started.elapsed();
+        
+        db.startup = StartupReport {
+            open_ms: opened.as_millis() as u64,
+            migrations_ms: (total - opened).as_millis() as u64,
+            migrations_applied,
+            total_ms: total.as_millis() as u64,
+        };
+        db.stats_stale = migrations_applied > 0;
+        log::info!(
+            "Database ready in {} ms (open {} ms, {} migrations in {} ms)",
+            db.startup.total_ms, db.startup.open_ms, migrations_applied, db.startup.migrations_ms
+        );
+        
+        Ok(db)
+    }
+    
+    /// Open another connection to an already initialized database file, without running migrations
+    pub(crate) fn open_existing(db_path: &Path) -> DatabaseResult<Self> {
+        let connection = Connection::open(db_path)?;
         
         // Enable foreign key constraints
         connection.execute("PRAGMA foreign_keys = ON", [])?;
         
-        let mut db = Database {
-            connection,
-            db_path,
-        };
-        
-        // Initialize the database schema
-        db.initialize_schema()?;
+        // WAL lets readers keep going while a write is in progress
+        let journal_mode: String = connection.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            log::warn!("Database is using {} journaling instead of WAL", journal_mode);
+        }
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
         
-        Ok(db)
+        Ok(Database {
+            connection,
+            db_path: db_path.to_path_buf(),
+            startup: StartupReport::default(),
+            stats_stale: false,
+            total_changes: 0,
+            last_change: Instant::now(),
+        })
     }
     
-    /// Initialize the database schema with all required tables
-    fn initialize_schema(&mut self) -> DatabaseResult<()> {
+    /// Initialize the database schema with all required tables. Returns how many migrations were applied.
+    fn initialize_schema(&mut self, allow_checksum_mismatch: bool) -> DatabaseResult<usize> {
         // Create migrations table first
         self.create_migrations_table()?;
         
+        // Refuse to build on top of migrations whose SQL changed since they ran
+        let edited = self.edited_migrations()?;
+        if !edited.is_empty() {
+            if !allow_checksum_mismatch {
+                return Err(DatabaseError::Migration(format!(
+                    "Applied migrations {:?} no longer match their definitions; refusing to start",
+                    edited
+                )));
+            }
+            log::warn!("Starting despite checksum mismatch in applied migrations {:?}", edited);
+        }
+        
         // Get current database version
         let current_version = self.get_current_version()?;
         
         // Apply all migrations
+        let mut applied = 0;
         let migrations = self.get_migrations();
         for migration in migrations {
             if migration.version > current_version {
                 self.apply_migration(&migration)?;
+                applied += 1;
             }
         }
         
-        // Create indexes for performance optimization
-        self.create_indexes()?;
-        
-        Ok(())
+        Ok(applied)
     }
     
     /// Create the migrations tracking table
@@ -83,13 +191,53 @@ impl Database {
             "CREATE TABLE IF NOT EXISTS migrations (
                 version INTEGER PRIMARY KEY,
                 description TEXT NOT NULL,
-                applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                applied_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                checksum TEXT
             )",
             [],
         )?;
+        
+        // Databases created before checksums were recorded
+        let has_checksum: bool = self.connection.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('migrations') WHERE name = 'checksum'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )?;
+        if !has_checksum {
+            self.connection.execute("ALTER TABLE migrations ADD COLUMN checksum TEXT", [])?;
+        }
+        
+        // Nothing to compare against for rows applied before then, so trust the current definitions
+        for migration in self.get_migrations() {
+            let updated = self.connection.execute(
+                "UPDATE migrations SET checksum = ?1 WHERE version = ?2 AND checksum IS NULL",
+                params![migration.checksum(), migration.version],
+            )?;
+            if updated > 0 {
+                log::info!("Recorded checksum for previously applied migration {}", migration.version);
+            }
+        }
+        
         Ok(())
     }
     
+    /// Applied migrations whose recorded checksum differs from the current definition
+    fn edited_migrations(&self) -> DatabaseResult<Vec<i32>> {
+        let migrations = self.get_migrations();
+        let edited = self
+            .get_migration_history()?
+            .into_iter()
+            .filter(|applied| {
+                migrations.iter().any(|m| {
+                    m.version == applied.version
+                        && applied.checksum.as_deref() != Some(m.checksum().as_str())
+                })
+            })
+            .map(|applied| applied.version)
+            .collect();
+        Ok(edited)
+    }
+    
     /// Get the current database version
     fn get_current_version(&self) -> DatabaseResult<i32> {
         let version: Result<Option<i32>, rusqlite::Error> = self.connection.query_row(
@@ -126,6 +274,7 @@ impl Database {
                     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
                 )".to_string(),
+                down_sql: Some("DROP TABLE students;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -139,6 +288,7 @@ impl Database {
                     FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE,
                     UNIQUE(student_id, date)
                 )".to_string(),
+                down_sql: Some("DROP TABLE attendance;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -149,6 +299,7 @@ impl Database {
                     value TEXT NOT NULL,
                     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
                 )".to_string(),
+                down_sql: Some("DROP TABLE settings;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -164,6 +315,7 @@ impl Database {
                     user_id TEXT,
                     timestamp DATETIME DEFAULT CURRENT_TIMESTAMP
                 )".to_string(),
+                down_sql: Some("DROP TABLE audit_log;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -177,6 +329,7 @@ impl Database {
                     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                     last_login DATETIME
                 )".to_string(),
+                down_sql: Some("DROP TABLE users;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -192,6 +345,7 @@ impl Database {
                     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                     FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
                 )".to_string(),
+                down_sql: Some("DROP TABLE payment_transactions;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -211,6 +365,11 @@ impl Database {
                     ('installment_amount', '2850'),
                     ('installment_interval', '3'),
                     ('reminder_days', '7')".to_string(),
+                down_sql: Some("DELETE FROM settings WHERE key IN (
+                    'payment_threshold', 'default_groups', 'enable_audit_log', 'language', 'theme',
+                    'enable_multi_user', 'backup_encryption', 'accessibility_mode', 'one_time_amount',
+                    'monthly_amount', 'installment_amount', 'installment_interval', 'reminder_days'
+                );".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -222,12 +381,14 @@ impl Database {
                     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
                     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
                 )".to_string(),
+                down_sql: Some("DROP TABLE groups;".to_string()),
                 applied_at: None,
             },
             Migration {
                 version: 9,
                 description: "Insert default groups".to_string(),
                 sql: "-- removed default groups creation".to_string(),
+                down_sql: Some("-- nothing to undo".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -243,6 +404,7 @@ impl Database {
                     payment_threshold INTEGER NOT NULL DEFAULT 6000,
                     updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
                 )".to_string(),
+                down_sql: Some("DROP TABLE payment_settings;".to_string()),
                 applied_at: None,
             },
             Migration {
@@ -250,18 +412,686 @@ impl Database {
                 description: "Insert default payment settings".to_string(),
                 sql: "INSERT OR IGNORE INTO payment_settings (id, one_time_amount, monthly_amount, installment_amount, installment_interval_months, reminder_days, payment_threshold) VALUES 
                     (1, 6000, 850, 2850, 3, 7, 6000)".to_string(),
+                down_sql: Some("DELETE FROM payment_settings WHERE id = 1;".to_string()),
                 applied_at: None,
             },
             Migration {
                 version: 12,
                 description: "Add deleted_at to students (soft delete)".to_string(),
                 sql: "ALTER TABLE students ADD COLUMN deleted_at DATETIME DEFAULT NULL".to_string(),
+                down_sql: Some("ALTER TABLE students DROP COLUMN deleted_at;".to_string()),
                 applied_at: None,
             },
             Migration {
                 version: 13,
                 description: "Add deleted_at to groups (soft delete)".to_string(),
                 sql: "ALTER TABLE groups ADD COLUMN deleted_at DATETIME DEFAULT NULL".to_string(),
+                down_sql: Some("ALTER TABLE groups DROP COLUMN deleted_at;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 14,
+                description: "Create installment_schedules table for per-student installments".to_string(),
+                sql: "CREATE TABLE installment_schedules (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    installment_number INTEGER NOT NULL,
+                    due_date TEXT NOT NULL,
+                    amount_due INTEGER NOT NULL,
+                    amount_allocated INTEGER NOT NULL DEFAULT 0,
+                    status TEXT NOT NULL DEFAULT 'pending',
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE,
+                    UNIQUE(student_id, installment_number)
+                )".to_string(),
+                down_sql: Some("DROP TABLE installment_schedules;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 15,
+                description: "Create enrollments table and link attendance and payments to enrollments".to_string(),
+                sql: "CREATE TABLE enrollments (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    group_name TEXT NOT NULL,
+                    course TEXT DEFAULT NULL,
+                    start_date TEXT NOT NULL,
+                    end_date TEXT DEFAULT NULL,
+                    payment_plan TEXT NOT NULL DEFAULT 'one-time',
+                    plan_amount INTEGER NOT NULL DEFAULT 6000,
+                    installment_count INTEGER DEFAULT NULL,
+                    paid_amount INTEGER NOT NULL DEFAULT 0,
+                    next_due_date TEXT DEFAULT NULL,
+                    payment_status TEXT NOT NULL DEFAULT 'pending',
+                    status TEXT NOT NULL DEFAULT 'active',
+                    is_primary INTEGER NOT NULL DEFAULT 0,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                -- Every existing student keeps their current group and plan as a primary enrollment
+                INSERT INTO enrollments (student_id, group_name, start_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status, status, is_primary, created_at, updated_at)
+                SELECT id, group_name, enrollment_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status,
+                       CASE WHEN deleted_at IS NULL THEN 'active' ELSE 'withdrawn' END, 1, created_at, updated_at
+                FROM students;
+
+                CREATE TABLE attendance_new (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    enrollment_id INTEGER DEFAULT NULL,
+                    date TEXT NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE,
+                    FOREIGN KEY (enrollment_id) REFERENCES enrollments (id) ON DELETE CASCADE,
+                    UNIQUE(student_id, enrollment_id, date)
+                );
+
+                INSERT INTO attendance_new (id, student_id, enrollment_id, date, created_at)
+                SELECT a.id, a.student_id, e.id, a.date, a.created_at
+                FROM attendance a
+                LEFT JOIN enrollments e ON e.student_id = a.student_id AND e.is_primary = 1;
+
+                DROP TABLE attendance;
+                ALTER TABLE attendance_new RENAME TO attendance;
+
+                -- The UNIQUE constraint treats NULLs as distinct, so marks outside any enrollment need their own
+                CREATE UNIQUE INDEX idx_attendance_unenrolled_date ON attendance (student_id, date) WHERE enrollment_id IS NULL;
+
+                ALTER TABLE payment_transactions ADD COLUMN enrollment_id INTEGER DEFAULT NULL REFERENCES enrollments (id) ON DELETE SET NULL;
+
+                UPDATE payment_transactions
+                SET enrollment_id = (SELECT e.id FROM enrollments e WHERE e.student_id = payment_transactions.student_id AND e.is_primary = 1);".to_string(),
+                down_sql: Some("-- Back to one attendance mark per student and day; marks in additional enrollments fold into it
+                CREATE TABLE attendance_old (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    date TEXT NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE,
+                    UNIQUE(student_id, date)
+                );
+
+                INSERT OR IGNORE INTO attendance_old (id, student_id, date, created_at)
+                SELECT id, student_id, date, created_at FROM attendance ORDER BY id;
+
+                DROP TABLE attendance;
+                ALTER TABLE attendance_old RENAME TO attendance;
+
+                DROP INDEX IF EXISTS idx_payment_transactions_enrollment_id;
+                ALTER TABLE payment_transactions DROP COLUMN enrollment_id;
+
+                DROP TABLE enrollments;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 16,
+                description: "Reference groups by group_id from students and enrollments".to_string(),
+                sql: "-- Retire whitespace variants of an existing group (\"Group A \" next to \"Group A\")
+                UPDATE groups SET deleted_at = CURRENT_TIMESTAMP
+                WHERE deleted_at IS NULL AND name <> TRIM(name)
+                  AND EXISTS (SELECT 1 FROM groups o WHERE o.id <> groups.id AND o.name = TRIM(groups.name) COLLATE NOCASE);
+
+                -- Create the groups that so far only existed as free text, one per spelling ignoring case
+                INSERT INTO groups (name)
+                SELECT MIN(n.name) FROM (
+                    SELECT TRIM(group_name) AS name FROM students
+                    UNION ALL
+                    SELECT TRIM(group_name) AS name FROM enrollments
+                ) n
+                WHERE n.name <> ''
+                  AND NOT EXISTS (SELECT 1 FROM groups g WHERE TRIM(g.name) = n.name COLLATE NOCASE)
+                GROUP BY LOWER(n.name);
+
+                ALTER TABLE students ADD COLUMN group_id INTEGER DEFAULT NULL REFERENCES groups (id) ON DELETE SET NULL;
+                ALTER TABLE enrollments ADD COLUMN group_id INTEGER DEFAULT NULL REFERENCES groups (id) ON DELETE SET NULL;
+
+                UPDATE students SET group_id = COALESCE(
+                    (SELECT id FROM groups WHERE name = TRIM(students.group_name) AND deleted_at IS NULL),
+                    (SELECT MIN(id) FROM groups WHERE TRIM(name) = TRIM(students.group_name) COLLATE NOCASE AND deleted_at IS NULL),
+                    (SELECT MIN(id) FROM groups WHERE TRIM(name) = TRIM(students.group_name) COLLATE NOCASE)
+                );
+
+                UPDATE enrollments SET group_id = COALESCE(
+                    (SELECT id FROM groups WHERE name = TRIM(enrollments.group_name) AND deleted_at IS NULL),
+                    (SELECT MIN(id) FROM groups WHERE TRIM(name) = TRIM(enrollments.group_name) COLLATE NOCASE AND deleted_at IS NULL),
+                    (SELECT MIN(id) FROM groups WHERE TRIM(name) = TRIM(enrollments.group_name) COLLATE NOCASE)
+                );
+
+                -- group_name is derived from the referenced group; the stored text is only a fallback
+                CREATE VIEW students_view AS
+                SELECT s.id, s.name, COALESCE(g.name, s.group_name) AS group_name, s.payment_plan, s.plan_amount,
+                       s.installment_count, s.paid_amount, s.enrollment_date, s.next_due_date, s.payment_status,
+                       s.created_at, s.updated_at, s.group_id, s.deleted_at
+                FROM students s
+                LEFT JOIN groups g ON g.id = s.group_id;
+
+                CREATE VIEW enrollments_view AS
+                SELECT e.id, e.student_id, COALESCE(g.name, e.group_name) AS group_name, e.course, e.start_date, e.end_date,
+                       e.payment_plan, e.plan_amount, e.installment_count, e.paid_amount, e.next_due_date, e.payment_status,
+                       e.status, e.is_primary, e.created_at, e.updated_at, e.group_id
+                FROM enrollments e
+                LEFT JOIN groups g ON g.id = e.group_id;".to_string(),
+                down_sql: Some("DROP VIEW IF EXISTS students_view;
+                DROP VIEW IF EXISTS enrollments_view;
+                DROP INDEX IF EXISTS idx_students_group_id;
+                DROP INDEX IF EXISTS idx_enrollments_group_id_status;
+
+                -- The stored name becomes the only link again, so bring it in line with the referenced group
+                UPDATE students SET group_name = COALESCE((SELECT name FROM groups WHERE id = students.group_id), group_name);
+                UPDATE enrollments SET group_name = COALESCE((SELECT name FROM groups WHERE id = enrollments.group_id), group_name);
+
+                ALTER TABLE students DROP COLUMN group_id;
+                ALTER TABLE enrollments DROP COLUMN group_id;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 17,
+                description: "Add group capacity, room, level, teachers and enrollment waitlist".to_string(),
+                sql: "ALTER TABLE groups ADD COLUMN capacity INTEGER DEFAULT NULL CHECK (capacity IS NULL OR capacity > 0);
+                ALTER TABLE groups ADD COLUMN room TEXT DEFAULT NULL;
+                ALTER TABLE groups ADD COLUMN level TEXT DEFAULT NULL;
+                ALTER TABLE groups ADD COLUMN subject TEXT DEFAULT NULL;
+                ALTER TABLE groups ADD COLUMN is_active BOOLEAN NOT NULL DEFAULT 1;
+
+                CREATE TABLE group_teachers (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    group_id INTEGER NOT NULL,
+                    teacher_name TEXT NOT NULL,
+                    position INTEGER NOT NULL DEFAULT 0,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (group_id) REFERENCES groups (id) ON DELETE CASCADE,
+                    UNIQUE(group_id, teacher_name)
+                );
+
+                -- Waitlisted enrollments hold a place in line until a seat frees up
+                ALTER TABLE enrollments ADD COLUMN waitlist_position INTEGER DEFAULT NULL;
+
+                DROP VIEW enrollments_view;
+                CREATE VIEW enrollments_view AS
+                SELECT e.id, e.student_id, COALESCE(g.name, e.group_name) AS group_name, e.course, e.start_date, e.end_date,
+                       e.payment_plan, e.plan_amount, e.installment_count, e.paid_amount, e.next_due_date, e.payment_status,
+                       e.status, e.is_primary, e.created_at, e.updated_at, e.group_id, e.waitlist_position
+                FROM enrollments e
+                LEFT JOIN groups g ON g.id = e.group_id;".to_string(),
+                down_sql: Some("DROP INDEX IF EXISTS idx_enrollments_waitlist;
+                DROP VIEW IF EXISTS enrollments_view;
+
+                -- Without waitlists, a place in line is no enrollment at all
+                UPDATE enrollments SET status = 'withdrawn' WHERE status = 'waitlisted';
+                ALTER TABLE enrollments DROP COLUMN waitlist_position;
+
+                CREATE VIEW enrollments_view AS
+                SELECT e.id, e.student_id, COALESCE(g.name, e.group_name) AS group_name, e.course, e.start_date, e.end_date,
+                       e.payment_plan, e.plan_amount, e.installment_count, e.paid_amount, e.next_due_date, e.payment_status,
+                       e.status, e.is_primary, e.created_at, e.updated_at, e.group_id
+                FROM enrollments e
+                LEFT JOIN groups g ON g.id = e.group_id;
+
+                DROP TABLE group_teachers;
+                ALTER TABLE groups DROP COLUMN capacity;
+                ALTER TABLE groups DROP COLUMN room;
+                ALTER TABLE groups DROP COLUMN level;
+                ALTER TABLE groups DROP COLUMN subject;
+                ALTER TABLE groups DROP COLUMN is_active;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 18,
+                description: "Track group membership history and attribute attendance and payments by date".to_string(),
+                sql: "CREATE TABLE group_memberships (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    group_id INTEGER DEFAULT NULL,
+                    group_name TEXT NOT NULL,
+                    start_date TEXT NOT NULL,
+                    end_date TEXT DEFAULT NULL,
+                    reason TEXT DEFAULT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE,
+                    FOREIGN KEY (group_id) REFERENCES groups (id) ON DELETE SET NULL
+                );
+
+                -- History starts with the current group, reaching back to the student's first recorded activity
+                INSERT INTO group_memberships (student_id, group_id, group_name, start_date)
+                SELECT s.id, s.group_id, s.group_name,
+                       MIN(s.enrollment_date,
+                           IFNULL((SELECT MIN(date) FROM attendance WHERE student_id = s.id), s.enrollment_date),
+                           IFNULL((SELECT MIN(payment_date) FROM payment_transactions WHERE student_id = s.id), s.enrollment_date))
+                FROM students s;
+
+                -- The group a mark or payment belongs to: an additional enrollment's own group,
+                -- otherwise the student's group on that date
+                CREATE VIEW attendance_groups_view AS
+                SELECT a.id, a.student_id, a.enrollment_id, a.date,
+                       CASE WHEN e.is_primary = 0 THEN e.group_id
+                            WHEN m.id IS NOT NULL THEN m.group_id
+                            ELSE s.group_id END AS group_id,
+                       CASE WHEN e.is_primary = 0 THEN COALESCE(eg.name, e.group_name)
+                            WHEN m.id IS NOT NULL THEN COALESCE(mg.name, m.group_name)
+                            ELSE COALESCE(sg.name, s.group_name) END AS group_name
+                FROM attendance a
+                JOIN students s ON s.id = a.student_id
+                LEFT JOIN enrollments e ON e.id = a.enrollment_id
+                LEFT JOIN groups eg ON eg.id = e.group_id
+                LEFT JOIN group_memberships m ON m.id = (
+                    SELECT m2.id FROM group_memberships m2
+                    WHERE m2.student_id = a.student_id AND m2.start_date <= a.date
+                    ORDER BY m2.start_date DESC, m2.id DESC
+                    LIMIT 1
+                )
+                LEFT JOIN groups mg ON mg.id = m.group_id
+                LEFT JOIN groups sg ON sg.id = s.group_id;
+
+                CREATE VIEW payment_groups_view AS
+                SELECT p.id, p.student_id, p.enrollment_id, p.payment_date, p.amount,
+                       CASE WHEN e.is_primary = 0 THEN e.group_id
+                            WHEN m.id IS NOT NULL THEN m.group_id
+                            ELSE s.group_id END AS group_id,
+                       CASE WHEN e.is_primary = 0 THEN COALESCE(eg.name, e.group_name)
+                            WHEN m.id IS NOT NULL THEN COALESCE(mg.name, m.group_name)
+                            ELSE COALESCE(sg.name, s.group_name) END AS group_name
+                FROM payment_transactions p
+                JOIN students s ON s.id = p.student_id
+                LEFT JOIN enrollments e ON e.id = p.enrollment_id
+                LEFT JOIN groups eg ON eg.id = e.group_id
+                LEFT JOIN group_memberships m ON m.id = (
+                    SELECT m2.id FROM group_memberships m2
+                    WHERE m2.student_id = p.student_id AND m2.start_date <= p.payment_date
+                    ORDER BY m2.start_date DESC, m2.id DESC
+                    LIMIT 1
+                )
+                LEFT JOIN groups mg ON mg.id = m.group_id
+                LEFT JOIN groups sg ON sg.id = s.group_id;".to_string(),
+                down_sql: Some("DROP VIEW IF EXISTS attendance_groups_view;
+                DROP VIEW IF EXISTS payment_groups_view;
+                DROP TABLE group_memberships;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 19,
+                description: "Create terms and associate enrollments, attendance and payments with them".to_string(),
+                sql: "CREATE TABLE terms (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    start_date TEXT NOT NULL,
+                    end_date TEXT NOT NULL,
+                    is_active BOOLEAN NOT NULL DEFAULT 0,
+                    closed_at DATETIME DEFAULT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    CHECK (end_date >= start_date)
+                );
+
+                ALTER TABLE enrollments ADD COLUMN term_id INTEGER DEFAULT NULL REFERENCES terms (id) ON DELETE SET NULL;
+                ALTER TABLE attendance ADD COLUMN term_id INTEGER DEFAULT NULL REFERENCES terms (id) ON DELETE SET NULL;
+                ALTER TABLE payment_transactions ADD COLUMN term_id INTEGER DEFAULT NULL REFERENCES terms (id) ON DELETE SET NULL;
+
+                -- Balances carried from one term into the next at rollover; positive is owed, negative is credit.
+                -- paid_before is what had been paid when it was carried; those payments belong to earlier terms.
+                CREATE TABLE term_balances (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    enrollment_id INTEGER DEFAULT NULL,
+                    from_term_id INTEGER NOT NULL,
+                    to_term_id INTEGER NOT NULL,
+                    amount INTEGER NOT NULL,
+                    paid_before INTEGER NOT NULL DEFAULT 0,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE,
+                    FOREIGN KEY (enrollment_id) REFERENCES enrollments (id) ON DELETE SET NULL,
+                    FOREIGN KEY (from_term_id) REFERENCES terms (id) ON DELETE CASCADE,
+                    FOREIGN KEY (to_term_id) REFERENCES terms (id) ON DELETE CASCADE
+                );
+
+                -- New rows land in the term covering their date, whichever code path inserts them
+                CREATE TRIGGER attendance_assign_term AFTER INSERT ON attendance
+                WHEN NEW.term_id IS NULL
+                BEGIN
+                    UPDATE attendance SET term_id = (SELECT id FROM terms WHERE NEW.date BETWEEN start_date AND end_date)
+                    WHERE id = NEW.id;
+                END;
+
+                CREATE TRIGGER payment_transactions_assign_term AFTER INSERT ON payment_transactions
+                WHEN NEW.term_id IS NULL
+                BEGIN
+                    UPDATE payment_transactions SET term_id = (SELECT id FROM terms WHERE NEW.payment_date BETWEEN start_date AND end_date)
+                    WHERE id = NEW.id;
+                END;
+
+                CREATE TRIGGER enrollments_assign_term AFTER INSERT ON enrollments
+                WHEN NEW.term_id IS NULL
+                BEGIN
+                    UPDATE enrollments SET term_id = (SELECT id FROM terms WHERE NEW.start_date BETWEEN start_date AND end_date)
+                    WHERE id = NEW.id;
+                END;
+
+                CREATE TRIGGER enrollments_reassign_term AFTER UPDATE OF start_date ON enrollments
+                WHEN NEW.start_date IS NOT OLD.start_date
+                BEGIN
+                    UPDATE enrollments SET term_id = (SELECT id FROM terms WHERE NEW.start_date BETWEEN start_date AND end_date)
+                    WHERE id = NEW.id;
+                END;
+
+                DROP VIEW enrollments_view;
+                CREATE VIEW enrollments_view AS
+                SELECT e.id, e.student_id, COALESCE(g.name, e.group_name) AS group_name, e.course, e.start_date, e.end_date,
+                       e.payment_plan, e.plan_amount, e.installment_count, e.paid_amount, e.next_due_date, e.payment_status,
+                       e.status, e.is_primary, e.created_at, e.updated_at, e.group_id, e.waitlist_position, e.term_id
+                FROM enrollments e
+                LEFT JOIN groups g ON g.id = e.group_id;".to_string(),
+                down_sql: Some("DROP TRIGGER IF EXISTS attendance_assign_term;
+                DROP TRIGGER IF EXISTS payment_transactions_assign_term;
+                DROP TRIGGER IF EXISTS enrollments_assign_term;
+                DROP TRIGGER IF EXISTS enrollments_reassign_term;
+                DROP INDEX IF EXISTS idx_enrollments_term_id;
+                DROP INDEX IF EXISTS idx_attendance_term_id;
+                DROP INDEX IF EXISTS idx_payment_transactions_term_id;
+                DROP VIEW IF EXISTS enrollments_view;
+
+                ALTER TABLE enrollments DROP COLUMN term_id;
+                ALTER TABLE attendance DROP COLUMN term_id;
+                ALTER TABLE payment_transactions DROP COLUMN term_id;
+
+                CREATE VIEW enrollments_view AS
+                SELECT e.id, e.student_id, COALESCE(g.name, e.group_name) AS group_name, e.course, e.start_date, e.end_date,
+                       e.payment_plan, e.plan_amount, e.installment_count, e.paid_amount, e.next_due_date, e.payment_status,
+                       e.status, e.is_primary, e.created_at, e.updated_at, e.group_id, e.waitlist_position
+                FROM enrollments e
+                LEFT JOIN groups g ON g.id = e.group_id;
+
+                DROP TABLE term_balances;
+                DROP TABLE terms;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 20,
+                description: "Create indexes as part of the schema and drop redundant ones".to_string(),
+                sql: "-- Duplicates of primary keys, UNIQUE constraints or a longer index with the same prefix
+                DROP INDEX IF EXISTS idx_students_id;
+                DROP INDEX IF EXISTS idx_students_group;
+                DROP INDEX IF EXISTS idx_attendance_student_id;
+                DROP INDEX IF EXISTS idx_attendance_date_desc;
+                DROP INDEX IF EXISTS idx_payment_transactions_student_id;
+                DROP INDEX IF EXISTS idx_audit_log_timestamp_desc;
+                DROP INDEX IF EXISTS idx_settings_key;
+                DROP INDEX IF EXISTS idx_users_username;
+                DROP INDEX IF EXISTS idx_groups_name;
+                DROP INDEX IF EXISTS idx_migrations_version;
+                DROP INDEX IF EXISTS idx_migrations_applied_at;
+
+                CREATE INDEX IF NOT EXISTS idx_students_paid_amount ON students(paid_amount);
+                CREATE INDEX IF NOT EXISTS idx_students_name ON students(name);
+                CREATE INDEX IF NOT EXISTS idx_students_group_paid ON students(group_name, paid_amount);
+                CREATE INDEX IF NOT EXISTS idx_students_created_at ON students(created_at);
+                CREATE INDEX IF NOT EXISTS idx_students_updated_at ON students(updated_at);
+                CREATE INDEX IF NOT EXISTS idx_students_payment_plan ON students(payment_plan);
+                CREATE INDEX IF NOT EXISTS idx_students_payment_status ON students(payment_status);
+                CREATE INDEX IF NOT EXISTS idx_students_next_due_date ON students(next_due_date);
+                CREATE INDEX IF NOT EXISTS idx_students_enrollment_date ON students(enrollment_date);
+                CREATE INDEX IF NOT EXISTS idx_students_group_id ON students(group_id);
+                CREATE INDEX IF NOT EXISTS idx_attendance_date ON attendance(date);
+                CREATE INDEX IF NOT EXISTS idx_attendance_student_date ON attendance(student_id, date);
+                CREATE INDEX IF NOT EXISTS idx_attendance_created_at ON attendance(created_at);
+                CREATE INDEX IF NOT EXISTS idx_attendance_enrollment_date ON attendance(enrollment_id, date);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_payment_date ON payment_transactions(payment_date);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_student_date ON payment_transactions(student_id, payment_date DESC);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_amount ON payment_transactions(amount);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_method ON payment_transactions(payment_method);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_created_at ON payment_transactions(created_at);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_enrollment_id ON payment_transactions(enrollment_id);
+                CREATE INDEX IF NOT EXISTS idx_audit_log_table_record ON audit_log(table_name, record_id);
+                CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp);
+                CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log(user_id);
+                CREATE INDEX IF NOT EXISTS idx_audit_log_action_type ON audit_log(action_type);
+                CREATE INDEX IF NOT EXISTS idx_audit_log_table_timestamp ON audit_log(table_name, timestamp DESC);
+                CREATE INDEX IF NOT EXISTS idx_settings_updated_at ON settings(updated_at);
+                CREATE INDEX IF NOT EXISTS idx_users_role ON users(role);
+                CREATE INDEX IF NOT EXISTS idx_users_last_login ON users(last_login);
+                CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at);
+                CREATE INDEX IF NOT EXISTS idx_groups_created_at ON groups(created_at);
+                CREATE INDEX IF NOT EXISTS idx_groups_updated_at ON groups(updated_at);
+                CREATE INDEX IF NOT EXISTS idx_installment_schedules_due_date ON installment_schedules(due_date);
+                CREATE INDEX IF NOT EXISTS idx_installment_schedules_status ON installment_schedules(status);
+                CREATE INDEX IF NOT EXISTS idx_enrollments_student_id ON enrollments(student_id);
+                CREATE INDEX IF NOT EXISTS idx_enrollments_group_status ON enrollments(group_name, status);
+                CREATE INDEX IF NOT EXISTS idx_enrollments_group_id_status ON enrollments(group_id, status);
+                CREATE INDEX IF NOT EXISTS idx_enrollments_waitlist ON enrollments(group_id, waitlist_position) WHERE status = 'waitlisted';
+                CREATE INDEX IF NOT EXISTS idx_group_teachers_group_id ON group_teachers(group_id, position);
+                CREATE INDEX IF NOT EXISTS idx_group_memberships_student_start ON group_memberships(student_id, start_date);
+                CREATE INDEX IF NOT EXISTS idx_group_memberships_group_id ON group_memberships(group_id);
+                CREATE INDEX IF NOT EXISTS idx_terms_dates ON terms(start_date, end_date);
+                CREATE INDEX IF NOT EXISTS idx_enrollments_term_id ON enrollments(term_id);
+                CREATE INDEX IF NOT EXISTS idx_attendance_term_id ON attendance(term_id);
+                CREATE INDEX IF NOT EXISTS idx_payment_transactions_term_id ON payment_transactions(term_id);
+                CREATE INDEX IF NOT EXISTS idx_term_balances_student_id ON term_balances(student_id);
+                CREATE INDEX IF NOT EXISTS idx_payment_settings_updated_at ON payment_settings(updated_at)".to_string(),
+                down_sql: Some("DROP INDEX IF EXISTS idx_students_paid_amount;
+                DROP INDEX IF EXISTS idx_students_name;
+                DROP INDEX IF EXISTS idx_students_group_paid;
+                DROP INDEX IF EXISTS idx_students_created_at;
+                DROP INDEX IF EXISTS idx_students_updated_at;
+                DROP INDEX IF EXISTS idx_students_payment_plan;
+                DROP INDEX IF EXISTS idx_students_payment_status;
+                DROP INDEX IF EXISTS idx_students_next_due_date;
+                DROP INDEX IF EXISTS idx_students_enrollment_date;
+                DROP INDEX IF EXISTS idx_students_group_id;
+                DROP INDEX IF EXISTS idx_attendance_date;
+                DROP INDEX IF EXISTS idx_attendance_student_date;
+                DROP INDEX IF EXISTS idx_attendance_created_at;
+                DROP INDEX IF EXISTS idx_attendance_enrollment_date;
+                DROP INDEX IF EXISTS idx_payment_transactions_payment_date;
+                DROP INDEX IF EXISTS idx_payment_transactions_student_date;
+                DROP INDEX IF EXISTS idx_payment_transactions_amount;
+                DROP INDEX IF EXISTS idx_payment_transactions_method;
+                DROP INDEX IF EXISTS idx_payment_transactions_created_at;
+                DROP INDEX IF EXISTS idx_payment_transactions_enrollment_id;
+                DROP INDEX IF EXISTS idx_audit_log_table_record;
+                DROP INDEX IF EXISTS idx_audit_log_timestamp;
+                DROP INDEX IF EXISTS idx_audit_log_user_id;
+                DROP INDEX IF EXISTS idx_audit_log_action_type;
+                DROP INDEX IF EXISTS idx_audit_log_table_timestamp;
+                DROP INDEX IF EXISTS idx_settings_updated_at;
+                DROP INDEX IF EXISTS idx_users_role;
+                DROP INDEX IF EXISTS idx_users_last_login;
+                DROP INDEX IF EXISTS idx_users_created_at;
+                DROP INDEX IF EXISTS idx_groups_created_at;
+                DROP INDEX IF EXISTS idx_groups_updated_at;
+                DROP INDEX IF EXISTS idx_installment_schedules_due_date;
+                DROP INDEX IF EXISTS idx_installment_schedules_status;
+                DROP INDEX IF EXISTS idx_enrollments_student_id;
+                DROP INDEX IF EXISTS idx_enrollments_group_status;
+                DROP INDEX IF EXISTS idx_enrollments_group_id_status;
+                DROP INDEX IF EXISTS idx_enrollments_waitlist;
+                DROP INDEX IF EXISTS idx_group_teachers_group_id;
+                DROP INDEX IF EXISTS idx_group_memberships_student_start;
+                DROP INDEX IF EXISTS idx_group_memberships_group_id;
+                DROP INDEX IF EXISTS idx_terms_dates;
+                DROP INDEX IF EXISTS idx_enrollments_term_id;
+                DROP INDEX IF EXISTS idx_attendance_term_id;
+                DROP INDEX IF EXISTS idx_payment_transactions_term_id;
+                DROP INDEX IF EXISTS idx_term_balances_student_id;
+                DROP INDEX IF EXISTS idx_payment_settings_updated_at".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 21,
+                description: "Record a status with each attendance mark".to_string(),
+                sql: "-- Existing marks were all presences
+                ALTER TABLE attendance ADD COLUMN status TEXT NOT NULL DEFAULT 'present'
+                    CHECK (status IN ('present', 'absent', 'late', 'excused'))".to_string(),
+                down_sql: Some("DELETE FROM attendance WHERE status NOT IN ('present', 'late');
+                ALTER TABLE attendance DROP COLUMN status".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 22,
+                description: "Create student contacts, notification templates and the notification outbox".to_string(),
+                sql: "CREATE TABLE student_contacts (
+                    student_id TEXT PRIMARY KEY,
+                    phone TEXT DEFAULT NULL,
+                    whatsapp TEXT DEFAULT NULL,
+                    email TEXT DEFAULT NULL,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                CREATE TABLE notification_templates (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    kind TEXT NOT NULL CHECK (kind IN ('due_soon', 'overdue')),
+                    locale TEXT NOT NULL CHECK (locale IN ('ar', 'en')),
+                    subject TEXT NOT NULL,
+                    body TEXT NOT NULL,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    UNIQUE (kind, locale)
+                );
+
+                INSERT INTO notification_templates (kind, locale, subject, body) VALUES
+                    ('due_soon', 'ar', 'تذكير بموعد الدفع', 'مرحباً {student}، نذكركم بأن دفعة بقيمة {amount} مستحقة بتاريخ {due_date}. شكراً لكم.'),
+                    ('due_soon', 'en', 'Payment reminder', 'Hello {student}, a payment of {amount} is due on {due_date}. Thank you.'),
+                    ('overdue', 'ar', 'دفعة متأخرة', 'مرحباً {student}، لم تُسدد بعد الدفعة بقيمة {amount} التي استحقت بتاريخ {due_date}. يرجى السداد في أقرب وقت.'),
+                    ('overdue', 'en', 'Overdue payment', 'Hello {student}, the payment of {amount} that was due on {due_date} has not been received yet. Please pay at your earliest convenience.');
+
+                -- One row per message and channel; dedupe_key stops the same reminder from being queued twice
+                CREATE TABLE notification_outbox (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    channel TEXT NOT NULL CHECK (channel IN ('email', 'sms', 'whatsapp')),
+                    recipient TEXT NOT NULL,
+                    subject TEXT DEFAULT NULL,
+                    body TEXT NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'sending', 'sent', 'failed', 'cancelled')),
+                    attempts INTEGER NOT NULL DEFAULT 0,
+                    max_attempts INTEGER NOT NULL DEFAULT 5,
+                    next_attempt_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    last_error TEXT DEFAULT NULL,
+                    provider_message_id TEXT DEFAULT NULL,
+                    dedupe_key TEXT NOT NULL UNIQUE,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    sent_at DATETIME DEFAULT NULL,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX idx_notification_outbox_status_next ON notification_outbox(status, next_attempt_at);
+                CREATE INDEX idx_notification_outbox_student_id ON notification_outbox(student_id)".to_string(),
+                down_sql: Some("DROP INDEX IF EXISTS idx_notification_outbox_status_next;
+                DROP INDEX IF EXISTS idx_notification_outbox_student_id;
+                DROP TABLE notification_outbox;
+                DROP TABLE notification_templates;
+                DROP TABLE student_contacts;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 23,
+                description: "Create dunning state, dunning actions and payment promises".to_string(),
+                sql: "-- Current escalation level of each overdue student; removed once they are no longer overdue
+                CREATE TABLE dunning_states (
+                    student_id TEXT PRIMARY KEY,
+                    level TEXT NOT NULL CHECK (level IN ('reminder', 'call', 'suspension_review')),
+                    due_date TEXT NOT NULL,
+                    level_since DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                CREATE TABLE dunning_actions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    action TEXT NOT NULL CHECK (action IN ('reminder', 'call_task', 'suspension_review', 'promise', 'promise_kept', 'promise_broken', 'note', 'resolved')),
+                    level TEXT DEFAULT NULL,
+                    due_date TEXT DEFAULT NULL,
+                    note TEXT DEFAULT NULL,
+                    created_by TEXT DEFAULT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                CREATE TABLE payment_promises (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    promised_date TEXT NOT NULL,
+                    amount INTEGER DEFAULT NULL CHECK (amount IS NULL OR amount > 0),
+                    note TEXT DEFAULT NULL,
+                    status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'kept', 'broken', 'superseded')),
+                    created_by TEXT DEFAULT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    resolved_at DATETIME DEFAULT NULL,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                CREATE UNIQUE INDEX idx_payment_promises_open ON payment_promises(student_id) WHERE status = 'open';
+                CREATE INDEX idx_dunning_actions_student_id ON dunning_actions(student_id, created_at)".to_string(),
+                down_sql: Some("DROP INDEX IF EXISTS idx_payment_promises_open;
+                DROP INDEX IF EXISTS idx_dunning_actions_student_id;
+                DROP TABLE payment_promises;
+                DROP TABLE dunning_actions;
+                DROP TABLE dunning_states;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 24,
+                description: "Record payment plan changes".to_string(),
+                sql: "CREATE TABLE plan_changes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    student_id TEXT NOT NULL,
+                    old_plan TEXT NOT NULL,
+                    old_amount INTEGER NOT NULL,
+                    old_installment_count INTEGER DEFAULT NULL,
+                    new_plan TEXT NOT NULL,
+                    new_amount INTEGER NOT NULL,
+                    new_installment_count INTEGER DEFAULT NULL,
+                    changed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (student_id) REFERENCES students (id) ON DELETE CASCADE
+                );
+
+                CREATE INDEX idx_plan_changes_changed_at ON plan_changes(changed_at);
+
+                -- Recorded whichever code path changes the plan, and whether or not auditing is on
+                CREATE TRIGGER students_record_plan_change AFTER UPDATE OF payment_plan, plan_amount, installment_count ON students
+                WHEN OLD.payment_plan IS NOT NEW.payment_plan
+                  OR OLD.plan_amount IS NOT NEW.plan_amount
+                  OR OLD.installment_count IS NOT NEW.installment_count
+                BEGIN
+                    INSERT INTO plan_changes (student_id, old_plan, old_amount, old_installment_count, new_plan, new_amount, new_installment_count)
+                    VALUES (OLD.id, OLD.payment_plan, OLD.plan_amount, OLD.installment_count, NEW.payment_plan, NEW.plan_amount, NEW.installment_count);
+                END;
+
+                -- Earlier changes are only known from the audit log, where it was kept
+                INSERT INTO plan_changes (student_id, old_plan, old_amount, old_installment_count, new_plan, new_amount, new_installment_count, changed_at)
+                SELECT a.record_id,
+                       json_extract(a.old_values, '$.payment_plan'), json_extract(a.old_values, '$.plan_amount'),
+                       json_extract(a.old_values, '$.installment_count'),
+                       json_extract(a.new_values, '$.payment_plan'), json_extract(a.new_values, '$.plan_amount'),
+                       json_extract(a.new_values, '$.installment_count'),
+                       a.timestamp
+                FROM audit_log a
+                JOIN students s ON s.id = a.record_id
+                WHERE a.table_name = 'students' AND a.action_type = 'UPDATE'
+                  AND json_valid(a.old_values) AND json_valid(a.new_values)
+                  AND json_extract(a.old_values, '$.payment_plan') IS NOT NULL
+                  AND json_extract(a.old_values, '$.plan_amount') IS NOT NULL
+                  AND json_extract(a.new_values, '$.payment_plan') IS NOT NULL
+                  AND json_extract(a.new_values, '$.plan_amount') IS NOT NULL
+                  AND (json_extract(a.old_values, '$.payment_plan') IS NOT json_extract(a.new_values, '$.payment_plan')
+                    OR json_extract(a.old_values, '$.plan_amount') IS NOT json_extract(a.new_values, '$.plan_amount')
+                    OR json_extract(a.old_values, '$.installment_count') IS NOT json_extract(a.new_values, '$.installment_count'))
+                ORDER BY a.timestamp, a.id".to_string(),
+                down_sql: Some("DROP TRIGGER IF EXISTS students_record_plan_change;
+                DROP INDEX IF EXISTS idx_plan_changes_changed_at;
+                DROP TABLE plan_changes;".to_string()),
+                applied_at: None,
+            },
+            Migration {
+                version: 25,
+                description: "Chain audit log entries by hash".to_string(),
+                sql: "-- Hash of the entry before, and of this entry's contents together with it; NULL on entries written before chaining
+                ALTER TABLE audit_log ADD COLUMN prev_hash TEXT DEFAULT NULL;
+                ALTER TABLE audit_log ADD COLUMN entry_hash TEXT DEFAULT NULL".to_string(),
+                down_sql: Some("ALTER TABLE audit_log DROP COLUMN entry_hash;
+                ALTER TABLE audit_log DROP COLUMN prev_hash;".to_string()),
                 applied_at: None,
             },
         ]
@@ -274,13 +1104,13 @@ impl Database {
         // Start transaction
         let tx = self.connection.unchecked_transaction()?;
         
-        // Execute the migration SQL
-        tx.execute(&migration.sql, [])?;
+        // Execute the migration SQL (may contain several statements or only comments)
+        tx.execute_batch(&migration.sql)?;
         
         // Record the migration
         tx.execute(
-            "INSERT INTO migrations (version, description) VALUES (?1, ?2)",
-            params![migration.version, migration.description],
+            "INSERT INTO migrations (version, description, checksum) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.description, migration.checksum()],
         )?;
         
         // Commit transaction
@@ -290,87 +1120,6 @@ impl Database {
         Ok(())
     }
     
-    /// Create performance indexes optimized for expected query patterns
-    fn create_indexes(&self) -> DatabaseResult<()> {
-        let indexes = vec![
-            // Students table indexes - optimized for common query patterns
-            "CREATE INDEX IF NOT EXISTS idx_students_id ON students(id)",  // Primary key lookup optimization
-            "CREATE INDEX IF NOT EXISTS idx_students_group ON students(group_name)",  // Group filtering
-            "CREATE INDEX IF NOT EXISTS idx_students_paid_amount ON students(paid_amount)",  // Payment status queries
-            "CREATE INDEX IF NOT EXISTS idx_students_name ON students(name)",  // Name searches
-            "CREATE INDEX IF NOT EXISTS idx_students_group_paid ON students(group_name, paid_amount)",  // Combined filtering
-            "CREATE INDEX IF NOT EXISTS idx_students_created_at ON students(created_at)",  // Date-based queries
-            "CREATE INDEX IF NOT EXISTS idx_students_updated_at ON students(updated_at)",  // Recently modified queries
-            "CREATE INDEX IF NOT EXISTS idx_students_payment_plan ON students(payment_plan)",  // Payment plan filtering
-            "CREATE INDEX IF NOT EXISTS idx_students_payment_status ON students(payment_status)",  // Payment status filtering
-            "CREATE INDEX IF NOT EXISTS idx_students_next_due_date ON students(next_due_date)",  // Due date queries
-            "CREATE INDEX IF NOT EXISTS idx_students_enrollment_date ON students(enrollment_date)",  // Enrollment tracking
-            
-            // Attendance table indexes - optimized for attendance tracking
-            "CREATE INDEX IF NOT EXISTS idx_attendance_student_id ON attendance(student_id)",  // Student lookup
-            "CREATE INDEX IF NOT EXISTS idx_attendance_date ON attendance(date)",  // Date-based queries
-            "CREATE INDEX IF NOT EXISTS idx_attendance_student_date ON attendance(student_id, date)",  // Duplicate prevention
-            "CREATE INDEX IF NOT EXISTS idx_attendance_date_desc ON attendance(date DESC)",  // Recent attendance first
-            "CREATE INDEX IF NOT EXISTS idx_attendance_created_at ON attendance(created_at)",  // Chronological queries
-            
-            // Payment transactions table indexes - optimized for payment tracking
-            "CREATE INDEX IF NOT EXISTS idx_payment_transactions_student_id ON payment_transactions(student_id)",  // Student payment history
-            "CREATE INDEX IF NOT EXISTS idx_payment_transactions_payment_date ON payment_transactions(payment_date)",  // Date-based queries
-            "CREATE INDEX IF NOT EXISTS idx_payment_transactions_student_date ON payment_transactions(student_id, payment_date DESC)",  // Recent payments first
-            "CREATE INDEX IF NOT EXISTS idx_payment_transactions_amount ON payment_transactions(amount)",  // Amount-based queries
-            "CREATE INDEX IF NOT EXISTS idx_payment_transactions_method ON payment_transactions(payment_method)",  // Payment method filtering
-            "CREATE INDEX IF NOT EXISTS idx_payment_transactions_created_at ON payment_transactions(created_at)",  // Transaction chronology
-            
-            // Audit log indexes - optimized for change tracking and reporting
-            "CREATE INDEX IF NOT EXISTS idx_audit_log_table_record ON audit_log(table_name, record_id)",  // Record history
-            "CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp)",  // Time-based queries
-            "CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp_desc ON audit_log(timestamp DESC)",  // Recent changes first
-            "CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log(user_id)",  // User activity tracking
-            "CREATE INDEX IF NOT EXISTS idx_audit_log_action_type ON audit_log(action_type)",  // Action filtering
-            "CREATE INDEX IF NOT EXISTS idx_audit_log_table_timestamp ON audit_log(table_name, timestamp DESC)",  // Table-specific history
-            
-            // Settings table index - optimized for configuration lookups
-            "CREATE INDEX IF NOT EXISTS idx_settings_key ON settings(key)",  // Key-based lookups (already unique)
-            "CREATE INDEX IF NOT EXISTS idx_settings_updated_at ON settings(updated_at)",  // Recently changed settings
-            
-            // Users table indexes - optimized for authentication and role management
-            "CREATE INDEX IF NOT EXISTS idx_users_username ON users(username)",  // Login lookups (already unique)
-            "CREATE INDEX IF NOT EXISTS idx_users_role ON users(role)",  // Role-based queries
-            "CREATE INDEX IF NOT EXISTS idx_users_last_login ON users(last_login)",  // Activity tracking
-            "CREATE INDEX IF NOT EXISTS idx_users_created_at ON users(created_at)",  // User registration tracking
-            
-            // Groups table indexes - optimized for group management
-            "CREATE INDEX IF NOT EXISTS idx_groups_name ON groups(name)",  // Group name lookups (already unique)
-            "CREATE INDEX IF NOT EXISTS idx_groups_created_at ON groups(created_at)",  // Creation date queries
-            "CREATE INDEX IF NOT EXISTS idx_groups_updated_at ON groups(updated_at)",  // Recently modified groups
-            
-            // Payment settings table indexes - optimized for settings management
-            "CREATE INDEX IF NOT EXISTS idx_payment_settings_updated_at ON payment_settings(updated_at)",  // Recently modified settings
-            
-            // Migrations table index - for migration management
-            "CREATE INDEX IF NOT EXISTS idx_migrations_version ON migrations(version)",  // Version lookups
-            "CREATE INDEX IF NOT EXISTS idx_migrations_applied_at ON migrations(applied_at)",  // Migration history
-        ];
-        
-        for index_sql in &indexes {
-            match self.connection.execute(index_sql, []) {
-                Ok(_) => {
-                    log::debug!("Created index: {}", index_sql);
-                }
-                Err(e) => {
-                    log::error!("Failed to create index: {} - Error: {}", index_sql, e);
-                    return Err(DatabaseError::Sqlite(e));
-                }
-            }
-        }
-        
-        // Analyze tables to update statistics for query optimizer
-        self.analyze_tables()?;
-        
-        log::info!("Successfully created {} database indexes with performance optimization", indexes.len());
-        Ok(())
-    }
-    
     /// Analyze tables to update SQLite query optimizer statistics
     fn analyze_tables(&self) -> DatabaseResult<()> {
         let tables = vec![
@@ -382,6 +1131,13 @@ impl Database {
             "users",
             "groups",
             "payment_settings",
+            "installment_schedules",
+            "enrollments",
+            "group_teachers",
+            "group_memberships",
+            "terms",
+            "term_balances",
+            "plan_changes",
             "migrations"
         ];
         
@@ -404,10 +1160,61 @@ impl Database {
         Ok(())
     }
     
+    /// How long opening and migrating the database took
+    pub fn startup_report(&self) -> &StartupReport {
+        &self.startup
+    }
+    
+    /// Refresh query planner statistics; meant to run while the app is idle rather than at startup.
+    /// Does a bounded full ANALYZE when statistics are missing or predate a migration, otherwise
+    /// leaves it to `PRAGMA optimize` to analyze only the tables that need it.
+    pub fn run_idle_maintenance(&mut self) -> DatabaseResult<()> {
+        let has_stats: bool = self.connection.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'sqlite_stat1'",
+            [],
+            |row| row.get::<_, i32>(0).map(|count| count > 0),
+        )?;
+        
+        let started = Instant::now();
+        self.connection.pragma_update(None, "analysis_limit", ANALYSIS_LIMIT)?;
+        if !has_stats || self.stats_stale {
+            self.connection.execute_batch("ANALYZE")?;
+            self.stats_stale = false;
+        } else {
+            self.connection.execute_batch("PRAGMA optimize")?;
+        }
+        
+        log::info!("Idle database maintenance took {} ms", started.elapsed().as_millis());
+        Ok(())
+    }
+    
+    /// How long nothing has been written on this connection. Writes are noticed when this is
+    /// called, so poll it to find a quiet moment.
+    pub fn idle_for(&mut self) -> DatabaseResult<Duration> {
+        let total_changes: i64 = self
+            .connection
+            .query_row("SELECT total_changes()", [], |row| row.get(0))?;
+        if total_changes != self.total_changes {
+            self.total_changes = total_changes;
+            self.last_change = Instant::now();
+        }
+        Ok(self.last_change.elapsed())
+    }
+    
     /// Get a reference to the database connection
     pub fn connection(&self) -> &Connection {
         &self.connection
     }
+
+    /// Start a transaction that can nest inside another. It is a SQLite savepoint, so an inner
+    /// one only becomes permanent once the outermost commits.
+    pub fn savepoint(&self) -> DatabaseResult<Savepoint<'_>> {
+        self.connection.execute_batch("SAVEPOINT nested")?;
+        Ok(Savepoint {
+            connection: &self.connection,
+            finished: false,
+        })
+    }
     
     /// Get the database file path
     pub fn db_path(&self) -> &PathBuf {
@@ -491,7 +1298,7 @@ impl Database {
     /// Get migration history
     pub fn get_migration_history(&self) -> DatabaseResult<Vec<AppliedMigration>> {
         let mut stmt = self.connection.prepare(
-            "SELECT version, description, applied_at FROM migrations ORDER BY version"
+            "SELECT version, description, applied_at, checksum FROM migrations ORDER BY version"
         )?;
         
         let migration_iter = stmt.query_map([], |row| {
@@ -499,6 +1306,7 @@ impl Database {
                 version: row.get(0)?,
                 description: row.get(1)?,
                 applied_at: row.get(2)?,
+                checksum: row.get(3)?,
             })
         })?;
         
@@ -518,26 +1326,30 @@ impl Database {
         Ok(pending)
     }
     
-    /// Validate migration integrity
+    /// Validate migration integrity: applied migrations must match their definitions,
+    /// and no defined migration below the current version may be missing
     pub fn validate_migrations(&self) -> DatabaseResult<MigrationValidation> {
         let applied_migrations = self.get_migration_history()?;
         let all_migrations = self.get_migrations();
+        let current_version = applied_migrations.iter().map(|m| m.version).max().unwrap_or(0);
         
         let mut validation = MigrationValidation {
             is_valid: true,
             issues: Vec::new(),
             applied_count: applied_migrations.len(),
             total_count: all_migrations.len(),
+            edited: Vec::new(),
+            missing: Vec::new(),
+            unknown: Vec::new(),
         };
         
-        // Check for gaps in migration versions
-        for (i, applied) in applied_migrations.iter().enumerate() {
-            let expected_version = i as i32 + 1;
-            if applied.version != expected_version {
-                validation.is_valid = false;
+        // Defined migrations that were skipped
+        for defined in all_migrations.iter().filter(|m| m.version < current_version) {
+            if !applied_migrations.iter().any(|a| a.version == defined.version) {
+                validation.missing.push(defined.version);
                 validation.issues.push(format!(
-                    "Migration version gap detected: expected {}, found {}",
-                    expected_version, applied.version
+                    "Migration {} ('{}') was never applied",
+                    defined.version, defined.description
                 ));
             }
         }
@@ -546,14 +1358,20 @@ impl Database {
         for applied in &applied_migrations {
             if let Some(defined) = all_migrations.iter().find(|m| m.version == applied.version) {
                 if defined.description != applied.description {
-                    validation.is_valid = false;
                     validation.issues.push(format!(
                         "Migration {} description mismatch: applied='{}', defined='{}'",
                         applied.version, applied.description, defined.description
                     ));
                 }
+                if applied.checksum.as_deref() != Some(defined.checksum().as_str()) {
+                    validation.edited.push(applied.version);
+                    validation.issues.push(format!(
+                        "Migration {} SQL was edited after it was applied",
+                        applied.version
+                    ));
+                }
             } else {
-                validation.is_valid = false;
+                validation.unknown.push(applied.version);
                 validation.issues.push(format!(
                     "Applied migration {} not found in defined migrations",
                     applied.version
@@ -561,6 +1379,7 @@ impl Database {
             }
         }
         
+        validation.is_valid = validation.issues.is_empty();
         Ok(validation)
     }
     
@@ -574,39 +1393,59 @@ impl Database {
         Ok(count > 0)
     }
 
-    /// Force apply a specific migration (use with caution)
-    pub fn force_apply_migration(&self, version: i32) -> DatabaseResult<()> {
+    /// Make sure `version` is the next migration to apply and that the history is consistent;
+    /// anything else would leave the migrations table out of step with the schema
+    fn ensure_next_migration(&self, version: i32) -> DatabaseResult<Migration> {
         if self.is_migration_applied(version)? {
             return Err(DatabaseError::Migration(format!(
                 "Migration {} is already applied", version
             )));
         }
-
-        let migrations = self.get_migrations();
-        if let Some(migration) = migrations.iter().find(|m| m.version == version) {
-            self.apply_migration(migration)?;
-            log::warn!("Force applied migration {}: {}", version, migration.description);
-        } else {
+        
+        let current_version = self.get_current_version()?;
+        if version != current_version + 1 {
             return Err(DatabaseError::Migration(format!(
-                "Migration {} not found", version
+                "Migration {} is not the next migration - current version is {}",
+                version, current_version
             )));
         }
         
+        let validation = self.validate_migrations()?;
+        if !validation.is_valid {
+            return Err(DatabaseError::Migration(format!(
+                "Migration history is inconsistent: {}",
+                validation.issues.join("; ")
+            )));
+        }
+        
+        self.get_migrations()
+            .into_iter()
+            .find(|m| m.version == version)
+            .ok_or_else(|| DatabaseError::Migration(format!("Migration {} not found", version)))
+    }
+
+    /// Apply the next pending migration now instead of at the next start (use with caution)
+    pub fn force_apply_migration(&self, version: i32) -> DatabaseResult<()> {
+        let migration = self.ensure_next_migration(version)?;
+        self.apply_migration(&migration)?;
+        log::warn!("Force applied migration {}: {}", version, migration.description);
         Ok(())
     }
     
-    /// Mark a migration as applied without executing it (use with extreme caution)
+    /// Mark the next pending migration as applied without executing it (use with extreme caution).
+    /// `description` must match the defined migration, as a guard against marking the wrong one.
     pub fn mark_migration_applied(&self, version: i32, description: String) -> DatabaseResult<()> {
-        // Check if already applied
-        if self.is_migration_applied(version)? {
+        let migration = self.ensure_next_migration(version)?;
+        if migration.description != description {
             return Err(DatabaseError::Migration(format!(
-                "Migration {} is already marked as applied", version
+                "Migration {} is '{}', not '{}'",
+                version, migration.description, description
             )));
         }
         
         self.connection.execute(
-            "INSERT INTO migrations (version, description) VALUES (?1, ?2)",
-            params![version, description],
+            "INSERT INTO migrations (version, description, checksum) VALUES (?1, ?2, ?3)",
+            params![version, migration.description, migration.checksum()],
         )?;
         
         log::warn!("Marked migration {} as applied without execution: {}", version, description);
@@ -640,23 +1479,137 @@ impl Database {
         }
         
         let migrations_to_rollback: Vec<i32> = ((target_version + 1)..=current_version).collect();
+        let missing_down_scripts = self.missing_down_scripts(&migrations_to_rollback);
+        
+        let warning = if missing_down_scripts.is_empty() {
+            "Data stored only by the rolled back migrations (their tables and columns) is discarded.".to_string()
+        } else {
+            format!(
+                "Migrations {:?} have no down script; rollback_to is not available for this target.",
+                missing_down_scripts
+            )
+        };
         
         let rollback_info = RollbackInfo {
             current_version,
             target_version,
             migrations_to_rollback: migrations_to_rollback.clone(),
-            warning: "SQLite does not support automatic migration rollbacks. Manual intervention required.".to_string(),
+            warning,
             instructions: vec![
-                "1. Create a backup of your current database".to_string(),
-                "2. Manually reverse the changes made by each migration".to_string(),
-                format!("3. Migrations to reverse (in order): {:?}", migrations_to_rollback),
-                "4. Update the migrations table to reflect the new version".to_string(),
-                "5. Verify data integrity after manual rollback".to_string(),
+                "1. A backup of the current database is written to the backups folder".to_string(),
+                format!("2. Down scripts run newest first in a single transaction: {:?}", migrations_to_rollback.iter().rev().collect::<Vec<_>>()),
+                format!("3. The migrations table is updated to version {}", target_version),
+                "4. Install the release matching the target version before reopening the app; this release would apply the migrations again".to_string(),
             ],
         };
         
         Ok(rollback_info)
     }
+    
+    /// Versions among `versions` whose migration has no down script
+    fn missing_down_scripts(&self, versions: &[i32]) -> Vec<i32> {
+        let migrations = self.get_migrations();
+        versions
+            .iter()
+            .copied()
+            .filter(|version| {
+                !migrations
+                    .iter()
+                    .any(|m| m.version == *version && m.down_sql.is_some())
+            })
+            .collect()
+    }
+    
+    /// Roll the schema back to `target_version`. A backup is written first, then the down scripts
+    /// of every later migration run newest first in one transaction.
+    pub fn rollback_to(&self, target_version: i32) -> DatabaseResult<RollbackResult> {
+        let current_version = self.get_current_version()?;
+        
+        if target_version < 0 || target_version >= current_version {
+            return Err(DatabaseError::Migration(format!(
+                "Cannot rollback to version {} - current version is {}",
+                target_version, current_version
+            )));
+        }
+        
+        let applied: Vec<i32> = {
+            let mut stmt = self.connection.prepare(
+                "SELECT version FROM migrations WHERE version > ?1 ORDER BY version DESC"
+            )?;
+            let version_iter = stmt.query_map([target_version], |row| row.get(0))?;
+            version_iter.collect::<Result<Vec<_>, _>>()?
+        };
+        
+        let missing = self.missing_down_scripts(&applied);
+        if !missing.is_empty() {
+            return Err(DatabaseError::Migration(format!(
+                "Migrations {:?} have no down script and cannot be rolled back automatically",
+                missing
+            )));
+        }
+        
+        let backup_path = self
+            .db_path
+            .parent()
+            .map(|dir| dir.join("backups"))
+            .unwrap_or_default()
+            .join(format!(
+                "pre_rollback_v{}_to_v{}_{}.db",
+                current_version,
+                target_version,
+                Utc::now().format("%Y%m%d_%H%M%S")
+            ));
+        self.backup_to_file(&backup_path)?;
+        
+        // Foreign keys can only be switched outside a transaction, and table rebuilds need them off
+        self.connection.execute_batch("PRAGMA foreign_keys = OFF")?;
+        let result = self.run_down_scripts(&applied);
+        self.connection.execute_batch("PRAGMA foreign_keys = ON")?;
+        result?;
+        
+        log::warn!(
+            "Rolled back database from version {} to {}; backup at {:?}",
+            current_version, target_version, backup_path
+        );
+        
+        Ok(RollbackResult {
+            from_version: current_version,
+            to_version: target_version,
+            rolled_back: applied,
+            backup_path: backup_path.to_string_lossy().to_string(),
+        })
+    }
+    
+    /// Run the down scripts of `versions` in order, in one transaction
+    fn run_down_scripts(&self, versions: &[i32]) -> DatabaseResult<()> {
+        let migrations = self.get_migrations();
+        let tx = self.connection.unchecked_transaction()?;
+        
+        for version in versions {
+            let down_sql = migrations
+                .iter()
+                .find(|m| m.version == *version)
+                .and_then(|m| m.down_sql.as_deref())
+                .unwrap_or_default();
+            
+            log::info!("Rolling back migration {}", version);
+            tx.execute_batch(down_sql).map_err(|e| {
+                DatabaseError::Migration(format!("Rolling back migration {} failed: {}", version, e))
+            })?;
+            tx.execute("DELETE FROM migrations WHERE version = ?1", [version])?;
+        }
+        
+        let violations: i32 = tx.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))?;
+        if violations > 0 {
+            return Err(DatabaseError::Migration(format!(
+                "Rollback left {} foreign key violations; nothing was changed",
+                violations
+            )));
+        }
+        
+        tx.commit()?;
+        Ok(())
+    }
 
     /// Get information about database indexes for performance monitoring
     pub fn get_index_info(&self) -> DatabaseResult<Vec<IndexInfo>> {
@@ -754,6 +1707,192 @@ impl Database {
     }
 }
 
+/// A nestable transaction from [`Database::savepoint`]; dropping it without `commit` rolls it back
+pub struct Savepoint<'a> {
+    connection: &'a Connection,
+    finished: bool,
+}
+
+impl Savepoint<'_> {
+    pub fn commit(mut self) -> DatabaseResult<()> {
+        self.connection.execute_batch("RELEASE nested")?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for Savepoint<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.connection
+    }
+}
+
+impl Drop for Savepoint<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self
+                .connection
+                .execute_batch("ROLLBACK TO nested; RELEASE nested");
+        }
+    }
+}
+
+/// Opens reader connections for [`DatabasePool`]
+pub struct DatabaseConnectionManager {
+    db_path: PathBuf,
+}
+
+impl r2d2::ManageConnection for DatabaseConnectionManager {
+    type Connection = Database;
+    type Error = DatabaseError;
+
+    fn connect(&self) -> Result<Database, DatabaseError> {
+        let db = Database::open_existing(&self.db_path)?;
+        // Writes belong on the writer; refuse them here rather than let them race it
+        db.connection.pragma_update(None, "query_only", true)?;
+        Ok(db)
+    }
+
+    fn is_valid(&self, db: &mut Database) -> Result<(), DatabaseError> {
+        db.connection.execute_batch("SELECT 1")?;
+        Ok(())
+    }
+
+    fn has_broken(&self, _db: &mut Database) -> bool {
+        false
+    }
+}
+
+pub type ReaderPool = r2d2::Pool<DatabaseConnectionManager>;
+pub type PooledDatabase = r2d2::PooledConnection<DatabaseConnectionManager>;
+
+/// One writer connection behind a lock plus a pool of reader connections. Writes are serialized
+/// through the writer; reads borrow a pooled connection and, thanks to WAL, never wait on writes.
+pub struct DatabasePool {
+    writer: Mutex<Database>,
+    /// Dropped after a schema rollback so no reader stays on the old schema
+    readers: Mutex<Option<ReaderPool>>,
+    /// Set after a schema rollback; every later request is refused until the app restarts
+    closed: AtomicBool,
+    startup: StartupReport,
+}
+
+impl DatabasePool {
+    /// Open the database, apply pending migrations on the writer, then start the reader pool
+    pub fn new(app_data_dir: PathBuf, max_readers: u32) -> DatabaseResult<Self> {
+        Self::from_writer(Database::new(app_data_dir)?, max_readers)
+    }
+
+    /// Build the pool around an already opened and migrated writer
+    pub fn from_writer(writer: Database, max_readers: u32) -> DatabaseResult<Self> {
+        let readers = r2d2::Pool::builder()
+            .max_size(max_readers.max(1))
+            .min_idle(Some(1))
+            .connection_timeout(Duration::from_secs(10))
+            .build(DatabaseConnectionManager {
+                db_path: writer.db_path.clone(),
+            })
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+
+        Ok(DatabasePool {
+            startup: writer.startup_report().clone(),
+            writer: Mutex::new(writer),
+            readers: Mutex::new(Some(readers)),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    /// Lock the writer connection; use it for anything that modifies data
+    pub fn writer(&self) -> DatabaseResult<MutexGuard<'_, Database>> {
+        self.ensure_open()?;
+        let writer = self
+            .writer
+            .lock()
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?;
+        // A rollback may have finished while we waited for the lock
+        self.ensure_open()?;
+        Ok(writer)
+    }
+
+    /// Borrow a reader connection from the pool
+    pub fn reader(&self) -> DatabaseResult<PooledDatabase> {
+        self.readers()?
+            .get()
+            .map_err(|e| DatabaseError::Connection(e.to_string()))
+    }
+
+    /// A handle on the reader pool that can be moved onto another thread
+    pub fn readers(&self) -> DatabaseResult<ReaderPool> {
+        self.ensure_open()?;
+        self.readers
+            .lock()
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?
+            .clone()
+            .ok_or_else(Self::closed_error)
+    }
+
+    /// Run [`Database::run_idle_maintenance`] on the writer once nothing has been written for
+    /// `quiet_period`. Never waits for the writer, since a busy writer means the app is not idle.
+    /// Returns whether maintenance ran.
+    pub fn run_idle_maintenance(&self, quiet_period: Duration) -> DatabaseResult<bool> {
+        self.ensure_open()?;
+        let mut writer = match self.writer.try_lock() {
+            Ok(writer) => writer,
+            Err(TryLockError::WouldBlock) => return Ok(false),
+            Err(TryLockError::Poisoned(e)) => {
+                return Err(DatabaseError::Connection(e.to_string()))
+            }
+        };
+        if writer.idle_for()? < quiet_period {
+            return Ok(false);
+        }
+        writer.run_idle_maintenance()?;
+        Ok(true)
+    }
+
+    /// Whether a schema rollback closed the pool
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+
+    /// Roll the schema back with [`Database::rollback_to`], then close the pool. The running
+    /// release expects the newer schema, so nothing may use the database until the app restarts.
+    pub fn rollback_to(&self, target_version: i32) -> DatabaseResult<RollbackResult> {
+        let writer = self.writer()?;
+        let result = writer.rollback_to(target_version)?;
+
+        self.closed.store(true, Ordering::SeqCst);
+        // Idle readers close now; borrowed ones close when they are returned
+        self.readers
+            .lock()
+            .map_err(|e| DatabaseError::Connection(e.to_string()))?
+            .take();
+        log::warn!("Database closed after the schema rollback; restart the app to use it again");
+
+        Ok(result)
+    }
+
+    fn ensure_open(&self) -> DatabaseResult<()> {
+        if self.is_closed() {
+            return Err(Self::closed_error());
+        }
+        Ok(())
+    }
+
+    fn closed_error() -> DatabaseError {
+        DatabaseError::Connection(
+            "The database schema was rolled back; restart the app to continue".to_string(),
+        )
+    }
+
+    /// Startup timings of the writer connection
+    pub fn startup_report(&self) -> &StartupReport {
+        &self.startup
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DatabaseStats {
     pub students_count: i32,
@@ -762,11 +1901,21 @@ pub struct DatabaseStats {
     pub database_size_bytes: u64,
 }
 
+/// Time spent opening the database at startup, in milliseconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StartupReport {
+    pub open_ms: u64,
+    pub migrations_ms: u64,
+    pub migrations_applied: usize,
+    pub total_ms: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppliedMigration {
     pub version: i32,
     pub description: String,
     pub applied_at: String,
+    pub checksum: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -775,6 +1924,12 @@ pub struct MigrationValidation {
     pub issues: Vec<String>,
     pub applied_count: usize,
     pub total_count: usize,
+    /// Applied migrations whose SQL changed since
+    pub edited: Vec<i32>,
+    /// Defined migrations skipped below the current version
+    pub missing: Vec<i32>,
+    /// Applied migrations that are no longer defined
+    pub unknown: Vec<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -809,6 +1964,14 @@ pub struct RollbackInfo {
     pub instructions: Vec<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RollbackResult {
+    pub from_version: i32,
+    pub to_version: i32,
+    pub rolled_back: Vec<i32>,
+    pub backup_path: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -820,12 +1983,96 @@ mod tests {
         (db, temp_dir)
     }
     
+    fn latest_version(db: &Database) -> i32 {
+        db.get_migrations().iter().map(|m| m.version).max().unwrap_or(0)
+    }
+    
     #[test]
     fn test_database_creation() {
         let (db, _temp_dir) = create_test_db();
         assert!(db.health_check().unwrap());
     }
     
+    #[test]
+    fn test_pool_readers_do_not_wait_for_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let pool = DatabasePool::new(temp_dir.path().to_path_buf(), 2).unwrap();
+        
+        let journal_mode: String = pool.reader().unwrap().connection()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode.to_lowercase(), "wal");
+        
+        let writer = pool.writer().unwrap();
+        writer.connection().execute_batch(
+            "BEGIN IMMEDIATE; INSERT INTO settings (key, value) VALUES ('pool_test', '1');"
+        ).unwrap();
+        
+        // A reader on another thread sees the last committed state while the write is open
+        let readers = pool.readers().unwrap();
+        let uncommitted = std::thread::spawn(move || {
+            let db = readers.get().unwrap();
+            db.connection()
+                .query_row("SELECT COUNT(*) FROM settings WHERE key = 'pool_test'", [], |row| row.get::<_, i32>(0))
+                .unwrap()
+        })
+        .join()
+        .unwrap();
+        assert_eq!(uncommitted, 0);
+        
+        writer.connection().execute_batch("COMMIT").unwrap();
+        drop(writer);
+        
+        let committed: i32 = pool.reader().unwrap().connection()
+            .query_row("SELECT COUNT(*) FROM settings WHERE key = 'pool_test'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(committed, 1);
+        
+        // Readers refuse writes so they can't go around the writer
+        assert!(pool.reader().unwrap().connection()
+            .execute("INSERT INTO settings (key, value) VALUES ('pool_reader', '1')", [])
+            .is_err());
+    }
+    
+    #[test]
+    fn test_idle_maintenance_waits_for_a_quiet_writer() {
+        let temp_dir = TempDir::new().unwrap();
+        let pool = DatabasePool::new(temp_dir.path().to_path_buf(), 1).unwrap();
+        
+        // Opening counts as activity
+        assert!(!pool.run_idle_maintenance(Duration::from_secs(60)).unwrap());
+        
+        {
+            let writer = pool.writer().unwrap();
+            writer.connection().execute(
+                "INSERT INTO settings (key, value) VALUES ('idle_test', '1')", [],
+            ).unwrap();
+            // A busy writer is not waited for
+            assert!(!pool.run_idle_maintenance(Duration::ZERO).unwrap());
+        }
+        
+        assert!(!pool.run_idle_maintenance(Duration::from_secs(60)).unwrap());
+        assert!(pool.run_idle_maintenance(Duration::ZERO).unwrap());
+    }
+    
+    #[test]
+    fn test_pool_closes_after_rollback() {
+        let temp_dir = TempDir::new().unwrap();
+        let pool = DatabasePool::new(temp_dir.path().to_path_buf(), 2).unwrap();
+        let latest = latest_version(&pool.writer().unwrap());
+        let reader = pool.reader().unwrap();
+        
+        pool.rollback_to(latest - 1).unwrap();
+        assert!(pool.is_closed());
+        assert!(pool.reader().is_err());
+        assert!(pool.readers().is_err());
+        assert!(pool.writer().is_err());
+        assert!(pool.rollback_to(0).is_err());
+        
+        // A reader borrowed before the rollback is closed when it is returned
+        drop(reader);
+    }
+    
     #[test]
     fn test_migrations_applied() {
         let (db, _temp_dir) = create_test_db();
@@ -883,8 +2130,8 @@ mod tests {
         let (db, _temp_dir) = create_test_db();
         let history = db.get_migration_history().unwrap();
         
-        // Should have all 7 migrations applied
-        assert_eq!(history.len(), 7);
+        // Should have all defined migrations applied
+        assert_eq!(history.len(), db.get_migrations().len());
         
         // Check that versions are sequential
         for (i, migration) in history.iter().enumerate() {
@@ -908,17 +2155,56 @@ mod tests {
         
         assert!(validation.is_valid);
         assert_eq!(validation.issues.len(), 0);
-        assert_eq!(validation.applied_count, 7);
-        assert_eq!(validation.total_count, 7);
+        assert_eq!(validation.applied_count, db.get_migrations().len());
+        assert_eq!(validation.total_count, db.get_migrations().len());
+    }
+    
+    #[test]
+    fn test_migration_checksums_detect_drift() {
+        let (db, temp_dir) = create_test_db();
+        let history = db.get_migration_history().unwrap();
+        assert!(history.iter().all(|m| m.checksum.is_some()));
+        
+        // Simulate migration 2 having been edited after it ran, and 3 being skipped
+        db.connection().execute("UPDATE migrations SET checksum = 'stale' WHERE version = 2", []).unwrap();
+        db.connection().execute("DELETE FROM migrations WHERE version = 3", []).unwrap();
+        db.connection().execute("INSERT INTO migrations (version, description, checksum) VALUES (999, 'From the future', 'x')", []).unwrap();
+        
+        let validation = db.validate_migrations().unwrap();
+        assert!(!validation.is_valid);
+        assert_eq!(validation.edited, vec![2]);
+        assert_eq!(validation.missing, vec![3]);
+        assert_eq!(validation.unknown, vec![999]);
+        
+        // Advanced operations refuse to build on an inconsistent history
+        assert!(db.force_apply_migration(3).is_err());
+        assert!(db.mark_migration_applied(3, "Create settings table".to_string()).is_err());
+        
+        db.connection().execute("DELETE FROM migrations WHERE version = 999", []).unwrap();
+        drop(db);
+        
+        assert!(Database::new(temp_dir.path().to_path_buf()).is_err());
+        assert!(Database::open(temp_dir.path().to_path_buf(), true).is_ok());
+    }
+    
+    #[test]
+    fn test_missing_checksums_are_backfilled() {
+        let (db, temp_dir) = create_test_db();
+        db.connection().execute("UPDATE migrations SET checksum = NULL", []).unwrap();
+        drop(db);
+        
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        assert!(db.validate_migrations().unwrap().is_valid);
     }
     
     #[test]
     fn test_schema_info() {
         let (db, _temp_dir) = create_test_db();
         let schema_info = db.get_schema_info().unwrap();
+        let latest = latest_version(&db);
         
-        assert_eq!(schema_info.current_version, 7);
-        assert_eq!(schema_info.latest_version, 7);
+        assert_eq!(schema_info.current_version, latest);
+        assert_eq!(schema_info.latest_version, latest);
         assert_eq!(schema_info.pending_migrations, 0);
         assert!(schema_info.is_up_to_date);
     }
@@ -928,7 +2214,7 @@ mod tests {
         let (db, _temp_dir) = create_test_db();
         let all_migrations = db.get_migrations();
         
-        assert_eq!(all_migrations.len(), 7);
+        assert_eq!(all_migrations.len() as i32, latest_version(&db));
         
         // Check that versions are sequential starting from 1
         for (i, migration) in all_migrations.iter().enumerate() {
@@ -943,28 +2229,74 @@ mod tests {
         let (db, _temp_dir) = create_test_db();
         
         // Test valid rollback info
+        let latest = latest_version(&db);
         let rollback_info = db.get_rollback_info(3).unwrap();
-        assert_eq!(rollback_info.current_version, 7);
+        assert_eq!(rollback_info.current_version, latest);
         assert_eq!(rollback_info.target_version, 3);
-        assert_eq!(rollback_info.migrations_to_rollback, vec![4, 5, 6, 7]);
-        assert!(rollback_info.warning.contains("SQLite does not support"));
+        assert_eq!(rollback_info.migrations_to_rollback, (4..=latest).collect::<Vec<_>>());
+        assert!(rollback_info.warning.contains("discarded"));
         assert!(!rollback_info.instructions.is_empty());
         
         // Test invalid rollback (target >= current)
-        let result = db.get_rollback_info(7);
+        let result = db.get_rollback_info(latest);
         assert!(result.is_err());
         
-        let result = db.get_rollback_info(8);
+        let result = db.get_rollback_info(latest + 1);
         assert!(result.is_err());
     }
     
+    #[test]
+    fn test_rollback_to_and_reapply() {
+        let (db, temp_dir) = create_test_db();
+        let latest = latest_version(&db);
+        
+        db.connection().execute(
+            "INSERT INTO students (id, name, group_name, enrollment_date) VALUES ('STU1', 'Kept', 'Group A', '2024-01-01')",
+            [],
+        ).unwrap();
+        db.connection().execute(
+            "INSERT INTO attendance (student_id, date) VALUES ('STU1', '2024-01-02')",
+            [],
+        ).unwrap();
+        
+        let result = db.rollback_to(11).unwrap();
+        assert_eq!(result.from_version, latest);
+        assert_eq!(result.rolled_back, (12..=latest).rev().collect::<Vec<_>>());
+        assert!(std::path::Path::new(&result.backup_path).exists());
+        assert_eq!(db.get_current_version().unwrap(), 11);
+        
+        let enrollments_table: i32 = db.connection().query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'enrollments'", [], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(enrollments_table, 0);
+        let attendance: i32 = db.connection().query_row(
+            "SELECT COUNT(*) FROM attendance WHERE student_id = 'STU1'", [], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(attendance, 1);
+        drop(db);
+        
+        // Reopening applies the rolled back migrations again
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(db.get_current_version().unwrap(), latest);
+        
+        // Every migration can be reversed
+        db.rollback_to(0).unwrap();
+        let tables: i32 = db.connection().query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type IN ('table', 'view') AND name != 'migrations' AND name NOT LIKE 'sqlite_%'",
+            [], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(tables, 0);
+        
+        assert!(db.rollback_to(0).is_err());
+    }
+    
     #[test]
     fn test_current_version() {
         let (db, _temp_dir) = create_test_db();
         let version = db.get_current_version().unwrap();
         
-        // Should be at the latest version (7)
-        assert_eq!(version, 7);
+        // Should be at the latest version
+        assert_eq!(version, latest_version(&db));
     }
     
     #[test]
@@ -979,19 +2311,58 @@ mod tests {
         let index_names: Vec<&String> = indexes.iter().map(|idx| &idx.name).collect();
         
         // Students table indexes
-        assert!(index_names.contains(&&"idx_students_id".to_string()));
-        assert!(index_names.contains(&&"idx_students_group".to_string()));
         assert!(index_names.contains(&&"idx_students_paid_amount".to_string()));
         assert!(index_names.contains(&&"idx_students_group_paid".to_string()));
         
         // Attendance table indexes
-        assert!(index_names.contains(&&"idx_attendance_student_id".to_string()));
         assert!(index_names.contains(&&"idx_attendance_date".to_string()));
         assert!(index_names.contains(&&"idx_attendance_student_date".to_string()));
         
         // Audit log indexes
         assert!(index_names.contains(&&"idx_audit_log_table_record".to_string()));
         assert!(index_names.contains(&&"idx_audit_log_timestamp".to_string()));
+        
+        // Redundant with a primary key or a longer index
+        assert!(!index_names.contains(&&"idx_students_id".to_string()));
+        assert!(!index_names.contains(&&"idx_students_group".to_string()));
+        assert!(!index_names.contains(&&"idx_attendance_student_id".to_string()));
+    }
+    
+    #[test]
+    fn test_startup_cost_is_bounded() {
+        let (db, temp_dir) = create_test_db();
+        assert!(db.startup_report().migrations_applied > 0);
+        let schema_version = |db: &Database| -> i32 {
+            db.connection().query_row("PRAGMA schema_version", [], |row| row.get(0)).unwrap()
+        };
+        
+        // 500 students with 100 days of attendance each
+        db.connection().execute_batch(
+            "WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 499)
+             INSERT INTO students (id, name, group_name, enrollment_date)
+             SELECT 'STU' || i, 'Student ' || i, 'Group A', '2024-01-01' FROM n;
+             WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 49999)
+             INSERT INTO attendance (student_id, date)
+             SELECT 'STU' || (i % 500), date('2024-01-01', '+' || (i / 500) || ' days') FROM n;",
+        ).unwrap();
+        let created_schema = schema_version(&db);
+        drop(db);
+        
+        // Reopening applies no migration and creates or drops no index
+        let mut db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        assert_eq!(db.startup_report().migrations_applied, 0);
+        assert_eq!(schema_version(&db), created_schema);
+        
+        // Statistics are gathered by idle maintenance, not at startup
+        let has_stats = |db: &Database| -> i32 {
+            db.connection().query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'sqlite_stat1'", [], |row| row.get(0),
+            ).unwrap()
+        };
+        assert_eq!(has_stats(&db), 0);
+        db.run_idle_maintenance().unwrap();
+        assert_eq!(has_stats(&db), 1);
+        db.run_idle_maintenance().unwrap();
     }
     
     #[test]
@@ -1031,4 +2402,35 @@ mod tests {
         let result = db.analyze_tables();
         assert!(result.is_ok(), "Table analysis should succeed: {:?}", result);
     }
+    
+    #[test]
+    fn test_savepoints_nest() {
+        let (db, _temp_dir) = create_test_db();
+        let count = |db: &Database| -> i32 {
+            db.connection()
+                .query_row("SELECT COUNT(*) FROM settings WHERE key LIKE 'nested_%'", [], |row| row.get(0))
+                .unwrap()
+        };
+        
+        // An inner commit is undone when the outer savepoint rolls back
+        {
+            let outer = db.savepoint().unwrap();
+            outer.execute("INSERT INTO settings (key, value) VALUES ('nested_outer', '1')", []).unwrap();
+            let inner = db.savepoint().unwrap();
+            inner.execute("INSERT INTO settings (key, value) VALUES ('nested_inner', '1')", []).unwrap();
+            inner.commit().unwrap();
+        }
+        assert_eq!(count(&db), 0);
+        
+        // An inner rollback leaves the outer change in place
+        let outer = db.savepoint().unwrap();
+        outer.execute("INSERT INTO settings (key, value) VALUES ('nested_outer', '1')", []).unwrap();
+        {
+            let inner = db.savepoint().unwrap();
+            inner.execute("INSERT INTO settings (key, value) VALUES ('nested_inner', '1')", []).unwrap();
+        }
+        outer.commit().unwrap();
+        assert_eq!(count(&db), 1);
+        assert!(db.connection().is_autocommit());
+    }
 }
\ No newline at end of file
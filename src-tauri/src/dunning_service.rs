@@ -0,0 +1,884 @@
+use crate::audit_service::{AuditService, AuditTrail};
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::notification_service::NotificationService;
+use crate::student_service::{PaymentPlan, PaymentStatus, Student, StudentService};
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Days past the due date at which each level starts
+const REMINDER_AFTER_DAYS: i64 = 1;
+const CALL_AFTER_DAYS: i64 = 10;
+const SUSPENSION_REVIEW_AFTER_DAYS: i64 = 30;
+
+/// One-time plans have no due date; like `calculate_payment_status`, they fall due this long
+/// after enrollment
+const ONE_TIME_DUE_AFTER_DAYS: i64 = 30;
+
+const ACTION_COLUMNS: &str =
+    "id, student_id, action, level, due_date, note, created_by, created_at";
+
+const PROMISE_COLUMNS: &str =
+    "id, student_id, promised_date, amount, note, status, created_by, created_at, resolved_at";
+
+/// How far chasing an overdue student has gone, from least to most serious
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DunningLevel {
+    /// Day 1: send a reminder
+    Reminder,
+    /// Day 10: phone the student or their guardian
+    Call,
+    /// Day 30: decide whether to suspend the student
+    SuspensionReview,
+}
+
+impl DunningLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DunningLevel::Reminder => "reminder",
+            DunningLevel::Call => "call",
+            DunningLevel::SuspensionReview => "suspension_review",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "reminder" => Ok(DunningLevel::Reminder),
+            "call" => Ok(DunningLevel::Call),
+            "suspension_review" => Ok(DunningLevel::SuspensionReview),
+            _ => Err(format!("Invalid dunning level: {}", s)),
+        }
+    }
+
+    /// The level reached this many days past the due date, if any
+    pub fn for_days_overdue(days: i64) -> Option<Self> {
+        if days >= SUSPENSION_REVIEW_AFTER_DAYS {
+            Some(DunningLevel::SuspensionReview)
+        } else if days >= CALL_AFTER_DAYS {
+            Some(DunningLevel::Call)
+        } else if days >= REMINDER_AFTER_DAYS {
+            Some(DunningLevel::Reminder)
+        } else {
+            None
+        }
+    }
+
+    /// The task staff get on reaching this level
+    pub fn action(&self) -> DunningActionKind {
+        match self {
+            DunningLevel::Reminder => DunningActionKind::Reminder,
+            DunningLevel::Call => DunningActionKind::CallTask,
+            DunningLevel::SuspensionReview => DunningActionKind::SuspensionReview,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DunningActionKind {
+    Reminder,
+    CallTask,
+    SuspensionReview,
+    Promise,
+    PromiseKept,
+    PromiseBroken,
+    /// Free text from staff, e.g. the outcome of a call
+    Note,
+    /// The student is no longer overdue
+    Resolved,
+}
+
+impl DunningActionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DunningActionKind::Reminder => "reminder",
+            DunningActionKind::CallTask => "call_task",
+            DunningActionKind::SuspensionReview => "suspension_review",
+            DunningActionKind::Promise => "promise",
+            DunningActionKind::PromiseKept => "promise_kept",
+            DunningActionKind::PromiseBroken => "promise_broken",
+            DunningActionKind::Note => "note",
+            DunningActionKind::Resolved => "resolved",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "reminder" => Ok(DunningActionKind::Reminder),
+            "call_task" => Ok(DunningActionKind::CallTask),
+            "suspension_review" => Ok(DunningActionKind::SuspensionReview),
+            "promise" => Ok(DunningActionKind::Promise),
+            "promise_kept" => Ok(DunningActionKind::PromiseKept),
+            "promise_broken" => Ok(DunningActionKind::PromiseBroken),
+            "note" => Ok(DunningActionKind::Note),
+            "resolved" => Ok(DunningActionKind::Resolved),
+            _ => Err(format!("Invalid dunning action: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromiseStatus {
+    /// Escalation waits until the promised date
+    Open,
+    /// The student stopped being overdue
+    Kept,
+    /// The promised date passed while still overdue
+    Broken,
+    /// Replaced by a newer promise
+    Superseded,
+}
+
+impl PromiseStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PromiseStatus::Open => "open",
+            PromiseStatus::Kept => "kept",
+            PromiseStatus::Broken => "broken",
+            PromiseStatus::Superseded => "superseded",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "open" => Ok(PromiseStatus::Open),
+            "kept" => Ok(PromiseStatus::Kept),
+            "broken" => Ok(PromiseStatus::Broken),
+            "superseded" => Ok(PromiseStatus::Superseded),
+            _ => Err(format!("Invalid promise status: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningAction {
+    pub id: i64,
+    pub student_id: String,
+    pub action: DunningActionKind,
+    /// Level the student was at when the action was logged
+    pub level: Option<DunningLevel>,
+    /// Due date the escalation counts from
+    pub due_date: Option<String>,
+    pub note: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentPromise {
+    pub id: i64,
+    pub student_id: String,
+    pub promised_date: String,
+    pub amount: Option<i32>,
+    pub note: Option<String>,
+    pub status: PromiseStatus,
+    pub created_by: Option<String>,
+    pub created_at: String,
+    pub resolved_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordPromiseRequest {
+    pub student_id: String,
+    pub promised_date: String,
+    pub amount: Option<i32>,
+    pub note: Option<String>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DunningRunSummary {
+    /// Students who reached a new level and got its task
+    pub escalated: usize,
+    /// Students no longer overdue whose escalation was cleared
+    pub resolved: usize,
+    pub promises_kept: usize,
+    pub promises_broken: usize,
+    /// Overdue students waiting on a promise
+    pub paused: usize,
+}
+
+/// One overdue student on the daily chase list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DunningWorklistEntry {
+    pub student_id: String,
+    pub student_name: String,
+    pub group_name: String,
+    pub due_date: Option<String>,
+    pub days_overdue: i64,
+    pub amount_due: i32,
+    /// `None` until the first level is reached
+    pub level: Option<DunningLevel>,
+    pub level_since: Option<String>,
+    pub promise: Option<PaymentPromise>,
+    /// An open promise is holding escalation back
+    pub paused: bool,
+    pub last_action: Option<DunningAction>,
+}
+
+struct DunningState {
+    level: DunningLevel,
+    due_date: String,
+    level_since: String,
+}
+
+pub struct DunningService;
+
+impl DunningService {
+    fn parse_error(index: usize, e: String) -> rusqlite::Error {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+    }
+
+    fn map_row_to_action(row: &rusqlite::Row) -> rusqlite::Result<DunningAction> {
+        Ok(DunningAction {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            action: DunningActionKind::from_str(&row.get::<_, String>(2)?)
+                .map_err(|e| Self::parse_error(2, e))?,
+            level: row
+                .get::<_, Option<String>>(3)?
+                .map(|level| DunningLevel::from_str(&level))
+                .transpose()
+                .map_err(|e| Self::parse_error(3, e))?,
+            due_date: row.get(4)?,
+            note: row.get(5)?,
+            created_by: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+
+    fn map_row_to_promise(row: &rusqlite::Row) -> rusqlite::Result<PaymentPromise> {
+        Ok(PaymentPromise {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            promised_date: row.get(2)?,
+            amount: row.get(3)?,
+            note: row.get(4)?,
+            status: PromiseStatus::from_str(&row.get::<_, String>(5)?)
+                .map_err(|e| Self::parse_error(5, e))?,
+            created_by: row.get(6)?,
+            created_at: row.get(7)?,
+            resolved_at: row.get(8)?,
+        })
+    }
+
+    /// The date a student's overdue days count from: the next due date, or for a one-time plan
+    /// without one, the end of its grace period after enrollment
+    fn overdue_since(student: &Student) -> Option<NaiveDate> {
+        if let Some(due_date) = &student.next_due_date {
+            return NaiveDate::parse_from_str(due_date, "%Y-%m-%d").ok();
+        }
+        match student.payment_plan {
+            PaymentPlan::OneTime => NaiveDate::parse_from_str(&student.enrollment_date, "%Y-%m-%d")
+                .ok()
+                .map(|enrolled| enrolled + Duration::days(ONE_TIME_DUE_AFTER_DAYS)),
+            _ => None,
+        }
+    }
+
+    fn log(
+        db: &Database,
+        student_id: &str,
+        action: DunningActionKind,
+        level: Option<DunningLevel>,
+        due_date: Option<&str>,
+        note: Option<&str>,
+        created_by: Option<&str>,
+    ) -> DatabaseResult<DunningAction> {
+        let action = db.connection().query_row(
+            &format!(
+                "INSERT INTO dunning_actions (student_id, action, level, due_date, note, created_by)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 RETURNING {}",
+                ACTION_COLUMNS
+            ),
+            params![
+                student_id,
+                action.as_str(),
+                level.map(|l| l.as_str()),
+                due_date,
+                note,
+                created_by
+            ],
+            Self::map_row_to_action,
+        )?;
+        Ok(action)
+    }
+
+    fn get_state(db: &Database, student_id: &str) -> DatabaseResult<Option<DunningState>> {
+        db.connection()
+            .query_row(
+                "SELECT level, due_date, level_since FROM dunning_states WHERE student_id = ?1",
+                params![student_id],
+                |row| {
+                    Ok(DunningState {
+                        level: DunningLevel::from_str(&row.get::<_, String>(0)?)
+                            .map_err(|e| Self::parse_error(0, e))?,
+                        due_date: row.get(1)?,
+                        level_since: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(DatabaseError::from)
+    }
+
+    pub fn get_open_promise(
+        db: &Database,
+        student_id: &str,
+    ) -> DatabaseResult<Option<PaymentPromise>> {
+        db.connection()
+            .query_row(
+                &format!(
+                    "SELECT {} FROM payment_promises WHERE student_id = ?1 AND status = 'open'",
+                    PROMISE_COLUMNS
+                ),
+                params![student_id],
+                Self::map_row_to_promise,
+            )
+            .optional()
+            .map_err(DatabaseError::from)
+    }
+
+    fn resolve_promise(
+        db: &Database,
+        audit: &mut AuditTrail,
+        promise: &PaymentPromise,
+        status: PromiseStatus,
+    ) -> DatabaseResult<()> {
+        let resolved = db.connection().query_row(
+            &format!(
+                "UPDATE payment_promises SET status = ?1, resolved_at = CURRENT_TIMESTAMP
+                 WHERE id = ?2
+                 RETURNING {}",
+                PROMISE_COLUMNS
+            ),
+            params![status.as_str(), promise.id],
+            Self::map_row_to_promise,
+        )?;
+        audit.update(
+            "payment_promises",
+            &promise.id.to_string(),
+            promise,
+            &resolved,
+            None,
+        );
+        Ok(())
+    }
+
+    /// Bring every student's level up to date for `today`. Levels only go up while the due date
+    /// stays the same; an open promise holds a student at their level until the promised date
+    /// has passed, and a student who is no longer overdue is cleared. Payment statuses should
+    /// be refreshed first. Promises kept or broken are audited with the run.
+    pub fn run_escalation(db: &Database, today: NaiveDate) -> DatabaseResult<DunningRunSummary> {
+        let summary = AuditService::audited(db, |audit| {
+            let mut summary = DunningRunSummary::default();
+
+            let overdue = StudentService::get_overdue_students(db)?;
+            let overdue_ids: HashSet<&str> = overdue.iter().map(|s| s.id.as_str()).collect();
+
+            // Students who caught up keep their promise and leave the escalation
+            let mut stmt = db.connection().prepare(&format!(
+                "SELECT {} FROM payment_promises WHERE status = 'open'",
+                PROMISE_COLUMNS
+            ))?;
+            let open_promises = stmt
+                .query_map([], Self::map_row_to_promise)?
+                .collect::<Result<Vec<_>, _>>()?;
+            for promise in open_promises {
+                if !overdue_ids.contains(promise.student_id.as_str()) {
+                    Self::resolve_promise(db, audit, &promise, PromiseStatus::Kept)?;
+                    Self::log(
+                        db,
+                        &promise.student_id,
+                        DunningActionKind::PromiseKept,
+                        None,
+                        None,
+                        None,
+                        None,
+                    )?;
+                    summary.promises_kept += 1;
+                }
+            }
+
+            let mut stmt = db
+                .connection()
+                .prepare("SELECT student_id, level, due_date FROM dunning_states")?;
+            let states = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (student_id, level, due_date) in states {
+                if !overdue_ids.contains(student_id.as_str()) {
+                    db.connection().execute(
+                        "DELETE FROM dunning_states WHERE student_id = ?1",
+                        params![student_id],
+                    )?;
+                    let level = DunningLevel::from_str(&level).ok();
+                    Self::log(
+                        db,
+                        &student_id,
+                        DunningActionKind::Resolved,
+                        level,
+                        Some(&due_date),
+                        None,
+                        None,
+                    )?;
+                    summary.resolved += 1;
+                }
+            }
+
+            let today_str = today.format("%Y-%m-%d").to_string();
+            for student in &overdue {
+                let Some(overdue_since) = Self::overdue_since(student) else {
+                    continue;
+                };
+                let due_date = overdue_since.format("%Y-%m-%d").to_string();
+
+                if let Some(promise) = Self::get_open_promise(db, &student.id)? {
+                    if promise.promised_date >= today_str {
+                        summary.paused += 1;
+                        continue;
+                    }
+                    Self::resolve_promise(db, audit, &promise, PromiseStatus::Broken)?;
+                    Self::log(
+                        db,
+                        &student.id,
+                        DunningActionKind::PromiseBroken,
+                        None,
+                        Some(&due_date),
+                        None,
+                        None,
+                    )?;
+                    summary.promises_broken += 1;
+                }
+
+                let Some(level) =
+                    DunningLevel::for_days_overdue((today - overdue_since).num_days())
+                else {
+                    continue;
+                };
+                // A new due date starts a new escalation, even at a lower level
+                if let Some(state) = Self::get_state(db, &student.id)? {
+                    if state.due_date == due_date && state.level >= level {
+                        continue;
+                    }
+                }
+
+                db.connection().execute(
+                    "INSERT INTO dunning_states (student_id, level, due_date, level_since, updated_at)
+                     VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)
+                     ON CONFLICT (student_id) DO UPDATE SET
+                        level = excluded.level, due_date = excluded.due_date,
+                        level_since = excluded.level_since, updated_at = excluded.updated_at",
+                    params![student.id, level.as_str(), due_date],
+                )?;
+                Self::log(
+                    db,
+                    &student.id,
+                    level.action(),
+                    Some(level),
+                    Some(&due_date),
+                    None,
+                    None,
+                )?;
+                summary.escalated += 1;
+            }
+
+            Ok(summary)
+        })?;
+
+        log::info!(
+            "Dunning run: {} escalated, {} resolved, {} promises kept, {} broken, {} paused",
+            summary.escalated,
+            summary.resolved,
+            summary.promises_kept,
+            summary.promises_broken,
+            summary.paused
+        );
+        Ok(summary)
+    }
+
+    /// Record that a student promised to pay by a date. Escalation pauses until then; an
+    /// earlier open promise is superseded.
+    pub fn record_promise(
+        db: &Database,
+        request: RecordPromiseRequest,
+        today: NaiveDate,
+    ) -> DatabaseResult<PaymentPromise> {
+        let student = StudentService::get_student_by_id(db, &request.student_id)?
+            .ok_or_else(|| DatabaseError::not_found("Student", &request.student_id))?;
+        if student.payment_status == PaymentStatus::Paid {
+            return Err(DatabaseError::forbidden(
+                "student_paid",
+                format!("{} has nothing left to pay", student.name),
+            ));
+        }
+
+        let promised_date =
+            NaiveDate::parse_from_str(&request.promised_date, "%Y-%m-%d").map_err(|_| {
+                DatabaseError::validation(
+                    "promised_date",
+                    format!(
+                        "Invalid date format '{}'. Expected format: YYYY-MM-DD",
+                        request.promised_date
+                    ),
+                )
+            })?;
+        if promised_date < today {
+            return Err(DatabaseError::validation(
+                "promised_date",
+                "The promised date cannot be in the past",
+            ));
+        }
+        if request.amount.is_some_and(|amount| amount <= 0) {
+            return Err(DatabaseError::validation(
+                "amount",
+                "Promised amount must be positive",
+            ));
+        }
+        let note = request
+            .note
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty());
+
+        AuditService::audited(db, |audit| {
+            if let Some(previous) = Self::get_open_promise(db, &student.id)? {
+                Self::resolve_promise(db, audit, &previous, PromiseStatus::Superseded)?;
+            }
+            let promise = db.connection().query_row(
+                &format!(
+                    "INSERT INTO payment_promises (student_id, promised_date, amount, note, created_by)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     RETURNING {}",
+                    PROMISE_COLUMNS
+                ),
+                params![
+                    student.id,
+                    request.promised_date,
+                    request.amount,
+                    note,
+                    request.created_by
+                ],
+                Self::map_row_to_promise,
+            )?;
+
+            let level = Self::get_state(db, &student.id)?.map(|state| state.level);
+            let due_date = Self::overdue_since(&student).map(|d| d.format("%Y-%m-%d").to_string());
+            Self::log(
+                db,
+                &student.id,
+                DunningActionKind::Promise,
+                level,
+                due_date.as_deref(),
+                Some(&format!("Promised to pay by {}", promise.promised_date)),
+                promise.created_by.as_deref(),
+            )?;
+
+            audit.create(
+                "payment_promises",
+                &promise.id.to_string(),
+                &promise,
+                promise.created_by.as_deref(),
+            );
+
+            Ok(promise)
+        })
+    }
+
+    /// Log something staff did or learned while chasing a student
+    pub fn add_note(
+        db: &Database,
+        student_id: &str,
+        note: &str,
+        created_by: Option<&str>,
+    ) -> DatabaseResult<DunningAction> {
+        if StudentService::get_student_by_id(db, student_id)?.is_none() {
+            return Err(DatabaseError::not_found("Student", student_id));
+        }
+        let note = note.trim();
+        if note.is_empty() {
+            return Err(DatabaseError::validation("note", "Note cannot be empty"));
+        }
+
+        let state = Self::get_state(db, student_id)?;
+        Self::log(
+            db,
+            student_id,
+            DunningActionKind::Note,
+            state.as_ref().map(|s| s.level),
+            state.as_ref().map(|s| s.due_date.as_str()),
+            Some(note),
+            created_by,
+        )
+    }
+
+    /// Everything logged for a student, newest first
+    pub fn get_history(db: &Database, student_id: &str) -> DatabaseResult<Vec<DunningAction>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM dunning_actions WHERE student_id = ?1 ORDER BY created_at DESC, id DESC",
+            ACTION_COLUMNS
+        ))?;
+        let actions = stmt
+            .query_map(params![student_id], Self::map_row_to_action)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(actions)
+    }
+
+    /// Overdue students, highest level first and then longest overdue, with any promise and the
+    /// last thing logged for each. Run `run_escalation` first for today's levels.
+    pub fn get_worklist(
+        db: &Database,
+        today: NaiveDate,
+    ) -> DatabaseResult<Vec<DunningWorklistEntry>> {
+        let today_str = today.format("%Y-%m-%d").to_string();
+        let mut entries = Vec::new();
+
+        for student in StudentService::get_overdue_students(db)? {
+            let overdue_since = Self::overdue_since(&student);
+            let state = Self::get_state(db, &student.id)?;
+            let promise = Self::get_open_promise(db, &student.id)?;
+            let last_action = Self::get_history(db, &student.id)?.into_iter().next();
+
+            entries.push(DunningWorklistEntry {
+                amount_due: NotificationService::amount_due(db, &student)?,
+                due_date: overdue_since.map(|d| d.format("%Y-%m-%d").to_string()),
+                days_overdue: overdue_since.map_or(0, |d| (today - d).num_days().max(0)),
+                level: state.as_ref().map(|s| s.level),
+                level_since: state.map(|s| s.level_since),
+                paused: promise
+                    .as_ref()
+                    .is_some_and(|p| p.promised_date >= today_str),
+                promise,
+                last_action,
+                student_id: student.id,
+                student_name: student.name,
+                group_name: student.group_name,
+            });
+        }
+
+        entries.sort_by(|a, b| {
+            b.level
+                .cmp(&a.level)
+                .then(b.days_overdue.cmp(&a.days_overdue))
+                .then_with(|| a.student_name.cmp(&b.student_name))
+        });
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::student_service::CreateStudentRequest;
+    use tempfile::TempDir;
+
+    fn setup_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_overdue_student(db: &Database, name: &str, due_date: &str) -> Student {
+        let student = StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: name.to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::Monthly,
+                plan_amount: 850,
+                installment_count: None,
+                paid_amount: None,
+                enrollment_date: Some("2024-01-01".to_string()),
+            },
+        )
+        .unwrap();
+        db.connection()
+            .execute(
+                "UPDATE students SET payment_status = 'overdue', next_due_date = ?1 WHERE id = ?2",
+                params![due_date, student.id],
+            )
+            .unwrap();
+        StudentService::get_student_by_id(db, &student.id)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn actions(db: &Database, student_id: &str) -> Vec<DunningActionKind> {
+        let mut actions: Vec<_> = DunningService::get_history(db, student_id)
+            .unwrap()
+            .into_iter()
+            .map(|a| a.action)
+            .collect();
+        actions.reverse();
+        actions
+    }
+
+    #[test]
+    fn test_levels_follow_days_overdue() {
+        assert_eq!(DunningLevel::for_days_overdue(0), None);
+        assert_eq!(
+            DunningLevel::for_days_overdue(1),
+            Some(DunningLevel::Reminder)
+        );
+        assert_eq!(
+            DunningLevel::for_days_overdue(9),
+            Some(DunningLevel::Reminder)
+        );
+        assert_eq!(DunningLevel::for_days_overdue(10), Some(DunningLevel::Call));
+        assert_eq!(
+            DunningLevel::for_days_overdue(30),
+            Some(DunningLevel::SuspensionReview)
+        );
+
+        let (db, _temp_dir) = setup_test_db();
+        let student = create_overdue_student(&db, "Sara", "2024-03-01");
+
+        for (today, escalated) in [
+            ("2024-03-01", 0),
+            ("2024-03-02", 1),
+            ("2024-03-05", 0),
+            ("2024-03-11", 1),
+            ("2024-03-31", 1),
+            ("2024-04-15", 0),
+        ] {
+            let summary = DunningService::run_escalation(&db, date(today)).unwrap();
+            assert_eq!(summary.escalated, escalated, "on {}", today);
+        }
+        assert_eq!(
+            actions(&db, &student.id),
+            vec![
+                DunningActionKind::Reminder,
+                DunningActionKind::CallTask,
+                DunningActionKind::SuspensionReview
+            ]
+        );
+
+        // Paying clears the escalation
+        db.connection()
+            .execute(
+                "UPDATE students SET payment_status = 'paid' WHERE id = ?1",
+                params![student.id],
+            )
+            .unwrap();
+        let summary = DunningService::run_escalation(&db, date("2024-04-16")).unwrap();
+        assert_eq!(summary.resolved, 1);
+        assert!(DunningService::get_state(&db, &student.id)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_promise_pauses_escalation_until_broken() {
+        let (db, _temp_dir) = setup_test_db();
+        let student = create_overdue_student(&db, "Omar", "2024-03-01");
+        DunningService::run_escalation(&db, date("2024-03-02")).unwrap();
+
+        let request = |promised_date: &str| RecordPromiseRequest {
+            student_id: student.id.clone(),
+            promised_date: promised_date.to_string(),
+            amount: Some(850),
+            note: Some("Salary comes on the 20th".to_string()),
+            created_by: None,
+        };
+        assert!(
+            DunningService::record_promise(&db, request("2024-03-01"), date("2024-03-05")).is_err()
+        );
+        let first =
+            DunningService::record_promise(&db, request("2024-03-15"), date("2024-03-05")).unwrap();
+        let promise =
+            DunningService::record_promise(&db, request("2024-03-20"), date("2024-03-05")).unwrap();
+        let first_status: String = db
+            .connection()
+            .query_row(
+                "SELECT status FROM payment_promises WHERE id = ?1",
+                params![first.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(first_status, "superseded");
+
+        // Past day 10, but the promise holds the level at reminder
+        let summary = DunningService::run_escalation(&db, date("2024-03-20")).unwrap();
+        assert_eq!(summary.paused, 1);
+        assert_eq!(summary.escalated, 0);
+        let worklist = DunningService::get_worklist(&db, date("2024-03-20")).unwrap();
+        assert_eq!(worklist[0].level, Some(DunningLevel::Reminder));
+        assert!(worklist[0].paused);
+        assert_eq!(worklist[0].promise.as_ref().unwrap().id, promise.id);
+
+        let summary = DunningService::run_escalation(&db, date("2024-03-21")).unwrap();
+        assert_eq!(summary.promises_broken, 1);
+        assert_eq!(summary.escalated, 1);
+        let audited =
+            AuditService::get_record_history(&db, "payment_promises", &promise.id.to_string())
+                .unwrap();
+        assert!(audited.iter().any(|entry| entry.action_type == "UPDATE"
+            && entry
+                .new_values
+                .as_deref()
+                .is_some_and(|values| values.contains("\"broken\""))));
+        assert_eq!(
+            actions(&db, &student.id),
+            vec![
+                DunningActionKind::Reminder,
+                DunningActionKind::Promise,
+                DunningActionKind::Promise,
+                DunningActionKind::PromiseBroken,
+                DunningActionKind::CallTask
+            ]
+        );
+
+        // A promise that is kept is closed once the student is no longer overdue
+        DunningService::record_promise(&db, request("2024-03-25"), date("2024-03-21")).unwrap();
+        db.connection()
+            .execute(
+                "UPDATE students SET payment_status = 'pending' WHERE id = ?1",
+                params![student.id],
+            )
+            .unwrap();
+        let summary = DunningService::run_escalation(&db, date("2024-03-22")).unwrap();
+        assert_eq!(summary.promises_kept, 1);
+        assert_eq!(summary.resolved, 1);
+        assert!(DunningService::get_open_promise(&db, &student.id)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_worklist_sorts_by_level() {
+        let (db, _temp_dir) = setup_test_db();
+        create_overdue_student(&db, "Recent", "2024-03-28");
+        let oldest = create_overdue_student(&db, "Oldest", "2024-02-01");
+        create_overdue_student(&db, "Middle", "2024-03-15");
+        DunningService::run_escalation(&db, date("2024-04-01")).unwrap();
+
+        DunningService::add_note(&db, &oldest.id, "  Called, no answer ", Some("secretary"))
+            .unwrap();
+        assert!(DunningService::add_note(&db, &oldest.id, " ", None).is_err());
+
+        let worklist = DunningService::get_worklist(&db, date("2024-04-01")).unwrap();
+        let names: Vec<_> = worklist.iter().map(|e| e.student_name.as_str()).collect();
+        assert_eq!(names, vec!["Oldest", "Middle", "Recent"]);
+        assert_eq!(worklist[0].level, Some(DunningLevel::SuspensionReview));
+        assert_eq!(worklist[0].days_overdue, 60);
+        assert_eq!(worklist[0].amount_due, 850);
+        let last_action = worklist[0].last_action.as_ref().unwrap();
+        assert_eq!(last_action.action, DunningActionKind::Note);
+        assert_eq!(last_action.note.as_deref(), Some("Called, no answer"));
+        assert_eq!(worklist[1].level, Some(DunningLevel::Call));
+        assert_eq!(worklist[2].level, Some(DunningLevel::Reminder));
+    }
+}
@@ -0,0 +1,1079 @@
+use crate::audit_service::AuditService;
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::groups_service::GroupsService;
+use crate::payment_service::{PaymentService, PaymentTransaction};
+use crate::student_service::{PaymentPlan, PaymentStatus, StudentService, UpdateStudentRequest};
+use crate::term_service::TermService;
+use chrono::Utc;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum EnrollmentStatus {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "withdrawn")]
+    Withdrawn,
+    #[serde(rename = "waitlisted")]
+    Waitlisted,
+}
+
+impl EnrollmentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnrollmentStatus::Active => "active",
+            EnrollmentStatus::Completed => "completed",
+            EnrollmentStatus::Withdrawn => "withdrawn",
+            EnrollmentStatus::Waitlisted => "waitlisted",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "active" => Ok(EnrollmentStatus::Active),
+            "completed" => Ok(EnrollmentStatus::Completed),
+            "withdrawn" => Ok(EnrollmentStatus::Withdrawn),
+            "waitlisted" => Ok(EnrollmentStatus::Waitlisted),
+            _ => Err(format!("Invalid enrollment status: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Enrollment {
+    pub id: i32,
+    pub student_id: String,
+    pub group_name: String,
+    pub group_id: Option<i32>,
+    pub course: Option<String>,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    pub payment_plan: PaymentPlan,
+    pub plan_amount: i32,
+    pub installment_count: Option<i32>,
+    pub paid_amount: i32,
+    pub next_due_date: Option<String>,
+    pub payment_status: PaymentStatus,
+    pub status: EnrollmentStatus,
+    pub is_primary: bool,
+    pub waitlist_position: Option<i32>,
+    pub term_id: Option<i32>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateEnrollmentRequest {
+    pub student_id: String,
+    pub group_name: String,
+    pub course: Option<String>,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub payment_plan: PaymentPlan,
+    pub plan_amount: i32,
+    pub installment_count: Option<i32>,
+    pub paid_amount: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateEnrollmentRequest {
+    pub group_name: String,
+    pub course: Option<String>,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    pub payment_plan: PaymentPlan,
+    pub plan_amount: i32,
+    pub installment_count: Option<i32>,
+    pub status: EnrollmentStatus,
+}
+
+const ENROLLMENT_COLUMNS: &str = "id, student_id, group_name, course, start_date, end_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status, status, is_primary, created_at, updated_at, group_id, waitlist_position, term_id";
+
+pub struct EnrollmentService;
+
+impl EnrollmentService {
+    /// Helper function to map database row to Enrollment struct
+    fn map_row_to_enrollment(row: &rusqlite::Row) -> rusqlite::Result<Enrollment> {
+        let payment_plan_str: String = row.get(6)?;
+        let payment_status_str: String = row.get(11)?;
+        let status_str: String = row.get(12)?;
+
+        Ok(Enrollment {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            group_name: row.get(2)?,
+            group_id: row.get(16)?,
+            course: row.get(3)?,
+            start_date: row.get(4)?,
+            end_date: row.get(5)?,
+            payment_plan: PaymentPlan::from_str(&payment_plan_str).unwrap_or(PaymentPlan::OneTime),
+            plan_amount: row.get(7)?,
+            installment_count: row.get(8)?,
+            paid_amount: row.get(9)?,
+            next_due_date: row.get(10)?,
+            payment_status: PaymentStatus::from_str(&payment_status_str)
+                .unwrap_or(PaymentStatus::Pending),
+            status: EnrollmentStatus::from_str(&status_str).unwrap_or(EnrollmentStatus::Active),
+            is_primary: row.get(13)?,
+            waitlist_position: row.get(17)?,
+            term_id: row.get(18)?,
+            created_at: row.get(14)?,
+            updated_at: row.get(15)?,
+        })
+    }
+
+    /// Validate enrollment terms
+    fn validate_enrollment_data(
+        group_name: &str,
+        payment_plan: &PaymentPlan,
+        plan_amount: i32,
+        installment_count: Option<i32>,
+        start_date: &str,
+        end_date: Option<&str>,
+    ) -> DatabaseResult<()> {
+        if group_name.trim().is_empty() {
+            return Err(DatabaseError::validation(
+                "group_name",
+                "Group name cannot be empty",
+            ));
+        }
+
+        if group_name.len() > 100 {
+            return Err(DatabaseError::validation(
+                "group_name",
+                "Group name cannot exceed 100 characters",
+            ));
+        }
+
+        if plan_amount <= 0 {
+            return Err(DatabaseError::validation(
+                "plan_amount",
+                "Plan amount must be positive",
+            ));
+        }
+
+        if plan_amount > 1_000_000 {
+            return Err(DatabaseError::validation(
+                "plan_amount",
+                "Plan amount cannot exceed 1,000,000",
+            ));
+        }
+
+        if *payment_plan == PaymentPlan::Installment {
+            match installment_count {
+                Some(count) if !(1..=12).contains(&count) => {
+                    return Err(DatabaseError::validation(
+                        "installment_count",
+                        "Installment count must be between 1 and 12",
+                    ));
+                }
+                None => {
+                    return Err(DatabaseError::validation(
+                        "installment_count",
+                        "Installment count is required for installment plans",
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        if chrono::NaiveDate::parse_from_str(start_date, "%Y-%m-%d").is_err() {
+            return Err(DatabaseError::validation(
+                "start_date",
+                "Invalid start date format. Use YYYY-MM-DD",
+            ));
+        }
+
+        if let Some(end) = end_date {
+            if chrono::NaiveDate::parse_from_str(end, "%Y-%m-%d").is_err() {
+                return Err(DatabaseError::validation(
+                    "end_date",
+                    "Invalid end date format. Use YYYY-MM-DD",
+                ));
+            }
+            if end < start_date {
+                return Err(DatabaseError::validation(
+                    "end_date",
+                    "End date cannot be before start date",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get an enrollment by ID
+    pub fn get_enrollment_by_id(db: &Database, id: i32) -> DatabaseResult<Option<Enrollment>> {
+        let result = db.connection().query_row(
+            &format!(
+                "SELECT {} FROM enrollments_view WHERE id = ?1",
+                ENROLLMENT_COLUMNS
+            ),
+            [id],
+            Self::map_row_to_enrollment,
+        );
+
+        match result {
+            Ok(enrollment) => Ok(Some(enrollment)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(DatabaseError::Sqlite(e)),
+        }
+    }
+
+    /// Get all enrollments of a student, primary first
+    pub fn get_student_enrollments(
+        db: &Database,
+        student_id: &str,
+    ) -> DatabaseResult<Vec<Enrollment>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM enrollments_view WHERE student_id = ?1 ORDER BY is_primary DESC, start_date, id",
+            ENROLLMENT_COLUMNS
+        ))?;
+
+        let enrollment_iter = stmt.query_map([student_id], Self::map_row_to_enrollment)?;
+
+        enrollment_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// Get active enrollments in a group
+    pub fn get_active_enrollments_by_group(
+        db: &Database,
+        group_name: &str,
+    ) -> DatabaseResult<Vec<Enrollment>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM enrollments_view WHERE group_name = ?1 AND status = 'active' ORDER BY start_date, id",
+            ENROLLMENT_COLUMNS
+        ))?;
+
+        let enrollment_iter = stmt.query_map([group_name], Self::map_row_to_enrollment)?;
+
+        enrollment_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// Mirror the student's own group and billing fields onto their primary enrollment,
+    /// creating it when missing. Returns the primary enrollment ID.
+    pub fn sync_primary_enrollment(db: &Database, student_id: &str) -> DatabaseResult<i32> {
+        let rows_affected = db.connection().execute(
+            "UPDATE enrollments
+             SET (group_name, group_id, start_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status, updated_at) =
+                 (SELECT group_name, group_id, enrollment_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status, updated_at
+                  FROM students WHERE id = enrollments.student_id)
+             WHERE student_id = ?1 AND is_primary = 1",
+            [student_id],
+        )?;
+
+        if rows_affected == 0 {
+            let inserted = db.connection().execute(
+                "INSERT INTO enrollments (student_id, group_name, group_id, start_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status, status, is_primary, created_at, updated_at)
+                 SELECT id, group_name, group_id, enrollment_date, payment_plan, plan_amount, installment_count, paid_amount, next_due_date, payment_status, 'active', 1, created_at, updated_at
+                 FROM students WHERE id = ?1",
+                [student_id],
+            )?;
+
+            if inserted == 0 {
+                return Err(DatabaseError::not_found("Student", student_id));
+            }
+        }
+
+        let id = db.connection().query_row(
+            "SELECT id FROM enrollments WHERE student_id = ?1 AND is_primary = 1",
+            [student_id],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Get the primary enrollment ID of a student, creating it for legacy rows without one
+    pub fn get_primary_enrollment_id(db: &Database, student_id: &str) -> DatabaseResult<i32> {
+        let result = db.connection().query_row(
+            "SELECT id FROM enrollments WHERE student_id = ?1 AND is_primary = 1",
+            [student_id],
+            |row| row.get(0),
+        );
+
+        match result {
+            Ok(id) => Ok(id),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                Self::sync_primary_enrollment(db, student_id)
+            }
+            Err(e) => Err(DatabaseError::Sqlite(e)),
+        }
+    }
+
+    /// Resolve which enrollment an attendance mark or payment belongs to.
+    /// Falls back to the primary enrollment when none is given.
+    pub fn resolve_enrollment(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: Option<i32>,
+        require_active: bool,
+    ) -> DatabaseResult<Enrollment> {
+        let id = match enrollment_id {
+            Some(id) => id,
+            None => Self::get_primary_enrollment_id(db, student_id)?,
+        };
+
+        let enrollment = Self::get_enrollment_by_id(db, id)?
+            .ok_or_else(|| DatabaseError::not_found("Enrollment", id))?;
+
+        if enrollment.student_id != student_id {
+            return Err(DatabaseError::validation(
+                "enrollment_id",
+                format!(
+                    "Enrollment {} does not belong to student '{}'",
+                    id, student_id
+                ),
+            ));
+        }
+
+        if require_active && enrollment.status != EnrollmentStatus::Active {
+            return Err(DatabaseError::forbidden(
+                "enrollment_inactive",
+                format!("Enrollment {} is not active", id),
+            ));
+        }
+
+        Ok(enrollment)
+    }
+
+    /// Enroll an existing student in an additional group or course
+    pub fn create_enrollment(
+        db: &Database,
+        request: CreateEnrollmentRequest,
+    ) -> DatabaseResult<Enrollment> {
+        AuditService::audited(db, |audit| {
+            let student = StudentService::get_student_by_id(db, &request.student_id)?
+                .ok_or_else(|| DatabaseError::not_found("Student", &request.student_id))?;
+
+            let start_date = request
+                .start_date
+                .clone()
+                .unwrap_or_else(|| Utc::now().date_naive().format("%Y-%m-%d").to_string());
+
+            Self::validate_enrollment_data(
+                &request.group_name,
+                &request.payment_plan,
+                request.plan_amount,
+                request.installment_count,
+                &start_date,
+                request.end_date.as_deref(),
+            )?;
+
+            // Make sure the student's original enrollment exists before adding another
+            Self::get_primary_enrollment_id(db, &student.id)?;
+
+            let group = GroupsService::resolve_group(db, &request.group_name)?;
+
+            if !group.is_active {
+                return Err(DatabaseError::forbidden(
+                    "group_inactive",
+                    format!("Group '{}' is not accepting new students", group.name),
+                ));
+            }
+
+            let duplicate: i32 = db.connection().query_row(
+                "SELECT COUNT(*) FROM enrollments
+                 WHERE student_id = ?1 AND group_id = ?2 AND IFNULL(course, '') = IFNULL(?3, '') AND status IN ('active', 'waitlisted')",
+                params![student.id, group.id, request.course],
+                |row| row.get(0),
+            )?;
+
+            if duplicate > 0 {
+                return Err(DatabaseError::conflict(
+                    "already_enrolled",
+                    format!(
+                        "Student '{}' is already enrolled or waitlisted in '{}'",
+                        student.id, group.name
+                    ),
+                ));
+            }
+
+            // Students already waiting take any free seats first, so a newcomer cannot jump the
+            // queue; past capacity the enrollment joins the end of the group's waitlist
+            Self::promote_waitlist(db, group.id)?;
+            let (status, waitlist_position) = if GroupsService::get_seats_remaining(db, group.id)?
+                == Some(0)
+            {
+                let last_position: i32 = db.connection().query_row(
+                        "SELECT IFNULL(MAX(waitlist_position), 0) FROM enrollments WHERE group_id = ?1 AND status = 'waitlisted'",
+                        [group.id],
+                        |row| row.get(0),
+                    )?;
+                (EnrollmentStatus::Waitlisted, Some(last_position + 1))
+            } else {
+                (EnrollmentStatus::Active, None)
+            };
+
+            let now = Utc::now().to_rfc3339();
+            db.connection().execute(
+                "INSERT INTO enrollments (student_id, group_name, group_id, course, start_date, end_date, payment_plan, plan_amount, installment_count, paid_amount, status, waitlist_position, is_primary, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, ?13, ?14)",
+                params![
+                    student.id,
+                    group.name,
+                    group.id,
+                    request.course,
+                    start_date,
+                    request.end_date,
+                    request.payment_plan.as_str(),
+                    request.plan_amount,
+                    request.installment_count,
+                    request.paid_amount.unwrap_or(0),
+                    status.as_str(),
+                    waitlist_position,
+                    now,
+                    now
+                ],
+            )?;
+
+            let enrollment_id = db.connection().last_insert_rowid() as i32;
+            Self::update_enrollment_payment_status(db, enrollment_id)?;
+
+            let enrollment = Self::get_enrollment_by_id(db, enrollment_id)?
+                .ok_or_else(|| DatabaseError::not_found("Enrollment", enrollment_id))?;
+
+            // Log audit entry for enrollment creation
+            audit.create("enrollments", &enrollment_id.to_string(), &enrollment, None);
+
+            Ok(enrollment)
+        })
+    }
+
+    /// Update an enrollment. Changes to the primary enrollment go through the student record.
+    pub fn update_enrollment(
+        db: &Database,
+        id: i32,
+        request: UpdateEnrollmentRequest,
+    ) -> DatabaseResult<Enrollment> {
+        AuditService::audited(db, |audit| {
+            Self::validate_enrollment_data(
+                &request.group_name,
+                &request.payment_plan,
+                request.plan_amount,
+                request.installment_count,
+                &request.start_date,
+                request.end_date.as_deref(),
+            )?;
+
+            let current = Self::get_enrollment_by_id(db, id)?
+                .ok_or_else(|| DatabaseError::not_found("Enrollment", id))?;
+
+            // The waitlist is only joined when enrolling into a full group
+            if request.status == EnrollmentStatus::Waitlisted
+                && (current.is_primary || current.status != EnrollmentStatus::Waitlisted)
+            {
+                return Err(DatabaseError::validation(
+                    "status",
+                    "An enrollment is only waitlisted when its group is full",
+                ));
+            }
+
+            let now = Utc::now().to_rfc3339();
+
+            if current.is_primary {
+                let student = StudentService::get_student_by_id(db, &current.student_id)?
+                    .ok_or_else(|| DatabaseError::not_found("Student", &current.student_id))?;
+
+                StudentService::update_student(
+                    db,
+                    &current.student_id,
+                    UpdateStudentRequest {
+                        name: student.name,
+                        group_name: request.group_name.clone(),
+                        payment_plan: request.payment_plan.clone(),
+                        plan_amount: request.plan_amount,
+                        installment_count: request.installment_count,
+                        paid_amount: None,
+                        enrollment_date: Some(request.start_date.clone()),
+                    },
+                )?;
+
+                db.connection().execute(
+                    "UPDATE enrollments SET course = ?1, end_date = ?2, status = ?3, updated_at = ?4 WHERE id = ?5",
+                    params![request.course, request.end_date, request.status.as_str(), now, id],
+                )?;
+            } else {
+                let group = GroupsService::resolve_group(db, &request.group_name)?;
+
+                if request.status == EnrollmentStatus::Waitlisted
+                    && current.group_id != Some(group.id)
+                {
+                    return Err(DatabaseError::validation(
+                        "group_name",
+                        "A waitlisted enrollment cannot move to another group's waitlist",
+                    ));
+                }
+
+                // Taking a seat, whether off the waitlist or in another group, needs a free one
+                let takes_seat = request.status == EnrollmentStatus::Active
+                    && (current.status != EnrollmentStatus::Active
+                        || current.group_id != Some(group.id));
+                if takes_seat {
+                    GroupsService::ensure_seat_available(db, &group)?;
+                }
+
+                db.connection().execute(
+                    "UPDATE enrollments
+                     SET group_name = ?1, group_id = ?2, course = ?3, start_date = ?4, end_date = ?5, payment_plan = ?6, plan_amount = ?7, installment_count = ?8, status = ?9,
+                         waitlist_position = CASE WHEN ?9 = 'waitlisted' THEN waitlist_position END, updated_at = ?10
+                     WHERE id = ?11",
+                    params![
+                        group.name,
+                        group.id,
+                        request.course,
+                        request.start_date,
+                        request.end_date,
+                        request.payment_plan.as_str(),
+                        request.plan_amount,
+                        request.installment_count,
+                        request.status.as_str(),
+                        now,
+                        id
+                    ],
+                )?;
+                Self::update_enrollment_payment_status(db, id)?;
+            }
+
+            let updated = Self::get_enrollment_by_id(db, id)?
+                .ok_or_else(|| DatabaseError::not_found("Enrollment", id))?;
+
+            // Log audit entry for enrollment update
+            audit.update("enrollments", &id.to_string(), &current, &updated, None);
+
+            // A seat freed in the old group goes to the head of its waitlist
+            if current.status == EnrollmentStatus::Active
+                && (updated.status != EnrollmentStatus::Active
+                    || updated.group_id != current.group_id)
+            {
+                if let Some(group_id) = current.group_id {
+                    Self::promote_waitlist(db, group_id)?;
+                }
+            }
+
+            Ok(updated)
+        })
+    }
+
+    /// End an enrollment as completed or withdrawn; attendance and payments are kept
+    pub fn end_enrollment(
+        db: &Database,
+        id: i32,
+        end_date: &str,
+        status: EnrollmentStatus,
+    ) -> DatabaseResult<Enrollment> {
+        if status == EnrollmentStatus::Active || status == EnrollmentStatus::Waitlisted {
+            return Err(DatabaseError::validation(
+                "status",
+                "An enrollment must end as completed or withdrawn",
+            ));
+        }
+
+        let current = Self::get_enrollment_by_id(db, id)?
+            .ok_or_else(|| DatabaseError::not_found("Enrollment", id))?;
+
+        Self::update_enrollment(
+            db,
+            id,
+            UpdateEnrollmentRequest {
+                group_name: current.group_name,
+                course: current.course,
+                start_date: current.start_date,
+                end_date: Some(end_date.to_string()),
+                payment_plan: current.payment_plan,
+                plan_amount: current.plan_amount,
+                installment_count: current.installment_count,
+                status,
+            },
+        )
+    }
+
+    /// Delete a secondary enrollment together with its attendance
+    pub fn delete_enrollment(db: &Database, id: i32) -> DatabaseResult<bool> {
+        AuditService::audited(db, |audit| {
+            let current = match Self::get_enrollment_by_id(db, id)? {
+                Some(enrollment) => enrollment,
+                None => return Ok(false),
+            };
+
+            if current.is_primary {
+                return Err(DatabaseError::forbidden(
+                    "primary_enrollment",
+                    "The primary enrollment can only be removed by deleting the student",
+                ));
+            }
+
+            let rows_affected = db
+                .connection()
+                .execute("DELETE FROM enrollments WHERE id = ?1", [id])?;
+
+            if rows_affected > 0 {
+                audit.delete("enrollments", &id.to_string(), &current, None);
+
+                if let (EnrollmentStatus::Active, Some(group_id)) =
+                    (&current.status, current.group_id)
+                {
+                    Self::promote_waitlist(db, group_id)?;
+                }
+            }
+
+            Ok(rows_affected > 0)
+        })
+    }
+
+    /// Get the waitlist of a group in promotion order
+    pub fn get_group_waitlist(db: &Database, group_id: i32) -> DatabaseResult<Vec<Enrollment>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM enrollments_view WHERE group_id = ?1 AND status = 'waitlisted' ORDER BY waitlist_position, id",
+            ENROLLMENT_COLUMNS
+        ))?;
+
+        let enrollment_iter = stmt.query_map([group_id], Self::map_row_to_enrollment)?;
+
+        enrollment_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// Move waitlisted enrollments into free seats of an active group, first in line first.
+    /// Returns the promoted enrollments.
+    pub fn promote_waitlist(db: &Database, group_id: i32) -> DatabaseResult<Vec<Enrollment>> {
+        AuditService::audited(db, |audit| {
+            let mut promoted = Vec::new();
+
+            let group = match GroupsService::get_group_by_id(db, group_id)? {
+                Some(group) if group.is_active => group,
+                _ => return Ok(promoted),
+            };
+
+            let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+
+            while GroupsService::get_seats_remaining(db, group.id)? != Some(0) {
+                let next = db.connection().query_row(
+                    "SELECT e.id FROM enrollments e
+                     JOIN students s ON s.id = e.student_id
+                     WHERE e.group_id = ?1 AND e.status = 'waitlisted' AND s.deleted_at IS NULL
+                     ORDER BY e.waitlist_position, e.id
+                     LIMIT 1",
+                    [group.id],
+                    |row| row.get::<_, i32>(0),
+                );
+
+                let next_id = match next {
+                    Ok(id) => id,
+                    Err(rusqlite::Error::QueryReturnedNoRows) => break,
+                    Err(e) => return Err(DatabaseError::Sqlite(e)),
+                };
+
+                let waiting = Self::get_enrollment_by_id(db, next_id)?
+                    .ok_or_else(|| DatabaseError::not_found("Enrollment", next_id))?;
+
+                // The seat starts counting from the day it is taken
+                db.connection().execute(
+                    "UPDATE enrollments SET status = 'active', waitlist_position = NULL, start_date = MAX(start_date, ?1), updated_at = ?2 WHERE id = ?3",
+                    params![today, Utc::now().to_rfc3339(), next_id],
+                )?;
+                Self::update_enrollment_payment_status(db, next_id)?;
+
+                let enrollment = Self::get_enrollment_by_id(db, next_id)?
+                    .ok_or_else(|| DatabaseError::not_found("Enrollment", next_id))?;
+
+                audit.update(
+                    "enrollments",
+                    &next_id.to_string(),
+                    &waiting,
+                    &enrollment,
+                    None,
+                );
+
+                promoted.push(enrollment);
+            }
+
+            Ok(promoted)
+        })
+    }
+
+    /// Recalculate due date and payment status of an enrollment from its own payments
+    pub fn update_enrollment_payment_status(db: &Database, id: i32) -> DatabaseResult<()> {
+        let enrollment = Self::get_enrollment_by_id(db, id)?
+            .ok_or_else(|| DatabaseError::not_found("Enrollment", id))?;
+
+        // The primary enrollment follows the student record
+        if enrollment.is_primary {
+            PaymentService::update_student_payment_status(db, &enrollment.student_id)?;
+            return Ok(());
+        }
+
+        let config = StudentService::get_payment_plan_config(db)?;
+
+        let last_payment_date: Option<String> = db.connection().query_row(
+            "SELECT MAX(payment_date) FROM payment_transactions WHERE enrollment_id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+
+        let next_due_date = StudentService::calculate_next_due_date(
+            &enrollment.payment_plan,
+            &enrollment.start_date,
+            last_payment_date.as_deref(),
+            config.installment_interval,
+        )?;
+
+        let paid_toward_term = TermService::paid_toward_term(
+            db,
+            &enrollment.student_id,
+            Some(id),
+            enrollment.paid_amount as i64,
+        )?;
+        let payment_status = StudentService::calculate_payment_status(
+            &enrollment.payment_plan,
+            enrollment.plan_amount,
+            paid_toward_term as i32,
+            enrollment.installment_count,
+            next_due_date.as_deref(),
+            &enrollment.start_date,
+            config.reminder_days,
+        )?;
+
+        db.connection().execute(
+            "UPDATE enrollments SET next_due_date = ?1, payment_status = ?2, updated_at = ?3 WHERE id = ?4",
+            params![next_due_date, payment_status.as_str(), Utc::now().to_rfc3339(), id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Get payments recorded against an enrollment
+    pub fn get_enrollment_payment_history(
+        db: &Database,
+        id: i32,
+    ) -> DatabaseResult<Vec<PaymentTransaction>> {
+        let enrollment = Self::get_enrollment_by_id(db, id)?
+            .ok_or_else(|| DatabaseError::not_found("Enrollment", id))?;
+
+        Ok(
+            PaymentService::get_student_payment_history(db, &enrollment.student_id)?
+                .into_iter()
+                .filter(|p| p.enrollment_id == Some(id))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attendance_service::AttendanceService;
+    use crate::payment_service::{PaymentMethod, RecordPaymentRequest};
+    use crate::student_service::CreateStudentRequest;
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(db: &Database, group: &str) -> String {
+        let request = CreateStudentRequest {
+            name: "Two Course Student".to_string(),
+            group_name: group.to_string(),
+            payment_plan: PaymentPlan::OneTime,
+            plan_amount: 6000,
+            installment_count: None,
+            paid_amount: Some(0),
+            enrollment_date: Some("2024-01-01".to_string()),
+        };
+        StudentService::create_student(db, request).unwrap().id
+    }
+
+    fn second_course(student_id: &str) -> CreateEnrollmentRequest {
+        CreateEnrollmentRequest {
+            student_id: student_id.to_string(),
+            group_name: "Physics".to_string(),
+            course: Some("Physics 101".to_string()),
+            start_date: Some("2024-02-01".to_string()),
+            end_date: None,
+            payment_plan: PaymentPlan::Monthly,
+            plan_amount: 850,
+            installment_count: None,
+            paid_amount: None,
+        }
+    }
+
+    #[test]
+    fn test_primary_enrollment_created_with_student() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Math");
+
+        let enrollments = EnrollmentService::get_student_enrollments(&db, &student_id).unwrap();
+        assert_eq!(enrollments.len(), 1);
+        assert!(enrollments[0].is_primary);
+        assert_eq!(enrollments[0].group_name, "Math");
+        assert_eq!(enrollments[0].plan_amount, 6000);
+        assert_eq!(enrollments[0].start_date, "2024-01-01");
+    }
+
+    #[test]
+    fn test_second_enrollment_and_duplicate_prevention() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Math");
+
+        let enrollment =
+            EnrollmentService::create_enrollment(&db, second_course(&student_id)).unwrap();
+        assert!(!enrollment.is_primary);
+        assert_eq!(enrollment.status, EnrollmentStatus::Active);
+
+        let duplicate = EnrollmentService::create_enrollment(&db, second_course(&student_id));
+        assert!(duplicate.is_err());
+
+        let enrollments = EnrollmentService::get_student_enrollments(&db, &student_id).unwrap();
+        assert_eq!(enrollments.len(), 2);
+    }
+
+    #[test]
+    fn test_attendance_per_enrollment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Math");
+        let physics =
+            EnrollmentService::create_enrollment(&db, second_course(&student_id)).unwrap();
+
+        AttendanceService::mark_attendance(&db, &student_id, None, "2024-02-05").unwrap();
+        let record =
+            AttendanceService::mark_attendance(&db, &student_id, Some(physics.id), "2024-02-05")
+                .unwrap();
+        assert_eq!(record.enrollment_id, Some(physics.id));
+
+        // Same course on the same day is still a duplicate
+        assert!(AttendanceService::mark_attendance(
+            &db,
+            &student_id,
+            Some(physics.id),
+            "2024-02-05"
+        )
+        .is_err());
+
+        let physics_summary =
+            AttendanceService::get_daily_attendance_summary(&db, "2024-02-05", Some("Physics"))
+                .unwrap();
+        assert_eq!(physics_summary.total_students, 1);
+        assert_eq!(physics_summary.present_students, 1);
+
+        let overall =
+            AttendanceService::get_daily_attendance_summary(&db, "2024-02-05", None).unwrap();
+        assert_eq!(overall.present_students, 1);
+    }
+
+    #[test]
+    fn test_payments_billed_to_enrollment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Math");
+        let physics =
+            EnrollmentService::create_enrollment(&db, second_course(&student_id)).unwrap();
+
+        let request = RecordPaymentRequest {
+            student_id: student_id.clone(),
+            enrollment_id: Some(physics.id),
+            amount: 850,
+            payment_date: "2024-02-01".to_string(),
+            payment_method: PaymentMethod::Cash,
+            notes: None,
+        };
+        let payment = PaymentService::record_payment(&db, request).unwrap();
+        assert_eq!(payment.enrollment_id, Some(physics.id));
+
+        let physics = EnrollmentService::get_enrollment_by_id(&db, physics.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(physics.paid_amount, 850);
+
+        // The primary (student-level) balance is untouched
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 0);
+
+        let history = EnrollmentService::get_enrollment_payment_history(&db, physics.id).unwrap();
+        assert_eq!(history.len(), 1);
+
+        PaymentService::delete_payment(&db, payment.id).unwrap();
+        let physics = EnrollmentService::get_enrollment_by_id(&db, physics.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(physics.paid_amount, 0);
+    }
+
+    #[test]
+    fn test_primary_enrollment_follows_student_payments() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Math");
+
+        let request = RecordPaymentRequest {
+            student_id: student_id.clone(),
+            enrollment_id: None,
+            amount: 6000,
+            payment_date: "2024-01-02".to_string(),
+            payment_method: PaymentMethod::Cash,
+            notes: None,
+        };
+        PaymentService::record_payment(&db, request).unwrap();
+
+        let primary = &EnrollmentService::get_student_enrollments(&db, &student_id).unwrap()[0];
+        assert_eq!(primary.paid_amount, 6000);
+        assert_eq!(primary.payment_status, PaymentStatus::Paid);
+    }
+
+    #[test]
+    fn test_end_and_delete_enrollment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Math");
+        let physics =
+            EnrollmentService::create_enrollment(&db, second_course(&student_id)).unwrap();
+
+        let ended = EnrollmentService::end_enrollment(
+            &db,
+            physics.id,
+            "2024-06-30",
+            EnrollmentStatus::Completed,
+        )
+        .unwrap();
+        assert_eq!(ended.status, EnrollmentStatus::Completed);
+        assert_eq!(ended.end_date.as_deref(), Some("2024-06-30"));
+
+        // Attendance can no longer be marked against an ended enrollment
+        assert!(AttendanceService::mark_attendance(
+            &db,
+            &student_id,
+            Some(physics.id),
+            "2024-07-01"
+        )
+        .is_err());
+
+        let primary_id = EnrollmentService::get_primary_enrollment_id(&db, &student_id).unwrap();
+        assert!(EnrollmentService::delete_enrollment(&db, primary_id).is_err());
+        assert!(EnrollmentService::delete_enrollment(&db, physics.id).unwrap());
+    }
+
+    #[test]
+    fn test_full_group_waitlist_and_promotion() {
+        let (db, _temp_dir) = create_test_db();
+        let physics = GroupsService::resolve_group(&db, "Physics").unwrap();
+        GroupsService::update_group_settings(
+            &db,
+            physics.id,
+            crate::groups_service::GroupSettings {
+                capacity: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let first = create_student(&db, "Math");
+        let second = create_student(&db, "Math");
+        let third = create_student(&db, "Math");
+
+        let seated = EnrollmentService::create_enrollment(&db, second_course(&first)).unwrap();
+        let waiting = EnrollmentService::create_enrollment(&db, second_course(&second)).unwrap();
+        let last = EnrollmentService::create_enrollment(&db, second_course(&third)).unwrap();
+        assert_eq!(seated.status, EnrollmentStatus::Active);
+        assert_eq!(waiting.status, EnrollmentStatus::Waitlisted);
+        assert_eq!(waiting.waitlist_position, Some(1));
+        assert_eq!(last.waitlist_position, Some(2));
+
+        // A new student cannot be placed straight into the full group
+        assert!(StudentService::create_student(
+            &db,
+            CreateStudentRequest {
+                name: "Late Student".to_string(),
+                group_name: "Physics".to_string(),
+                payment_plan: PaymentPlan::OneTime,
+                plan_amount: 6000,
+                installment_count: None,
+                paid_amount: None,
+                enrollment_date: None,
+            },
+        )
+        .is_err());
+
+        EnrollmentService::end_enrollment(
+            &db,
+            seated.id,
+            "2024-03-01",
+            EnrollmentStatus::Withdrawn,
+        )
+        .unwrap();
+
+        let promoted = EnrollmentService::get_enrollment_by_id(&db, waiting.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(promoted.status, EnrollmentStatus::Active);
+        assert_eq!(promoted.waitlist_position, None);
+
+        let waitlist = EnrollmentService::get_group_waitlist(&db, physics.id).unwrap();
+        assert_eq!(waitlist.len(), 1);
+        assert_eq!(waitlist[0].id, last.id);
+        assert_eq!(
+            GroupsService::get_seats_remaining(&db, physics.id).unwrap(),
+            Some(0)
+        );
+
+        // Lifting the limit seats everyone still waiting
+        GroupsService::update_group_settings(&db, physics.id, Default::default()).unwrap();
+        assert!(EnrollmentService::get_group_waitlist(&db, physics.id)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_waitlist_keeps_its_turn_on_freed_seat() {
+        let (db, _temp_dir) = create_test_db();
+        let physics = GroupsService::resolve_group(&db, "Physics").unwrap();
+        GroupsService::update_group_settings(
+            &db,
+            physics.id,
+            crate::groups_service::GroupSettings {
+                capacity: Some(1),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let first = create_student(&db, "Math");
+        let second = create_student(&db, "Math");
+        let third = create_student(&db, "Math");
+
+        EnrollmentService::create_enrollment(&db, second_course(&first)).unwrap();
+        let waiting = EnrollmentService::create_enrollment(&db, second_course(&second)).unwrap();
+        assert_eq!(waiting.status, EnrollmentStatus::Waitlisted);
+
+        // A seat frees up without the waitlist being promoted
+        db.connection()
+            .execute("UPDATE groups SET capacity = 2 WHERE id = ?1", [physics.id])
+            .unwrap();
+
+        let newcomer = EnrollmentService::create_enrollment(&db, second_course(&third)).unwrap();
+        assert_eq!(newcomer.status, EnrollmentStatus::Waitlisted);
+        assert_eq!(newcomer.waitlist_position, Some(1));
+
+        let waiting = EnrollmentService::get_enrollment_by_id(&db, waiting.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(waiting.status, EnrollmentStatus::Active);
+    }
+
+    #[test]
+    fn test_enrollment_belongs_to_student() {
+        let (db, _temp_dir) = create_test_db();
+        let first = create_student(&db, "Math");
+        let second = create_student(&db, "Math");
+        let first_primary = EnrollmentService::get_primary_enrollment_id(&db, &first).unwrap();
+
+        let result = EnrollmentService::resolve_enrollment(&db, &second, Some(first_primary), true);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,237 @@
+use crate::backup_service::BackupError;
+use crate::database::DatabaseError;
+use crate::i18n::{self, Locale};
+use crate::payment_service::PaymentError;
+use crate::qr_service::QRServiceError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Stable category of a failed command, for the frontend to branch on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    NotFound,
+    Validation,
+    Conflict,
+    Forbidden,
+    /// The database or file system failed; nothing the user can correct
+    Storage,
+}
+
+/// Error returned by every IPC command, serialized to the frontend as JSON
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
+#[error("{message}")]
+pub struct AppError {
+    pub code: ErrorCode,
+    /// Message catalog key, e.g. `errors.not_found.student` or `errors.validation.group_name`
+    pub message_key: String,
+    /// English text, for logs and for keys the catalog does not know yet
+    pub message: String,
+    /// `message_key` rendered in the app language
+    pub localized_message: String,
+    /// The offending input for validation errors
+    pub field: Option<&'static str>,
+    /// Values to interpolate into the catalog message
+    pub params: BTreeMap<String, String>,
+}
+
+impl AppError {
+    fn new(code: ErrorCode, message_key: String, message: impl Into<String>) -> Self {
+        AppError {
+            code,
+            message_key,
+            message: message.into(),
+            localized_message: String::new(),
+            field: None,
+            params: BTreeMap::new(),
+        }
+    }
+
+    pub fn not_found(entity: &str, id: impl ToString) -> Self {
+        let id = id.to_string();
+        let mut error = Self::new(
+            ErrorCode::NotFound,
+            format!("errors.not_found.{}", catalog_segment(entity)),
+            format!("{} with ID {} not found", entity, id),
+        );
+        error.params.insert("id".to_string(), id);
+        error.localized()
+    }
+
+    pub fn validation(field: &'static str, message: impl Into<String>) -> Self {
+        let mut error = Self::new(
+            ErrorCode::Validation,
+            format!("errors.validation.{}", field),
+            message,
+        );
+        error.field = Some(field);
+        error.localized()
+    }
+
+    pub fn conflict(reason: &str, message: impl Into<String>) -> Self {
+        Self::new(
+            ErrorCode::Conflict,
+            format!("errors.conflict.{}", reason),
+            message,
+        )
+        .localized()
+    }
+
+    pub fn forbidden(reason: &str, message: impl Into<String>) -> Self {
+        Self::new(
+            ErrorCode::Forbidden,
+            format!("errors.forbidden.{}", reason),
+            message,
+        )
+        .localized()
+    }
+
+    pub fn storage(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Storage, "errors.storage".to_string(), message).localized()
+    }
+
+    /// Render in the default language; commands render again in the app language
+    fn localized(self) -> Self {
+        self.localized_in(Locale::default())
+    }
+
+    /// Render the message in `locale`. A key the catalog lacks falls back to the specific
+    /// English message, or in Arabic to the general message for the category.
+    pub fn localized_in(mut self, locale: Locale) -> Self {
+        let specific = i18n::format(locale, &self.message_key, &self.params);
+        self.localized_message = match (specific, locale) {
+            (Some(text), _) => text,
+            (None, Locale::En) => self.message.clone(),
+            (None, _) => {
+                let category = self.message_key.rsplit_once('.').map(|(c, _)| c);
+                category
+                    .and_then(|c| i18n::lookup(locale, c))
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.message.clone())
+            }
+        };
+        self
+    }
+
+    /// Prefix the English message with what the command was doing
+    pub fn context(mut self, context: &str) -> Self {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+}
+
+/// `map_err` adapter for commands: `.map_err(context("Failed to get students"))`
+pub fn context<E: Into<AppError>>(context: &'static str) -> impl FnOnce(E) -> AppError {
+    move |error| {
+        let error = error.into();
+        if error.code == ErrorCode::Storage {
+            log::error!("{}: {}", context, error.message);
+        }
+        error.context(context)
+    }
+}
+
+/// "Payment settings" becomes `payment_settings`
+fn catalog_segment(entity: &str) -> String {
+    entity.to_lowercase().replace(' ', "_")
+}
+
+impl From<DatabaseError> for AppError {
+    fn from(error: DatabaseError) -> Self {
+        match error {
+            DatabaseError::NotFound { entity, id } => AppError::not_found(entity, id),
+            DatabaseError::Validation { field, message } => AppError::validation(field, message),
+            DatabaseError::Conflict { reason, message } => AppError::conflict(reason, message),
+            DatabaseError::Forbidden { reason, message } => AppError::forbidden(reason, message),
+            other => AppError::storage(other.to_string()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(error: rusqlite::Error) -> Self {
+        AppError::from(DatabaseError::Sqlite(error))
+    }
+}
+
+impl From<PaymentError> for AppError {
+    fn from(error: PaymentError) -> Self {
+        match error {
+            PaymentError::ValidationError(message) => AppError::validation("payment", message),
+            PaymentError::StudentNotFound(id) => AppError::not_found("Student", id),
+            PaymentError::DatabaseError(error) => AppError::from(error),
+        }
+    }
+}
+
+impl From<BackupError> for AppError {
+    fn from(error: BackupError) -> Self {
+        match error {
+            BackupError::Database(error) => AppError::from(error),
+            BackupError::Encryption(message) => AppError::validation("password", message),
+            BackupError::Validation(message) => AppError::validation("backup", message),
+            other => AppError::storage(other.to_string()),
+        }
+    }
+}
+
+impl From<QRServiceError> for AppError {
+    fn from(error: QRServiceError) -> Self {
+        match error {
+            QRServiceError::Database(error) => AppError::from(error),
+            QRServiceError::StudentNotFound(id) => AppError::not_found("Student", id),
+            other => AppError::storage(other.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_found_serializes_with_code_and_key() {
+        let error = context::<DatabaseError>("Failed to get student")(DatabaseError::not_found(
+            "Student", "STU001",
+        ));
+        let json = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(json["code"], "NOT_FOUND");
+        assert_eq!(json["message_key"], "errors.not_found.student");
+        assert_eq!(json["params"]["id"], "STU001");
+        assert_eq!(
+            json["message"],
+            "Failed to get student: Student with ID STU001 not found"
+        );
+    }
+
+    #[test]
+    fn test_validation_keeps_field() {
+        let error = AppError::from(DatabaseError::validation(
+            "group_name",
+            "Group name cannot be empty",
+        ));
+
+        assert_eq!(error.code, ErrorCode::Validation);
+        assert_eq!(error.field, Some("group_name"));
+        assert_eq!(error.message_key, "errors.validation.group_name");
+
+        let storage = AppError::from(DatabaseError::Connection("pool exhausted".to_string()));
+        assert_eq!(storage.code, ErrorCode::Storage);
+        assert_eq!(storage.message_key, "errors.storage");
+    }
+
+    #[test]
+    fn test_localized_message_follows_locale() {
+        let error = AppError::from(DatabaseError::validation(
+            "installments",
+            "Installment 2 has no amount",
+        ));
+
+        let english = error.clone().localized_in(Locale::En);
+        assert_eq!(english.localized_message, "Installment 2 has no amount");
+
+        let arabic = error.localized_in(Locale::Ar);
+        assert_eq!(arabic.localized_message, "قيمة غير صالحة");
+    }
+}
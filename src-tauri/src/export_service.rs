@@ -1,11 +1,65 @@
-use crate::attendance_service::AttendanceService;
-use crate::database::{Database, DatabaseResult};
-use chrono::{DateTime, Datelike, Local, NaiveDate, Utc};
+use crate::analytics_service::{AnalyticsService, CashFlowForecast, CashFlowForecastRequest};
+use crate::arabic_shaping;
+use crate::attendance_service::{
+    AttendanceMatrix, AttendanceService, AttendanceStatus, AttendanceTally, PRESENT_CONDITION,
+};
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::i18n::{self, Locale};
+use crate::receivables_service::{AgingReport, ReceivablesService};
+use crate::term_service::TermService;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, Utc};
 use csv::Writer;
+use rust_xlsxwriter::{Color, Format, FormatAlign, FormatBorder, Workbook, Worksheet, XlsxError};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
 use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Marks a session the student attended in the attendance sheet
+const PRESENT_MARK: &str = "✓";
+
+/// Fonts with Arabic glyphs that ship with Windows, macOS and common Linux distributions
+const PDF_FONT_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "C:\\Windows\\Fonts\\tahoma.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+];
+
+/// File types a report can be exported as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Xlsx,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "xlsx" => Ok(ExportFormat::Xlsx),
+            "pdf" => Ok(ExportFormat::Pdf),
+            _ => Err(format!("Invalid export format: {}", s)),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AttendanceExportRecord {
     pub student_id: String,
@@ -54,56 +108,141 @@ pub struct OverdueStudentExportRecord {
     pub enrollment_date: String,
 }
 
+/// Totals written to the summary sheet of the XLSX workbook
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkbookSummary {
+    pub student_count: usize,
+    pub session_count: usize,
+    pub attendance_count: usize,
+    pub payment_count: usize,
+    pub overdue_count: usize,
+    pub total_plan_amount: i64,
+    pub total_paid_amount: i64,
+    pub payments_received: i64,
+    pub total_amount_due: i64,
+}
+
+impl WorkbookSummary {
+    fn from_records(
+        students: &[PaymentSummaryExportRecord],
+        attendance: &[AttendanceExportRecord],
+        payments: &[PaymentHistoryExportRecord],
+        overdue: &[OverdueStudentExportRecord],
+    ) -> Self {
+        let sessions: HashSet<&str> = attendance.iter().map(|a| a.date.as_str()).collect();
+        WorkbookSummary {
+            student_count: students.len(),
+            session_count: sessions.len(),
+            attendance_count: attendance.len(),
+            payment_count: payments.len(),
+            overdue_count: overdue.len(),
+            total_plan_amount: students.iter().map(|s| i64::from(s.plan_amount)).sum(),
+            total_paid_amount: students.iter().map(|s| i64::from(s.paid_amount)).sum(),
+            payments_received: payments.iter().map(|p| i64::from(p.payment_amount)).sum(),
+            total_amount_due: overdue.iter().map(|o| i64::from(o.amount_due)).sum(),
+        }
+    }
+}
+
+/// Cell formats shared by every sheet of the workbook
+struct XlsxFormats {
+    header: Format,
+    header_date: Format,
+    centered: Format,
+    amount: Format,
+    date: Format,
+    datetime: Format,
+    percent: Format,
+}
+
+impl XlsxFormats {
+    fn new() -> Self {
+        let header = Format::new()
+            .set_bold()
+            .set_background_color(Color::RGB(0xDDEBF7))
+            .set_border_bottom(FormatBorder::Thin);
+        XlsxFormats {
+            header_date: header.clone().set_num_format("yyyy-mm-dd"),
+            header,
+            centered: Format::new().set_align(FormatAlign::Center),
+            amount: Format::new().set_num_format("#,##0"),
+            date: Format::new().set_num_format("yyyy-mm-dd"),
+            datetime: Format::new().set_num_format("yyyy-mm-dd hh:mm"),
+            percent: Format::new()
+                .set_num_format("0.0")
+                .set_align(FormatAlign::Center),
+        }
+    }
+}
+
 pub struct ExportService;
 
 impl ExportService {
-    /// Export attendance records to CSV file
-    pub fn export_attendance_csv(
-        db: &Database,
-        file_path: &str,
-        start_date: Option<&str>,
-        end_date: Option<&str>,
-        group_name: Option<&str>,
-    ) -> DatabaseResult<()> {
-        // Validate file path
+    /// Column headers in `locale`, looked up as `export.<column>`
+    fn headers(locale: Locale, columns: &[&str]) -> Vec<String> {
+        columns
+            .iter()
+            .map(|column| i18n::t(locale, &format!("export.{}", column)))
+            .collect()
+    }
+
+    /// Fail early when the export would go to a directory that does not exist
+    fn ensure_parent_exists(file_path: &str) -> DatabaseResult<()> {
         let path = Path::new(file_path);
         if let Some(parent) = path.parent() {
             if !parent.exists() {
-                return Err(crate::database::DatabaseError::Migration(format!(
-                    "Directory does not exist: {}",
-                    parent.display()
-                )));
+                return Err(DatabaseError::validation(
+                    "file_path",
+                    format!("Directory does not exist: {}", parent.display()),
+                ));
             }
         }
+        Ok(())
+    }
+
+    /// Create a CSV file starting with a UTF-8 byte order mark, without which Excel
+    /// reads Arabic text as mojibake
+    fn create_csv_writer(file_path: &str, headers: Vec<String>) -> DatabaseResult<Writer<File>> {
+        Self::ensure_parent_exists(file_path)?;
+
+        let mut file = File::create(file_path)
+            .map_err(|e| DatabaseError::Storage(format!("Failed to create file: {}", e)))?;
+        file.write_all(UTF8_BOM)
+            .map_err(|e| DatabaseError::Storage(format!("Failed to create file: {}", e)))?;
 
-        // Create CSV writer
-        let file = File::create(file_path).map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to create file: {}", e))
-        })?;
         let mut writer = Writer::from_writer(file);
+        writer
+            .write_record(headers)
+            .map_err(|e| DatabaseError::Storage(format!("Failed to write CSV headers: {}", e)))?;
+        Ok(writer)
+    }
 
-        // Write CSV headers in Arabic
+    fn write_csv_record(writer: &mut Writer<File>, record: &[&str]) -> DatabaseResult<()> {
         writer
-            .write_record(&[
-                "رقم الطالب",
-                "اسم الطالب",
-                "المجموعة",
-                "التاريخ",
-                "وقت التسجيل",
-            ])
-            .map_err(|e| {
-                crate::database::DatabaseError::Migration(format!(
-                    "Failed to write CSV headers: {}",
-                    e
-                ))
-            })?;
+            .write_record(record)
+            .map_err(|e| DatabaseError::Storage(format!("Failed to write CSV record: {}", e)))
+    }
 
+    fn finish_csv(mut writer: Writer<File>) -> DatabaseResult<()> {
+        writer
+            .flush()
+            .map_err(|e| DatabaseError::Storage(format!("Failed to flush CSV writer: {}", e)))
+    }
+
+    /// Presences and late arrivals, newest first
+    fn load_attendance(
+        db: &Database,
+        start_date: Option<&str>,
+        end_date: Option<&str>,
+        group_name: Option<&str>,
+    ) -> DatabaseResult<Vec<AttendanceExportRecord>> {
         // Build query with filters
-        let mut query = "SELECT a.student_id, s.name, s.group_name, a.date, a.created_at 
-                        FROM attendance a 
-                        JOIN students s ON a.student_id = s.id"
+        let mut query = "SELECT a.student_id, s.name, v.group_name, a.date, a.created_at
+                        FROM attendance a
+                        JOIN students_view s ON a.student_id = s.id
+                        JOIN attendance_groups_view v ON v.id = a.id"
             .to_string();
-        let mut conditions = Vec::new();
+        let mut conditions = vec![PRESENT_CONDITION.to_string()];
         let mut params_vec = Vec::new();
 
         if let Some(start) = start_date {
@@ -119,7 +258,7 @@ impl ExportService {
         }
 
         if let Some(group) = group_name {
-            conditions.push("s.group_name = ?".to_string());
+            conditions.push("v.group_name = ?".to_string());
             params_vec.push(group.to_string());
         }
 
@@ -130,19 +269,9 @@ impl ExportService {
 
         query.push_str(" ORDER BY a.date DESC, s.name ASC");
 
-        // Execute query and write records
-        let mut stmt = db
-            .connection()
-            .prepare(&query)
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec
-            .iter()
-            .map(|p| p as &dyn rusqlite::ToSql)
-            .collect();
-
-        let attendance_iter = stmt
-            .query_map(&params_refs[..], |row| {
+        let mut stmt = db.connection().prepare(&query)?;
+        let records = stmt
+            .query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
                 Ok(AttendanceExportRecord {
                     student_id: row.get(0)?,
                     student_name: row.get(1)?,
@@ -153,82 +282,20 @@ impl ExportService {
                         .format("%Y-%m-%d %H:%M:%S")
                         .to_string(),
                 })
-            })
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        for record_result in attendance_iter {
-            let record = record_result.map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-            writer
-                .write_record(&[
-                    &record.student_id,
-                    &record.student_name,
-                    &record.group_name,
-                    &record.date,
-                    &record.created_at,
-                ])
-                .map_err(|e| {
-                    crate::database::DatabaseError::Migration(format!(
-                        "Failed to write CSV record: {}",
-                        e
-                    ))
-                })?;
-        }
-
-        writer.flush().map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to flush CSV writer: {}", e))
-        })?;
-
-        log::info!("Exported attendance records to: {}", file_path);
-        Ok(())
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
     }
 
-    /// Export payment summary to CSV file
-    pub fn export_payment_summary_csv(
+    /// Active students with their payment state, by group and name
+    fn load_payment_summary(
         db: &Database,
-        file_path: &str,
         group_name: Option<&str>,
-    ) -> DatabaseResult<()> {
-        // Validate file path
-        let path = Path::new(file_path);
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                return Err(crate::database::DatabaseError::Migration(format!(
-                    "Directory does not exist: {}",
-                    parent.display()
-                )));
-            }
-        }
-
-        // Create CSV writer
-        let file = File::create(file_path).map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to create file: {}", e))
-        })?;
-        let mut writer = Writer::from_writer(file);
-
-        // Write CSV headers in Arabic
-        writer
-            .write_record(&[
-                "رقم الطالب",
-                "اسم الطالب",
-                "المجموعة",
-                "خطة الدفع",
-                "مبلغ الخطة",
-                "المبلغ المدفوع",
-                "حالة الدفع",
-                "تاريخ الاستحقاق التالي",
-                "تاريخ التسجيل",
-            ])
-            .map_err(|e| {
-                crate::database::DatabaseError::Migration(format!(
-                    "Failed to write CSV headers: {}",
-                    e
-                ))
-            })?;
-
+    ) -> DatabaseResult<Vec<PaymentSummaryExportRecord>> {
         // Build query with group filter
-        let mut query = "SELECT id, name, group_name, payment_plan, plan_amount, paid_amount, 
-                               payment_status, next_due_date, enrollment_date 
-                        FROM students WHERE deleted_at IS NULL"
+        let mut query = "SELECT id, name, group_name, payment_plan, plan_amount, paid_amount,
+                               payment_status, next_due_date, enrollment_date
+                        FROM students_view WHERE deleted_at IS NULL"
             .to_string();
         let mut params_vec = Vec::new();
 
@@ -239,19 +306,9 @@ impl ExportService {
 
         query.push_str(" ORDER BY group_name ASC, name ASC");
 
-        // Execute query and write records
-        let mut stmt = db
-            .connection()
-            .prepare(&query)
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec
-            .iter()
-            .map(|p| p as &dyn rusqlite::ToSql)
-            .collect();
-
-        let students_iter = stmt
-            .query_map(&params_refs[..], |row| {
+        let mut stmt = db.connection().prepare(&query)?;
+        let records = stmt
+            .query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
                 Ok(PaymentSummaryExportRecord {
                     student_id: row.get(0)?,
                     student_name: row.get(1)?,
@@ -263,106 +320,25 @@ impl ExportService {
                     next_due_date: row.get(7)?,
                     enrollment_date: row.get(8)?,
                 })
-            })
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        for record_result in students_iter {
-            let record = record_result.map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-            // Translate payment plan to Arabic
-            let payment_plan_ar = match record.payment_plan.as_str() {
-                "one-time" => "دفعة واحدة",
-                "monthly" => "شهري",
-                "installment" => "أقساط",
-                _ => &record.payment_plan,
-            };
-
-            // Translate payment status to Arabic
-            let payment_status_ar = match record.payment_status.as_str() {
-                "paid" => "مدفوع",
-                "pending" => "معلق",
-                "overdue" => "متأخر",
-                "due_soon" => "مستحق قريباً",
-                _ => &record.payment_status,
-            };
-
-            writer
-                .write_record(&[
-                    &record.student_id,
-                    &record.student_name,
-                    &record.group_name,
-                    payment_plan_ar,
-                    &record.plan_amount.to_string(),
-                    &record.paid_amount.to_string(),
-                    payment_status_ar,
-                    &record.next_due_date.unwrap_or_default(),
-                    &record.enrollment_date,
-                ])
-                .map_err(|e| {
-                    crate::database::DatabaseError::Migration(format!(
-                        "Failed to write CSV record: {}",
-                        e
-                    ))
-                })?;
-        }
-
-        writer.flush().map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to flush CSV writer: {}", e))
-        })?;
-
-        log::info!("Exported payment summary to: {}", file_path);
-        Ok(())
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
     }
 
-    /// Export payment transaction history to CSV file
-    pub fn export_payment_history_csv(
+    /// Payment transactions, newest first
+    fn load_payment_history(
         db: &Database,
-        file_path: &str,
         student_id: Option<&str>,
         start_date: Option<&str>,
         end_date: Option<&str>,
-    ) -> DatabaseResult<()> {
-        // Validate file path
-        let path = Path::new(file_path);
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                return Err(crate::database::DatabaseError::Migration(format!(
-                    "Directory does not exist: {}",
-                    parent.display()
-                )));
-            }
-        }
-
-        // Create CSV writer
-        let file = File::create(file_path).map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to create file: {}", e))
-        })?;
-        let mut writer = Writer::from_writer(file);
-
-        // Write CSV headers in Arabic
-        writer
-            .write_record(&[
-                "رقم الطالب",
-                "اسم الطالب",
-                "المجموعة",
-                "مبلغ الدفع",
-                "تاريخ الدفع",
-                "طريقة الدفع",
-                "ملاحظات",
-                "وقت التسجيل",
-            ])
-            .map_err(|e| {
-                crate::database::DatabaseError::Migration(format!(
-                    "Failed to write CSV headers: {}",
-                    e
-                ))
-            })?;
-
+        group_name: Option<&str>,
+    ) -> DatabaseResult<Vec<PaymentHistoryExportRecord>> {
         // Build query with filters
-        let mut query = "SELECT pt.student_id, s.name, s.group_name, pt.amount, pt.payment_date, 
+        let mut query = "SELECT pt.student_id, s.name, v.group_name, pt.amount, pt.payment_date,
                                pt.payment_method, pt.notes, pt.created_at
-                        FROM payment_transactions pt 
-                        JOIN students s ON pt.student_id = s.id"
+                        FROM payment_transactions pt
+                        JOIN students_view s ON pt.student_id = s.id
+                        JOIN payment_groups_view v ON v.id = pt.id"
             .to_string();
         let mut conditions = Vec::new();
         let mut params_vec = Vec::new();
@@ -384,6 +360,11 @@ impl ExportService {
             params_vec.push(end.to_string());
         }
 
+        if let Some(group) = group_name {
+            conditions.push("v.group_name = ?".to_string());
+            params_vec.push(group.to_string());
+        }
+
         if !conditions.is_empty() {
             query.push_str(" WHERE ");
             query.push_str(&conditions.join(" AND "));
@@ -391,19 +372,9 @@ impl ExportService {
 
         query.push_str(" ORDER BY pt.payment_date DESC, s.name ASC");
 
-        // Execute query and write records
-        let mut stmt = db
-            .connection()
-            .prepare(&query)
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec
-            .iter()
-            .map(|p| p as &dyn rusqlite::ToSql)
-            .collect();
-
-        let payment_iter = stmt
-            .query_map(&params_refs[..], |row| {
+        let mut stmt = db.connection().prepare(&query)?;
+        let records = stmt
+            .query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
                 Ok(PaymentHistoryExportRecord {
                     student_id: row.get(0)?,
                     student_name: row.get(1)?,
@@ -417,95 +388,20 @@ impl ExportService {
                         .format("%Y-%m-%d %H:%M:%S")
                         .to_string(),
                 })
-            })
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        for record_result in payment_iter {
-            let record = record_result.map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-            // Translate payment method to Arabic
-            let payment_method_ar = match record.payment_method.as_str() {
-                "cash" => "نقدي",
-                "bank_transfer" => "تحويل بنكي",
-                "check" => "شيك",
-                _ => &record.payment_method,
-            };
-
-            writer
-                .write_record(&[
-                    &record.student_id,
-                    &record.student_name,
-                    &record.group_name,
-                    &record.payment_amount.to_string(),
-                    &record.payment_date,
-                    payment_method_ar,
-                    &record.notes.unwrap_or_default(),
-                    &record.created_at,
-                ])
-                .map_err(|e| {
-                    crate::database::DatabaseError::Migration(format!(
-                        "Failed to write CSV record: {}",
-                        e
-                    ))
-                })?;
-        }
-
-        writer.flush().map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to flush CSV writer: {}", e))
-        })?;
-
-        log::info!("Exported payment history to: {}", file_path);
-        Ok(())
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
     }
 
-    /// Export overdue students report to CSV file
-    pub fn export_overdue_students_csv(
+    /// Overdue students with the amount they owe, longest overdue first
+    fn load_overdue_students(
         db: &Database,
-        file_path: &str,
         group_name: Option<&str>,
-    ) -> DatabaseResult<()> {
-        // Validate file path
-        let path = Path::new(file_path);
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                return Err(crate::database::DatabaseError::Migration(format!(
-                    "Directory does not exist: {}",
-                    parent.display()
-                )));
-            }
-        }
-
-        // Create CSV writer
-        let file = File::create(file_path).map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to create file: {}", e))
-        })?;
-        let mut writer = Writer::from_writer(file);
-
-        // Write CSV headers in Arabic
-        writer
-            .write_record(&[
-                "رقم الطالب",
-                "اسم الطالب",
-                "المجموعة",
-                "خطة الدفع",
-                "مبلغ الخطة",
-                "المبلغ المدفوع",
-                "المبلغ المستحق",
-                "تاريخ الاستحقاق التالي",
-                "أيام التأخير",
-                "تاريخ التسجيل",
-            ])
-            .map_err(|e| {
-                crate::database::DatabaseError::Migration(format!(
-                    "Failed to write CSV headers: {}",
-                    e
-                ))
-            })?;
-
+    ) -> DatabaseResult<Vec<OverdueStudentExportRecord>> {
         // Build query to get overdue students
-        let mut query = "SELECT id, name, group_name, payment_plan, plan_amount, paid_amount, 
-                               payment_status, next_due_date, enrollment_date 
-                        FROM students 
+        let mut query = "SELECT id, name, group_name, payment_plan, plan_amount, paid_amount,
+                               payment_status, next_due_date, enrollment_date
+                        FROM students_view
                         WHERE payment_status = 'overdue'"
             .to_string();
         let mut params_vec = Vec::new();
@@ -517,19 +413,9 @@ impl ExportService {
 
         query.push_str(" ORDER BY next_due_date ASC, name ASC");
 
-        // Execute query and write records
-        let mut stmt = db
-            .connection()
-            .prepare(&query)
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
-
-        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec
-            .iter()
-            .map(|p| p as &dyn rusqlite::ToSql)
-            .collect();
-
-        let students_iter = stmt
-            .query_map(&params_refs[..], |row| {
+        let mut stmt = db.connection().prepare(&query)?;
+        let records = stmt
+            .query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
                 let next_due_date: Option<String> = row.get(7)?;
                 let enrollment_date: String = row.get(8)?;
 
@@ -567,61 +453,1411 @@ impl ExportService {
                     payment_plan,
                     plan_amount,
                     paid_amount,
-                    amount_due: amount_due.max(0),
+                    amount_due,
                     next_due_date,
                     days_overdue,
                     enrollment_date,
                 })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Payments before a term rollover went to earlier terms, and debt carried from them is owed too
+        records
+            .into_iter()
+            .map(|mut record| {
+                let paid = TermService::paid_toward_term(
+                    db,
+                    &record.student_id,
+                    None,
+                    record.paid_amount as i64,
+                )?;
+                record.amount_due = (record.amount_due as i64 + record.paid_amount as i64 - paid)
+                    .clamp(0, i32::MAX as i64) as i32;
+                Ok(record)
             })
-            .map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+            .collect()
+    }
 
-        for record_result in students_iter {
-            let record = record_result.map_err(|e| crate::database::DatabaseError::Sqlite(e))?;
+    /// Export attendance records to CSV file
+    pub fn export_attendance_csv(
+        db: &Database,
+        file_path: &str,
+        start_date: Option<&str>,
+        end_date: Option<&str>,
+        group_name: Option<&str>,
+        locale: Locale,
+    ) -> DatabaseResult<()> {
+        let mut writer = Self::create_csv_writer(
+            file_path,
+            Self::headers(
+                locale,
+                &["student_id", "student_name", "group", "date", "recorded_at"],
+            ),
+        )?;
+
+        for record in Self::load_attendance(db, start_date, end_date, group_name)? {
+            Self::write_csv_record(
+                &mut writer,
+                &[
+                    &record.student_id,
+                    &record.student_name,
+                    &record.group_name,
+                    &record.date,
+                    &record.created_at,
+                ],
+            )?;
+        }
 
-            // Translate payment plan to Arabic
-            let payment_plan_ar = match record.payment_plan.as_str() {
-                "one-time" => "دفعة واحدة",
-                "monthly" => "شهري",
-                "installment" => "أقساط",
-                _ => &record.payment_plan,
-            };
+        Self::finish_csv(writer)?;
+        log::info!("Exported attendance records to: {}", file_path);
+        Ok(())
+    }
+
+    /// Export payment summary to CSV file
+    pub fn export_payment_summary_csv(
+        db: &Database,
+        file_path: &str,
+        group_name: Option<&str>,
+        locale: Locale,
+    ) -> DatabaseResult<()> {
+        let mut writer = Self::create_csv_writer(
+            file_path,
+            Self::headers(
+                locale,
+                &[
+                    "student_id",
+                    "student_name",
+                    "group",
+                    "payment_plan",
+                    "plan_amount",
+                    "paid_amount",
+                    "payment_status",
+                    "next_due_date",
+                    "enrollment_date",
+                ],
+            ),
+        )?;
+
+        for record in Self::load_payment_summary(db, group_name)? {
+            let payment_plan_label = i18n::label(locale, "payment_plan", &record.payment_plan);
+            let payment_status_label =
+                i18n::label(locale, "payment_status", &record.payment_status);
+
+            Self::write_csv_record(
+                &mut writer,
+                &[
+                    &record.student_id,
+                    &record.student_name,
+                    &record.group_name,
+                    &payment_plan_label,
+                    &record.plan_amount.to_string(),
+                    &record.paid_amount.to_string(),
+                    &payment_status_label,
+                    record.next_due_date.as_deref().unwrap_or_default(),
+                    &record.enrollment_date,
+                ],
+            )?;
+        }
+
+        Self::finish_csv(writer)?;
+        log::info!("Exported payment summary to: {}", file_path);
+        Ok(())
+    }
+
+    /// Export payment transaction history to CSV file
+    pub fn export_payment_history_csv(
+        db: &Database,
+        file_path: &str,
+        student_id: Option<&str>,
+        start_date: Option<&str>,
+        end_date: Option<&str>,
+        locale: Locale,
+    ) -> DatabaseResult<()> {
+        let mut writer = Self::create_csv_writer(
+            file_path,
+            Self::headers(
+                locale,
+                &[
+                    "student_id",
+                    "student_name",
+                    "group",
+                    "payment_amount",
+                    "payment_date",
+                    "payment_method",
+                    "notes",
+                    "recorded_at",
+                ],
+            ),
+        )?;
+
+        for record in Self::load_payment_history(db, student_id, start_date, end_date, None)? {
+            let payment_method_label =
+                i18n::label(locale, "payment_method", &record.payment_method);
+
+            Self::write_csv_record(
+                &mut writer,
+                &[
+                    &record.student_id,
+                    &record.student_name,
+                    &record.group_name,
+                    &record.payment_amount.to_string(),
+                    &record.payment_date,
+                    &payment_method_label,
+                    record.notes.as_deref().unwrap_or_default(),
+                    &record.created_at,
+                ],
+            )?;
+        }
 
-            writer
-                .write_record(&[
+        Self::finish_csv(writer)?;
+        log::info!("Exported payment history to: {}", file_path);
+        Ok(())
+    }
+
+    /// Export overdue students report to CSV file
+    pub fn export_overdue_students_csv(
+        db: &Database,
+        file_path: &str,
+        group_name: Option<&str>,
+        locale: Locale,
+    ) -> DatabaseResult<()> {
+        let mut writer = Self::create_csv_writer(
+            file_path,
+            Self::headers(
+                locale,
+                &[
+                    "student_id",
+                    "student_name",
+                    "group",
+                    "payment_plan",
+                    "plan_amount",
+                    "paid_amount",
+                    "amount_due",
+                    "next_due_date",
+                    "days_overdue",
+                    "enrollment_date",
+                ],
+            ),
+        )?;
+
+        for record in Self::load_overdue_students(db, group_name)? {
+            let payment_plan_label = i18n::label(locale, "payment_plan", &record.payment_plan);
+
+            Self::write_csv_record(
+                &mut writer,
+                &[
                     &record.student_id,
                     &record.student_name,
                     &record.group_name,
-                    payment_plan_ar,
+                    &payment_plan_label,
                     &record.plan_amount.to_string(),
                     &record.paid_amount.to_string(),
                     &record.amount_due.to_string(),
-                    &record.next_due_date.unwrap_or_default(),
+                    record.next_due_date.as_deref().unwrap_or_default(),
                     &record.days_overdue.to_string(),
                     &record.enrollment_date,
-                ])
-                .map_err(|e| {
-                    crate::database::DatabaseError::Migration(format!(
-                        "Failed to write CSV record: {}",
-                        e
-                    ))
-                })?;
+                ],
+            )?;
         }
 
-        writer.flush().map_err(|e| {
-            crate::database::DatabaseError::Migration(format!("Failed to flush CSV writer: {}", e))
-        })?;
-
+        Self::finish_csv(writer)?;
         log::info!("Exported overdue students report to: {}", file_path);
         Ok(())
     }
 
+    /// Export a workbook with summary, students, attendance, payments and overdue sheets.
+    /// Dates and amounts are real Excel values, so sorting and sums work without reformatting.
+    pub fn export_workbook_xlsx(
+        db: &Database,
+        file_path: &str,
+        start_date: Option<&str>,
+        end_date: Option<&str>,
+        group_name: Option<&str>,
+        locale: Locale,
+    ) -> DatabaseResult<WorkbookSummary> {
+        Self::ensure_parent_exists(file_path)?;
+
+        let students = Self::load_payment_summary(db, group_name)?;
+        let attendance = Self::load_attendance(db, start_date, end_date, group_name)?;
+        let payments = Self::load_payment_history(db, None, start_date, end_date, group_name)?;
+        let overdue = Self::load_overdue_students(db, group_name)?;
+
+        let summary = WorkbookSummary::from_records(&students, &attendance, &payments, &overdue);
+        Self::write_workbook(
+            file_path,
+            locale,
+            (start_date, end_date),
+            &summary,
+            &students,
+            &attendance,
+            &payments,
+            &overdue,
+        )
+        .map_err(|e| DatabaseError::Storage(format!("Failed to write XLSX file: {}", e)))?;
+
+        log::info!("Exported workbook to: {}", file_path);
+        Ok(summary)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn write_workbook(
+        file_path: &str,
+        locale: Locale,
+        period: (Option<&str>, Option<&str>),
+        summary: &WorkbookSummary,
+        students: &[PaymentSummaryExportRecord],
+        attendance: &[AttendanceExportRecord],
+        payments: &[PaymentHistoryExportRecord],
+        overdue: &[OverdueStudentExportRecord],
+    ) -> Result<(), XlsxError> {
+        let formats = XlsxFormats::new();
+        let mut workbook = Workbook::new();
+
+        workbook.push_worksheet(Self::summary_sheet(locale, period, summary, &formats)?);
+        workbook.push_worksheet(Self::students_sheet(locale, students, &formats)?);
+        workbook.push_worksheet(Self::attendance_sheet(
+            locale, students, attendance, &formats,
+        )?);
+        workbook.push_worksheet(Self::payments_sheet(locale, payments, &formats)?);
+        workbook.push_worksheet(Self::overdue_sheet(locale, overdue, &formats)?);
+
+        workbook.save(file_path)
+    }
+
+    /// A sheet with a frozen header row, laid out right to left for Arabic
+    fn new_sheet(
+        locale: Locale,
+        name_key: &str,
+        headers: &[String],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let mut sheet = Worksheet::new();
+        sheet.set_name(i18n::t(locale, name_key))?;
+        sheet.set_right_to_left(locale == Locale::Ar);
+        for (col, header) in headers.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, header, &formats.header)?;
+        }
+        sheet.set_freeze_panes(1, 0)?;
+        Ok(sheet)
+    }
+
+    /// Write a `YYYY-MM-DD` value as an Excel date, or as text when it does not parse
+    fn write_date(
+        sheet: &mut Worksheet,
+        row: u32,
+        col: u16,
+        value: &str,
+        formats: &XlsxFormats,
+    ) -> Result<(), XlsxError> {
+        match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(date) => sheet.write_datetime_with_format(row, col, date, &formats.date)?,
+            Err(_) => sheet.write_string(row, col, value)?,
+        };
+        Ok(())
+    }
+
+    fn write_datetime(
+        sheet: &mut Worksheet,
+        row: u32,
+        col: u16,
+        value: &str,
+        formats: &XlsxFormats,
+    ) -> Result<(), XlsxError> {
+        match NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+            Ok(datetime) => {
+                sheet.write_datetime_with_format(row, col, datetime, &formats.datetime)?
+            }
+            Err(_) => sheet.write_string(row, col, value)?,
+        };
+        Ok(())
+    }
+
+    fn summary_sheet(
+        locale: Locale,
+        period: (Option<&str>, Option<&str>),
+        summary: &WorkbookSummary,
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let mut sheet = Worksheet::new();
+        sheet.set_name(i18n::t(locale, "xlsx.sheet.summary"))?;
+        sheet.set_right_to_left(locale == Locale::Ar);
+
+        sheet.write_string_with_format(
+            0,
+            0,
+            i18n::t(locale, "xlsx.summary.title"),
+            &formats.header,
+        )?;
+        let period_text = match period {
+            (None, None) => i18n::t(locale, "xlsx.summary.all_dates"),
+            (start, end) => format!("{} – {}", start.unwrap_or(""), end.unwrap_or("")),
+        };
+        sheet.write_string(1, 0, i18n::t(locale, "xlsx.summary.period"))?;
+        sheet.write_string(1, 1, period_text)?;
+
+        let counts = [
+            ("xlsx.summary.student_count", summary.student_count),
+            ("xlsx.summary.session_count", summary.session_count),
+            ("xlsx.summary.attendance_count", summary.attendance_count),
+            ("xlsx.summary.payment_count", summary.payment_count),
+            ("xlsx.summary.overdue_count", summary.overdue_count),
+        ];
+        let amounts = [
+            ("xlsx.summary.total_plan_amount", summary.total_plan_amount),
+            ("xlsx.summary.total_paid_amount", summary.total_paid_amount),
+            ("xlsx.summary.payments_received", summary.payments_received),
+            ("xlsx.summary.total_amount_due", summary.total_amount_due),
+        ];
+
+        let mut row = 3;
+        for (key, count) in counts {
+            sheet.write_string(row, 0, i18n::t(locale, key))?;
+            sheet.write_number(row, 1, count as f64)?;
+            row += 1;
+        }
+        for (key, amount) in amounts {
+            sheet.write_string(row, 0, i18n::t(locale, key))?;
+            sheet.write_number_with_format(row, 1, amount as f64, &formats.amount)?;
+            row += 1;
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    fn students_sheet(
+        locale: Locale,
+        students: &[PaymentSummaryExportRecord],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let headers = Self::headers(
+            locale,
+            &[
+                "student_id",
+                "student_name",
+                "group",
+                "payment_plan",
+                "plan_amount",
+                "paid_amount",
+                "payment_status",
+                "next_due_date",
+                "enrollment_date",
+            ],
+        );
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.students", &headers, formats)?;
+
+        for (index, record) in students.iter().enumerate() {
+            let row = index as u32 + 1;
+            sheet.write_string(row, 0, &record.student_id)?;
+            sheet.write_string(row, 1, &record.student_name)?;
+            sheet.write_string(row, 2, &record.group_name)?;
+            sheet.write_string(
+                row,
+                3,
+                i18n::label(locale, "payment_plan", &record.payment_plan),
+            )?;
+            sheet.write_number_with_format(row, 4, record.plan_amount, &formats.amount)?;
+            sheet.write_number_with_format(row, 5, record.paid_amount, &formats.amount)?;
+            sheet.write_string(
+                row,
+                6,
+                i18n::label(locale, "payment_status", &record.payment_status),
+            )?;
+            if let Some(due) = &record.next_due_date {
+                Self::write_date(&mut sheet, row, 7, due, formats)?;
+            }
+            Self::write_date(&mut sheet, row, 8, &record.enrollment_date, formats)?;
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    /// One row per student and one column per session date, marking who attended
+    fn attendance_sheet(
+        locale: Locale,
+        students: &[PaymentSummaryExportRecord],
+        attendance: &[AttendanceExportRecord],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let dates: BTreeSet<&str> = attendance.iter().map(|a| a.date.as_str()).collect();
+        let attended: HashSet<(&str, &str)> = attendance
+            .iter()
+            .map(|a| (a.student_id.as_str(), a.date.as_str()))
+            .collect();
+
+        let headers = Self::headers(locale, &["student_id", "student_name"]);
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.attendance", &headers, formats)?;
+        let total_col = (dates.len() + 2) as u16;
+        for (index, date) in dates.iter().enumerate() {
+            let col = index as u16 + 2;
+            match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                Ok(day) => sheet.write_datetime_with_format(0, col, day, &formats.header_date)?,
+                Err(_) => sheet.write_string_with_format(0, col, *date, &formats.header)?,
+            };
+        }
+        sheet.write_string_with_format(
+            0,
+            total_col,
+            i18n::t(locale, "export.total"),
+            &formats.header,
+        )?;
+        // Keep the names in view while scrolling through the dates
+        sheet.set_freeze_panes(1, 2)?;
+
+        for (index, student) in students.iter().enumerate() {
+            let row = index as u32 + 1;
+            sheet.write_string(row, 0, &student.student_id)?;
+            sheet.write_string(row, 1, &student.student_name)?;
+
+            let mut present = 0;
+            for (offset, date) in dates.iter().enumerate() {
+                if attended.contains(&(student.student_id.as_str(), *date)) {
+                    sheet.write_string_with_format(
+                        row,
+                        offset as u16 + 2,
+                        PRESENT_MARK,
+                        &formats.centered,
+                    )?;
+                    present += 1;
+                }
+            }
+            sheet.write_number(row, total_col, present)?;
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    fn payments_sheet(
+        locale: Locale,
+        payments: &[PaymentHistoryExportRecord],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let headers = Self::headers(
+            locale,
+            &[
+                "student_id",
+                "student_name",
+                "group",
+                "payment_amount",
+                "payment_date",
+                "payment_method",
+                "notes",
+                "recorded_at",
+            ],
+        );
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.payments", &headers, formats)?;
+
+        for (index, record) in payments.iter().enumerate() {
+            let row = index as u32 + 1;
+            sheet.write_string(row, 0, &record.student_id)?;
+            sheet.write_string(row, 1, &record.student_name)?;
+            sheet.write_string(row, 2, &record.group_name)?;
+            sheet.write_number_with_format(row, 3, record.payment_amount, &formats.amount)?;
+            Self::write_date(&mut sheet, row, 4, &record.payment_date, formats)?;
+            sheet.write_string(
+                row,
+                5,
+                i18n::label(locale, "payment_method", &record.payment_method),
+            )?;
+            if let Some(notes) = &record.notes {
+                sheet.write_string(row, 6, notes)?;
+            }
+            Self::write_datetime(&mut sheet, row, 7, &record.created_at, formats)?;
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    fn overdue_sheet(
+        locale: Locale,
+        overdue: &[OverdueStudentExportRecord],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let headers = Self::headers(
+            locale,
+            &[
+                "student_id",
+                "student_name",
+                "group",
+                "payment_plan",
+                "plan_amount",
+                "paid_amount",
+                "amount_due",
+                "next_due_date",
+                "days_overdue",
+                "enrollment_date",
+            ],
+        );
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.overdue", &headers, formats)?;
+
+        for (index, record) in overdue.iter().enumerate() {
+            let row = index as u32 + 1;
+            sheet.write_string(row, 0, &record.student_id)?;
+            sheet.write_string(row, 1, &record.student_name)?;
+            sheet.write_string(row, 2, &record.group_name)?;
+            sheet.write_string(
+                row,
+                3,
+                i18n::label(locale, "payment_plan", &record.payment_plan),
+            )?;
+            sheet.write_number_with_format(row, 4, record.plan_amount, &formats.amount)?;
+            sheet.write_number_with_format(row, 5, record.paid_amount, &formats.amount)?;
+            sheet.write_number_with_format(row, 6, record.amount_due, &formats.amount)?;
+            if let Some(due) = &record.next_due_date {
+                Self::write_date(&mut sheet, row, 7, due, formats)?;
+            }
+            sheet.write_number(row, 8, record.days_overdue)?;
+            Self::write_date(&mut sheet, row, 9, &record.enrollment_date, formats)?;
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    /// Export a group's attendance register between two dates: one row per student, one column
+    /// per session, and attended counts and rates for every row and column
+    pub fn export_attendance_matrix(
+        db: &Database,
+        file_path: &str,
+        group_name: &str,
+        start_date: &str,
+        end_date: &str,
+        format: ExportFormat,
+        locale: Locale,
+    ) -> DatabaseResult<AttendanceMatrix> {
+        Self::ensure_parent_exists(file_path)?;
+        let matrix =
+            AttendanceService::get_attendance_matrix(db, group_name, start_date, end_date)?;
+
+        match format {
+            ExportFormat::Csv => {
+                let mut rows = Self::matrix_table(&matrix, locale).into_iter();
+                let mut writer =
+                    Self::create_csv_writer(file_path, rows.next().unwrap_or_default())?;
+                for row in rows {
+                    let row: Vec<&str> = row.iter().map(String::as_str).collect();
+                    Self::write_csv_record(&mut writer, &row)?;
+                }
+                Self::finish_csv(writer)?;
+            }
+            ExportFormat::Xlsx => {
+                let mut workbook = Workbook::new();
+                workbook.push_worksheet(
+                    Self::matrix_sheet(locale, &matrix, &XlsxFormats::new()).map_err(|e| {
+                        DatabaseError::Storage(format!("Failed to write XLSX file: {}", e))
+                    })?,
+                );
+                workbook.save(file_path).map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write XLSX file: {}", e))
+                })?;
+            }
+            ExportFormat::Pdf => {
+                let mut table = Self::matrix_table(&matrix, locale);
+                let font = Self::pdf_font(&table)?;
+                // Day and month are enough in the narrow date columns; the title has the year
+                for (header, date) in table[0][2..].iter_mut().zip(&matrix.dates) {
+                    if let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                        *header = day.format("%d/%m").to_string();
+                    }
+                }
+                let bytes = Self::matrix_pdf(&matrix, &table, locale, font).map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write PDF file: {}", e))
+                })?;
+                std::fs::write(file_path, bytes)
+                    .map_err(|e| DatabaseError::Storage(format!("Failed to create file: {}", e)))?;
+            }
+        }
+
+        log::info!(
+            "Exported attendance matrix of '{}' to: {}",
+            group_name,
+            file_path
+        );
+        Ok(matrix)
+    }
+
+    /// The register as text: a header row, one row per student, then the attended count and
+    /// the rate of every session
+    fn matrix_table(matrix: &AttendanceMatrix, locale: Locale) -> Vec<Vec<String>> {
+        let statuses = [
+            AttendanceStatus::Present,
+            AttendanceStatus::Late,
+            AttendanceStatus::Absent,
+            AttendanceStatus::Excused,
+        ];
+        let counts = |tally: &AttendanceTally| {
+            vec![
+                tally.present.to_string(),
+                tally.late.to_string(),
+                tally.absent.to_string(),
+                tally.excused.to_string(),
+                format!("{:.1}", tally.attendance_rate),
+            ]
+        };
+
+        let mut header = Self::headers(locale, &["student_id", "student_name"]);
+        header.extend(matrix.dates.iter().cloned());
+        header.extend(
+            statuses
+                .iter()
+                .map(|status| i18n::label(locale, "attendance_status", status.as_str())),
+        );
+        header.push(i18n::t(locale, "export.attendance_rate"));
+
+        let mut table = vec![header];
+        for row in &matrix.rows {
+            let mut line = vec![row.student_id.clone(), row.student_name.clone()];
+            line.extend(row.cells.iter().map(|cell| match cell {
+                Some(status) => i18n::label(locale, "attendance_code", status.as_str()),
+                None => String::new(),
+            }));
+            line.extend(counts(&row.totals));
+            table.push(line);
+        }
+
+        let mut attended = vec![String::new(), i18n::t(locale, "matrix.attended")];
+        attended.extend(
+            matrix
+                .date_totals
+                .iter()
+                .map(|tally| (tally.present + tally.late).to_string()),
+        );
+        attended.extend(counts(&matrix.totals));
+        table.push(attended);
+
+        let mut rates = vec![String::new(), i18n::t(locale, "export.attendance_rate")];
+        rates.extend(
+            matrix
+                .date_totals
+                .iter()
+                .map(|tally| format!("{:.1}", tally.attendance_rate)),
+        );
+        rates.extend(std::iter::repeat(String::new()).take(5));
+        table.push(rates);
+
+        table
+    }
+
+    fn matrix_sheet(
+        locale: Locale,
+        matrix: &AttendanceMatrix,
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let table = Self::matrix_table(matrix, locale);
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.matrix", &table[0], formats)?;
+        for (index, date) in matrix.dates.iter().enumerate() {
+            if let Ok(day) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                sheet.write_datetime_with_format(0, index as u16 + 2, day, &formats.header_date)?;
+            }
+        }
+        sheet.set_freeze_panes(1, 2)?;
+
+        let first_total = matrix.dates.len() as u16 + 2;
+        let footer_start = matrix.rows.len() + 1;
+        for (index, line) in table.iter().enumerate().skip(1) {
+            let row = index as u32;
+            for (col, value) in line.iter().enumerate() {
+                let col = col as u16;
+                if value.is_empty() {
+                    continue;
+                }
+                if index >= footer_start && col == 1 {
+                    sheet.write_string_with_format(row, col, value, &formats.header)?;
+                } else if col < 2 {
+                    sheet.write_string(row, col, value)?;
+                } else if col < first_total && index < footer_start {
+                    sheet.write_string_with_format(row, col, value, &formats.centered)?;
+                } else if let Ok(number) = value.parse::<f64>() {
+                    let format = if col == first_total + 4 || index == footer_start + 1 {
+                        &formats.percent
+                    } else {
+                        &formats.centered
+                    };
+                    sheet.write_number_with_format(row, col, number, format)?;
+                }
+            }
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    /// A font that can draw `table`: the builtin Helvetica for plain ASCII, otherwise the first
+    /// system font with Arabic glyphs
+    fn pdf_font(table: &[Vec<String>]) -> DatabaseResult<Option<Vec<u8>>> {
+        if table.iter().flatten().all(|text| text.is_ascii()) {
+            return Ok(None);
+        }
+        PDF_FONT_CANDIDATES
+            .iter()
+            .find_map(|path| std::fs::read(path).ok())
+            .map(Some)
+            .ok_or_else(|| {
+                DatabaseError::Storage(
+                    "No font with Arabic glyphs found; install Arial or DejaVu Sans".to_string(),
+                )
+            })
+    }
+
+    /// Landscape A4 pages with the header repeated on each; columns run right to left in Arabic
+    fn matrix_pdf(
+        matrix: &AttendanceMatrix,
+        table: &[Vec<String>],
+        locale: Locale,
+        font: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, printpdf::Error> {
+        use printpdf::{BuiltinFont, Line, Mm, PdfDocument, Point};
+
+        const PAGE_WIDTH: f32 = 297.0;
+        const PAGE_HEIGHT: f32 = 210.0;
+        const MARGIN: f32 = 12.0;
+        const ROW_HEIGHT: f32 = 6.0;
+        const PT_TO_MM: f32 = 0.3528;
+
+        let mut params = std::collections::BTreeMap::new();
+        params.insert("group".to_string(), matrix.group_name.clone());
+        params.insert("start".to_string(), matrix.start_date.clone());
+        params.insert("end".to_string(), matrix.end_date.clone());
+        let title = i18n::format(locale, "matrix.title", &params).unwrap_or_default();
+        let period = i18n::format(locale, "matrix.period", &params).unwrap_or_default();
+        let legend = i18n::t(locale, "matrix.legend");
+
+        // Student ID and name, the sessions, then four counts and the rate
+        let date_count = matrix.dates.len();
+        let fixed = [22.0, 45.0];
+        let total_width = 11.0;
+        let date_width = if date_count == 0 {
+            0.0
+        } else {
+            ((PAGE_WIDTH - 2.0 * MARGIN - fixed[0] - fixed[1] - 5.0 * total_width)
+                / date_count as f32)
+                .min(12.0)
+        };
+        let widths: Vec<f32> = fixed
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(date_width).take(date_count))
+            .chain(std::iter::repeat(total_width).take(5))
+            .collect();
+        let mut lefts = Vec::with_capacity(widths.len());
+        let mut x = MARGIN;
+        for width in &widths {
+            lefts.push(if locale == Locale::Ar {
+                PAGE_WIDTH - x - width
+            } else {
+                x
+            });
+            x += width;
+        }
+        let (table_left, table_right) = if locale == Locale::Ar {
+            (PAGE_WIDTH - x, PAGE_WIDTH - MARGIN)
+        } else {
+            (MARGIN, x)
+        };
+
+        let (document, first_page, first_layer) =
+            PdfDocument::new(title.clone(), Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "register");
+        let font = match font {
+            Some(data) => document.add_external_font(&data[..])?,
+            None => document.add_builtin_font(BuiltinFont::Helvetica)?,
+        };
+        let text_width =
+            |text: &str, size: f32| text.chars().count() as f32 * size * 0.5 * PT_TO_MM;
+        let date_size = if date_count == 0 {
+            7.0
+        } else {
+            (date_width / text_width("00/00", 1.0)).min(7.0)
+        };
+
+        let table_top = PAGE_HEIGHT - MARGIN - 16.0;
+        let rows_per_page = (((table_top - MARGIN - 8.0) / ROW_HEIGHT) as usize).max(2) - 1;
+        let (header, body) = table
+            .split_first()
+            .map_or((&[][..], &[][..]), |(h, b)| (&h[..], b));
+        let pages: Vec<&[Vec<String>]> = if body.is_empty() {
+            vec![&[]]
+        } else {
+            body.chunks(rows_per_page).collect()
+        };
+
+        for (page_index, rows) in pages.iter().enumerate() {
+            let layer = if page_index == 0 {
+                document.get_page(first_page).get_layer(first_layer)
+            } else {
+                let (page, layer) = document.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "register");
+                document.get_page(page).get_layer(layer)
+            };
+            layer.set_outline_thickness(0.3);
+
+            let heading_x = |text: &str, size: f32| {
+                if locale == Locale::Ar {
+                    table_right - text_width(text, size)
+                } else {
+                    table_left
+                }
+            };
+            let title_text = arabic_shaping::visual_order(&title);
+            let period_text = arabic_shaping::visual_order(&period);
+            layer.use_text(
+                title_text.as_str(),
+                14.0,
+                Mm(heading_x(&title_text, 14.0)),
+                Mm(PAGE_HEIGHT - MARGIN - 5.0),
+                &font,
+            );
+            layer.use_text(
+                period_text.as_str(),
+                9.0,
+                Mm(heading_x(&period_text, 9.0)),
+                Mm(PAGE_HEIGHT - MARGIN - 11.0),
+                &font,
+            );
+
+            let mut top = table_top;
+            let lines = std::iter::once(header).chain(rows.iter().map(|row| &row[..]));
+            for (line_index, line) in lines.enumerate() {
+                let bottom = top - ROW_HEIGHT;
+                for (col, value) in line.iter().enumerate() {
+                    if value.is_empty() || col >= widths.len() {
+                        continue;
+                    }
+                    let text = arabic_shaping::visual_order(value);
+                    let is_date = line_index == 0 && (2..2 + date_count).contains(&col);
+                    let size = if is_date { date_size } else { 7.0 };
+                    let width = text_width(&text, size);
+                    let x = if col == 1 && line_index > 0 {
+                        // Names start at the reading edge of their cell
+                        if locale == Locale::Ar {
+                            lefts[col] + widths[col] - 1.0 - width
+                        } else {
+                            lefts[col] + 1.0
+                        }
+                    } else {
+                        lefts[col] + (widths[col] - width).max(0.0) / 2.0
+                    };
+                    layer.use_text(text.as_str(), size, Mm(x), Mm(bottom + 1.8), &font);
+                }
+
+                layer.add_line(Line {
+                    points: vec![
+                        (Point::new(Mm(table_left), Mm(bottom)), false),
+                        (Point::new(Mm(table_right), Mm(bottom)), false),
+                    ],
+                    is_closed: false,
+                });
+                top = bottom;
+            }
+
+            // Frame and column rules
+            let edges = lefts
+                .iter()
+                .copied()
+                .chain([table_left, table_right])
+                .collect::<Vec<_>>();
+            for x in edges {
+                layer.add_line(Line {
+                    points: vec![
+                        (Point::new(Mm(x), Mm(table_top)), false),
+                        (Point::new(Mm(x), Mm(top)), false),
+                    ],
+                    is_closed: false,
+                });
+            }
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(table_left), Mm(table_top)), false),
+                    (Point::new(Mm(table_right), Mm(table_top)), false),
+                ],
+                is_closed: false,
+            });
+
+            if page_index == pages.len() - 1 {
+                let legend_text = arabic_shaping::visual_order(&legend);
+                layer.use_text(
+                    legend_text.as_str(),
+                    8.0,
+                    Mm(heading_x(&legend_text, 8.0)),
+                    Mm(top - 6.0),
+                    &font,
+                );
+            }
+        }
+
+        document.save_to_bytes()
+    }
+
+    /// Export the receivables aging report on `as_of`: one row per student who owes something,
+    /// a subtotal after each group and a grand total. The XLSX file adds a sheet listing every
+    /// unpaid due date.
+    pub fn export_aging_report(
+        db: &Database,
+        file_path: &str,
+        as_of: &str,
+        group_name: Option<&str>,
+        format: ExportFormat,
+        locale: Locale,
+    ) -> DatabaseResult<AgingReport> {
+        Self::ensure_parent_exists(file_path)?;
+        let report = ReceivablesService::get_aging_report(db, as_of, group_name)?;
+        let table = Self::aging_table(&report, locale);
+
+        match format {
+            ExportFormat::Csv => {
+                let mut rows = table.into_iter();
+                let mut writer =
+                    Self::create_csv_writer(file_path, rows.next().unwrap_or_default())?;
+                for row in rows {
+                    let row: Vec<&str> = row.iter().map(String::as_str).collect();
+                    Self::write_csv_record(&mut writer, &row)?;
+                }
+                Self::finish_csv(writer)?;
+            }
+            ExportFormat::Xlsx => {
+                let formats = XlsxFormats::new();
+                let mut workbook = Workbook::new();
+                let sheets = Self::aging_sheet(locale, &table, &formats).and_then(|summary| {
+                    Ok([summary, Self::aging_detail_sheet(locale, &report, &formats)?])
+                });
+                for sheet in sheets.map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write XLSX file: {}", e))
+                })? {
+                    workbook.push_worksheet(sheet);
+                }
+                workbook.save(file_path).map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write XLSX file: {}", e))
+                })?;
+            }
+            ExportFormat::Pdf => {
+                let font = Self::pdf_font(&table)?;
+                let mut params = std::collections::BTreeMap::new();
+                params.insert("date".to_string(), report.as_of.clone());
+                let title = match &report.group_name {
+                    Some(group) => format!("{} - {}", i18n::t(locale, "aging.title"), group),
+                    None => i18n::t(locale, "aging.title"),
+                };
+                let subtitle = i18n::format(locale, "aging.as_of", &params).unwrap_or_default();
+                let widths = [22.0, 50.0, 35.0, 22.0, 22.0, 22.0, 22.0, 24.0, 28.0];
+                let bytes = Self::table_pdf(&title, &subtitle, &table, &widths, 3, locale, font)
+                    .map_err(|e| {
+                        DatabaseError::Storage(format!("Failed to write PDF file: {}", e))
+                    })?;
+                std::fs::write(file_path, bytes)
+                    .map_err(|e| DatabaseError::Storage(format!("Failed to create file: {}", e)))?;
+            }
+        }
+
+        log::info!("Exported receivables aging report to: {}", file_path);
+        Ok(report)
+    }
+
+    /// The aging report as text: a header row, each group's students followed by the group's
+    /// subtotal, then the grand total
+    fn aging_table(report: &AgingReport, locale: Locale) -> Vec<Vec<String>> {
+        let amounts = |values: [i64; 5]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        let mut table = vec![Self::headers(
+            locale,
+            &[
+                "student_id",
+                "student_name",
+                "group",
+                "aging_0_30",
+                "aging_31_60",
+                "aging_61_90",
+                "aging_over_90",
+                "total",
+                "oldest_due_date",
+            ],
+        )];
+
+        for group in &report.groups {
+            for student in report
+                .students
+                .iter()
+                .filter(|s| s.group_name == group.group_name)
+            {
+                let mut line = vec![
+                    student.student_id.clone(),
+                    student.student_name.clone(),
+                    student.group_name.clone(),
+                ];
+                line.extend(amounts(student.buckets.values()));
+                line.push(student.oldest_due_date.clone().unwrap_or_default());
+                table.push(line);
+            }
+
+            let mut params = std::collections::BTreeMap::new();
+            params.insert("group".to_string(), group.group_name.clone());
+            let mut line = vec![
+                String::new(),
+                i18n::format(locale, "aging.group_total", &params).unwrap_or_default(),
+                group.group_name.clone(),
+            ];
+            line.extend(amounts(group.buckets.values()));
+            line.push(String::new());
+            table.push(line);
+        }
+
+        let mut line = vec![String::new(), i18n::t(locale, "export.total"), String::new()];
+        line.extend(amounts(report.totals.values()));
+        line.push(String::new());
+        table.push(line);
+
+        table
+    }
+
+    fn aging_sheet(
+        locale: Locale,
+        table: &[Vec<String>],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.aging", &table[0], formats)?;
+
+        for (index, line) in table.iter().enumerate().skip(1) {
+            let row = index as u32;
+            // Subtotal and total rows have no student ID
+            let is_total = line[0].is_empty();
+            for (col, value) in line.iter().enumerate() {
+                let col = col as u16;
+                if value.is_empty() {
+                    continue;
+                }
+                if (3..8).contains(&col) {
+                    let number = value.parse::<f64>().unwrap_or_default();
+                    sheet.write_number_with_format(row, col, number, &formats.amount)?;
+                } else if col == 8 {
+                    Self::write_date(&mut sheet, row, col, value, formats)?;
+                } else if is_total {
+                    sheet.write_string_with_format(row, col, value, &formats.header)?;
+                } else {
+                    sheet.write_string(row, col, value)?;
+                }
+            }
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    /// Every unpaid due date behind the aging report, for drilling down into a student
+    fn aging_detail_sheet(
+        locale: Locale,
+        report: &AgingReport,
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let headers = Self::headers(
+            locale,
+            &[
+                "student_id",
+                "student_name",
+                "group",
+                "due_date",
+                "days_overdue",
+                "amount_due",
+            ],
+        );
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.aging_detail", &headers, formats)?;
+
+        let items = report
+            .students
+            .iter()
+            .flat_map(|student| student.items.iter().map(move |item| (student, item)));
+        for (index, (student, item)) in items.enumerate() {
+            let row = index as u32 + 1;
+            sheet.write_string(row, 0, &student.student_id)?;
+            sheet.write_string(row, 1, &student.student_name)?;
+            sheet.write_string(row, 2, &student.group_name)?;
+            Self::write_date(&mut sheet, row, 3, &item.due_date, formats)?;
+            sheet.write_number(row, 4, item.days_past_due as f64)?;
+            sheet.write_number_with_format(row, 5, item.amount as f64, &formats.amount)?;
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    /// Export the cash-flow forecast with one row per period and a total row
+    pub fn export_cash_flow_forecast(
+        db: &Database,
+        file_path: &str,
+        request: &CashFlowForecastRequest,
+        today: NaiveDate,
+        format: ExportFormat,
+        locale: Locale,
+    ) -> DatabaseResult<CashFlowForecast> {
+        Self::ensure_parent_exists(file_path)?;
+        let forecast = AnalyticsService::get_cash_flow_forecast(db, request, today)?;
+        let table = Self::forecast_table(&forecast, locale);
+
+        match format {
+            ExportFormat::Csv => {
+                let mut rows = table.into_iter();
+                let mut writer =
+                    Self::create_csv_writer(file_path, rows.next().unwrap_or_default())?;
+                for row in rows {
+                    let row: Vec<&str> = row.iter().map(String::as_str).collect();
+                    Self::write_csv_record(&mut writer, &row)?;
+                }
+                Self::finish_csv(writer)?;
+            }
+            ExportFormat::Xlsx => {
+                let formats = XlsxFormats::new();
+                let mut workbook = Workbook::new();
+                let sheet = Self::forecast_sheet(locale, &table, &formats).map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write XLSX file: {}", e))
+                })?;
+                workbook.push_worksheet(sheet);
+                workbook.save(file_path).map_err(|e| {
+                    DatabaseError::Storage(format!("Failed to write XLSX file: {}", e))
+                })?;
+            }
+            ExportFormat::Pdf => {
+                let font = Self::pdf_font(&table)?;
+                let mut params = std::collections::BTreeMap::new();
+                params.insert("date".to_string(), forecast.as_of.clone());
+                params.insert("arrears".to_string(), forecast.arrears.to_string());
+                params.insert(
+                    "rate".to_string(),
+                    forecast
+                        .on_time_rate
+                        .map_or_else(|| "-".to_string(), |rate| format!("{}%", rate)),
+                );
+                let subtitle =
+                    i18n::format(locale, "forecast.subtitle", &params).unwrap_or_default();
+                let widths = [30.0, 30.0, 30.0, 40.0, 40.0, 40.0, 40.0];
+                let bytes = Self::table_pdf(
+                    &i18n::t(locale, "forecast.title"),
+                    &subtitle,
+                    &table,
+                    &widths,
+                    1,
+                    locale,
+                    font,
+                )
+                .map_err(|e| DatabaseError::Storage(format!("Failed to write PDF file: {}", e)))?;
+                std::fs::write(file_path, bytes)
+                    .map_err(|e| DatabaseError::Storage(format!("Failed to create file: {}", e)))?;
+            }
+        }
+
+        log::info!("Exported cash-flow forecast to: {}", file_path);
+        Ok(forecast)
+    }
+
+    /// The forecast as text: a header row, a row per period, then the totals
+    fn forecast_table(forecast: &CashFlowForecast, locale: Locale) -> Vec<Vec<String>> {
+        let mut table = vec![Self::headers(
+            locale,
+            &[
+                "period",
+                "start_date",
+                "end_date",
+                "scheduled",
+                "best_case",
+                "expected_case",
+                "worst_case",
+            ],
+        )];
+
+        for (index, period) in forecast.periods.iter().enumerate() {
+            table.push(vec![
+                period.label.clone(),
+                period.start_date.clone(),
+                period.end_date.clone(),
+                forecast.scheduled[index].to_string(),
+                forecast.best[index].to_string(),
+                forecast.expected[index].to_string(),
+                forecast.worst[index].to_string(),
+            ]);
+        }
+
+        let totals = &forecast.totals;
+        table.push(vec![
+            i18n::t(locale, "export.total"),
+            String::new(),
+            String::new(),
+            totals.scheduled.to_string(),
+            totals.best.to_string(),
+            totals.expected.to_string(),
+            totals.worst.to_string(),
+        ]);
+
+        table
+    }
+
+    fn forecast_sheet(
+        locale: Locale,
+        table: &[Vec<String>],
+        formats: &XlsxFormats,
+    ) -> Result<Worksheet, XlsxError> {
+        let mut sheet = Self::new_sheet(locale, "xlsx.sheet.forecast", &table[0], formats)?;
+        let total_row = table.len() - 1;
+
+        for (index, line) in table.iter().enumerate().skip(1) {
+            let row = index as u32;
+            for (col, value) in line.iter().enumerate() {
+                let col = col as u16;
+                if value.is_empty() {
+                    continue;
+                }
+                if col >= 3 {
+                    let number = value.parse::<f64>().unwrap_or_default();
+                    sheet.write_number_with_format(row, col, number, &formats.amount)?;
+                } else if col > 0 {
+                    Self::write_date(&mut sheet, row, col, value, formats)?;
+                } else if index == total_row {
+                    sheet.write_string_with_format(row, col, value, &formats.header)?;
+                } else {
+                    sheet.write_string(row, col, value)?;
+                }
+            }
+        }
+
+        sheet.autofit();
+        Ok(sheet)
+    }
+
+    /// A report table on landscape A4 pages with the header repeated on each. The first
+    /// `text_columns` columns start at the reading edge, the rest are centred; columns run
+    /// right to left in Arabic.
+    fn table_pdf(
+        title: &str,
+        subtitle: &str,
+        table: &[Vec<String>],
+        widths: &[f32],
+        text_columns: usize,
+        locale: Locale,
+        font: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, printpdf::Error> {
+        use printpdf::{BuiltinFont, Line, Mm, PdfDocument, Point};
+
+        const PAGE_WIDTH: f32 = 297.0;
+        const PAGE_HEIGHT: f32 = 210.0;
+        const MARGIN: f32 = 12.0;
+        const ROW_HEIGHT: f32 = 6.0;
+        const FONT_SIZE: f32 = 7.0;
+        const PT_TO_MM: f32 = 0.3528;
+
+        let mut lefts = Vec::with_capacity(widths.len());
+        let mut x = MARGIN;
+        for width in widths {
+            lefts.push(if locale == Locale::Ar {
+                PAGE_WIDTH - x - width
+            } else {
+                x
+            });
+            x += width;
+        }
+        let (table_left, table_right) = if locale == Locale::Ar {
+            (PAGE_WIDTH - x, PAGE_WIDTH - MARGIN)
+        } else {
+            (MARGIN, x)
+        };
+
+        let (document, first_page, first_layer) =
+            PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "report");
+        let font = match font {
+            Some(data) => document.add_external_font(&data[..])?,
+            None => document.add_builtin_font(BuiltinFont::Helvetica)?,
+        };
+        let text_width =
+            |text: &str, size: f32| text.chars().count() as f32 * size * 0.5 * PT_TO_MM;
+
+        let table_top = PAGE_HEIGHT - MARGIN - 16.0;
+        let rows_per_page = (((table_top - MARGIN) / ROW_HEIGHT) as usize).max(2) - 1;
+        let (header, body) = table
+            .split_first()
+            .map_or((&[][..], &[][..]), |(h, b)| (&h[..], b));
+        let pages: Vec<&[Vec<String>]> = if body.is_empty() {
+            vec![&[]]
+        } else {
+            body.chunks(rows_per_page).collect()
+        };
+
+        for (page_index, rows) in pages.iter().enumerate() {
+            let layer = if page_index == 0 {
+                document.get_page(first_page).get_layer(first_layer)
+            } else {
+                let (page, layer) = document.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "report");
+                document.get_page(page).get_layer(layer)
+            };
+            layer.set_outline_thickness(0.3);
+
+            for (text, size, y) in [(title, 14.0, 5.0), (subtitle, 9.0, 11.0)] {
+                let text = arabic_shaping::visual_order(text);
+                let x = if locale == Locale::Ar {
+                    table_right - text_width(&text, size)
+                } else {
+                    table_left
+                };
+                layer.use_text(text.as_str(), size, Mm(x), Mm(PAGE_HEIGHT - MARGIN - y), &font);
+            }
+
+            let mut top = table_top;
+            let lines = std::iter::once(header).chain(rows.iter().map(|row| &row[..]));
+            for (line_index, line) in lines.enumerate() {
+                let bottom = top - ROW_HEIGHT;
+                for (col, value) in line.iter().enumerate() {
+                    if value.is_empty() || col >= widths.len() {
+                        continue;
+                    }
+                    let text = arabic_shaping::visual_order(value);
+                    let width = text_width(&text, FONT_SIZE);
+                    let x = if col < text_columns && line_index > 0 {
+                        if locale == Locale::Ar {
+                            lefts[col] + widths[col] - 1.0 - width
+                        } else {
+                            lefts[col] + 1.0
+                        }
+                    } else {
+                        lefts[col] + (widths[col] - width).max(0.0) / 2.0
+                    };
+                    layer.use_text(text.as_str(), FONT_SIZE, Mm(x), Mm(bottom + 1.8), &font);
+                }
+
+                layer.add_line(Line {
+                    points: vec![
+                        (Point::new(Mm(table_left), Mm(bottom)), false),
+                        (Point::new(Mm(table_right), Mm(bottom)), false),
+                    ],
+                    is_closed: false,
+                });
+                top = bottom;
+            }
+
+            let edges = lefts
+                .iter()
+                .copied()
+                .chain([table_left, table_right])
+                .collect::<Vec<_>>();
+            for x in edges {
+                layer.add_line(Line {
+                    points: vec![
+                        (Point::new(Mm(x), Mm(table_top)), false),
+                        (Point::new(Mm(x), Mm(top)), false),
+                    ],
+                    is_closed: false,
+                });
+            }
+            layer.add_line(Line {
+                points: vec![
+                    (Point::new(Mm(table_left), Mm(table_top)), false),
+                    (Point::new(Mm(table_right), Mm(table_top)), false),
+                ],
+                is_closed: false,
+            });
+        }
+
+        document.save_to_bytes()
+    }
+
     /// Calculate days overdue from a due date
     fn calculate_days_overdue(due_date: &str) -> DatabaseResult<i32> {
         let due = NaiveDate::parse_from_str(due_date, "%Y-%m-%d").map_err(|_| {
-            crate::database::DatabaseError::Migration(format!(
-                "Invalid due date format: {}",
-                due_date
-            ))
+            DatabaseError::validation("due_date", format!("Invalid due date format: {}", due_date))
         })?;
 
         let today = Local::now().date_naive();
@@ -632,10 +1868,10 @@ impl ExportService {
     /// Calculate months since enrollment
     fn calculate_months_since_enrollment(enrollment_date: &str) -> DatabaseResult<i32> {
         let enrollment = NaiveDate::parse_from_str(enrollment_date, "%Y-%m-%d").map_err(|_| {
-            crate::database::DatabaseError::Migration(format!(
-                "Invalid enrollment date format: {}",
-                enrollment_date
-            ))
+            DatabaseError::validation(
+                "enrollment_date",
+                format!("Invalid enrollment date format: {}", enrollment_date),
+            )
         })?;
 
         let today = Local::now().date_naive();
@@ -646,17 +1882,7 @@ impl ExportService {
 
     /// Validate that the file path is writable
     pub fn validate_export_path(file_path: &str) -> DatabaseResult<()> {
-        let path = Path::new(file_path);
-
-        // Check if parent directory exists
-        if let Some(parent) = path.parent() {
-            if !parent.exists() {
-                return Err(crate::database::DatabaseError::Migration(format!(
-                    "Directory does not exist: {}",
-                    parent.display()
-                )));
-            }
-        }
+        Self::ensure_parent_exists(file_path)?;
 
         // Check if we can create/write to the file
         match File::create(file_path) {
@@ -665,7 +1891,7 @@ impl ExportService {
                 let _ = std::fs::remove_file(file_path);
                 Ok(())
             }
-            Err(e) => Err(crate::database::DatabaseError::Migration(format!(
+            Err(e) => Err(DatabaseError::Storage(format!(
                 "Cannot write to file {}: {}",
                 file_path, e
             ))),
@@ -704,8 +1930,8 @@ mod tests {
         create_test_student(&db, "student2", "Test Student 2", "Group B");
 
         // Mark some attendance
-        let _ = AttendanceService::mark_attendance(&db, "student1", "2024-01-15");
-        let _ = AttendanceService::mark_attendance(&db, "student2", "2024-01-16");
+        let _ = AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15");
+        let _ = AttendanceService::mark_attendance(&db, "student2", None, "2024-01-16");
 
         // Export to CSV
         let export_path = temp_dir.path().join("attendance_export.csv");
@@ -715,6 +1941,7 @@ mod tests {
             None,
             None,
             None,
+            Locale::Ar,
         );
 
         assert!(result.is_ok());
@@ -735,8 +1962,12 @@ mod tests {
 
         // Export to CSV
         let export_path = temp_dir.path().join("payment_summary_export.csv");
-        let result =
-            ExportService::export_payment_summary_csv(&db, export_path.to_str().unwrap(), None);
+        let result = ExportService::export_payment_summary_csv(
+            &db,
+            export_path.to_str().unwrap(),
+            None,
+            Locale::Ar,
+        );
 
         assert!(result.is_ok());
         assert!(export_path.exists());
@@ -748,6 +1979,26 @@ mod tests {
         assert!(content.contains("دفعة واحدة")); // Arabic translation for "one-time"
     }
 
+    #[test]
+    fn test_export_payment_summary_csv_in_english() {
+        let (db, temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Test Student 1", "Group A");
+
+        let export_path = temp_dir.path().join("payment_summary_en.csv");
+        ExportService::export_payment_summary_csv(
+            &db,
+            export_path.to_str().unwrap(),
+            None,
+            Locale::En,
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(&export_path).unwrap();
+        assert!(content.starts_with("\u{feff}Student ID,Student Name,Group,Payment Plan"));
+        assert!(content.contains("One-time"));
+        assert!(!content.contains("رقم الطالب"));
+    }
+
     #[test]
     fn test_export_with_group_filter() {
         let (db, temp_dir) = setup_test_db();
@@ -760,6 +2011,7 @@ mod tests {
             &db,
             export_path.to_str().unwrap(),
             Some("Group A"),
+            Locale::Ar,
         );
 
         assert!(result.is_ok());
@@ -771,6 +2023,220 @@ mod tests {
         assert!(!content.contains("Test Student 2"));
     }
 
+    #[test]
+    fn test_export_workbook_xlsx() {
+        let (db, temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Test Student 1", "Group A");
+        create_test_student(&db, "student2", "Test Student 2", "Group A");
+        create_test_student(&db, "student3", "Test Student 3", "Group B");
+
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student2", None, "2024-01-15").unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-22").unwrap();
+        AttendanceService::mark_attendance(&db, "student3", None, "2024-01-22").unwrap();
+
+        let export_path = temp_dir.path().join("report.xlsx");
+        let summary = ExportService::export_workbook_xlsx(
+            &db,
+            export_path.to_str().unwrap(),
+            Some("2024-01-01"),
+            Some("2024-01-31"),
+            Some("Group A"),
+            Locale::Ar,
+        )
+        .unwrap();
+
+        assert_eq!(summary.student_count, 2);
+        assert_eq!(summary.session_count, 2);
+        assert_eq!(summary.attendance_count, 3);
+        assert_eq!(summary.total_plan_amount, 12000);
+
+        // XLSX files are zip archives
+        let bytes = fs::read(&export_path).unwrap();
+        assert!(bytes.starts_with(b"PK"));
+    }
+
+    #[test]
+    fn test_export_attendance_matrix() {
+        let (db, temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Test Student 1", "Group A");
+        create_test_student(&db, "student2", "Test Student 2", "Group A");
+
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-15").unwrap();
+        AttendanceService::set_attendance_status(
+            &db,
+            "student2",
+            None,
+            "2024-01-15",
+            AttendanceStatus::Late,
+        )
+        .unwrap();
+        AttendanceService::mark_attendance(&db, "student1", None, "2024-01-22").unwrap();
+
+        let csv_path = temp_dir.path().join("register.csv");
+        let matrix = ExportService::export_attendance_matrix(
+            &db,
+            csv_path.to_str().unwrap(),
+            "Group A",
+            "2024-01-01",
+            "2024-01-31",
+            ExportFormat::Csv,
+            Locale::En,
+        )
+        .unwrap();
+        assert_eq!(matrix.totals.sessions, 4);
+
+        let content = fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = content.trim_start_matches('\u{feff}').lines().collect();
+        assert_eq!(
+            lines[0],
+            "Student ID,Student Name,2024-01-15,2024-01-22,Present,Late,Absent,Excused,Attendance %"
+        );
+        assert_eq!(lines[1], "student1,Test Student 1,P,P,2,0,0,0,100.0");
+        assert_eq!(lines[2], "student2,Test Student 2,L,A,0,1,1,0,50.0");
+        assert_eq!(lines[3], ",Attended,2,1,2,1,1,0,75.0");
+        assert_eq!(lines[4], ",Attendance %,100.0,50.0,,,,,");
+
+        let xlsx_path = temp_dir.path().join("register.xlsx");
+        let pdf_path = temp_dir.path().join("register.pdf");
+        for (path, format) in [
+            (&xlsx_path, ExportFormat::Xlsx),
+            (&pdf_path, ExportFormat::Pdf),
+        ] {
+            ExportService::export_attendance_matrix(
+                &db,
+                path.to_str().unwrap(),
+                "Group A",
+                "2024-01-01",
+                "2024-01-31",
+                format,
+                Locale::En,
+            )
+            .unwrap();
+        }
+        assert!(fs::read(&xlsx_path).unwrap().starts_with(b"PK"));
+        assert!(fs::read(&pdf_path).unwrap().starts_with(b"%PDF"));
+
+        assert!(ExportFormat::from_str("docx").is_err());
+    }
+
+    #[test]
+    fn test_export_aging_report() {
+        let (db, temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Test Student 1", "Group A");
+        create_test_student(&db, "student2", "Test Student 2", "Group B");
+        db.connection()
+            .execute(
+                "UPDATE students SET paid_amount = 2000, next_due_date = '2024-03-01' WHERE id = 'student2'",
+                [],
+            )
+            .unwrap();
+
+        let csv_path = temp_dir.path().join("aging.csv");
+        let report = ExportService::export_aging_report(
+            &db,
+            csv_path.to_str().unwrap(),
+            "2024-04-15",
+            None,
+            ExportFormat::Csv,
+            Locale::En,
+        )
+        .unwrap();
+        assert_eq!(report.totals.total, 10000);
+
+        let content = fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = content.trim_start_matches('\u{feff}').lines().collect();
+        assert_eq!(
+            lines[0],
+            "Student ID,Student Name,Group,0-30 Days,31-60 Days,61-90 Days,90+ Days,Total,Oldest Due Date"
+        );
+        assert_eq!(lines[1], "student1,Test Student 1,Group A,0,0,0,6000,6000,2024-01-01");
+        assert_eq!(lines[2], ",Group A total,Group A,0,0,0,6000,6000,");
+        assert_eq!(lines[3], "student2,Test Student 2,Group B,0,4000,0,0,4000,2024-03-01");
+        assert_eq!(lines[4], ",Group B total,Group B,0,4000,0,0,4000,");
+        assert_eq!(lines[5], ",Total,,0,4000,0,6000,10000,");
+
+        let xlsx_path = temp_dir.path().join("aging.xlsx");
+        let pdf_path = temp_dir.path().join("aging.pdf");
+        for (path, format) in [
+            (&xlsx_path, ExportFormat::Xlsx),
+            (&pdf_path, ExportFormat::Pdf),
+        ] {
+            ExportService::export_aging_report(
+                &db,
+                path.to_str().unwrap(),
+                "2024-04-15",
+                Some("Group A"),
+                format,
+                Locale::En,
+            )
+            .unwrap();
+        }
+        assert!(fs::read(&xlsx_path).unwrap().starts_with(b"PK"));
+        assert!(fs::read(&pdf_path).unwrap().starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn test_export_cash_flow_forecast() {
+        let (db, temp_dir) = setup_test_db();
+        create_test_student(&db, "student1", "Test Student 1", "Group A");
+        create_test_student(&db, "student2", "Test Student 2", "Group B");
+        db.connection()
+            .execute(
+                "UPDATE students SET paid_amount = 2000, next_due_date = '2024-05-10' WHERE id = 'student2'",
+                [],
+            )
+            .unwrap();
+
+        let request = CashFlowForecastRequest {
+            months: 1,
+            interval: crate::analytics_service::SeriesInterval::Month,
+        };
+        let today = NaiveDate::from_ymd_opt(2024, 4, 15).unwrap();
+        let csv_path = temp_dir.path().join("forecast.csv");
+        let forecast = ExportService::export_cash_flow_forecast(
+            &db,
+            csv_path.to_str().unwrap(),
+            &request,
+            today,
+            ExportFormat::Csv,
+            Locale::En,
+        )
+        .unwrap();
+        assert_eq!(forecast.arrears, 6000);
+
+        let content = fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = content.trim_start_matches('\u{feff}').lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Period,From,To,Scheduled,Best Case,Expected,Worst Case",
+                "2024-04,2024-04-01,2024-04-30,0,6000,0,0",
+                "2024-05,2024-05-01,2024-05-31,4000,4000,0,0",
+                "Total,,,4000,10000,0,0",
+            ]
+        );
+
+        let xlsx_path = temp_dir.path().join("forecast.xlsx");
+        let pdf_path = temp_dir.path().join("forecast.pdf");
+        for (path, format) in [
+            (&xlsx_path, ExportFormat::Xlsx),
+            (&pdf_path, ExportFormat::Pdf),
+        ] {
+            ExportService::export_cash_flow_forecast(
+                &db,
+                path.to_str().unwrap(),
+                &request,
+                today,
+                format,
+                Locale::En,
+            )
+            .unwrap();
+        }
+        assert!(fs::read(&xlsx_path).unwrap().starts_with(b"PK"));
+        assert!(fs::read(&pdf_path).unwrap().starts_with(b"%PDF"));
+    }
+
     #[test]
     fn test_validate_export_path() {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
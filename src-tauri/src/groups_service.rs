@@ -1,5 +1,8 @@
 use crate::audit_service::AuditService;
-use crate::database::{Database, DatabaseResult};
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::enrollment_service::EnrollmentService;
+use crate::membership_service::MembershipService;
+use crate::term_service::TERM_GROUP_MEMBERS_SQL;
 use chrono::Utc;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
@@ -8,13 +11,55 @@ use serde::{Deserialize, Serialize};
 pub struct Group {
     pub id: i32,
     pub name: String,
+    pub capacity: Option<i32>,
+    pub room: Option<String>,
+    pub level: Option<String>,
+    pub subject: Option<String>,
+    pub teachers: Vec<String>,
+    pub is_active: bool,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// Scheduling details of a group; a `capacity` of `None` means unlimited seats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupSettings {
+    #[serde(default)]
+    pub capacity: Option<i32>,
+    #[serde(default)]
+    pub room: Option<String>,
+    #[serde(default)]
+    pub level: Option<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub teachers: Vec<String>,
+    #[serde(default = "default_is_active")]
+    pub is_active: bool,
+}
+
+fn default_is_active() -> bool {
+    true
+}
+
+impl Default for GroupSettings {
+    fn default() -> Self {
+        Self {
+            capacity: None,
+            room: None,
+            level: None,
+            subject: None,
+            teachers: Vec::new(),
+            is_active: true,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateGroupRequest {
     pub name: String,
+    #[serde(default)]
+    pub settings: GroupSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,51 +72,99 @@ pub struct GroupWithStudentCount {
     pub id: i32,
     pub name: String,
     pub student_count: i32,
+    pub capacity: Option<i32>,
+    pub seats_remaining: Option<i32>,
+    pub waitlist_count: i32,
+    pub is_active: bool,
     pub created_at: String,
     pub updated_at: String,
 }
 
+const GROUP_COLUMNS: &str =
+    "id, name, created_at, updated_at, capacity, room, level, subject, is_active";
+
+/// Active group memberships: each student's own group plus their active enrollments
+const GROUP_MEMBERS_SQL: &str =
+    "SELECT s.id AS student_id, s.group_id FROM students s WHERE s.deleted_at IS NULL
+     UNION
+     SELECT e.student_id, e.group_id FROM enrollments e
+     JOIN students s ON s.id = e.student_id
+     WHERE e.status = 'active' AND s.deleted_at IS NULL";
+
 pub struct GroupsService;
 
 impl GroupsService {
-    /// Get all groups
-    pub fn get_all_groups(db: &Database) -> DatabaseResult<Vec<Group>> {
-        let mut stmt = db
-            .connection()
-            .prepare("SELECT id, name, created_at, updated_at FROM groups WHERE deleted_at IS NULL ORDER BY name")?;
+    /// Helper function to map database row to Group struct; teachers are loaded separately
+    fn map_row_to_group(row: &rusqlite::Row) -> rusqlite::Result<Group> {
+        Ok(Group {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            created_at: row.get(2)?,
+            updated_at: row.get(3)?,
+            capacity: row.get(4)?,
+            room: row.get(5)?,
+            level: row.get(6)?,
+            subject: row.get(7)?,
+            is_active: row.get(8)?,
+            teachers: Vec::new(),
+        })
+    }
 
-        let group_iter = stmt.query_map([], |row| {
-            Ok(Group {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
-                updated_at: row.get(3)?,
-            })
-        })?;
+    /// Attach the assigned teachers to a group
+    fn with_teachers(db: &Database, mut group: Group) -> DatabaseResult<Group> {
+        let mut stmt = db.connection().prepare(
+            "SELECT teacher_name FROM group_teachers WHERE group_id = ?1 ORDER BY position, id",
+        )?;
+        group.teachers = stmt
+            .query_map([group.id], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(group)
+    }
 
-        let groups: Result<Vec<Group>, _> = group_iter.collect();
-        groups.map_err(Into::into)
+    /// Get all groups
+    pub fn get_all_groups(db: &Database) -> DatabaseResult<Vec<Group>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM groups WHERE deleted_at IS NULL ORDER BY name",
+            GROUP_COLUMNS
+        ))?;
+
+        let groups = stmt
+            .query_map([], Self::map_row_to_group)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        groups
+            .into_iter()
+            .map(|group| Self::with_teachers(db, group))
+            .collect()
     }
 
     /// Get all groups with student counts
     pub fn get_all_groups_with_counts(db: &Database) -> DatabaseResult<Vec<GroupWithStudentCount>> {
-        let mut stmt = db.connection().prepare(
+        let mut stmt = db.connection().prepare(&format!(
             "SELECT g.id, g.name, g.created_at, g.updated_at, 
-                    COUNT(s.id) as student_count
+                    COUNT(m.student_id) as student_count, g.capacity, g.is_active,
+                    (SELECT COUNT(*) FROM enrollments w WHERE w.group_id = g.id AND w.status = 'waitlisted') as waitlist_count
              FROM groups g
-             LEFT JOIN students s ON g.name = s.group_name AND s.deleted_at IS NULL
+             LEFT JOIN ({}) m ON g.id = m.group_id
              WHERE g.deleted_at IS NULL
-             GROUP BY g.id, g.name, g.created_at, g.updated_at
+             GROUP BY g.id, g.name, g.created_at, g.updated_at, g.capacity, g.is_active
              ORDER BY g.name",
-        )?;
+            GROUP_MEMBERS_SQL
+        ))?;
 
         let group_iter = stmt.query_map([], |row| {
+            let student_count: i32 = row.get(4)?;
+            let capacity: Option<i32> = row.get(5)?;
             Ok(GroupWithStudentCount {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 created_at: row.get(2)?,
                 updated_at: row.get(3)?,
-                student_count: row.get(4)?,
+                student_count,
+                capacity,
+                seats_remaining: capacity.map(|capacity| (capacity - student_count).max(0)),
+                waitlist_count: row.get(7)?,
+                is_active: row.get(6)?,
             })
         })?;
 
@@ -81,235 +174,405 @@ impl GroupsService {
 
     /// Get a group by ID
     pub fn get_group_by_id(db: &Database, id: i32) -> DatabaseResult<Option<Group>> {
-        let mut stmt = db
-            .connection()
-            .prepare("SELECT id, name, created_at, updated_at FROM groups WHERE id = ?1 AND deleted_at IS NULL")?;
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM groups WHERE id = ?1 AND deleted_at IS NULL",
+            GROUP_COLUMNS
+        ))?;
 
-        let mut group_iter = stmt.query_map([id], |row| {
-            Ok(Group {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
-                updated_at: row.get(3)?,
-            })
-        })?;
+        let mut group_iter = stmt.query_map([id], Self::map_row_to_group)?;
 
         match group_iter.next() {
-            Some(group) => Ok(Some(group?)),
+            Some(group) => Ok(Some(Self::with_teachers(db, group?)?)),
             None => Ok(None),
         }
     }
 
     /// Get a group by name
     pub fn get_group_by_name(db: &Database, name: &str) -> DatabaseResult<Option<Group>> {
-        let mut stmt = db
-            .connection()
-            .prepare("SELECT id, name, created_at, updated_at FROM groups WHERE name = ?1 AND deleted_at IS NULL")?;
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM groups WHERE name = ?1 AND deleted_at IS NULL",
+            GROUP_COLUMNS
+        ))?;
 
-        let mut group_iter = stmt.query_map([name], |row| {
-            Ok(Group {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at: row.get(2)?,
-                updated_at: row.get(3)?,
-            })
-        })?;
+        let mut group_iter = stmt.query_map([name], Self::map_row_to_group)?;
 
         match group_iter.next() {
-            Some(group) => Ok(Some(group?)),
+            Some(group) => Ok(Some(Self::with_teachers(db, group?)?)),
             None => Ok(None),
         }
     }
 
+    /// Find the group a free-text name refers to, ignoring surrounding whitespace and case.
+    /// A matching soft-deleted group is restored; otherwise a new group is created.
+    pub fn resolve_group(db: &Database, name: &str) -> DatabaseResult<Group> {
+        let name = name.trim();
+
+        let existing = db.connection().query_row(
+            &format!(
+                "SELECT {}, deleted_at IS NOT NULL FROM groups
+                 WHERE TRIM(name) = ?1 COLLATE NOCASE
+                 ORDER BY deleted_at IS NOT NULL, name = ?1 DESC, id
+                 LIMIT 1",
+                GROUP_COLUMNS
+            ),
+            [name],
+            |row| Ok((Self::map_row_to_group(row)?, row.get::<_, bool>(9)?)),
+        );
+
+        match existing {
+            Ok((group, false)) => Self::with_teachers(db, group),
+            Ok((group, true)) => {
+                db.connection().execute(
+                    "UPDATE groups SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+                    params![Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(), group.id],
+                )?;
+                Self::with_teachers(db, group)
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => Self::create_group(
+                db,
+                CreateGroupRequest {
+                    name: name.to_string(),
+                    settings: GroupSettings::default(),
+                },
+            ),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Create a new group
     pub fn create_group(db: &Database, request: CreateGroupRequest) -> DatabaseResult<Group> {
-        // Validate group name
-        if request.name.trim().is_empty() {
-            return Err(crate::database::DatabaseError::Migration(
-                "Group name cannot be empty".to_string(),
-            ));
-        }
+        AuditService::audited(db, |audit| {
+            // Validate group name
+            if request.name.trim().is_empty() {
+                return Err(DatabaseError::validation(
+                    "name",
+                    "Group name cannot be empty",
+                ));
+            }
 
-        // Check if group already exists
-        if Self::get_group_by_name(db, &request.name)?.is_some() {
-            return Err(crate::database::DatabaseError::Migration(format!(
-                "Group '{}' already exists",
-                request.name
-            )));
-        }
+            // Surrounding whitespace would create a look-alike group
+            let name = request.name.trim().to_string();
+            let settings = Self::normalize_settings(request.settings)?;
 
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            // Check if group already exists
+            if Self::get_group_by_name(db, &name)?.is_some() {
+                return Err(DatabaseError::conflict(
+                    "group_exists",
+                    format!("Group '{}' already exists", name),
+                ));
+            }
 
-        // Insert the group
-        db.connection().execute(
-            "INSERT INTO groups (name, created_at, updated_at) VALUES (?1, ?2, ?3)",
-            params![request.name, now, now],
-        )?;
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+            // Insert the group
+            db.connection().execute(
+                "INSERT INTO groups (name, capacity, room, level, subject, is_active, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    name,
+                    settings.capacity,
+                    settings.room,
+                    settings.level,
+                    settings.subject,
+                    settings.is_active,
+                    now,
+                    now
+                ],
+            )?;
 
-        let group_id = db.connection().last_insert_rowid() as i32;
-
-        // Create audit log entry
-        let new_values = serde_json::json!({
-            "id": group_id,
-            "name": request.name,
-            "created_at": now,
-            "updated_at": now
-        });
-
-        AuditService::log_action(
-            db,
-            "CREATE",
-            "groups",
-            &group_id.to_string(),
-            None,
-            Some(&new_values.to_string()),
-            None,
-        )?;
+            let group_id = db.connection().last_insert_rowid() as i32;
+            Self::replace_teachers(db, group_id, &settings.teachers)?;
+
+            let group = Group {
+                id: group_id,
+                name,
+                capacity: settings.capacity,
+                room: settings.room,
+                level: settings.level,
+                subject: settings.subject,
+                teachers: settings.teachers,
+                is_active: settings.is_active,
+                created_at: now.clone(),
+                updated_at: now,
+            };
 
-        // Return the created group
-        Ok(Group {
-            id: group_id,
-            name: request.name,
-            created_at: now.clone(),
-            updated_at: now,
+            // Create audit log entry
+            audit.create("groups", &group_id.to_string(), &group, None);
+
+            // Return the created group
+            Ok(group)
         })
     }
 
-    /// Update a group
-    pub fn update_group(db: &Database, id: i32, request: UpdateGroupRequest) -> DatabaseResult<()> {
-        // Validate group name
-        if request.name.trim().is_empty() {
-            return Err(crate::database::DatabaseError::Migration(
-                "Group name cannot be empty".to_string(),
-            ));
+    /// Trim and validate group settings
+    fn normalize_settings(settings: GroupSettings) -> DatabaseResult<GroupSettings> {
+        if let Some(capacity) = settings.capacity {
+            if capacity <= 0 {
+                return Err(DatabaseError::validation(
+                    "capacity",
+                    "Group capacity must be positive",
+                ));
+            }
         }
 
-        // Get the existing group for audit logging
-        let existing_group = Self::get_group_by_id(db, id)?.ok_or_else(|| {
-            crate::database::DatabaseError::Migration(format!("Group with ID {} not found", id))
-        })?;
+        let clean = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
 
-        // Check if another group with the same name exists (excluding current group)
-        let mut stmt = db
-            .connection()
-            .prepare("SELECT COUNT(*) FROM groups WHERE name = ?1 AND id != ?2")?;
-        let count: i32 = stmt.query_row(params![request.name, id], |row| row.get(0))?;
-
-        if count > 0 {
-            return Err(crate::database::DatabaseError::Migration(format!(
-                "Group '{}' already exists",
-                request.name
-            )));
+        let mut teachers: Vec<String> = Vec::new();
+        for teacher in settings.teachers {
+            let teacher = teacher.trim().to_string();
+            if teacher.is_empty() {
+                continue;
+            }
+            if teacher.len() > 100 {
+                return Err(DatabaseError::validation(
+                    "teachers",
+                    "Teacher name cannot be longer than 100 characters",
+                ));
+            }
+            if !teachers.iter().any(|t| t.eq_ignore_ascii_case(&teacher)) {
+                teachers.push(teacher);
+            }
         }
 
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        Ok(GroupSettings {
+            capacity: settings.capacity,
+            room: clean(settings.room),
+            level: clean(settings.level),
+            subject: clean(settings.subject),
+            teachers,
+            is_active: settings.is_active,
+        })
+    }
 
-        // Update the group
-        let rows_affected = db.connection().execute(
-            "UPDATE groups SET name = ?1, updated_at = ?2 WHERE id = ?3",
-            params![request.name, now, id],
-        )?;
+    /// Replace the teachers assigned to a group, keeping the given order
+    fn replace_teachers(db: &Database, group_id: i32, teachers: &[String]) -> DatabaseResult<()> {
+        db.connection()
+            .execute("DELETE FROM group_teachers WHERE group_id = ?1", [group_id])?;
 
-        if rows_affected == 0 {
-            return Err(crate::database::DatabaseError::Migration(format!(
-                "Group with ID {} not found",
-                id
-            )));
+        for (position, teacher) in teachers.iter().enumerate() {
+            db.connection().execute(
+                "INSERT INTO group_teachers (group_id, teacher_name, position) VALUES (?1, ?2, ?3)",
+                params![group_id, teacher, position as i32],
+            )?;
         }
 
-        // Update all students with the old group name to use the new name
-        db.connection().execute(
-            "UPDATE students SET group_name = ?1, updated_at = ?2 WHERE group_name = ?3",
-            params![request.name, now, existing_group.name],
-        )?;
+        Ok(())
+    }
+
+    /// Update capacity, room, level, subject, teachers and active flag of a group.
+    /// Raising or removing the capacity promotes waitlisted enrollments into the freed seats.
+    pub fn update_group_settings(
+        db: &Database,
+        id: i32,
+        settings: GroupSettings,
+    ) -> DatabaseResult<Group> {
+        AuditService::audited(db, |audit| {
+            let settings = Self::normalize_settings(settings)?;
+
+            let existing_group =
+                Self::get_group_by_id(db, id)?.ok_or_else(|| DatabaseError::not_found("Group", id))?;
+
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+            db.connection().execute(
+                "UPDATE groups SET capacity = ?1, room = ?2, level = ?3, subject = ?4, is_active = ?5, updated_at = ?6 WHERE id = ?7",
+                params![
+                    settings.capacity,
+                    settings.room,
+                    settings.level,
+                    settings.subject,
+                    settings.is_active,
+                    now,
+                    id
+                ],
+            )?;
+            Self::replace_teachers(db, id, &settings.teachers)?;
+
+            EnrollmentService::promote_waitlist(db, id)?;
+
+            let updated_group =
+                Self::get_group_by_id(db, id)?.ok_or_else(|| DatabaseError::not_found("Group", id))?;
+
+            audit.update("groups", &id.to_string(), &existing_group, &updated_group, None);
+
+            Ok(updated_group)
+        })
+    }
 
-        // Create audit log entry
-        let old_values = serde_json::json!({
-            "id": existing_group.id,
-            "name": existing_group.name,
-            "created_at": existing_group.created_at,
-            "updated_at": existing_group.updated_at
-        });
-
-        let new_values = serde_json::json!({
-            "id": id,
-            "name": request.name,
-            "created_at": existing_group.created_at,
-            "updated_at": now
-        });
-
-        AuditService::log_action(
-            db,
-            "UPDATE",
-            "groups",
-            &id.to_string(),
-            Some(&old_values.to_string()),
-            Some(&new_values.to_string()),
-            None,
+    /// Seats left in a group, or `None` when it has no capacity limit
+    pub fn get_seats_remaining(db: &Database, group_id: i32) -> DatabaseResult<Option<i32>> {
+        let capacity: Option<i32> = db.connection().query_row(
+            "SELECT capacity FROM groups WHERE id = ?1",
+            [group_id],
+            |row| row.get(0),
         )?;
 
+        match capacity {
+            Some(capacity) => {
+                let members = Self::get_students_count_by_group_id(db, group_id)?;
+                Ok(Some((capacity - members).max(0)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reject new members for inactive or full groups
+    pub fn ensure_seat_available(db: &Database, group: &Group) -> DatabaseResult<()> {
+        if !group.is_active {
+            return Err(DatabaseError::forbidden(
+                "group_inactive",
+                format!("Group '{}' is not accepting new students", group.name),
+            ));
+        }
+
+        if Self::get_seats_remaining(db, group.id)? == Some(0) {
+            return Err(DatabaseError::conflict(
+                "group_full",
+                format!(
+                    "Group '{}' is full ({} seats); add the student to its waitlist instead",
+                    group.name,
+                    group.capacity.unwrap_or(0)
+                ),
+            ));
+        }
+
         Ok(())
     }
 
+    /// Update a group
+    pub fn update_group(db: &Database, id: i32, request: UpdateGroupRequest) -> DatabaseResult<()> {
+        AuditService::audited(db, |audit| {
+            // Validate group name
+            if request.name.trim().is_empty() {
+                return Err(DatabaseError::validation(
+                    "name",
+                    "Group name cannot be empty",
+                ));
+            }
+
+            let request = UpdateGroupRequest {
+                name: request.name.trim().to_string(),
+            };
+
+            // Get the existing group for audit logging
+            let existing_group =
+                Self::get_group_by_id(db, id)?.ok_or_else(|| DatabaseError::not_found("Group", id))?;
+
+            // Check if another group with the same name exists (excluding current group)
+            let mut stmt = db
+                .connection()
+                .prepare("SELECT COUNT(*) FROM groups WHERE name = ?1 AND id != ?2")?;
+            let count: i32 = stmt.query_row(params![request.name, id], |row| row.get(0))?;
+
+            if count > 0 {
+                return Err(DatabaseError::conflict(
+                    "group_exists",
+                    format!("Group '{}' already exists", request.name),
+                ));
+            }
+
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+            // Update the group
+            let rows_affected = db.connection().execute(
+                "UPDATE groups SET name = ?1, updated_at = ?2 WHERE id = ?3",
+                params![request.name, now, id],
+            )?;
+
+            if rows_affected == 0 {
+                return Err(DatabaseError::not_found("Group", id));
+            }
+
+            // Create audit log entry
+            let old_values = serde_json::json!({
+                "id": existing_group.id,
+                "name": existing_group.name,
+                "created_at": existing_group.created_at,
+                "updated_at": existing_group.updated_at
+            });
+
+            let new_values = serde_json::json!({
+                "id": id,
+                "name": request.name,
+                "created_at": existing_group.created_at,
+                "updated_at": now
+            });
+
+            audit.update("groups", &id.to_string(), &old_values, &new_values, None);
+
+            Ok(())
+        })
+    }
+
     /// Delete a group
     pub fn delete_group(db: &Database, id: i32) -> DatabaseResult<bool> {
-        // Get the existing group for audit logging and validation
-        let existing_group = match Self::get_group_by_id(db, id)? {
-            Some(group) => group,
-            None => return Ok(false), // Group doesn't exist
-        };
+        AuditService::audited(db, |audit| {
+            // Get the existing group for audit logging and validation
+            let existing_group = match Self::get_group_by_id(db, id)? {
+                Some(group) => group,
+                None => return Ok(false), // Group doesn't exist
+            };
+
+            // Soft delete strategy: mark all students in this group as deleted, then mark the group as deleted
+            let now = Utc::now().to_rfc3339();
+
+            // Withdraw every active or waitlisted enrollment in this group
+            db.connection().execute(
+                "UPDATE enrollments SET status = 'withdrawn', end_date = ?1, waitlist_position = NULL, updated_at = ?2 WHERE group_id = ?3 AND status IN ('active', 'waitlisted')",
+                params![Utc::now().format("%Y-%m-%d").to_string(), now, id],
+            )?;
 
-        // Soft delete strategy: mark all students in this group as deleted, then mark the group as deleted
-        let now = Utc::now().to_rfc3339();
+            // Soft delete students in this group and clear their group assignment
+            db.connection().execute(
+                "UPDATE students SET deleted_at = ?1, updated_at = ?1, group_name = '', group_id = NULL WHERE group_id = ?2 AND deleted_at IS NULL",
+                params![now, id],
+            )?;
 
-        // Soft delete students in this group and clear their group assignment
-        db.connection().execute(
-            "UPDATE students SET deleted_at = ?1, updated_at = ?1, group_name = '' WHERE group_name = ?2 AND deleted_at IS NULL",
-            params![now, existing_group.name],
-        )?;
+            // Soft delete the group
+            let rows_affected = db.connection().execute(
+                "UPDATE groups SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+                params![now, id],
+            )?;
 
-        // Soft delete the group
-        let rows_affected = db.connection().execute(
-            "UPDATE groups SET deleted_at = ?1, updated_at = ?1 WHERE id = ?2 AND deleted_at IS NULL",
-            params![now, id],
-        )?;
+            if rows_affected == 0 {
+                return Ok(false);
+            }
 
-        if rows_affected == 0 {
-            return Ok(false);
-        }
+            // Audit log
+            let old_values = serde_json::json!({
+                "id": existing_group.id,
+                "name": existing_group.name,
+                "created_at": existing_group.created_at,
+                "updated_at": existing_group.updated_at
+            });
 
-        // Audit log
-        let old_values = serde_json::json!({
-            "id": existing_group.id,
-            "name": existing_group.name,
-            "created_at": existing_group.created_at,
-            "updated_at": existing_group.updated_at
-        });
-
-        AuditService::log_action(
-            db,
-            "DELETE",
-            "groups",
-            &id.to_string(),
-            Some(&old_values.to_string()),
-            None,
-            Some(&"Soft delete including group members".to_string()),
-        )?;
+            audit.delete(
+                "groups",
+                &id.to_string(),
+                &old_values,
+                Some("Soft delete including group members"),
+            );
 
-        Ok(true)
+            Ok(true)
+        })
     }
 
     /// Get count of students in a group by group ID
     pub fn get_students_count_by_group_id(db: &Database, group_id: i32) -> DatabaseResult<i32> {
         // First get the group name
-        let group = Self::get_group_by_id(db, group_id)?.ok_or_else(|| {
-            crate::database::DatabaseError::Migration(format!(
-                "Group with ID {} not found",
-                group_id
-            ))
-        })?;
+        let group = Self::get_group_by_id(db, group_id)?
+            .ok_or_else(|| DatabaseError::not_found("Group", group_id))?;
+
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT COUNT(*) FROM ({}) WHERE group_id = ?1",
+            GROUP_MEMBERS_SQL
+        ))?;
 
-        Self::get_students_count_by_group_name(db, &group.name)
+        let count: i32 = stmt.query_row([group.id], |row| row.get(0))?;
+        Ok(count)
     }
 
     /// Get count of students in a group by group name
@@ -317,12 +580,10 @@ impl GroupsService {
         db: &Database,
         group_name: &str,
     ) -> DatabaseResult<i32> {
-        let mut stmt = db.connection().prepare(
-            "SELECT COUNT(*) FROM students WHERE group_name = ?1 AND deleted_at IS NULL",
-        )?;
-
-        let count: i32 = stmt.query_row([group_name], |row| row.get(0))?;
-        Ok(count)
+        match Self::get_group_by_name(db, group_name)? {
+            Some(group) => Self::get_students_count_by_group_id(db, group.id),
+            None => Ok(0),
+        }
     }
 
     /// Force delete a group and reassign students to default group
@@ -331,51 +592,87 @@ impl GroupsService {
         id: i32,
         default_group_name: &str,
     ) -> DatabaseResult<bool> {
-        // Get the existing group
-        let existing_group = match Self::get_group_by_id(db, id)? {
-            Some(group) => group,
-            None => return Ok(false), // Group doesn't exist
-        };
-
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-
-        // Reassign all students to the default group
-        let students_updated = db.connection().execute(
-            "UPDATE students SET group_name = ?1, updated_at = ?2 WHERE group_name = ?3",
-            params![default_group_name, now, existing_group.name],
-        )?;
+        AuditService::audited(db, |audit| {
+            // Get the existing group
+            let existing_group = match Self::get_group_by_id(db, id)? {
+                Some(group) => group,
+                None => return Ok(false), // Group doesn't exist
+            };
+
+            let default_group = Self::resolve_group(db, default_group_name)?;
+            if default_group.id == existing_group.id {
+                return Err(DatabaseError::validation(
+                    "default_group_name",
+                    format!(
+                        "Cannot reassign students of '{}' to the group being deleted",
+                        existing_group.name
+                    ),
+                ));
+            }
 
-        // Delete the group
-        let rows_affected = db
-            .connection()
-            .execute("DELETE FROM groups WHERE id = ?1", params![id])?;
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+            // The move shows up in each student's group history from today
+            let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+            let moved_students: Vec<(String, String)> = {
+                let mut stmt = db
+                    .connection()
+                    .prepare("SELECT id, enrollment_date FROM students WHERE group_id = ?1")?;
+                let rows = stmt
+                    .query_map([id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                rows
+            };
+            for (student_id, enrollment_date) in &moved_students {
+                MembershipService::record_group_change(
+                    db,
+                    student_id,
+                    &default_group,
+                    std::cmp::max(&today, enrollment_date),
+                    Some("Group deleted"),
+                )?;
+            }
 
-        if rows_affected > 0 {
-            // Create audit log entry
-            let old_values = serde_json::json!({
-                "id": existing_group.id,
-                "name": existing_group.name,
-                "created_at": existing_group.created_at,
-                "updated_at": existing_group.updated_at
-            });
+            // Reassign all students to the default group
+            let students_updated = db.connection().execute(
+                "UPDATE students SET group_id = ?1, group_name = ?2, updated_at = ?3 WHERE group_id = ?4",
+                params![default_group.id, default_group.name, now, id],
+            )?;
 
-            AuditService::log_action(
-                db,
-                "DELETE",
-                "groups",
-                &id.to_string(),
-                Some(&old_values.to_string()),
-                None,
-                Some(&format!(
-                    "Force deleted with {} students reassigned to '{}'",
-                    students_updated, default_group_name
-                )),
+            db.connection().execute(
+                "UPDATE enrollments SET group_id = ?1, group_name = ?2, updated_at = ?3 WHERE group_id = ?4",
+                params![default_group.id, default_group.name, now, id],
             )?;
 
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+            // Delete the group
+            let rows_affected = db
+                .connection()
+                .execute("DELETE FROM groups WHERE id = ?1", params![id])?;
+
+            if rows_affected > 0 {
+                // Create audit log entry
+                let old_values = serde_json::json!({
+                    "id": existing_group.id,
+                    "name": existing_group.name,
+                    "created_at": existing_group.created_at,
+                    "updated_at": existing_group.updated_at
+                });
+
+                audit.delete(
+                    "groups",
+                    &id.to_string(),
+                    &old_values,
+                    Some(&format!(
+                        "Force deleted with {} students reassigned to '{}'",
+                        students_updated, default_group.name
+                    )),
+                );
+
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        })
     }
 
     /// Ensure default groups exist
@@ -385,32 +682,46 @@ impl GroupsService {
         Ok(())
     }
 
-    /// Get group statistics
-    pub fn get_group_statistics(db: &Database) -> DatabaseResult<GroupStatistics> {
+    /// Get group statistics, optionally counting only the members each group had during a term
+    pub fn get_group_statistics(
+        db: &Database,
+        term_id: Option<i32>,
+    ) -> DatabaseResult<GroupStatistics> {
         let total_groups: i32 =
             db.connection()
                 .query_row("SELECT COUNT(*) FROM groups", [], |row| row.get(0))?;
 
+        let members = format!(
+            "SELECT student_id, group_id FROM ({}) WHERE ?1 IS NULL
+             UNION
+             SELECT student_id, group_id FROM ({})",
+            GROUP_MEMBERS_SQL, TERM_GROUP_MEMBERS_SQL
+        );
+
         let groups_with_students: i32 = db.connection().query_row(
-            "SELECT COUNT(DISTINCT g.id) FROM groups g 
-             INNER JOIN students s ON g.name = s.group_name",
-            [],
+            &format!(
+                "SELECT COUNT(DISTINCT g.id) FROM groups g 
+                 INNER JOIN ({}) m ON g.id = m.group_id",
+                members
+            ),
+            [term_id],
             |row| row.get(0),
         )?;
 
         let empty_groups = total_groups - groups_with_students;
 
         let largest_group: Option<(String, i32)> = {
-            let mut stmt = db.connection().prepare(
-                "SELECT g.name, COUNT(s.id) as student_count
+            let mut stmt = db.connection().prepare(&format!(
+                "SELECT g.name, COUNT(m.student_id) as student_count
                  FROM groups g
-                 LEFT JOIN students s ON g.name = s.group_name
+                 LEFT JOIN ({}) m ON g.id = m.group_id
                  GROUP BY g.id, g.name
                  ORDER BY student_count DESC
                  LIMIT 1",
-            )?;
+                members
+            ))?;
 
-            let mut rows = stmt.query_map([], |row| {
+            let mut rows = stmt.query_map([term_id], |row| {
                 Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?))
             })?;
 
@@ -430,20 +741,29 @@ impl GroupsService {
     }
 
     /// Validate group name
-    pub fn validate_group_name(name: &str) -> Result<(), String> {
+    pub fn validate_group_name(name: &str) -> DatabaseResult<()> {
         let trimmed = name.trim();
 
         if trimmed.is_empty() {
-            return Err("Group name cannot be empty".to_string());
+            return Err(DatabaseError::validation(
+                "name",
+                "Group name cannot be empty",
+            ));
         }
 
         if trimmed.len() > 100 {
-            return Err("Group name cannot be longer than 100 characters".to_string());
+            return Err(DatabaseError::validation(
+                "name",
+                "Group name cannot be longer than 100 characters",
+            ));
         }
 
         // Check for invalid characters (optional - you can customize this)
         if trimmed.contains('\n') || trimmed.contains('\r') || trimmed.contains('\t') {
-            return Err("Group name cannot contain line breaks or tabs".to_string());
+            return Err(DatabaseError::validation(
+                "name",
+                "Group name cannot contain line breaks or tabs",
+            ));
         }
 
         Ok(())
@@ -479,6 +799,7 @@ mod tests {
 
         let request = CreateGroupRequest {
             name: "Test Group".to_string(),
+            settings: GroupSettings::default(),
         };
 
         let group = GroupsService::create_group(&db, request).expect("Failed to create group");
@@ -502,6 +823,7 @@ mod tests {
 
         let request = CreateGroupRequest {
             name: "Duplicate Group".to_string(),
+            settings: GroupSettings::default(),
         };
 
         // First creation should succeed
@@ -519,6 +841,7 @@ mod tests {
         // Create a group
         let create_request = CreateGroupRequest {
             name: "Original Name".to_string(),
+            settings: GroupSettings::default(),
         };
         let group =
             GroupsService::create_group(&db, create_request).expect("Failed to create group");
@@ -544,4 +867,126 @@ mod tests {
         assert!(GroupsService::validate_group_name("   ").is_err());
         assert!(GroupsService::validate_group_name("Name\nwith\nnewlines").is_err());
     }
+
+    fn create_student(db: &Database, group: &str) -> crate::student_service::Student {
+        let request = crate::student_service::CreateStudentRequest {
+            name: "Test Student".to_string(),
+            group_name: group.to_string(),
+            payment_plan: crate::student_service::PaymentPlan::OneTime,
+            plan_amount: 6000,
+            installment_count: None,
+            paid_amount: Some(0),
+            enrollment_date: Some("2024-01-01".to_string()),
+        };
+        crate::student_service::StudentService::create_student(db, request)
+            .expect("Failed to create student")
+    }
+
+    #[test]
+    fn test_group_name_variants_resolve_to_one_group() {
+        let (db, _temp_dir) = setup_test_db();
+        let group = GroupsService::create_group(
+            &db,
+            CreateGroupRequest {
+                name: "Group A".to_string(),
+                settings: GroupSettings::default(),
+            },
+        )
+        .expect("Failed to create group");
+
+        let first = create_student(&db, "Group A ");
+        let second = create_student(&db, "group a");
+
+        assert_eq!(first.group_id, Some(group.id));
+        assert_eq!(second.group_id, Some(group.id));
+        assert_eq!(second.group_name, "Group A");
+        assert_eq!(GroupsService::get_all_groups(&db).unwrap().len(), 1);
+        assert_eq!(
+            GroupsService::get_students_count_by_group_id(&db, group.id).unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_rename_group_follows_group_id() {
+        let (db, _temp_dir) = setup_test_db();
+        let student = create_student(&db, "Physics");
+        let group_id = student.group_id.expect("Student should reference a group");
+
+        GroupsService::update_group(
+            &db,
+            group_id,
+            UpdateGroupRequest {
+                name: "Physics 2025".to_string(),
+            },
+        )
+        .expect("Failed to rename group");
+
+        let renamed = crate::student_service::StudentService::get_student_by_id(&db, &student.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(renamed.group_name, "Physics 2025");
+        assert_eq!(renamed.group_id, Some(group_id));
+        assert_eq!(
+            GroupsService::get_students_count_by_group_name(&db, "Physics 2025").unwrap(),
+            1
+        );
+        assert_eq!(
+            GroupsService::get_students_count_by_group_name(&db, "Physics").unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_group_settings_and_seats_remaining() {
+        let (db, _temp_dir) = setup_test_db();
+        let group = GroupsService::create_group(
+            &db,
+            CreateGroupRequest {
+                name: "Chemistry".to_string(),
+                settings: GroupSettings {
+                    capacity: Some(2),
+                    room: Some(" Lab 3 ".to_string()),
+                    level: Some("Advanced".to_string()),
+                    subject: Some("Chemistry".to_string()),
+                    teachers: vec!["Ms. Salma".to_string(), "ms. salma".to_string()],
+                    is_active: true,
+                },
+            },
+        )
+        .expect("Failed to create group");
+
+        assert_eq!(group.room.as_deref(), Some("Lab 3"));
+        assert_eq!(group.teachers, vec!["Ms. Salma".to_string()]);
+
+        create_student(&db, "Chemistry");
+        let counts = GroupsService::get_all_groups_with_counts(&db).unwrap();
+        assert_eq!(counts[0].student_count, 1);
+        assert_eq!(counts[0].seats_remaining, Some(1));
+
+        create_student(&db, "Chemistry");
+        assert_eq!(
+            GroupsService::get_seats_remaining(&db, group.id).unwrap(),
+            Some(0)
+        );
+
+        let invalid = GroupSettings {
+            capacity: Some(0),
+            ..Default::default()
+        };
+        assert!(GroupsService::update_group_settings(&db, group.id, invalid).is_err());
+
+        let inactive = GroupsService::update_group_settings(
+            &db,
+            group.id,
+            GroupSettings {
+                is_active: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!inactive.is_active);
+        assert!(inactive.teachers.is_empty());
+        assert!(GroupsService::ensure_seat_available(&db, &inactive).is_err());
+    }
 }
@@ -0,0 +1,264 @@
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::settings_service::SettingsService;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Languages the backend can produce text in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ar,
+    En,
+}
+
+impl Locale {
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::Ar => "ar",
+            Locale::En => "en",
+        }
+    }
+
+    /// Accepts `ar`, `en` and regional variants such as `en-US`
+    pub fn parse(code: &str) -> Option<Self> {
+        let language = code.trim().split(['-', '_']).next().unwrap_or("");
+        match language.to_lowercase().as_str() {
+            "ar" => Some(Locale::Ar),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// The `language` setting, or Arabic when it is missing or not supported
+    pub fn from_settings(db: &Database) -> DatabaseResult<Self> {
+        Ok(SettingsService::get_setting(db, "language")?
+            .and_then(|code| Self::parse(&code))
+            .unwrap_or_default())
+    }
+
+    /// A locale requested by the caller, falling back to the `language` setting
+    pub fn resolve(db: &Database, requested: Option<&str>) -> DatabaseResult<Self> {
+        match requested {
+            Some(code) => Self::parse(code).ok_or_else(|| {
+                DatabaseError::validation("language", format!("Unsupported language: {}", code))
+            }),
+            None => Self::from_settings(db),
+        }
+    }
+}
+
+/// Text for `key`, or `None` when the catalog does not have it
+pub fn lookup(locale: Locale, key: &str) -> Option<&'static str> {
+    CATALOG
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, ar, en)| match locale {
+            Locale::Ar => *ar,
+            Locale::En => *en,
+        })
+}
+
+/// Text for `key`, falling back to the key itself so gaps show up instead of blank cells
+pub fn t(locale: Locale, key: &str) -> String {
+    lookup(locale, key).unwrap_or(key).to_string()
+}
+
+/// Text for `key` with `{name}` placeholders filled from `params`
+pub fn format(locale: Locale, key: &str, params: &BTreeMap<String, String>) -> Option<String> {
+    lookup(locale, key).map(|template| {
+        params
+            .iter()
+            .fold(template.to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    })
+}
+
+/// Label for a stored code such as a payment status; unknown codes are shown as they are
+pub fn label(locale: Locale, kind: &str, code: &str) -> String {
+    lookup(locale, &format!("{}.{}", kind, code))
+        .unwrap_or(code)
+        .to_string()
+}
+
+/// (key, Arabic, English)
+#[rustfmt::skip]
+const CATALOG: &[(&str, &str, &str)] = &[
+    // Export column headers
+    ("export.student_id", "رقم الطالب", "Student ID"),
+    ("export.student_name", "اسم الطالب", "Student Name"),
+    ("export.group", "المجموعة", "Group"),
+    ("export.date", "التاريخ", "Date"),
+    ("export.recorded_at", "وقت التسجيل", "Recorded At"),
+    ("export.payment_plan", "خطة الدفع", "Payment Plan"),
+    ("export.plan_amount", "مبلغ الخطة", "Plan Amount"),
+    ("export.paid_amount", "المبلغ المدفوع", "Paid Amount"),
+    ("export.payment_status", "حالة الدفع", "Payment Status"),
+    ("export.next_due_date", "تاريخ الاستحقاق التالي", "Next Due Date"),
+    ("export.enrollment_date", "تاريخ التسجيل", "Enrollment Date"),
+    ("export.payment_amount", "مبلغ الدفع", "Payment Amount"),
+    ("export.payment_date", "تاريخ الدفع", "Payment Date"),
+    ("export.payment_method", "طريقة الدفع", "Payment Method"),
+    ("export.notes", "ملاحظات", "Notes"),
+    ("export.amount_due", "المبلغ المستحق", "Amount Due"),
+    ("export.days_overdue", "أيام التأخير", "Days Overdue"),
+    ("export.total", "المجموع", "Total"),
+    ("export.attendance_rate", "نسبة الحضور", "Attendance %"),
+    ("export.due_date", "تاريخ الاستحقاق", "Due Date"),
+    ("export.oldest_due_date", "أقدم تاريخ استحقاق", "Oldest Due Date"),
+    ("export.aging_0_30", "0-30 يوماً", "0-30 Days"),
+    ("export.aging_31_60", "31-60 يوماً", "31-60 Days"),
+    ("export.aging_61_90", "61-90 يوماً", "61-90 Days"),
+    ("export.aging_over_90", "أكثر من 90 يوماً", "90+ Days"),
+    ("export.period", "الفترة", "Period"),
+    ("export.start_date", "من", "From"),
+    ("export.end_date", "إلى", "To"),
+    ("export.scheduled", "المستحق المجدول", "Scheduled"),
+    ("export.best_case", "أفضل حالة", "Best Case"),
+    ("export.expected_case", "الحالة المتوقعة", "Expected"),
+    ("export.worst_case", "أسوأ حالة", "Worst Case"),
+    // Attendance matrix
+    ("matrix.title", "سجل الحضور - {group}", "Attendance Register - {group}"),
+    ("matrix.period", "من {start} إلى {end}", "From {start} to {end}"),
+    ("matrix.legend", "ح = حاضر، ت = متأخر، غ = غائب، ع = بعذر", "P = Present, L = Late, A = Absent, E = Excused"),
+    ("matrix.attended", "الحاضرون", "Attended"),
+    // Receivables aging
+    ("aging.title", "أعمار الذمم المدينة", "Receivables Aging"),
+    ("aging.as_of", "حتى تاريخ {date}", "As of {date}"),
+    ("aging.group_total", "مجموع {group}", "{group} total"),
+    // Cash-flow forecast
+    ("forecast.title", "توقعات التدفق النقدي", "Cash-Flow Forecast"),
+    ("forecast.subtitle", "حتى تاريخ {date} - المتأخرات {arrears} - نسبة السداد في الموعد {rate}", "As of {date} - arrears {arrears} - on-time rate {rate}"),
+    // At-risk students
+    ("risk.attendance_decline", "انخفض الحضور من {previous}% إلى {recent}%", "Attendance fell from {previous}% to {recent}%"),
+    ("risk.consecutive_absences", "{count} غيابات متتالية", "{count} absences in a row"),
+    ("risk.overdue_balance", "{amount} متأخرة منذ {days} يوماً", "{amount} overdue for {days} days"),
+    ("risk.plan_downgrade", "خُفّض مبلغ الخطة من {old} إلى {new} بتاريخ {date}", "Plan lowered from {old} to {new} on {date}"),
+    // XLSX workbook
+    ("xlsx.sheet.summary", "الملخص", "Summary"),
+    ("xlsx.sheet.students", "الطلاب", "Students"),
+    ("xlsx.sheet.attendance", "الحضور", "Attendance"),
+    ("xlsx.sheet.payments", "المدفوعات", "Payments"),
+    ("xlsx.sheet.overdue", "المتأخرون", "Overdue"),
+    ("xlsx.sheet.matrix", "سجل الحضور", "Register"),
+    ("xlsx.sheet.aging", "أعمار الذمم", "Aging"),
+    ("xlsx.sheet.aging_detail", "تفاصيل الاستحقاقات", "Aging Detail"),
+    ("xlsx.sheet.forecast", "التوقعات", "Forecast"),
+    ("xlsx.summary.title", "تقرير الطلاب والمدفوعات", "Students and Payments Report"),
+    ("xlsx.summary.period", "الفترة", "Period"),
+    ("xlsx.summary.all_dates", "كل التواريخ", "All dates"),
+    ("xlsx.summary.student_count", "عدد الطلاب", "Students"),
+    ("xlsx.summary.session_count", "عدد الحصص", "Sessions"),
+    ("xlsx.summary.attendance_count", "مرات الحضور", "Attendances"),
+    ("xlsx.summary.payment_count", "عدد المدفوعات", "Payments"),
+    ("xlsx.summary.overdue_count", "الطلاب المتأخرون", "Overdue students"),
+    ("xlsx.summary.total_plan_amount", "إجمالي مبالغ الخطط", "Total plan amount"),
+    ("xlsx.summary.total_paid_amount", "إجمالي المدفوع", "Total paid"),
+    ("xlsx.summary.payments_received", "المدفوعات المستلمة في الفترة", "Payments received in period"),
+    ("xlsx.summary.total_amount_due", "إجمالي المستحق المتأخر", "Total overdue amount"),
+    // Stored codes
+    ("payment_plan.one-time", "دفعة واحدة", "One-time"),
+    ("payment_plan.monthly", "شهري", "Monthly"),
+    ("payment_plan.installment", "أقساط", "Installments"),
+    ("payment_status.paid", "مدفوع", "Paid"),
+    ("payment_status.pending", "معلق", "Pending"),
+    ("payment_status.overdue", "متأخر", "Overdue"),
+    ("payment_status.due_soon", "مستحق قريباً", "Due Soon"),
+    ("payment_method.cash", "نقدي", "Cash"),
+    ("payment_method.bank_transfer", "تحويل بنكي", "Bank Transfer"),
+    ("payment_method.check", "شيك", "Check"),
+    ("attendance_status.present", "حاضر", "Present"),
+    ("attendance_status.late", "متأخر", "Late"),
+    ("attendance_status.absent", "غائب", "Absent"),
+    ("attendance_status.excused", "بعذر", "Excused"),
+    ("attendance_code.present", "ح", "P"),
+    ("attendance_code.late", "ت", "L"),
+    ("attendance_code.absent", "غ", "A"),
+    ("attendance_code.excused", "ع", "E"),
+    // QR code sheets
+    ("qr.title", "رموز QR للطلاب", "Student QR Codes"),
+    ("qr.title_all", "رموز QR لجميع الطلاب", "All Student QR Codes"),
+    ("qr.title_group", "رموز QR - {group}", "QR Codes - {group}"),
+    ("qr.student", "الطالب", "Student"),
+    ("qr.id", "الرقم", "ID"),
+    ("qr.group", "المجموعة", "Group"),
+    // Errors, by category and then by entity, field or reason
+    ("errors.storage", "تعذر حفظ البيانات أو قراءتها", "The data could not be read or saved"),
+    ("errors.not_found", "السجل غير موجود", "Record not found"),
+    ("errors.not_found.student", "الطالب رقم {id} غير موجود", "Student {id} not found"),
+    ("errors.not_found.group", "المجموعة رقم {id} غير موجودة", "Group {id} not found"),
+    ("errors.not_found.enrollment", "التسجيل رقم {id} غير موجود", "Enrollment {id} not found"),
+    ("errors.not_found.term", "الفصل الدراسي رقم {id} غير موجود", "Term {id} not found"),
+    ("errors.not_found.payment_settings", "إعدادات الدفع غير موجودة", "Payment settings not found"),
+    ("errors.validation", "قيمة غير صالحة", "Invalid value"),
+    ("errors.validation.name", "الاسم مطلوب ولا يتجاوز 100 حرف", "Name is required and at most 100 characters"),
+    ("errors.validation.group_name", "المجموعة مطلوبة", "Group is required"),
+    ("errors.validation.plan_amount", "مبلغ الخطة يجب أن يكون أكبر من صفر", "Plan amount must be greater than 0"),
+    ("errors.validation.installment_count", "عدد الأقساط غير صالح", "Invalid number of installments"),
+    ("errors.validation.payment_plan", "خطة الدفع غير صالحة", "Invalid payment plan"),
+    ("errors.validation.payment_method", "طريقة الدفع غير صالحة", "Invalid payment method"),
+    ("errors.validation.payment", "بيانات الدفع غير صالحة", "Invalid payment"),
+    ("errors.validation.date", "صيغة التاريخ غير صالحة، استخدم YYYY-MM-DD", "Invalid date format, use YYYY-MM-DD"),
+    ("errors.validation.start_date", "تاريخ البداية غير صالح", "Invalid start date"),
+    ("errors.validation.end_date", "تاريخ النهاية غير صالح أو قبل تاريخ البداية", "Invalid end date, or before the start date"),
+    ("errors.validation.enrollment_date", "تاريخ التسجيل غير صالح", "Invalid enrollment date"),
+    ("errors.validation.payment_date", "تاريخ الدفع غير صالح", "Invalid payment date"),
+    ("errors.validation.due_date", "تاريخ الاستحقاق غير صالح", "Invalid due date"),
+    ("errors.validation.effective_date", "تاريخ السريان غير صالح", "Invalid effective date"),
+    ("errors.validation.capacity", "سعة المجموعة يجب أن تكون أكبر من صفر", "Group capacity must be positive"),
+    ("errors.validation.file_path", "المجلد غير موجود", "The folder does not exist"),
+    ("errors.validation.password", "كلمة المرور غير صحيحة", "Wrong password"),
+    ("errors.validation.backup", "ملف النسخة الاحتياطية غير صالح", "Invalid backup file"),
+    ("errors.validation.language", "اللغة غير مدعومة", "Unsupported language"),
+    ("errors.validation.format", "صيغة الملف غير مدعومة", "Unsupported file format"),
+    ("errors.validation.status", "الحالة غير صالحة", "Invalid status"),
+    ("errors.conflict", "يتعارض مع بيانات موجودة", "Conflicts with existing data"),
+    ("errors.conflict.attendance_already_recorded", "تم تسجيل الحضور مسبقاً لهذا اليوم", "Attendance already recorded for this day"),
+    ("errors.conflict.already_enrolled", "الطالب مسجل بالفعل في هذه المجموعة", "The student is already enrolled in this group"),
+    ("errors.conflict.already_in_group", "الطالب في هذه المجموعة بالفعل", "The student is already in this group"),
+    ("errors.conflict.group_exists", "توجد مجموعة بهذا الاسم", "A group with this name already exists"),
+    ("errors.conflict.group_full", "المجموعة ممتلئة، أضف الطالب إلى قائمة الانتظار", "The group is full; add the student to its waitlist"),
+    ("errors.conflict.term_overlap", "تتداخل تواريخ الفصل مع فصل آخر", "The term dates overlap another term"),
+    ("errors.forbidden", "العملية غير مسموح بها", "This action is not allowed"),
+    ("errors.forbidden.group_inactive", "المجموعة لا تقبل طلاباً جدداً", "The group is not accepting new students"),
+    ("errors.forbidden.enrollment_inactive", "التسجيل غير نشط", "The enrollment is not active"),
+    ("errors.forbidden.primary_enrollment", "لا يمكن حذف التسجيل الأساسي إلا بحذف الطالب", "The primary enrollment can only be removed by deleting the student"),
+    ("errors.forbidden.term_closed", "الفصل الدراسي مغلق", "The term is closed"),
+    ("errors.forbidden.term_rolled_over", "تم ترحيل الفصل الدراسي ولا يمكن حذفه", "The term was rolled over and cannot be deleted"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog_has_both_languages_for_every_key() {
+        for (key, ar, en) in CATALOG {
+            assert!(!ar.is_empty() && !en.is_empty(), "{} is missing text", key);
+            assert_eq!(
+                CATALOG.iter().filter(|(k, _, _)| k == key).count(),
+                1,
+                "{} is defined twice",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_lookup_and_interpolation() {
+        assert_eq!(Locale::parse("en-US"), Some(Locale::En));
+        assert_eq!(Locale::parse("fr"), None);
+        assert_eq!(t(Locale::En, "export.student_id"), "Student ID");
+        assert_eq!(t(Locale::Ar, "export.student_id"), "رقم الطالب");
+        assert_eq!(label(Locale::En, "payment_status", "due_soon"), "Due Soon");
+        assert_eq!(label(Locale::En, "payment_method", "voucher"), "voucher");
+
+        let mut params = BTreeMap::new();
+        params.insert("id".to_string(), "STU001".to_string());
+        assert_eq!(
+            format(Locale::En, "errors.not_found.student", &params).as_deref(),
+            Some("Student STU001 not found")
+        );
+    }
+}
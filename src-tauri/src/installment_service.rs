@@ -0,0 +1,720 @@
+use crate::audit_service::AuditService;
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::payment_service::PaymentService;
+use crate::student_service::{PaymentPlan, PaymentStatus, StudentService};
+use crate::term_service::TermService;
+use chrono::{Months, NaiveDate, Utc};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_INSTALLMENT_COUNT: i32 = 3; // Matches the fallback used before schedules existed
+const MAX_INSTALLMENTS: usize = 12; // Same cap as StudentService::validate_student_data
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum InstallmentStatus {
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "partial")]
+    Partial,
+    #[serde(rename = "paid")]
+    Paid,
+    #[serde(rename = "overdue")]
+    Overdue,
+}
+
+impl InstallmentStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InstallmentStatus::Pending => "pending",
+            InstallmentStatus::Partial => "partial",
+            InstallmentStatus::Paid => "paid",
+            InstallmentStatus::Overdue => "overdue",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "pending" => Ok(InstallmentStatus::Pending),
+            "partial" => Ok(InstallmentStatus::Partial),
+            "paid" => Ok(InstallmentStatus::Paid),
+            "overdue" => Ok(InstallmentStatus::Overdue),
+            _ => Err(format!("Invalid installment status: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Installment {
+    pub id: i32,
+    pub student_id: String,
+    pub installment_number: i32,
+    pub due_date: String,
+    pub amount_due: i32,
+    pub amount_allocated: i32,
+    pub status: InstallmentStatus,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallmentInput {
+    pub due_date: String,
+    pub amount_due: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallmentSchedule {
+    pub student_id: String,
+    pub installments: Vec<Installment>,
+    pub total_due: i64,
+    pub total_allocated: i64,
+    pub remaining_amount: i64,
+    pub paid_count: i32,
+    pub pending_count: i32,
+    pub next_due_date: Option<String>,
+}
+
+pub struct InstallmentService;
+
+impl InstallmentService {
+    /// Helper function to map database row to Installment struct
+    fn map_row_to_installment(row: &rusqlite::Row) -> rusqlite::Result<Installment> {
+        let status_str: String = row.get(6)?;
+
+        Ok(Installment {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            installment_number: row.get(2)?,
+            due_date: row.get(3)?,
+            amount_due: row.get(4)?,
+            amount_allocated: row.get(5)?,
+            status: InstallmentStatus::from_str(&status_str).unwrap_or(InstallmentStatus::Pending),
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    }
+
+    /// Derive the status of a single installment from its allocation and due date
+    fn calculate_installment_status(
+        amount_due: i32,
+        amount_allocated: i32,
+        due_date: &str,
+        today: NaiveDate,
+    ) -> InstallmentStatus {
+        if amount_allocated >= amount_due {
+            return InstallmentStatus::Paid;
+        }
+
+        let is_past_due = NaiveDate::parse_from_str(due_date, "%Y-%m-%d")
+            .map(|due| due < today)
+            .unwrap_or(false);
+
+        if is_past_due {
+            InstallmentStatus::Overdue
+        } else if amount_allocated > 0 {
+            InstallmentStatus::Partial
+        } else {
+            InstallmentStatus::Pending
+        }
+    }
+
+    /// Validate a custom installment arrangement
+    fn validate_installments(installments: &[InstallmentInput]) -> Result<(), String> {
+        if installments.is_empty() {
+            return Err("Installment schedule cannot be empty".to_string());
+        }
+
+        if installments.len() > MAX_INSTALLMENTS {
+            return Err(format!(
+                "Installment schedule cannot exceed {} installments",
+                MAX_INSTALLMENTS
+            ));
+        }
+
+        let mut previous_due: Option<NaiveDate> = None;
+        for (index, installment) in installments.iter().enumerate() {
+            if installment.amount_due <= 0 {
+                return Err(format!("Installment {} amount must be positive", index + 1));
+            }
+
+            let due =
+                NaiveDate::parse_from_str(&installment.due_date, "%Y-%m-%d").map_err(|_| {
+                    format!(
+                        "Installment {} has an invalid due date. Use YYYY-MM-DD",
+                        index + 1
+                    )
+                })?;
+
+            if let Some(previous) = previous_due {
+                if due < previous {
+                    return Err(format!(
+                        "Installment {} is due before the installment preceding it",
+                        index + 1
+                    ));
+                }
+            }
+            previous_due = Some(due);
+        }
+
+        Ok(())
+    }
+
+    /// Build the default schedule: equal installments every `interval_months` from enrollment
+    pub fn build_default_installments(
+        enrollment_date: &str,
+        installment_amount: i32,
+        installment_count: Option<i32>,
+        interval_months: i32,
+    ) -> DatabaseResult<Vec<InstallmentInput>> {
+        let enrollment = NaiveDate::parse_from_str(enrollment_date, "%Y-%m-%d").map_err(|_| {
+            DatabaseError::validation("enrollment_date", "Invalid enrollment date format")
+        })?;
+
+        let count = installment_count
+            .unwrap_or(DEFAULT_INSTALLMENT_COUNT)
+            .max(1);
+        let interval = interval_months.max(1) as u32;
+
+        (0..count as u32)
+            .map(|i| {
+                let due = enrollment
+                    .checked_add_months(Months::new(i * interval))
+                    .ok_or_else(|| {
+                        DatabaseError::validation("due_date", "Installment due date out of range")
+                    })?;
+                Ok(InstallmentInput {
+                    due_date: due.format("%Y-%m-%d").to_string(),
+                    amount_due: installment_amount,
+                })
+            })
+            .collect()
+    }
+
+    /// Replace all schedule rows for a student without auditing or reallocating
+    fn replace_installments(
+        db: &Database,
+        student_id: &str,
+        installments: &[InstallmentInput],
+    ) -> DatabaseResult<()> {
+        let now = Utc::now().to_rfc3339();
+        let tx = db.savepoint()?;
+
+        tx.execute(
+            "DELETE FROM installment_schedules WHERE student_id = ?1",
+            [student_id],
+        )?;
+
+        for (index, installment) in installments.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO installment_schedules (student_id, installment_number, due_date, amount_due, amount_allocated, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 0, 'pending', ?5, ?6)",
+                params![
+                    student_id,
+                    index as i32 + 1,
+                    installment.due_date,
+                    installment.amount_due,
+                    now,
+                    now
+                ],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Generate the default schedule for a student on enrollment (or after plan terms change)
+    pub fn generate_schedule(
+        db: &Database,
+        student_id: &str,
+        enrollment_date: &str,
+        installment_amount: i32,
+        installment_count: Option<i32>,
+        interval_months: i32,
+    ) -> DatabaseResult<()> {
+        let installments = Self::build_default_installments(
+            enrollment_date,
+            installment_amount,
+            installment_count,
+            interval_months,
+        )?;
+
+        Self::replace_installments(db, student_id, &installments)?;
+        Self::allocate_payments(db, student_id)
+    }
+
+    /// Remove a student's schedule, e.g. when they leave the installment plan
+    pub fn delete_schedule(db: &Database, student_id: &str) -> DatabaseResult<()> {
+        db.connection().execute(
+            "DELETE FROM installment_schedules WHERE student_id = ?1",
+            [student_id],
+        )?;
+        Ok(())
+    }
+
+    /// Get the raw installment rows for a student, in allocation order
+    pub fn get_installments(db: &Database, student_id: &str) -> DatabaseResult<Vec<Installment>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT id, student_id, installment_number, due_date, amount_due, amount_allocated, status, created_at, updated_at
+             FROM installment_schedules
+             WHERE student_id = ?1
+             ORDER BY installment_number",
+        )?;
+
+        let installment_iter = stmt.query_map([student_id], Self::map_row_to_installment)?;
+
+        installment_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// Spread what the student has paid toward the current term over the schedule, oldest
+    /// installment first
+    pub fn allocate_payments(db: &Database, student_id: &str) -> DatabaseResult<()> {
+        let installments = Self::get_installments(db, student_id)?;
+        if installments.is_empty() {
+            return Ok(());
+        }
+
+        let paid_amount: i32 = db.connection().query_row(
+            "SELECT paid_amount FROM students WHERE id = ?1",
+            [student_id],
+            |row| row.get(0),
+        )?;
+
+        let today = Utc::now().date_naive();
+        let now = Utc::now().to_rfc3339();
+        let paid = TermService::paid_toward_term(db, student_id, None, paid_amount as i64)?;
+        let mut remaining = paid.clamp(0, i32::MAX as i64) as i32;
+
+        let tx = db.savepoint()?;
+        for installment in &installments {
+            let allocated = remaining.min(installment.amount_due);
+            remaining -= allocated;
+
+            let status = Self::calculate_installment_status(
+                installment.amount_due,
+                allocated,
+                &installment.due_date,
+                today,
+            );
+
+            if allocated != installment.amount_allocated || status != installment.status {
+                tx.execute(
+                    "UPDATE installment_schedules SET amount_allocated = ?1, status = ?2, updated_at = ?3 WHERE id = ?4",
+                    params![allocated, status.as_str(), now, installment.id],
+                )?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Give installment-plan students enrolled before schedules existed their default schedule.
+    /// Runs once at startup; students who already have a schedule are left alone.
+    pub fn backfill_schedules(db: &Database) -> DatabaseResult<usize> {
+        let student_ids = {
+            let mut stmt = db.connection().prepare(
+                "SELECT s.id FROM students s
+                 WHERE s.payment_plan = ?1 AND s.deleted_at IS NULL AND NOT EXISTS (
+                     SELECT 1 FROM installment_schedules i WHERE i.student_id = s.id)",
+            )?;
+            let rows = stmt.query_map([PaymentPlan::Installment.as_str()], |row| {
+                row.get::<_, String>(0)
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+        if student_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let config = StudentService::get_payment_plan_config(db)?;
+        let tx = db.savepoint()?;
+        for student_id in &student_ids {
+            let student = StudentService::get_student_by_id(db, student_id)?
+                .ok_or_else(|| DatabaseError::not_found("Student", student_id))?;
+            Self::generate_schedule(
+                db,
+                student_id,
+                &student.enrollment_date,
+                student.plan_amount,
+                student.installment_count,
+                config.installment_interval,
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(student_ids.len())
+    }
+
+    /// Get a student's installment schedule with paid/pending totals. Statuses are worked out
+    /// against today's date, so an installment shows as overdue without waiting for the next allocation.
+    pub fn get_installment_schedule(
+        db: &Database,
+        student_id: &str,
+    ) -> DatabaseResult<InstallmentSchedule> {
+        if StudentService::get_student_by_id(db, student_id)?.is_none() {
+            return Err(DatabaseError::not_found("Student", student_id));
+        }
+
+        let today = Utc::now().date_naive();
+        let installments: Vec<Installment> = Self::get_installments(db, student_id)?
+            .into_iter()
+            .map(|mut installment| {
+                installment.status = Self::calculate_installment_status(
+                    installment.amount_due,
+                    installment.amount_allocated,
+                    &installment.due_date,
+                    today,
+                );
+                installment
+            })
+            .collect();
+
+        let total_due: i64 = installments.iter().map(|i| i.amount_due as i64).sum();
+        let total_allocated: i64 = installments.iter().map(|i| i.amount_allocated as i64).sum();
+        let paid_count = installments
+            .iter()
+            .filter(|i| i.status == InstallmentStatus::Paid)
+            .count() as i32;
+        let next_due_date = installments
+            .iter()
+            .find(|i| i.status != InstallmentStatus::Paid)
+            .map(|i| i.due_date.clone());
+
+        Ok(InstallmentSchedule {
+            student_id: student_id.to_string(),
+            total_due,
+            total_allocated,
+            remaining_amount: (total_due - total_allocated).max(0),
+            paid_count,
+            pending_count: installments.len() as i32 - paid_count,
+            next_due_date,
+            installments,
+        })
+    }
+
+    /// Replace a student's schedule with a custom (possibly uneven) arrangement
+    pub fn update_installment_schedule(
+        db: &Database,
+        student_id: &str,
+        installments: Vec<InstallmentInput>,
+    ) -> DatabaseResult<InstallmentSchedule> {
+        AuditService::audited(db, |audit| {
+            Self::validate_installments(&installments)
+                .map_err(|e| DatabaseError::validation("installments", e))?;
+
+            let student = StudentService::get_student_by_id(db, student_id)?
+                .ok_or_else(|| DatabaseError::not_found("Student", student_id))?;
+
+            if student.payment_plan != PaymentPlan::Installment {
+                return Err(DatabaseError::validation(
+                    "payment_plan",
+                    format!("Student {} is not on an installment plan", student_id),
+                ));
+            }
+
+            let old_schedule = Self::get_installments(db, student_id)?;
+
+            Self::replace_installments(db, student_id, &installments)?;
+
+            db.connection().execute(
+                "UPDATE students SET installment_count = ?1, updated_at = ?2 WHERE id = ?3",
+                params![
+                    installments.len() as i32,
+                    Utc::now().to_rfc3339(),
+                    student_id
+                ],
+            )?;
+
+            Self::allocate_payments(db, student_id)?;
+            PaymentService::update_student_payment_status(db, student_id)?;
+
+            let schedule = Self::get_installment_schedule(db, student_id)?;
+
+            audit.update(
+                "installment_schedules",
+                student_id,
+                &old_schedule,
+                &schedule.installments,
+                None,
+            );
+
+            Ok(schedule)
+        })
+    }
+
+    /// Next due date and payment status as dictated by the schedule, if the student has one
+    pub fn get_schedule_payment_state(
+        db: &Database,
+        student_id: &str,
+        reminder_days: i32,
+    ) -> DatabaseResult<Option<(Option<String>, PaymentStatus)>> {
+        let installments = Self::get_installments(db, student_id)?;
+        if installments.is_empty() {
+            return Ok(None);
+        }
+
+        let next_open = installments
+            .iter()
+            .find(|i| i.amount_allocated < i.amount_due);
+
+        let Some(next_open) = next_open else {
+            return Ok(Some((None, PaymentStatus::Paid)));
+        };
+
+        let due_date = NaiveDate::parse_from_str(&next_open.due_date, "%Y-%m-%d")
+            .map_err(|_| DatabaseError::validation("due_date", "Invalid due date format"))?;
+        let days_until_due = (due_date - Utc::now().date_naive()).num_days();
+
+        let status = if days_until_due < 0 {
+            PaymentStatus::Overdue
+        } else if days_until_due <= reminder_days as i64 {
+            PaymentStatus::DueSoon
+        } else {
+            PaymentStatus::Pending
+        };
+
+        Ok(Some((Some(next_open.due_date.clone()), status)))
+    }
+
+    /// Total amount due across a student's schedule, if the student has one
+    pub fn get_schedule_total(db: &Database, student_id: &str) -> DatabaseResult<Option<i64>> {
+        let total: Option<i64> = db.connection().query_row(
+            "SELECT SUM(amount_due) FROM installment_schedules WHERE student_id = ?1",
+            [student_id],
+            |row| row.get(0),
+        )?;
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment_service::{PaymentMethod, RecordPaymentRequest};
+    use crate::student_service::CreateStudentRequest;
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_installment_student(db: &Database, enrollment_date: &str) -> String {
+        let request = CreateStudentRequest {
+            name: "Installment Student".to_string(),
+            group_name: "Group A".to_string(),
+            payment_plan: PaymentPlan::Installment,
+            plan_amount: 1000,
+            installment_count: Some(3),
+            paid_amount: Some(0),
+            enrollment_date: Some(enrollment_date.to_string()),
+        };
+        StudentService::create_student(db, request).unwrap().id
+    }
+
+    fn pay(db: &Database, student_id: &str, amount: i32) {
+        let request = RecordPaymentRequest {
+            student_id: student_id.to_string(),
+            enrollment_id: None,
+            amount,
+            payment_date: Utc::now().date_naive().format("%Y-%m-%d").to_string(),
+            payment_method: PaymentMethod::Cash,
+            notes: None,
+        };
+        PaymentService::record_payment(db, request).unwrap();
+    }
+
+    #[test]
+    fn test_schedule_generated_on_enrollment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2030-01-31");
+
+        let schedule = InstallmentService::get_installment_schedule(&db, &student_id).unwrap();
+        assert_eq!(schedule.installments.len(), 3);
+        assert_eq!(schedule.total_due, 3000);
+        assert_eq!(schedule.installments[0].due_date, "2030-01-31");
+        // Default interval is 3 months; month-end dates are clamped
+        assert_eq!(schedule.installments[1].due_date, "2030-04-30");
+        assert_eq!(schedule.installments[2].due_date, "2030-07-31");
+        assert_eq!(schedule.next_due_date.as_deref(), Some("2030-01-31"));
+    }
+
+    #[test]
+    fn test_payments_allocated_oldest_first() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2030-01-01");
+
+        pay(&db, &student_id, 1500);
+
+        let schedule = InstallmentService::get_installment_schedule(&db, &student_id).unwrap();
+        assert_eq!(schedule.installments[0].amount_allocated, 1000);
+        assert_eq!(schedule.installments[0].status, InstallmentStatus::Paid);
+        assert_eq!(schedule.installments[1].amount_allocated, 500);
+        assert_eq!(schedule.installments[1].status, InstallmentStatus::Partial);
+        assert_eq!(schedule.installments[2].amount_allocated, 0);
+        assert_eq!(schedule.remaining_amount, 1500);
+        assert_eq!(schedule.paid_count, 1);
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.next_due_date.as_deref(), Some("2030-04-01"));
+    }
+
+    #[test]
+    fn test_deleting_payment_reallocates() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2030-01-01");
+
+        pay(&db, &student_id, 1000);
+        let payment_id =
+            PaymentService::get_student_payment_history(&db, &student_id).unwrap()[0].id;
+        PaymentService::delete_payment(&db, payment_id).unwrap();
+
+        let schedule = InstallmentService::get_installment_schedule(&db, &student_id).unwrap();
+        assert_eq!(schedule.total_allocated, 0);
+        assert_eq!(schedule.installments[0].status, InstallmentStatus::Pending);
+    }
+
+    #[test]
+    fn test_custom_uneven_schedule() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2030-01-01");
+        pay(&db, &student_id, 600);
+
+        let custom = vec![
+            InstallmentInput {
+                due_date: "2030-01-15".to_string(),
+                amount_due: 500,
+            },
+            InstallmentInput {
+                due_date: "2030-03-15".to_string(),
+                amount_due: 1500,
+            },
+            InstallmentInput {
+                due_date: "2030-05-15".to_string(),
+                amount_due: 800,
+            },
+            InstallmentInput {
+                due_date: "2030-07-15".to_string(),
+                amount_due: 200,
+            },
+        ];
+
+        let schedule =
+            InstallmentService::update_installment_schedule(&db, &student_id, custom).unwrap();
+        assert_eq!(schedule.installments.len(), 4);
+        assert_eq!(schedule.total_due, 3000);
+        assert_eq!(schedule.installments[0].status, InstallmentStatus::Paid);
+        assert_eq!(schedule.installments[1].amount_allocated, 100);
+        assert_eq!(schedule.next_due_date.as_deref(), Some("2030-03-15"));
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.installment_count, Some(4));
+        assert_eq!(student.next_due_date.as_deref(), Some("2030-03-15"));
+    }
+
+    #[test]
+    fn test_custom_schedule_validation() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2030-01-01");
+
+        let empty = InstallmentService::update_installment_schedule(&db, &student_id, vec![]);
+        assert!(empty.is_err());
+
+        let out_of_order = vec![
+            InstallmentInput {
+                due_date: "2030-03-01".to_string(),
+                amount_due: 500,
+            },
+            InstallmentInput {
+                due_date: "2030-02-01".to_string(),
+                amount_due: 500,
+            },
+        ];
+        assert!(
+            InstallmentService::update_installment_schedule(&db, &student_id, out_of_order)
+                .is_err()
+        );
+
+        let negative = vec![InstallmentInput {
+            due_date: "2030-03-01".to_string(),
+            amount_due: -5,
+        }];
+        assert!(
+            InstallmentService::update_installment_schedule(&db, &student_id, negative).is_err()
+        );
+    }
+
+    #[test]
+    fn test_schedule_status_drives_payment_status() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2020-01-01");
+
+        let schedule = InstallmentService::get_installment_schedule(&db, &student_id).unwrap();
+        assert!(schedule
+            .installments
+            .iter()
+            .all(|i| i.status == InstallmentStatus::Overdue));
+
+        pay(&db, &student_id, 3000);
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.payment_status, PaymentStatus::Paid);
+        assert_eq!(student.next_due_date, None);
+    }
+
+    #[test]
+    fn test_schedule_removed_when_leaving_installment_plan() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2030-01-01");
+
+        let request = crate::student_service::UpdateStudentRequest {
+            name: "Installment Student".to_string(),
+            group_name: "Group A".to_string(),
+            payment_plan: PaymentPlan::OneTime,
+            plan_amount: 6000,
+            installment_count: None,
+            paid_amount: None,
+            enrollment_date: None,
+        };
+        StudentService::update_student(&db, &student_id, request).unwrap();
+
+        assert!(InstallmentService::get_installments(&db, &student_id)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_backfill_and_read_time_status() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_installment_student(&db, "2020-01-01");
+        InstallmentService::delete_schedule(&db, &student_id).unwrap();
+
+        // Reading never creates a schedule
+        let schedule = InstallmentService::get_installment_schedule(&db, &student_id).unwrap();
+        assert!(schedule.installments.is_empty());
+
+        assert_eq!(InstallmentService::backfill_schedules(&db).unwrap(), 1);
+        assert_eq!(InstallmentService::backfill_schedules(&db).unwrap(), 0);
+
+        // A stored status that went stale is corrected on read
+        db.connection()
+            .execute("UPDATE installment_schedules SET status = 'pending'", [])
+            .unwrap();
+        let schedule = InstallmentService::get_installment_schedule(&db, &student_id).unwrap();
+        assert_eq!(schedule.installments.len(), 3);
+        assert!(schedule
+            .installments
+            .iter()
+            .all(|i| i.status == InstallmentStatus::Overdue));
+    }
+}
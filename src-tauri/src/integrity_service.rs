@@ -0,0 +1,609 @@
+use crate::audit_service::{AuditService, AuditTrail};
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::enrollment_service::EnrollmentService;
+use crate::groups_service::GroupsService;
+use crate::installment_service::InstallmentService;
+use crate::payment_service::PaymentService;
+use crate::student_service::StudentService;
+use chrono::Utc;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// One problem found by the integrity check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    /// Which check found it, e.g. `paid_amount` or `group_name`
+    pub check: String,
+    pub table_name: String,
+    pub record_id: Option<String>,
+    pub message: String,
+    /// Whether fix mode knows how to repair it
+    pub fixable: bool,
+    pub fixed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub checked_at: String,
+    pub fix_mode: bool,
+    /// Result of SQLite's own `PRAGMA integrity_check`
+    pub database_ok: bool,
+    pub issues: Vec<IntegrityIssue>,
+    pub fixed_count: usize,
+}
+
+/// A stored paid amount next to the one its payment transactions add up to
+struct PaidAmountCheck {
+    record_id: String,
+    enrollment_id: Option<i32>,
+    stored: i64,
+    expected: i64,
+}
+
+pub struct IntegrityService;
+
+impl IntegrityService {
+    /// Check the database file, foreign keys and the derived values we keep by hand.
+    /// With `fix`, repairable issues are corrected and each repair is written to the audit log.
+    pub fn run_integrity_check(db: &Database, fix: bool) -> DatabaseResult<IntegrityReport> {
+        AuditService::audited(db, |audit| {
+            let mut issues = Vec::new();
+
+            let database_ok = Self::check_sqlite_integrity(db, &mut issues)?;
+            Self::check_foreign_keys(db, &mut issues)?;
+            Self::check_orphaned_attendance(db, fix, audit, &mut issues)?;
+            Self::check_paid_amounts(db, fix, audit, &mut issues)?;
+            Self::check_group_names(db, fix, audit, &mut issues)?;
+            // After paid amounts, so a repaired amount is reflected in the recalculated status
+            Self::check_payment_states(db, fix, audit, &mut issues)?;
+
+            let fixed_count = issues.iter().filter(|issue| issue.fixed).count();
+            if fixed_count > 0 {
+                log::warn!("Integrity check repaired {} issues", fixed_count);
+            }
+
+            Ok(IntegrityReport {
+                checked_at: Utc::now().to_rfc3339(),
+                fix_mode: fix,
+                database_ok,
+                issues,
+                fixed_count,
+            })
+        })
+    }
+
+    fn issue(
+        check: &str,
+        table_name: &str,
+        record_id: Option<String>,
+        message: String,
+        fixable: bool,
+    ) -> IntegrityIssue {
+        IntegrityIssue {
+            check: check.to_string(),
+            table_name: table_name.to_string(),
+            record_id,
+            message,
+            fixable,
+            fixed: false,
+        }
+    }
+
+    fn log_repair(
+        audit: &mut AuditTrail,
+        table_name: &str,
+        record_id: &str,
+        old_values: serde_json::Value,
+        new_values: serde_json::Value,
+    ) {
+        audit.change(
+            "REPAIR",
+            table_name,
+            record_id,
+            &old_values,
+            &new_values,
+            None,
+        );
+    }
+
+    fn check_sqlite_integrity(
+        db: &Database,
+        issues: &mut Vec<IntegrityIssue>,
+    ) -> DatabaseResult<bool> {
+        let mut stmt = db.connection().prepare("PRAGMA integrity_check")?;
+        let messages = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let ok = messages.len() == 1 && messages[0] == "ok";
+        if !ok {
+            issues.extend(
+                messages
+                    .into_iter()
+                    .map(|message| Self::issue("sqlite_integrity", "", None, message, false)),
+            );
+        }
+        Ok(ok)
+    }
+
+    fn check_foreign_keys(db: &Database, issues: &mut Vec<IntegrityIssue>) -> DatabaseResult<()> {
+        let mut stmt = db.connection().prepare(
+            "SELECT \"table\", rowid, parent FROM pragma_foreign_key_check
+             WHERE NOT (\"table\" = 'attendance' AND parent = 'students')",
+        )?;
+        let violations = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<i64>>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (table, rowid, parent) in violations {
+            issues.push(Self::issue(
+                "foreign_key",
+                &table,
+                rowid.map(|id| id.to_string()),
+                format!("References a missing row in {}", parent),
+                false,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Attendance left behind by students that no longer exist
+    fn check_orphaned_attendance(
+        db: &Database,
+        fix: bool,
+        audit: &mut AuditTrail,
+        issues: &mut Vec<IntegrityIssue>,
+    ) -> DatabaseResult<()> {
+        let mut stmt = db.connection().prepare(
+            "SELECT a.id, a.student_id, a.date FROM attendance a
+             LEFT JOIN students s ON s.id = a.student_id
+             WHERE s.id IS NULL
+             ORDER BY a.id",
+        )?;
+        let orphans = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (id, student_id, date) in orphans {
+            let mut issue = Self::issue(
+                "attendance_student",
+                "attendance",
+                Some(id.to_string()),
+                format!(
+                    "Attendance on {} belongs to deleted student {}",
+                    date, student_id
+                ),
+                true,
+            );
+
+            if fix {
+                db.connection()
+                    .execute("DELETE FROM attendance WHERE id = ?1", [id])?;
+                let old_values = serde_json::json!({
+                    "id": id,
+                    "student_id": student_id,
+                    "date": date
+                });
+                audit.delete("attendance", &id.to_string(), &old_values, None);
+                issue.fixed = true;
+            }
+
+            issues.push(issue);
+        }
+        Ok(())
+    }
+
+    /// Paid amounts that differ from the sum of their payment transactions
+    fn find_paid_amount_mismatches(db: &Database) -> DatabaseResult<Vec<PaidAmountCheck>> {
+        let mut checks = Vec::new();
+
+        let students = {
+            let mut stmt = db.connection().prepare(
+                "SELECT s.id, s.paid_amount, e.id FROM students s
+                 LEFT JOIN enrollments e ON e.student_id = s.id AND e.is_primary = 1
+                 ORDER BY s.id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<i32>>(2)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        for (student_id, stored, primary_id) in students {
+            let paid: i64 = db.connection().query_row(
+                "SELECT IFNULL(SUM(amount), 0) FROM payment_transactions
+                 WHERE student_id = ?1 AND (enrollment_id IS NULL OR enrollment_id = ?2)",
+                params![student_id, primary_id],
+                |row| row.get(0),
+            )?;
+
+            checks.push(PaidAmountCheck {
+                record_id: student_id,
+                enrollment_id: None,
+                stored,
+                expected: paid,
+            });
+        }
+
+        let secondary = {
+            let mut stmt = db.connection().prepare(
+                "SELECT id, student_id, paid_amount FROM enrollments WHERE is_primary = 0 ORDER BY id",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, i32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        for (enrollment_id, student_id, stored) in secondary {
+            let paid: i64 = db.connection().query_row(
+                "SELECT IFNULL(SUM(amount), 0) FROM payment_transactions WHERE enrollment_id = ?1",
+                [enrollment_id],
+                |row| row.get(0),
+            )?;
+
+            checks.push(PaidAmountCheck {
+                record_id: student_id,
+                enrollment_id: Some(enrollment_id),
+                stored,
+                expected: paid,
+            });
+        }
+
+        Ok(checks
+            .into_iter()
+            .filter(|check| check.stored != check.expected)
+            .collect())
+    }
+
+    fn check_paid_amounts(
+        db: &Database,
+        fix: bool,
+        audit: &mut AuditTrail,
+        issues: &mut Vec<IntegrityIssue>,
+    ) -> DatabaseResult<()> {
+        for mismatch in Self::find_paid_amount_mismatches(db)? {
+            let (table_name, record_id) = match mismatch.enrollment_id {
+                Some(id) => ("enrollments", id.to_string()),
+                None => ("students", mismatch.record_id.clone()),
+            };
+            // An amount typed in when the record was created or edited has no transaction behind
+            // it, so a paid amount above the transactions is left for someone to review
+            let fixable = mismatch.stored < mismatch.expected;
+            let mut message = format!(
+                "Paid amount is {} but payment transactions add up to {}",
+                mismatch.stored, mismatch.expected
+            );
+            if !fixable {
+                message.push_str("; part of it may have been entered by hand, review it manually");
+            }
+            let mut issue = Self::issue(
+                "paid_amount",
+                table_name,
+                Some(record_id.clone()),
+                message,
+                fixable,
+            );
+
+            if fix && fixable {
+                let now = Utc::now().to_rfc3339();
+                match mismatch.enrollment_id {
+                    Some(id) => {
+                        db.connection().execute(
+                            "UPDATE enrollments SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
+                            params![mismatch.expected, now, id],
+                        )?;
+                        EnrollmentService::update_enrollment_payment_status(db, id)?;
+                    }
+                    None => {
+                        db.connection().execute(
+                            "UPDATE students SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
+                            params![mismatch.expected, now, mismatch.record_id],
+                        )?;
+                        InstallmentService::allocate_payments(db, &mismatch.record_id)?;
+                        PaymentService::update_student_payment_status(db, &mismatch.record_id)?;
+                    }
+                }
+                Self::log_repair(
+                    audit,
+                    table_name,
+                    &record_id,
+                    serde_json::json!({ "paid_amount": mismatch.stored }),
+                    serde_json::json!({ "paid_amount": mismatch.expected }),
+                );
+                issue.fixed = true;
+            }
+
+            issues.push(issue);
+        }
+        Ok(())
+    }
+
+    /// Students and open enrollments whose group is missing or stored under another name
+    fn check_group_names(
+        db: &Database,
+        fix: bool,
+        audit: &mut AuditTrail,
+        issues: &mut Vec<IntegrityIssue>,
+    ) -> DatabaseResult<()> {
+        let unmatched = {
+            let mut stmt = db.connection().prepare(
+                "SELECT 'students', s.id, s.group_name, s.group_id FROM students s
+                 WHERE s.deleted_at IS NULL AND NOT EXISTS (
+                     SELECT 1 FROM groups g WHERE g.id = s.group_id AND g.deleted_at IS NULL AND g.name = s.group_name)
+                 UNION ALL
+                 SELECT 'enrollments', CAST(e.id AS TEXT), e.group_name, e.group_id FROM enrollments e
+                 WHERE e.is_primary = 0 AND e.status IN ('active', 'waitlisted') AND NOT EXISTS (
+                     SELECT 1 FROM groups g WHERE g.id = e.group_id AND g.deleted_at IS NULL AND g.name = e.group_name)",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<i32>>(3)?,
+                ))
+            })?;
+            rows.collect::<Result<Vec<_>, _>>()?
+        };
+
+        for (table_name, record_id, group_name, group_id) in unmatched {
+            let fixable = !group_name.trim().is_empty();
+            let mut issue = Self::issue(
+                "group_name",
+                &table_name,
+                Some(record_id.clone()),
+                format!("Group '{}' does not match an existing group", group_name),
+                fixable,
+            );
+
+            if fix && fixable {
+                let group = GroupsService::resolve_group(db, &group_name)?;
+                let sql = if table_name == "students" {
+                    "UPDATE students SET group_id = ?1, group_name = ?2, updated_at = ?3 WHERE id = ?4"
+                } else {
+                    "UPDATE enrollments SET group_id = ?1, group_name = ?2, updated_at = ?3 WHERE id = ?4"
+                };
+                db.connection().execute(
+                    sql,
+                    params![group.id, group.name, Utc::now().to_rfc3339(), record_id],
+                )?;
+                if table_name == "students" {
+                    EnrollmentService::sync_primary_enrollment(db, &record_id)?;
+                }
+                Self::log_repair(
+                    audit,
+                    &table_name,
+                    &record_id,
+                    serde_json::json!({ "group_id": group_id, "group_name": group_name }),
+                    serde_json::json!({ "group_id": group.id, "group_name": group.name }),
+                );
+                issue.fixed = true;
+            }
+
+            issues.push(issue);
+        }
+        Ok(())
+    }
+
+    /// Stored payment status and next due date against a fresh calculation
+    fn check_payment_states(
+        db: &Database,
+        fix: bool,
+        audit: &mut AuditTrail,
+        issues: &mut Vec<IntegrityIssue>,
+    ) -> DatabaseResult<()> {
+        for student in StudentService::get_all_students(db)? {
+            let (next_due_date, payment_status) =
+                match PaymentService::calculate_student_payment_state(db, &student.id) {
+                    Ok(state) => state,
+                    Err(e) => {
+                        issues.push(Self::issue(
+                            "payment_status",
+                            "students",
+                            Some(student.id.clone()),
+                            format!("Payment status cannot be calculated: {}", e),
+                            false,
+                        ));
+                        continue;
+                    }
+                };
+
+            if next_due_date == student.next_due_date && payment_status == student.payment_status {
+                continue;
+            }
+
+            let mut issue = Self::issue(
+                "payment_status",
+                "students",
+                Some(student.id.clone()),
+                format!(
+                    "Stored status {} due {} should be {} due {}",
+                    student.payment_status.as_str(),
+                    student.next_due_date.as_deref().unwrap_or("-"),
+                    payment_status.as_str(),
+                    next_due_date.as_deref().unwrap_or("-")
+                ),
+                true,
+            );
+
+            if fix {
+                PaymentService::update_student_payment_status(db, &student.id)?;
+                Self::log_repair(
+                    audit,
+                    "students",
+                    &student.id,
+                    serde_json::json!({
+                        "payment_status": student.payment_status.as_str(),
+                        "next_due_date": student.next_due_date
+                    }),
+                    serde_json::json!({
+                        "payment_status": payment_status.as_str(),
+                        "next_due_date": next_due_date
+                    }),
+                );
+                issue.fixed = true;
+            }
+
+            issues.push(issue);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payment_service::{PaymentMethod, RecordPaymentRequest};
+    use crate::student_service::{CreateStudentRequest, PaymentPlan};
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_paying_student(db: &Database) -> String {
+        let student = StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: "Checked Student".to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::OneTime,
+                plan_amount: 6000,
+                installment_count: None,
+                paid_amount: Some(0),
+                enrollment_date: Some("2024-01-01".to_string()),
+            },
+        )
+        .unwrap();
+
+        PaymentService::record_payment(
+            db,
+            RecordPaymentRequest {
+                student_id: student.id.clone(),
+                enrollment_id: None,
+                amount: 2000,
+                payment_date: "2024-01-05".to_string(),
+                payment_method: PaymentMethod::Cash,
+                notes: None,
+            },
+        )
+        .unwrap();
+
+        student.id
+    }
+
+    #[test]
+    fn test_clean_database_has_no_issues() {
+        let (db, _temp_dir) = create_test_db();
+        create_paying_student(&db);
+
+        let report = IntegrityService::run_integrity_check(&db, false).unwrap();
+        assert!(report.database_ok);
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn test_fix_mode_repairs_drift() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_paying_student(&db);
+
+        db.connection()
+            .execute(
+                "UPDATE students SET paid_amount = 500, group_name = 'Group Z' WHERE id = ?1",
+                [&student_id],
+            )
+            .unwrap();
+        db.connection()
+            .execute_batch("PRAGMA foreign_keys = OFF")
+            .unwrap();
+        db.connection()
+            .execute(
+                "INSERT INTO attendance (student_id, date) VALUES ('GONE', '2024-01-02')",
+                [],
+            )
+            .unwrap();
+        db.connection()
+            .execute_batch("PRAGMA foreign_keys = ON")
+            .unwrap();
+
+        let report = IntegrityService::run_integrity_check(&db, false).unwrap();
+        let checks: Vec<&str> = report.issues.iter().map(|i| i.check.as_str()).collect();
+        assert!(checks.contains(&"paid_amount"));
+        assert!(checks.contains(&"group_name"));
+        assert!(checks.contains(&"attendance_student"));
+        assert_eq!(report.fixed_count, 0);
+
+        let report = IntegrityService::run_integrity_check(&db, true).unwrap();
+        assert!(report.fixed_count >= 3);
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 2000);
+        assert_eq!(student.group_name, "Group Z");
+        assert!(GroupsService::get_group_by_name(&db, "Group Z")
+            .unwrap()
+            .is_some());
+
+        let repairs: i32 = db
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM audit_log WHERE action_type = 'REPAIR'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(repairs >= 2);
+
+        let report = IntegrityService::run_integrity_check(&db, false).unwrap();
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn test_hand_entered_paid_amount_is_left_for_review() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_paying_student(&db);
+        db.connection()
+            .execute(
+                "UPDATE students SET paid_amount = paid_amount + 1000 WHERE id = ?1",
+                [&student_id],
+            )
+            .unwrap();
+
+        let report = IntegrityService::run_integrity_check(&db, true).unwrap();
+        let issue = report
+            .issues
+            .iter()
+            .find(|issue| issue.check == "paid_amount")
+            .unwrap();
+        assert!(!issue.fixable && !issue.fixed);
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 3000);
+    }
+}
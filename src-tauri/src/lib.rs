@@ -1,30 +1,75 @@
+pub mod analytics_service;
+pub mod arabic_shaping;
 pub mod attendance_service;
 pub mod audit_service;
 pub mod backup_service;
 pub mod database;
+pub mod dunning_service;
+pub mod enrollment_service;
+pub mod error;
 pub mod export_service;
 pub mod groups_service;
+pub mod i18n;
+pub mod installment_service;
+pub mod integrity_service;
+pub mod membership_service;
+pub mod notification_providers;
+pub mod notification_service;
 pub mod payment_service;
 pub mod payment_settings_service;
 pub mod qr_service;
+pub mod receivables_service;
+pub mod revert_service;
+pub mod risk_service;
 pub mod settings_service;
 pub mod student_service;
+pub mod term_service;
 
 // Re-export commonly used types and services
+pub use analytics_service::{
+    AnalyticsService, CashFlowForecast, CashFlowForecastRequest, ForecastTotals, RevenueSeries,
+    RevenueSeriesRequest, SeriesBreakdown, SeriesInterval, SeriesLine, SeriesPeriod, SeriesTotals,
+};
 pub use attendance_service::{
-    AttendanceHistoryFilter, AttendanceRecord, AttendanceService, AttendanceStats,
-    DailyAttendanceSummary,
+    AttendanceHistoryFilter, AttendanceMatrix, AttendanceMatrixRow, AttendanceRecord,
+    AttendanceService, AttendanceStats, AttendanceStatus, AttendanceTally, DailyAttendanceSummary,
+};
+pub use audit_service::{
+    AuditChainBreak, AuditChainReport, AuditCheckpoint, AuditEntryDiff, AuditLogEntry,
+    AuditLogFilter, AuditService, AuditStatistics, ChainBreakKind, FieldChange, RecordSnapshot,
 };
-pub use audit_service::{AuditLogEntry, AuditLogFilter, AuditService, AuditStatistics};
 pub use backup_service::{BackupMetadata, BackupService, BackupValidationResult, RestoreResult};
 pub use database::{
-    AppliedMigration, Database, DatabaseError, DatabaseResult, Migration, MigrationValidation,
-    RollbackInfo, SchemaInfo,
+    AppliedMigration, Database, DatabaseError, DatabasePool, DatabaseResult, Migration,
+    MigrationValidation, PooledDatabase, RollbackInfo, RollbackResult, SchemaInfo, StartupReport,
+};
+pub use dunning_service::{
+    DunningAction, DunningActionKind, DunningLevel, DunningRunSummary, DunningService,
+    DunningWorklistEntry, PaymentPromise, PromiseStatus, RecordPromiseRequest,
+};
+pub use enrollment_service::{
+    CreateEnrollmentRequest, Enrollment, EnrollmentService, EnrollmentStatus,
+    UpdateEnrollmentRequest,
 };
-pub use export_service::ExportService;
+pub use error::{AppError, ErrorCode};
+pub use export_service::{ExportFormat, ExportService, WorkbookSummary};
 pub use groups_service::{
-    CreateGroupRequest, Group, GroupStatistics, GroupWithStudentCount, GroupsService,
-    UpdateGroupRequest,
+    CreateGroupRequest, Group, GroupSettings, GroupStatistics, GroupWithStudentCount,
+    GroupsService, UpdateGroupRequest,
+};
+pub use i18n::Locale;
+pub use installment_service::{
+    Installment, InstallmentInput, InstallmentSchedule, InstallmentService, InstallmentStatus,
+};
+pub use integrity_service::{IntegrityIssue, IntegrityReport, IntegrityService};
+pub use membership_service::{GroupMembership, MembershipService, TransferStudentRequest};
+pub use notification_providers::{
+    Channel, DeliveryError, FileProvider, HttpGatewayConfig, HttpGatewayProvider,
+    NotificationProvider, Providers, SmtpConfig, SmtpProvider, SmtpSecurity,
+};
+pub use notification_service::{
+    DeliveryReport, EnqueueSummary, NotificationConfig, NotificationService, NotificationStatus,
+    NotificationTemplate, OutboxMessage, ReminderKind, StudentContact,
 };
 pub use payment_service::{
     PaymentHistoryFilter, PaymentMethod, PaymentService, PaymentStatistics, PaymentSummary,
@@ -35,6 +80,14 @@ pub use payment_settings_service::{
     UpdatePaymentSettingsRequest,
 };
 pub use qr_service::{QRCodeBatch, QRCodeData, QRCodeStatistics, QRService};
+pub use receivables_service::{
+    AgingBuckets, AgingItem, AgingReport, GroupAging, OutstandingDue, ReceivablesService,
+    StudentAging,
+};
+pub use revert_service::{RevertResult, RevertService};
+pub use risk_service::{
+    AtRiskStudent, RiskConfig, RiskFactor, RiskService, RiskSignal, RiskWeights,
+};
 pub use settings_service::{
     AppSettings, PaymentPlanConfig as SettingsPaymentPlanConfig, SettingRecord, SettingsService,
 };
@@ -42,36 +95,112 @@ pub use student_service::{
     CreateStudentRequest, PaymentPlan, PaymentPlanConfig, Student, StudentService,
     StudentStatistics, StudentWithAttendance, UpdateStudentRequest,
 };
+pub use term_service::{
+    CreateTermRequest, RolloverRequest, RolloverSummary, Term, TermBalance, TermService,
+    UpdateTermRequest,
+};
 
 // Internal imports
-use std::sync::Mutex;
+use error::context;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
 use tauri::{Manager, State};
 
+/// Reader connections kept open next to the single writer
+const READER_POOL_SIZE: u32 = 4;
+
+/// How often the maintenance thread looks for a quiet moment
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long nothing must have been written before statistics are refreshed
+const IDLE_PERIOD: Duration = Duration::from_secs(120);
+
+/// Minimum time between statistics refreshes
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Interval between background passes over the notification outbox
+const OUTBOX_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Messages sent per outbox pass
+const OUTBOX_BATCH_SIZE: i64 = 50;
+
+/// Interval between background dunning escalation runs
+const DUNNING_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Environment variable that lets the app start even if applied migrations were edited
+const ALLOW_MIGRATION_CHECKSUM_MISMATCH_ENV: &str = "ALLOW_MIGRATION_CHECKSUM_MISMATCH";
+
 // Global database instance
 pub struct AppState {
-    pub db: Mutex<Database>,
+    pub db: DatabasePool,
+    /// Language command errors are rendered in, kept in step with the `language` setting
+    locale: RwLock<Locale>,
+}
+
+impl AppState {
+    fn locale(&self) -> Locale {
+        *self.locale.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Re-read the `language` setting after a command that may have changed it
+    fn sync_locale(&self, db: &Database) {
+        match Locale::from_settings(db) {
+            Ok(locale) => *self.locale.write().unwrap_or_else(|e| e.into_inner()) = locale,
+            Err(e) => log::warn!("Failed to read the language setting: {}", e),
+        }
+    }
+
+    /// [`context`] that also renders the error in the app language
+    fn context<E: Into<AppError>>(&self, message: &'static str) -> impl FnOnce(E) -> AppError {
+        let locale = self.locale();
+        move |error| context(message)(error).localized_in(locale)
+    }
+}
+
+/// Run a long read on a pooled connection on the blocking thread pool, so exports and
+/// batch reports don't hold up other commands while they run. Errors are rendered in the
+/// app language on the way out.
+async fn run_blocking_read<T, F>(state: &AppState, f: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce(&Database) -> Result<T, AppError> + Send + 'static,
+{
+    let locale = state.locale();
+    let readers = state
+        .db
+        .readers()
+        .map_err(state.context("Failed to get database connection"))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = readers.get().map_err(|e| {
+            AppError::storage(format!("Failed to get database connection: {}", e))
+        })?;
+        f(&db)
+    })
+    .await
+    .map_err(|e| AppError::storage(format!("Background task failed: {}", e)))?
+    .map_err(|error| error.localized_in(locale))
 }
 
 // Student-related IPC commands
 #[tauri::command]
-async fn get_all_students(state: State<'_, AppState>) -> Result<Vec<Student>, String> {
+async fn get_all_students(state: State<'_, AppState>) -> Result<Vec<Student>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    StudentService::get_all_students(&db).map_err(|e| format!("Failed to get students: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    StudentService::get_all_students(&db).map_err(state.context("Failed to get students"))
 }
 
 #[tauri::command]
 async fn get_all_students_with_attendance(
     state: State<'_, AppState>,
-) -> Result<Vec<StudentWithAttendance>, String> {
+) -> Result<Vec<StudentWithAttendance>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     StudentService::get_all_students_with_attendance(&db)
-        .map_err(|e| format!("Failed to get students with attendance: {}", e))
+        .map_err(state.context("Failed to get students with attendance"))
 }
 
 #[tauri::command]
@@ -84,13 +213,13 @@ async fn add_student(
     #[allow(non_snake_case)] installmentCount: Option<i32>,
     #[allow(non_snake_case)] paidAmount: Option<i32>,
     #[allow(non_snake_case)] enrollmentDate: Option<String>,
-) -> Result<Student, String> {
+) -> Result<Student, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     let payment_plan_enum =
-        PaymentPlan::from_str(&paymentPlan).map_err(|e| format!("Invalid payment plan: {}", e))?;
+        PaymentPlan::from_str(&paymentPlan).map_err(|e| AppError::validation("payment_plan", e))?;
     let request = CreateStudentRequest {
         name,
         group_name: groupName,
@@ -101,7 +230,7 @@ async fn add_student(
         enrollment_date: enrollmentDate,
     };
     StudentService::create_student(&db, request)
-        .map_err(|e| format!("Failed to create student: {}", e))
+        .map_err(state.context("Failed to create student"))
 }
 
 #[tauri::command]
@@ -115,13 +244,13 @@ async fn update_student(
     #[allow(non_snake_case)] installmentCount: Option<i32>,
     #[allow(non_snake_case)] paidAmount: Option<i32>,
     #[allow(non_snake_case)] enrollmentDate: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     let payment_plan_enum =
-        PaymentPlan::from_str(&paymentPlan).map_err(|e| format!("Invalid payment plan: {}", e))?;
+        PaymentPlan::from_str(&paymentPlan).map_err(|e| AppError::validation("payment_plan", e))?;
     let request = UpdateStudentRequest {
         name,
         group_name: groupName,
@@ -132,104 +261,133 @@ async fn update_student(
         paid_amount: paidAmount,
     };
     StudentService::update_student(&db, &id, request)
-        .map_err(|e| format!("Failed to update student: {}", e))
+        .map_err(state.context("Failed to update student"))
+}
+
+#[tauri::command]
+async fn delete_student(state: State<'_, AppState>, id: String) -> Result<(), AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    StudentService::delete_student(&db, &id).map_err(state.context("Failed to delete student"))
+}
+
+#[tauri::command]
+async fn transfer_student(
+    state: State<'_, AppState>,
+    request: TransferStudentRequest,
+) -> Result<Student, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    MembershipService::transfer_student(&db, request)
+        .map_err(state.context("Failed to transfer student"))
 }
 
 #[tauri::command]
-async fn delete_student(state: State<'_, AppState>, id: String) -> Result<(), String> {
+async fn get_student_group_history(
+    state: State<'_, AppState>,
+    student_id: String,
+) -> Result<Vec<GroupMembership>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    StudentService::delete_student(&db, &id).map_err(|e| format!("Failed to delete student: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    MembershipService::get_student_group_history(&db, &student_id)
+        .map_err(state.context("Failed to get student group history"))
 }
 
 #[tauri::command]
 async fn get_student_by_id(
     state: State<'_, AppState>,
     id: String,
-) -> Result<Option<Student>, String> {
+) -> Result<Option<Student>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    StudentService::get_student_by_id(&db, &id).map_err(|e| format!("Failed to get student: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    StudentService::get_student_by_id(&db, &id).map_err(state.context("Failed to get student"))
 }
 
 #[tauri::command]
 async fn get_students_by_group(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] groupName: String,
-) -> Result<Vec<Student>, String> {
+) -> Result<Vec<Student>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     StudentService::get_students_by_group(&db, &groupName)
-        .map_err(|e| format!("Failed to get students by group: {}", e))
+        .map_err(state.context("Failed to get students by group"))
 }
 
 #[tauri::command]
 async fn get_students_by_payment_status(
     state: State<'_, AppState>,
     status: String,
-) -> Result<Vec<Student>, String> {
+) -> Result<Vec<Student>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     StudentService::get_students_by_payment_status(&db, &status)
-        .map_err(|e| format!("Failed to get students by payment status: {}", e))
+        .map_err(state.context("Failed to get students by payment status"))
 }
 
 #[tauri::command]
-async fn get_overdue_students(state: State<'_, AppState>) -> Result<Vec<Student>, String> {
+async fn get_overdue_students(state: State<'_, AppState>) -> Result<Vec<Student>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     StudentService::get_overdue_students(&db)
-        .map_err(|e| format!("Failed to get overdue students: {}", e))
+        .map_err(state.context("Failed to get overdue students"))
 }
 
 #[tauri::command]
-async fn get_due_soon_students(state: State<'_, AppState>) -> Result<Vec<Student>, String> {
+async fn get_due_soon_students(state: State<'_, AppState>) -> Result<Vec<Student>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     StudentService::get_due_soon_students(&db)
-        .map_err(|e| format!("Failed to get due soon students: {}", e))
+        .map_err(state.context("Failed to get due soon students"))
 }
 
 #[tauri::command]
-async fn update_payment_statuses(state: State<'_, AppState>) -> Result<(), String> {
+async fn update_payment_statuses(state: State<'_, AppState>) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     StudentService::update_payment_statuses(&db)
-        .map_err(|e| format!("Failed to update payment statuses: {}", e))
+        .map_err(state.context("Failed to update payment statuses"))
 }
 
 #[tauri::command]
-async fn get_payment_plan_config(state: State<'_, AppState>) -> Result<PaymentPlanConfig, String> {
+async fn get_payment_plan_config(state: State<'_, AppState>) -> Result<PaymentPlanConfig, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     StudentService::get_payment_plan_config(&db)
-        .map_err(|e| format!("Failed to get payment plan config: {}", e))
+        .map_err(state.context("Failed to get payment plan config"))
 }
 
 #[tauri::command]
-async fn get_student_statistics(state: State<'_, AppState>) -> Result<StudentStatistics, String> {
+async fn get_student_statistics(
+    state: State<'_, AppState>,
+    term_id: Option<i32>,
+) -> Result<StudentStatistics, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    StudentService::get_student_statistics(&db)
-        .map_err(|e| format!("Failed to get student statistics: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    StudentService::get_student_statistics(&db, term_id)
+        .map_err(state.context("Failed to get student statistics"))
 }
 
 // Attendance-related IPC commands
@@ -237,27 +395,28 @@ async fn get_student_statistics(state: State<'_, AppState>) -> Result<StudentSta
 async fn mark_attendance(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] studentId: String,
+    #[allow(non_snake_case)] enrollmentId: Option<i32>,
     date: String,
-) -> Result<AttendanceRecord, String> {
+) -> Result<AttendanceRecord, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    AttendanceService::mark_attendance(&db, &studentId, &date)
-        .map_err(|e| format!("Failed to mark attendance: {}", e))
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    AttendanceService::mark_attendance(&db, &studentId, enrollmentId, &date)
+        .map_err(state.context("Failed to mark attendance"))
 }
 
 #[tauri::command]
 async fn check_attendance_today(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] studentId: String,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AttendanceService::check_attendance_today(&db, &studentId)
-        .map_err(|e| format!("Failed to check attendance: {}", e))
+        .map_err(state.context("Failed to check attendance"))
 }
 
 #[tauri::command]
@@ -265,13 +424,13 @@ async fn check_attendance_on_date(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] studentId: String,
     date: String,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AttendanceService::check_attendance_on_date(&db, &studentId, &date)
-        .map_err(|e| format!("Failed to check attendance: {}", e))
+        .map_err(state.context("Failed to check attendance"))
 }
 
 #[tauri::command]
@@ -281,11 +440,11 @@ async fn get_attendance_history(
     #[allow(non_snake_case)] startDate: Option<String>,
     #[allow(non_snake_case)] endDate: Option<String>,
     #[allow(non_snake_case)] groupName: Option<String>,
-) -> Result<Vec<AttendanceRecord>, String> {
+) -> Result<Vec<AttendanceRecord>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
 
     let filter =
         if studentId.is_some() || startDate.is_some() || endDate.is_some() || groupName.is_some() {
@@ -300,20 +459,20 @@ async fn get_attendance_history(
         };
 
     AttendanceService::get_attendance_history(&db, filter)
-        .map_err(|e| format!("Failed to get attendance history: {}", e))
+        .map_err(state.context("Failed to get attendance history"))
 }
 
 #[tauri::command]
 async fn get_student_attendance_history(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] studentId: String,
-) -> Result<Vec<AttendanceRecord>, String> {
+) -> Result<Vec<AttendanceRecord>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AttendanceService::get_student_attendance_history(&db, &studentId)
-        .map_err(|e| format!("Failed to get student attendance history: {}", e))
+        .map_err(state.context("Failed to get student attendance history"))
 }
 
 #[tauri::command]
@@ -322,18 +481,20 @@ async fn get_student_attendance_stats(
     student_id: String,
     start_date: Option<String>,
     end_date: Option<String>,
-) -> Result<AttendanceStats, String> {
+    term_id: Option<i32>,
+) -> Result<AttendanceStats, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AttendanceService::get_student_attendance_stats(
         &db,
         &student_id,
         start_date.as_deref(),
         end_date.as_deref(),
+        term_id,
     )
-    .map_err(|e| format!("Failed to get attendance stats: {}", e))
+    .map_err(state.context("Failed to get attendance stats"))
 }
 
 #[tauri::command]
@@ -341,13 +502,13 @@ async fn get_daily_attendance_summary(
     state: State<'_, AppState>,
     date: String,
     group_name: Option<String>,
-) -> Result<DailyAttendanceSummary, String> {
+) -> Result<DailyAttendanceSummary, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AttendanceService::get_daily_attendance_summary(&db, &date, group_name.as_deref())
-        .map_err(|e| format!("Failed to get daily attendance summary: {}", e))
+        .map_err(state.context("Failed to get daily attendance summary"))
 }
 
 #[tauri::command]
@@ -356,42 +517,75 @@ async fn get_attendance_summary_range(
     start_date: String,
     end_date: String,
     group_name: Option<String>,
-) -> Result<Vec<DailyAttendanceSummary>, String> {
+) -> Result<Vec<DailyAttendanceSummary>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AttendanceService::get_attendance_summary_range(
         &db,
         &start_date,
         &end_date,
         group_name.as_deref(),
     )
-    .map_err(|e| format!("Failed to get attendance summary range: {}", e))
+    .map_err(state.context("Failed to get attendance summary range"))
+}
+
+#[tauri::command]
+async fn set_attendance_status(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] studentId: String,
+    #[allow(non_snake_case)] enrollmentId: Option<i32>,
+    date: String,
+    status: String,
+) -> Result<AttendanceRecord, AppError> {
+    let status =
+        AttendanceStatus::from_str(&status).map_err(|e| AppError::validation("status", e))?;
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    AttendanceService::set_attendance_status(&db, &studentId, enrollmentId, &date, status)
+        .map_err(state.context("Failed to record attendance status"))
+}
+
+#[tauri::command]
+async fn get_attendance_matrix(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] groupName: String,
+    #[allow(non_snake_case)] startDate: String,
+    #[allow(non_snake_case)] endDate: String,
+) -> Result<AttendanceMatrix, AppError> {
+    run_blocking_read(&state, move |db| {
+        AttendanceService::get_attendance_matrix(db, &groupName, &startDate, &endDate)
+            .map_err(context("Failed to get attendance matrix"))
+    })
+    .await
 }
 
 #[tauri::command]
 async fn delete_attendance(
     state: State<'_, AppState>,
     student_id: String,
+    enrollment_id: Option<i32>,
     date: String,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    AttendanceService::delete_attendance(&db, &student_id, &date)
-        .map_err(|e| format!("Failed to delete attendance: {}", e))
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    AttendanceService::delete_attendance(&db, &student_id, enrollment_id, &date)
+        .map_err(state.context("Failed to delete attendance"))
 }
 
 #[tauri::command]
-async fn get_current_date() -> Result<String, String> {
+async fn get_current_date() -> Result<String, AppError> {
     Ok(AttendanceService::get_current_date())
 }
 
 #[tauri::command]
-async fn format_date(date_str: String) -> Result<String, String> {
-    AttendanceService::format_date(&date_str).map_err(|e| format!("Failed to format date: {}", e))
+async fn format_date(state: State<'_, AppState>, date_str: String) -> Result<String, AppError> {
+    AttendanceService::format_date(&date_str).map_err(state.context("Failed to format date"))
 }
 
 // Payment-related IPC commands
@@ -399,26 +593,28 @@ async fn format_date(date_str: String) -> Result<String, String> {
 async fn record_payment(
     state: State<'_, AppState>,
     student_id: String,
+    enrollment_id: Option<i32>,
     amount: i32,
     payment_date: String,
     payment_method: String,
     notes: Option<String>,
-) -> Result<PaymentTransaction, String> {
+) -> Result<PaymentTransaction, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     let payment_method_enum = PaymentMethod::from_str(&payment_method)
-        .map_err(|e| format!("Invalid payment method: {}", e))?;
+        .map_err(|e| AppError::validation("payment_method", e))?;
     let request = RecordPaymentRequest {
         student_id,
+        enrollment_id,
         amount,
         payment_date,
         payment_method: payment_method_enum,
         notes,
     };
     PaymentService::record_payment(&db, request)
-        .map_err(|e| format!("Failed to record payment: {}", e))
+        .map_err(state.context("Failed to record payment"))
 }
 
 #[tauri::command]
@@ -430,16 +626,16 @@ async fn get_payment_history(
     payment_method: Option<String>,
     min_amount: Option<i32>,
     max_amount: Option<i32>,
-) -> Result<Vec<PaymentTransaction>, String> {
+) -> Result<Vec<PaymentTransaction>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
 
     let payment_method_enum = if let Some(method) = payment_method {
         Some(
             PaymentMethod::from_str(&method)
-                .map_err(|e| format!("Invalid payment method: {}", e))?,
+                .map_err(|e| AppError::validation("payment_method", e))?,
         )
     } else {
         None
@@ -465,64 +661,67 @@ async fn get_payment_history(
     };
 
     PaymentService::get_payment_history(&db, filter)
-        .map_err(|e| format!("Failed to get payment history: {}", e))
+        .map_err(state.context("Failed to get payment history"))
 }
 
 #[tauri::command]
 async fn get_student_payment_history(
     state: State<'_, AppState>,
     student_id: String,
-) -> Result<Vec<PaymentTransaction>, String> {
+) -> Result<Vec<PaymentTransaction>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     PaymentService::get_student_payment_history(&db, &student_id)
-        .map_err(|e| format!("Failed to get student payment history: {}", e))
+        .map_err(state.context("Failed to get student payment history"))
 }
 
 #[tauri::command]
-async fn get_payment_summary(state: State<'_, AppState>) -> Result<PaymentSummary, String> {
+async fn get_payment_summary(
+    state: State<'_, AppState>,
+    term_id: Option<i32>,
+) -> Result<PaymentSummary, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    PaymentService::get_payment_summary(&db)
-        .map_err(|e| format!("Failed to get payment summary: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    PaymentService::get_payment_summary(&db, term_id)
+        .map_err(state.context("Failed to get payment summary"))
 }
 
 #[tauri::command]
 async fn update_student_payment_status(
     state: State<'_, AppState>,
     student_id: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentService::update_student_payment_status(&db, &student_id)
-        .map_err(|e| format!("Failed to update student payment status: {}", e))
+        .map_err(state.context("Failed to update student payment status"))
 }
 
 #[tauri::command]
-async fn update_all_payment_statuses(state: State<'_, AppState>) -> Result<(), String> {
+async fn update_all_payment_statuses(state: State<'_, AppState>) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentService::update_all_payment_statuses(&db)
         .map(|_| ())
-        .map_err(|e| format!("Failed to update all payment statuses: {}", e))
+        .map_err(state.context("Failed to update all payment statuses"))
 }
 
 #[tauri::command]
-async fn delete_payment(state: State<'_, AppState>, payment_id: i32) -> Result<bool, String> {
+async fn delete_payment(state: State<'_, AppState>, payment_id: i32) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentService::delete_payment(&db, payment_id)
-        .map_err(|e| format!("Failed to delete payment: {}", e))
+        .map_err(state.context("Failed to delete payment"))
 }
 
 #[tauri::command]
@@ -530,116 +729,252 @@ async fn get_payment_statistics(
     state: State<'_, AppState>,
     start_date: Option<String>,
     end_date: Option<String>,
-) -> Result<PaymentStatistics, String> {
+    term_id: Option<i32>,
+) -> Result<PaymentStatistics, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    PaymentService::get_payment_statistics(
+        &db,
+        start_date.as_deref(),
+        end_date.as_deref(),
+        term_id,
+    )
+        .map_err(state.context("Failed to get payment statistics"))
+}
+
+// Installment schedule IPC commands
+#[tauri::command]
+async fn get_installment_schedule(
+    state: State<'_, AppState>,
+    student_id: String,
+) -> Result<InstallmentSchedule, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    InstallmentService::get_installment_schedule(&db, &student_id)
+        .map_err(state.context("Failed to get installment schedule"))
+}
+
+#[tauri::command]
+async fn update_installment_schedule(
+    state: State<'_, AppState>,
+    student_id: String,
+    installments: Vec<InstallmentInput>,
+) -> Result<InstallmentSchedule, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    InstallmentService::update_installment_schedule(&db, &student_id, installments)
+        .map_err(state.context("Failed to update installment schedule"))
+}
+
+// Enrollment-related IPC commands
+#[tauri::command]
+async fn get_student_enrollments(
+    state: State<'_, AppState>,
+    student_id: String,
+) -> Result<Vec<Enrollment>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    EnrollmentService::get_student_enrollments(&db, &student_id)
+        .map_err(state.context("Failed to get student enrollments"))
+}
+
+#[tauri::command]
+async fn create_enrollment(
+    state: State<'_, AppState>,
+    request: CreateEnrollmentRequest,
+) -> Result<Enrollment, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    EnrollmentService::create_enrollment(&db, request)
+        .map_err(state.context("Failed to create enrollment"))
+}
+
+#[tauri::command]
+async fn update_enrollment(
+    state: State<'_, AppState>,
+    id: i32,
+    request: UpdateEnrollmentRequest,
+) -> Result<Enrollment, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    EnrollmentService::update_enrollment(&db, id, request)
+        .map_err(state.context("Failed to update enrollment"))
+}
+
+#[tauri::command]
+async fn end_enrollment(
+    state: State<'_, AppState>,
+    id: i32,
+    end_date: String,
+    status: String,
+) -> Result<Enrollment, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    let status_enum =
+        EnrollmentStatus::from_str(&status).map_err(|e| AppError::validation("status", e))?;
+    EnrollmentService::end_enrollment(&db, id, &end_date, status_enum)
+        .map_err(state.context("Failed to end enrollment"))
+}
+
+#[tauri::command]
+async fn delete_enrollment(state: State<'_, AppState>, id: i32) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    PaymentService::get_payment_statistics(&db, start_date.as_deref(), end_date.as_deref())
-        .map_err(|e| format!("Failed to get payment statistics: {}", e))
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    EnrollmentService::delete_enrollment(&db, id)
+        .map_err(state.context("Failed to delete enrollment"))
+}
+
+#[tauri::command]
+async fn get_enrollment_payment_history(
+    state: State<'_, AppState>,
+    id: i32,
+) -> Result<Vec<PaymentTransaction>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    EnrollmentService::get_enrollment_payment_history(&db, id)
+        .map_err(state.context("Failed to get enrollment payment history"))
+}
+
+#[tauri::command]
+async fn get_group_waitlist(
+    state: State<'_, AppState>,
+    group_id: i32,
+) -> Result<Vec<Enrollment>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    EnrollmentService::get_group_waitlist(&db, group_id)
+        .map_err(state.context("Failed to get group waitlist"))
 }
 
 // Settings-related IPC commands
 #[tauri::command]
-async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
+async fn get_settings(state: State<'_, AppState>) -> Result<AppSettings, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    SettingsService::get_settings(&db).map_err(|e| format!("Failed to get settings: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    SettingsService::get_settings(&db).map_err(state.context("Failed to get settings"))
 }
 
 #[tauri::command]
-async fn update_settings(state: State<'_, AppState>, settings: AppSettings) -> Result<(), String> {
+async fn update_settings(state: State<'_, AppState>, settings: AppSettings) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     SettingsService::update_settings(&db, settings)
-        .map_err(|e| format!("Failed to update settings: {}", e))
+        .map_err(state.context("Failed to update settings"))?;
+    state.sync_locale(&db);
+    Ok(())
 }
 
 #[tauri::command]
 async fn get_payment_plan_config_settings(
     state: State<'_, AppState>,
-) -> Result<SettingsPaymentPlanConfig, String> {
+) -> Result<SettingsPaymentPlanConfig, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     SettingsService::get_payment_plan_config(&db)
-        .map_err(|e| format!("Failed to get payment plan config: {}", e))
+        .map_err(state.context("Failed to get payment plan config"))
 }
 
 #[tauri::command]
 async fn update_payment_plan_config_settings(
     state: State<'_, AppState>,
     config: SettingsPaymentPlanConfig,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     SettingsService::update_payment_plan_config(&db, config)
-        .map_err(|e| format!("Failed to update payment plan config: {}", e))
+        .map_err(state.context("Failed to update payment plan config"))
 }
 
 #[tauri::command]
-async fn get_setting(state: State<'_, AppState>, key: String) -> Result<Option<String>, String> {
+async fn get_setting(state: State<'_, AppState>, key: String) -> Result<Option<String>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    SettingsService::get_setting(&db, &key).map_err(|e| format!("Failed to get setting: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    SettingsService::get_setting(&db, &key).map_err(state.context("Failed to get setting"))
 }
 
 #[tauri::command]
-async fn set_setting(state: State<'_, AppState>, key: String, value: String) -> Result<(), String> {
+async fn set_setting(state: State<'_, AppState>, key: String, value: String) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     SettingsService::set_setting(&db, &key, &value)
-        .map_err(|e| format!("Failed to set setting: {}", e))
+        .map_err(state.context("Failed to set setting"))?;
+    state.sync_locale(&db);
+    Ok(())
 }
 
 #[tauri::command]
-async fn get_all_settings(state: State<'_, AppState>) -> Result<Vec<SettingRecord>, String> {
+async fn get_all_settings(state: State<'_, AppState>) -> Result<Vec<SettingRecord>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    SettingsService::get_all_settings(&db).map_err(|e| format!("Failed to get all settings: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    SettingsService::get_all_settings(&db).map_err(state.context("Failed to get all settings"))
 }
 
 #[tauri::command]
-async fn delete_setting(state: State<'_, AppState>, key: String) -> Result<bool, String> {
+async fn delete_setting(state: State<'_, AppState>, key: String) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    SettingsService::delete_setting(&db, &key)
-        .map_err(|e| format!("Failed to delete setting: {}", e))
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    let deleted = SettingsService::delete_setting(&db, &key)
+        .map_err(state.context("Failed to delete setting"))?;
+    state.sync_locale(&db);
+    Ok(deleted)
 }
 
 #[tauri::command]
-async fn reset_settings_to_defaults(state: State<'_, AppState>) -> Result<(), String> {
+async fn reset_settings_to_defaults(state: State<'_, AppState>) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     SettingsService::reset_to_defaults(&db)
-        .map_err(|e| format!("Failed to reset settings to defaults: {}", e))
+        .map_err(state.context("Failed to reset settings to defaults"))?;
+    state.sync_locale(&db);
+    Ok(())
 }
 
 #[tauri::command]
-async fn validate_settings(state: State<'_, AppState>) -> Result<bool, String> {
+async fn validate_settings(state: State<'_, AppState>) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     SettingsService::validate_settings(&db)
-        .map_err(|e| format!("Failed to validate settings: {}", e))
+        .map_err(state.context("Failed to validate settings"))
 }
 
 // Audit-related IPC commands
@@ -652,11 +987,11 @@ async fn get_audit_log(
     user_id: Option<String>,
     start_date: Option<String>,
     end_date: Option<String>,
-) -> Result<Vec<AuditLogEntry>, String> {
+) -> Result<Vec<AuditLogEntry>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
 
     let filter = if table_name.is_some()
         || record_id.is_some()
@@ -677,7 +1012,7 @@ async fn get_audit_log(
         None
     };
 
-    AuditService::get_audit_log(&db, filter).map_err(|e| format!("Failed to get audit log: {}", e))
+    AuditService::get_audit_log(&db, filter).map_err(state.context("Failed to get audit log"))
 }
 
 #[tauri::command]
@@ -685,130 +1020,201 @@ async fn get_record_history(
     state: State<'_, AppState>,
     table_name: String,
     record_id: String,
-) -> Result<Vec<AuditLogEntry>, String> {
+) -> Result<Vec<AuditLogEntry>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AuditService::get_record_history(&db, &table_name, &record_id)
-        .map_err(|e| format!("Failed to get record history: {}", e))
+        .map_err(state.context("Failed to get record history"))
 }
 
 #[tauri::command]
 async fn get_recent_audit_activity(
     state: State<'_, AppState>,
-) -> Result<Vec<AuditLogEntry>, String> {
+) -> Result<Vec<AuditLogEntry>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AuditService::get_recent_activity(&db)
-        .map_err(|e| format!("Failed to get recent audit activity: {}", e))
+        .map_err(state.context("Failed to get recent audit activity"))
 }
 
 #[tauri::command]
-async fn get_audit_statistics(state: State<'_, AppState>) -> Result<AuditStatistics, String> {
+async fn get_audit_statistics(state: State<'_, AppState>) -> Result<AuditStatistics, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     AuditService::get_audit_statistics(&db)
-        .map_err(|e| format!("Failed to get audit statistics: {}", e))
+        .map_err(state.context("Failed to get audit statistics"))
 }
 
 #[tauri::command]
 async fn cleanup_old_audit_entries(
     state: State<'_, AppState>,
     days_to_keep: i32,
-) -> Result<i32, String> {
+) -> Result<i32, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     AuditService::cleanup_old_entries(&db, days_to_keep)
-        .map_err(|e| format!("Failed to cleanup old audit entries: {}", e))
+        .map_err(state.context("Failed to cleanup old audit entries"))
+}
+
+#[tauri::command]
+async fn verify_audit_chain(state: State<'_, AppState>) -> Result<AuditChainReport, AppError> {
+    run_blocking_read(&state, |db| {
+        AuditService::verify_chain(db).map_err(context("Failed to verify audit log"))
+    })
+    .await
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+async fn get_record_changes(
+    state: State<'_, AppState>,
+    tableName: String,
+    recordId: String,
+) -> Result<Vec<AuditEntryDiff>, AppError> {
+    run_blocking_read(&state, move |db| {
+        AuditService::get_record_changes(db, &tableName, &recordId)
+            .map_err(context("Failed to get record changes"))
+    })
+    .await
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+async fn reconstruct_record(
+    state: State<'_, AppState>,
+    tableName: String,
+    recordId: String,
+    timestamp: String,
+) -> Result<RecordSnapshot, AppError> {
+    run_blocking_read(&state, move |db| {
+        AuditService::reconstruct_record(db, &tableName, &recordId, &timestamp)
+            .map_err(context("Failed to reconstruct record"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn revert_audit_entry(
+    state: State<'_, AppState>,
+    entry_id: i32,
+) -> Result<RevertResult, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    RevertService::revert_audit_entry(&db, entry_id)
+        .map_err(state.context("Failed to revert audit entry"))
 }
 
 // Groups-related IPC commands
 #[tauri::command]
-async fn get_all_groups(state: State<'_, AppState>) -> Result<Vec<Group>, String> {
+async fn get_all_groups(state: State<'_, AppState>) -> Result<Vec<Group>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    GroupsService::get_all_groups(&db).map_err(|e| format!("Failed to get all groups: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    GroupsService::get_all_groups(&db).map_err(state.context("Failed to get all groups"))
 }
 
 #[tauri::command]
 async fn get_all_groups_with_counts(
     state: State<'_, AppState>,
-) -> Result<Vec<GroupWithStudentCount>, String> {
+) -> Result<Vec<GroupWithStudentCount>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     GroupsService::get_all_groups_with_counts(&db)
-        .map_err(|e| format!("Failed to get groups with counts: {}", e))
+        .map_err(state.context("Failed to get groups with counts"))
 }
 
 #[tauri::command]
-async fn get_group_by_id(state: State<'_, AppState>, id: i32) -> Result<Option<Group>, String> {
+async fn get_group_by_id(state: State<'_, AppState>, id: i32) -> Result<Option<Group>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    GroupsService::get_group_by_id(&db, id).map_err(|e| format!("Failed to get group by ID: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    GroupsService::get_group_by_id(&db, id).map_err(state.context("Failed to get group by ID"))
 }
 
 #[tauri::command]
 async fn get_group_by_name(
     state: State<'_, AppState>,
     name: String,
-) -> Result<Option<Group>, String> {
+) -> Result<Option<Group>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     GroupsService::get_group_by_name(&db, &name)
-        .map_err(|e| format!("Failed to get group by name: {}", e))
+        .map_err(state.context("Failed to get group by name"))
 }
 
 #[tauri::command]
-async fn add_group(state: State<'_, AppState>, name: String) -> Result<Group, String> {
+async fn add_group(
+    state: State<'_, AppState>,
+    name: String,
+    settings: Option<GroupSettings>,
+) -> Result<Group, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
 
     // Validate group name
-    GroupsService::validate_group_name(&name).map_err(|e| format!("Invalid group name: {}", e))?;
+    GroupsService::validate_group_name(&name).map_err(state.context("Invalid group name"))?;
 
-    let request = CreateGroupRequest { name };
-    GroupsService::create_group(&db, request).map_err(|e| format!("Failed to create group: {}", e))
+    let request = CreateGroupRequest {
+        name,
+        settings: settings.unwrap_or_default(),
+    };
+    GroupsService::create_group(&db, request).map_err(state.context("Failed to create group"))
 }
 
 #[tauri::command]
-async fn update_group(state: State<'_, AppState>, id: i32, name: String) -> Result<(), String> {
+async fn update_group(state: State<'_, AppState>, id: i32, name: String) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
 
     // Validate group name
-    GroupsService::validate_group_name(&name).map_err(|e| format!("Invalid group name: {}", e))?;
+    GroupsService::validate_group_name(&name).map_err(state.context("Invalid group name"))?;
 
     let request = UpdateGroupRequest { name };
     GroupsService::update_group(&db, id, request)
-        .map_err(|e| format!("Failed to update group: {}", e))
+        .map_err(state.context("Failed to update group"))
 }
 
 #[tauri::command]
-async fn delete_group(state: State<'_, AppState>, id: i32) -> Result<bool, String> {
+async fn update_group_settings(
+    state: State<'_, AppState>,
+    id: i32,
+    settings: GroupSettings,
+) -> Result<Group, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    GroupsService::delete_group(&db, id).map_err(|e| format!("Failed to delete group: {}", e))
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    GroupsService::update_group_settings(&db, id, settings)
+        .map_err(state.context("Failed to update group settings"))
+}
+
+#[tauri::command]
+async fn delete_group(state: State<'_, AppState>, id: i32) -> Result<bool, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    GroupsService::delete_group(&db, id).map_err(state.context("Failed to delete group"))
 }
 
 #[tauri::command]
@@ -816,140 +1222,230 @@ async fn force_delete_group_with_reassignment(
     state: State<'_, AppState>,
     id: i32,
     default_group_name: String,
-) -> Result<bool, String> {
+) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     GroupsService::force_delete_group_with_reassignment(&db, id, &default_group_name)
-        .map_err(|e| format!("Failed to force delete group: {}", e))
+        .map_err(state.context("Failed to force delete group"))
 }
 
 #[tauri::command]
 async fn get_students_count_by_group_id(
     state: State<'_, AppState>,
     group_id: i32,
-) -> Result<i32, String> {
+) -> Result<i32, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     GroupsService::get_students_count_by_group_id(&db, group_id)
-        .map_err(|e| format!("Failed to get students count: {}", e))
+        .map_err(state.context("Failed to get students count"))
 }
 
 #[tauri::command]
 async fn get_students_count_by_group_name(
     state: State<'_, AppState>,
     group_name: String,
-) -> Result<i32, String> {
+) -> Result<i32, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     GroupsService::get_students_count_by_group_name(&db, &group_name)
-        .map_err(|e| format!("Failed to get students count: {}", e))
+        .map_err(state.context("Failed to get students count"))
 }
 
 #[tauri::command]
-async fn ensure_default_groups_exist(state: State<'_, AppState>) -> Result<(), String> {
+async fn ensure_default_groups_exist(state: State<'_, AppState>) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     GroupsService::ensure_default_groups_exist(&db)
-        .map_err(|e| format!("Failed to ensure default groups exist: {}", e))
+        .map_err(state.context("Failed to ensure default groups exist"))
 }
 
 #[tauri::command]
-async fn get_group_statistics(state: State<'_, AppState>) -> Result<GroupStatistics, String> {
+async fn get_group_statistics(
+    state: State<'_, AppState>,
+    term_id: Option<i32>,
+) -> Result<GroupStatistics, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    GroupsService::get_group_statistics(&db)
-        .map_err(|e| format!("Failed to get group statistics: {}", e))
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    GroupsService::get_group_statistics(&db, term_id)
+        .map_err(state.context("Failed to get group statistics"))
+}
+
+#[tauri::command]
+async fn validate_group_name(name: String) -> Result<(), AppError> {
+    GroupsService::validate_group_name(&name).map_err(AppError::from)
 }
 
+// Term-related IPC commands
 #[tauri::command]
-async fn validate_group_name(name: String) -> Result<(), String> {
-    GroupsService::validate_group_name(&name)
+async fn get_all_terms(state: State<'_, AppState>) -> Result<Vec<Term>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    TermService::get_all_terms(&db).map_err(state.context("Failed to get terms"))
+}
+
+#[tauri::command]
+async fn get_active_term(state: State<'_, AppState>) -> Result<Option<Term>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    TermService::get_active_term(&db).map_err(state.context("Failed to get active term"))
+}
+
+#[tauri::command]
+async fn create_term(state: State<'_, AppState>, request: CreateTermRequest) -> Result<Term, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    TermService::create_term(&db, request).map_err(state.context("Failed to create term"))
+}
+
+#[tauri::command]
+async fn update_term(
+    state: State<'_, AppState>,
+    id: i32,
+    request: UpdateTermRequest,
+) -> Result<Term, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    TermService::update_term(&db, id, request).map_err(state.context("Failed to update term"))
+}
+
+#[tauri::command]
+async fn delete_term(state: State<'_, AppState>, id: i32) -> Result<bool, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    TermService::delete_term(&db, id).map_err(state.context("Failed to delete term"))
+}
+
+#[tauri::command]
+async fn set_active_term(state: State<'_, AppState>, id: i32) -> Result<Term, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    TermService::set_active_term(&db, id).map_err(state.context("Failed to set active term"))
+}
+
+#[tauri::command]
+async fn get_student_term_balances(
+    state: State<'_, AppState>,
+    student_id: String,
+) -> Result<Vec<TermBalance>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    TermService::get_student_term_balances(&db, &student_id)
+        .map_err(state.context("Failed to get term balances"))
+}
+
+#[tauri::command]
+async fn rollover_term(
+    state: State<'_, AppState>,
+    request: RolloverRequest,
+) -> Result<RolloverSummary, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    TermService::rollover_term(&db, request).map_err(state.context("Failed to roll over term"))
 }
 
 // Payment Settings-related IPC commands
 #[tauri::command]
-async fn get_payment_settings(state: State<'_, AppState>) -> Result<PaymentSettings, String> {
+async fn get_payment_settings(state: State<'_, AppState>) -> Result<PaymentSettings, AppError> {
+    // The writer, because missing settings are created on first read
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to get database connection"))?;
     PaymentSettingsService::get_payment_settings(&db)
-        .map_err(|e| format!("Failed to get payment settings: {}", e))
+        .map_err(state.context("Failed to get payment settings"))
 }
 
 #[tauri::command]
 async fn update_payment_settings(
     state: State<'_, AppState>,
     settings: UpdatePaymentSettingsRequest,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentSettingsService::update_payment_settings(&db, settings)
-        .map_err(|e| format!("Failed to update payment settings: {}", e))
+        .map_err(state.context("Failed to update payment settings"))
 }
 
 #[tauri::command]
-async fn reset_payment_settings_to_defaults(state: State<'_, AppState>) -> Result<(), String> {
+async fn reset_payment_settings_to_defaults(state: State<'_, AppState>) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentSettingsService::reset_to_defaults(&db)
-        .map_err(|e| format!("Failed to reset payment settings: {}", e))
+        .map_err(state.context("Failed to reset payment settings"))
 }
 
 #[tauri::command]
-async fn get_payment_config(state: State<'_, AppState>) -> Result<PaymentConfig, String> {
+async fn get_payment_config(state: State<'_, AppState>) -> Result<PaymentConfig, AppError> {
+    // The writer, because missing settings are created on first read
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to get database connection"))?;
     PaymentSettingsService::get_payment_config(&db)
-        .map_err(|e| format!("Failed to get payment config: {}", e))
+        .map_err(state.context("Failed to get payment config"))
 }
 
 #[tauri::command]
 async fn get_payment_settings_history(
     state: State<'_, AppState>,
-) -> Result<Vec<PaymentSettingsHistoryEntry>, String> {
+) -> Result<Vec<PaymentSettingsHistoryEntry>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     PaymentSettingsService::get_settings_history(&db)
-        .map_err(|e| format!("Failed to get payment settings history: {}", e))
+        .map_err(state.context("Failed to get payment settings history"))
 }
 
 #[tauri::command]
-async fn ensure_payment_settings_exist(state: State<'_, AppState>) -> Result<(), String> {
+async fn ensure_payment_settings_exist(state: State<'_, AppState>) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentSettingsService::ensure_settings_exist(&db)
-        .map_err(|e| format!("Failed to ensure payment settings exist: {}", e))
+        .map_err(state.context("Failed to ensure payment settings exist"))
 }
 
 #[tauri::command]
-async fn get_amount_for_plan(state: State<'_, AppState>, plan_type: String) -> Result<i32, String> {
+async fn get_amount_for_plan(state: State<'_, AppState>, plan_type: String) -> Result<i32, AppError> {
+    // The writer, because missing settings are created on first read
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to get database connection"))?;
     PaymentSettingsService::get_amount_for_plan(&db, &plan_type)
-        .map_err(|e| format!("Failed to get amount for plan: {}", e))
+        .map_err(state.context("Failed to get amount for plan"))
 }
 
 #[tauri::command]
@@ -957,64 +1453,70 @@ async fn update_specific_payment_setting(
     state: State<'_, AppState>,
     setting_name: String,
     value: i32,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     PaymentSettingsService::update_specific_setting(&db, &setting_name, value)
-        .map_err(|e| format!("Failed to update specific setting: {}", e))
+        .map_err(state.context("Failed to update specific setting"))
 }
 
 #[tauri::command]
 async fn validate_payment_settings_request(
+    state: State<'_, AppState>,
     settings: UpdatePaymentSettingsRequest,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     PaymentSettingsService::validate_payment_settings(&settings)
-        .map_err(|e| format!("Invalid payment settings: {}", e))
+        .map_err(state.context("Invalid payment settings"))
 }
 
 // Migration-related IPC commands
 #[tauri::command]
 async fn get_migration_history(
     state: State<'_, AppState>,
-) -> Result<Vec<AppliedMigration>, String> {
+) -> Result<Vec<AppliedMigration>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     db.get_migration_history()
-        .map_err(|e| format!("Failed to get migration history: {}", e))
+        .map_err(state.context("Failed to get migration history"))
+}
+
+#[tauri::command]
+async fn get_startup_report(state: State<'_, AppState>) -> Result<StartupReport, AppError> {
+    Ok(state.db.startup_report().clone())
 }
 
 #[tauri::command]
-async fn get_schema_info(state: State<'_, AppState>) -> Result<SchemaInfo, String> {
+async fn get_schema_info(state: State<'_, AppState>) -> Result<SchemaInfo, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     db.get_schema_info()
-        .map_err(|e| format!("Failed to get schema info: {}", e))
+        .map_err(state.context("Failed to get schema info"))
 }
 
 #[tauri::command]
-async fn validate_migrations(state: State<'_, AppState>) -> Result<MigrationValidation, String> {
+async fn validate_migrations(state: State<'_, AppState>) -> Result<MigrationValidation, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     db.validate_migrations()
-        .map_err(|e| format!("Failed to validate migrations: {}", e))
+        .map_err(state.context("Failed to validate migrations"))
 }
 
 #[tauri::command]
-async fn force_apply_migration(state: State<'_, AppState>, version: i32) -> Result<(), String> {
+async fn force_apply_migration(state: State<'_, AppState>, version: i32) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     db.force_apply_migration(version)
-        .map_err(|e| format!("Failed to force apply migration: {}", e))
+        .map_err(state.context("Failed to force apply migration"))
 }
 
 #[tauri::command]
@@ -1022,43 +1524,43 @@ async fn mark_migration_applied(
     state: State<'_, AppState>,
     version: i32,
     description: String,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
     db.mark_migration_applied(version, description)
-        .map_err(|e| format!("Failed to mark migration as applied: {}", e))
+        .map_err(state.context("Failed to mark migration as applied"))
 }
 
 #[tauri::command]
-async fn get_pending_migrations(state: State<'_, AppState>) -> Result<Vec<Migration>, String> {
+async fn get_pending_migrations(state: State<'_, AppState>) -> Result<Vec<Migration>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     db.get_pending_migrations()
-        .map_err(|e| format!("Failed to get pending migrations: {}", e))
+        .map_err(state.context("Failed to get pending migrations"))
 }
 
 #[tauri::command]
-async fn get_all_migrations(state: State<'_, AppState>) -> Result<Vec<Migration>, String> {
+async fn get_all_migrations(state: State<'_, AppState>) -> Result<Vec<Migration>, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     Ok(db.get_migrations())
 }
 
 #[tauri::command]
-async fn check_migration_integrity(state: State<'_, AppState>) -> Result<bool, String> {
+async fn check_migration_integrity(state: State<'_, AppState>) -> Result<bool, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     let validation = db
         .validate_migrations()
-        .map_err(|e| format!("Failed to validate migrations: {}", e))?;
+        .map_err(state.context("Failed to validate migrations"))?;
     Ok(validation.is_valid)
 }
 
@@ -1066,80 +1568,116 @@ async fn check_migration_integrity(state: State<'_, AppState>) -> Result<bool, S
 async fn get_rollback_info(
     state: State<'_, AppState>,
     target_version: i32,
-) -> Result<RollbackInfo, String> {
+) -> Result<RollbackInfo, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     db.get_rollback_info(target_version)
-        .map_err(|e| format!("Failed to get rollback info: {}", e))
+        .map_err(state.context("Failed to get rollback info"))
+}
+
+#[tauri::command]
+async fn rollback_to(
+    state: State<'_, AppState>,
+    target_version: i32,
+) -> Result<RollbackResult, AppError> {
+    state
+        .db
+        .rollback_to(target_version)
+        .map_err(state.context("Failed to rollback database"))
+}
+
+// Integrity check IPC commands
+#[tauri::command]
+async fn run_integrity_check(
+    state: State<'_, AppState>,
+    fix: Option<bool>,
+) -> Result<IntegrityReport, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    IntegrityService::run_integrity_check(&db, fix.unwrap_or(false))
+        .map_err(state.context("Failed to run integrity check"))
 }
 
 // QR Code-related IPC commands
 #[tauri::command]
-async fn generate_qr_code_for_student_id(student_id: String) -> Result<String, String> {
+async fn generate_qr_code_for_student_id(
+    state: State<'_, AppState>,
+    student_id: String,
+) -> Result<String, AppError> {
     QRService::generate_qr_code_for_student_id(&student_id)
-        .map_err(|e| format!("Failed to generate QR code: {}", e))
+        .map_err(state.context("Failed to generate QR code"))
 }
 
 #[tauri::command]
 async fn generate_qr_code_for_student(
     state: State<'_, AppState>,
     student_id: String,
-) -> Result<QRCodeData, String> {
+) -> Result<QRCodeData, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     QRService::generate_qr_code_for_student(&db, &student_id)
-        .map_err(|e| format!("Failed to generate QR code for student: {}", e))
+        .map_err(state.context("Failed to generate QR code for student"))
 }
 
 #[tauri::command]
 async fn generate_qr_codes_for_all_students(
     state: State<'_, AppState>,
-) -> Result<Vec<QRCodeData>, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    QRService::generate_qr_codes_for_all_students(&db)
-        .map_err(|e| format!("Failed to generate QR codes for all students: {}", e))
+) -> Result<Vec<QRCodeData>, AppError> {
+    run_blocking_read(&state, |db| {
+        QRService::generate_qr_codes_for_all_students(db)
+            .map_err(context("Failed to generate QR codes for all students"))
+    })
+    .await
 }
 
 #[tauri::command]
 async fn generate_qr_codes_by_group(
     state: State<'_, AppState>,
-) -> Result<Vec<QRCodeBatch>, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    QRService::generate_qr_codes_by_group(&db)
-        .map_err(|e| format!("Failed to generate QR codes by group: {}", e))
+) -> Result<Vec<QRCodeBatch>, AppError> {
+    run_blocking_read(&state, |db| {
+        QRService::generate_qr_codes_by_group(db)
+            .map_err(context("Failed to generate QR codes by group"))
+    })
+    .await
 }
 
 #[tauri::command]
 async fn generate_qr_codes_for_group(
     state: State<'_, AppState>,
     group_name: String,
-) -> Result<QRCodeBatch, String> {
+) -> Result<QRCodeBatch, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     QRService::generate_qr_codes_for_group(&db, &group_name)
-        .map_err(|e| format!("Failed to generate QR codes for group: {}", e))
+        .map_err(state.context("Failed to generate QR codes for group"))
 }
 
 #[tauri::command]
 async fn export_qr_codes_to_pdf(
+    state: State<'_, AppState>,
     qr_codes: Vec<QRCodeData>,
     file_path: String,
     title: Option<String>,
-) -> Result<(), String> {
-    QRService::export_qr_codes_to_pdf(&qr_codes, &file_path, title.as_deref())
-        .map_err(|e| format!("Failed to export QR codes to PDF: {}", e))
+    language: Option<String>,
+) -> Result<(), AppError> {
+    let locale = {
+        let db = state
+            .db
+            .reader()
+            .map_err(state.context("Failed to get database connection"))?;
+        Locale::resolve(&db, language.as_deref())
+            .map_err(state.context("Failed to export QR codes to PDF"))?
+    };
+    QRService::export_qr_codes_to_pdf(&qr_codes, &file_path, title.as_deref(), locale)
+        .map_err(state.context("Failed to export QR codes to PDF"))
 }
 
 #[tauri::command]
@@ -1147,29 +1685,34 @@ async fn export_qr_codes_by_group_to_pdf(
     state: State<'_, AppState>,
     file_path: String,
     group_name: Option<String>,
-) -> Result<(), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    QRService::export_qr_codes_by_group_to_pdf(&db, &file_path, group_name.as_deref())
-        .map_err(|e| format!("Failed to export QR codes by group to PDF: {}", e))
+    language: Option<String>,
+) -> Result<(), AppError> {
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export QR codes by group to PDF"))?;
+        QRService::export_qr_codes_by_group_to_pdf(db, &file_path, group_name.as_deref(), locale)
+            .map_err(context("Failed to export QR codes by group to PDF"))
+    })
+    .await
 }
 
 #[tauri::command]
-async fn validate_qr_code(student_id: String) -> Result<bool, String> {
+async fn validate_qr_code(
+    state: State<'_, AppState>,
+    student_id: String,
+) -> Result<bool, AppError> {
     QRService::validate_qr_code(&student_id)
-        .map_err(|e| format!("Failed to validate QR code: {}", e))
+        .map_err(state.context("Failed to validate QR code"))
 }
 
 #[tauri::command]
-async fn get_qr_code_statistics(state: State<'_, AppState>) -> Result<QRCodeStatistics, String> {
+async fn get_qr_code_statistics(state: State<'_, AppState>) -> Result<QRCodeStatistics, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
     QRService::get_qr_code_statistics(&db)
-        .map_err(|e| format!("Failed to get QR code statistics: {}", e))
+        .map_err(state.context("Failed to get QR code statistics"))
 }
 
 // Export-related IPC commands
@@ -1180,19 +1723,22 @@ async fn export_attendance_csv(
     #[allow(non_snake_case)] startDate: Option<String>,
     #[allow(non_snake_case)] endDate: Option<String>,
     #[allow(non_snake_case)] groupName: Option<String>,
-) -> Result<(), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    ExportService::export_attendance_csv(
-        &db,
-        &filePath,
-        startDate.as_deref(),
-        endDate.as_deref(),
-        groupName.as_deref(),
-    )
-    .map_err(|e| format!("Failed to export attendance CSV: {}", e))
+    language: Option<String>,
+) -> Result<(), AppError> {
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export attendance CSV"))?;
+        ExportService::export_attendance_csv(
+            db,
+            &filePath,
+            startDate.as_deref(),
+            endDate.as_deref(),
+            groupName.as_deref(),
+            locale,
+        )
+        .map_err(context("Failed to export attendance CSV"))
+    })
+    .await
 }
 
 #[tauri::command]
@@ -1200,13 +1746,15 @@ async fn export_payment_summary_csv(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] filePath: String,
     #[allow(non_snake_case)] groupName: Option<String>,
-) -> Result<(), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    ExportService::export_payment_summary_csv(&db, &filePath, groupName.as_deref())
-        .map_err(|e| format!("Failed to export payment summary CSV: {}", e))
+    language: Option<String>,
+) -> Result<(), AppError> {
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export payment summary CSV"))?;
+        ExportService::export_payment_summary_csv(db, &filePath, groupName.as_deref(), locale)
+            .map_err(context("Failed to export payment summary CSV"))
+    })
+    .await
 }
 
 #[tauri::command]
@@ -1216,19 +1764,22 @@ async fn export_payment_history_csv(
     #[allow(non_snake_case)] studentId: Option<String>,
     #[allow(non_snake_case)] startDate: Option<String>,
     #[allow(non_snake_case)] endDate: Option<String>,
-) -> Result<(), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    ExportService::export_payment_history_csv(
-        &db,
-        &filePath,
-        studentId.as_deref(),
-        startDate.as_deref(),
-        endDate.as_deref(),
-    )
-    .map_err(|e| format!("Failed to export payment history CSV: {}", e))
+    language: Option<String>,
+) -> Result<(), AppError> {
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export payment history CSV"))?;
+        ExportService::export_payment_history_csv(
+            db,
+            &filePath,
+            studentId.as_deref(),
+            startDate.as_deref(),
+            endDate.as_deref(),
+            locale,
+        )
+        .map_err(context("Failed to export payment history CSV"))
+    })
+    .await
 }
 
 #[tauri::command]
@@ -1236,19 +1787,112 @@ async fn export_overdue_students_csv(
     state: State<'_, AppState>,
     #[allow(non_snake_case)] filePath: String,
     #[allow(non_snake_case)] groupName: Option<String>,
-) -> Result<(), String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    ExportService::export_overdue_students_csv(&db, &filePath, groupName.as_deref())
-        .map_err(|e| format!("Failed to export overdue students CSV: {}", e))
+    language: Option<String>,
+) -> Result<(), AppError> {
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export overdue students CSV"))?;
+        ExportService::export_overdue_students_csv(db, &filePath, groupName.as_deref(), locale)
+            .map_err(context("Failed to export overdue students CSV"))
+    })
+    .await
 }
 
 #[tauri::command]
-async fn validate_export_path(#[allow(non_snake_case)] filePath: String) -> Result<(), String> {
+async fn export_workbook_xlsx(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] filePath: String,
+    #[allow(non_snake_case)] startDate: Option<String>,
+    #[allow(non_snake_case)] endDate: Option<String>,
+    #[allow(non_snake_case)] groupName: Option<String>,
+    language: Option<String>,
+) -> Result<WorkbookSummary, AppError> {
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export XLSX workbook"))?;
+        ExportService::export_workbook_xlsx(
+            db,
+            &filePath,
+            startDate.as_deref(),
+            endDate.as_deref(),
+            groupName.as_deref(),
+            locale,
+        )
+        .map_err(context("Failed to export XLSX workbook"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_attendance_matrix(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] filePath: String,
+    #[allow(non_snake_case)] groupName: String,
+    #[allow(non_snake_case)] startDate: String,
+    #[allow(non_snake_case)] endDate: String,
+    format: String,
+    language: Option<String>,
+) -> Result<AttendanceMatrix, AppError> {
+    let format = ExportFormat::from_str(&format).map_err(|e| AppError::validation("format", e))?;
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export attendance matrix"))?;
+        ExportService::export_attendance_matrix(
+            db, &filePath, &groupName, &startDate, &endDate, format, locale,
+        )
+        .map_err(context("Failed to export attendance matrix"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_aging_report(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] asOf: Option<String>,
+    #[allow(non_snake_case)] groupName: Option<String>,
+) -> Result<AgingReport, AppError> {
+    let as_of = asOf.unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
+    run_blocking_read(&state, move |db| {
+        ReceivablesService::get_aging_report(db, &as_of, groupName.as_deref())
+            .map_err(context("Failed to get aging report"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_aging_report(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] filePath: String,
+    #[allow(non_snake_case)] asOf: Option<String>,
+    #[allow(non_snake_case)] groupName: Option<String>,
+    format: String,
+    language: Option<String>,
+) -> Result<AgingReport, AppError> {
+    let format = ExportFormat::from_str(&format).map_err(|e| AppError::validation("format", e))?;
+    let as_of = asOf.unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%d").to_string());
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export aging report"))?;
+        ExportService::export_aging_report(
+            db,
+            &filePath,
+            &as_of,
+            groupName.as_deref(),
+            format,
+            locale,
+        )
+        .map_err(context("Failed to export aging report"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn validate_export_path(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] filePath: String,
+) -> Result<(), AppError> {
     ExportService::validate_export_path(&filePath)
-        .map_err(|e| format!("Invalid export path: {}", e))
+        .map_err(state.context("Invalid export path"))
 }
 
 // Backup-related IPC commands
@@ -1257,19 +1901,21 @@ async fn create_backup(
     state: State<'_, AppState>,
     file_path: String,
     password: Option<String>,
-) -> Result<BackupMetadata, String> {
-    let db = state
-        .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    BackupService::create_backup(&db, &file_path, password.as_deref())
-        .map_err(|e| format!("Failed to create backup: {}", e))
+) -> Result<BackupMetadata, AppError> {
+    run_blocking_read(&state, move |db| {
+        BackupService::create_backup(db, &file_path, password.as_deref())
+            .map_err(context("Failed to create backup"))
+    })
+    .await
 }
 
 #[tauri::command]
-async fn validate_backup(file_path: String) -> Result<BackupValidationResult, String> {
+async fn validate_backup(
+    state: State<'_, AppState>,
+    file_path: String,
+) -> Result<BackupValidationResult, AppError> {
     BackupService::validate_backup(&file_path)
-        .map_err(|e| format!("Failed to validate backup: {}", e))
+        .map_err(state.context("Failed to validate backup"))
 }
 
 #[tauri::command]
@@ -1277,19 +1923,342 @@ async fn restore_backup(
     state: State<'_, AppState>,
     file_path: String,
     password: Option<String>,
-) -> Result<RestoreResult, String> {
+) -> Result<RestoreResult, AppError> {
     let db = state
         .db
-        .lock()
-        .map_err(|e| format!("Failed to lock database: {}", e))?;
-    BackupService::restore_backup(&db, &file_path, password.as_deref())
-        .map_err(|e| format!("Failed to restore backup: {}", e))
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    let result = BackupService::restore_backup(&db, &file_path, password.as_deref())
+        .map_err(state.context("Failed to restore backup"))?;
+    // The restored settings may be in another language
+    state.sync_locale(&db);
+    Ok(result)
 }
 
 #[tauri::command]
-async fn get_backup_metadata(file_path: String) -> Result<BackupMetadata, String> {
+async fn get_backup_metadata(
+    state: State<'_, AppState>,
+    file_path: String,
+) -> Result<BackupMetadata, AppError> {
     BackupService::get_backup_metadata(&file_path)
-        .map_err(|e| format!("Failed to get backup metadata: {}", e))
+        .map_err(state.context("Failed to get backup metadata"))
+}
+
+// Notification-related IPC commands
+#[tauri::command]
+async fn get_notification_config(
+    state: State<'_, AppState>,
+) -> Result<NotificationConfig, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    NotificationService::get_redacted_config(&db)
+        .map_err(state.context("Failed to get notification settings"))
+}
+
+#[tauri::command]
+async fn update_notification_config(
+    state: State<'_, AppState>,
+    config: NotificationConfig,
+) -> Result<(), AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    NotificationService::update_config(&db, &config)
+        .map_err(state.context("Failed to update notification settings"))
+}
+
+#[tauri::command]
+async fn get_student_contact(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] studentId: String,
+) -> Result<StudentContact, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    NotificationService::get_student_contact(&db, &studentId)
+        .map_err(state.context("Failed to get student contact"))
+}
+
+#[tauri::command]
+async fn set_student_contact(
+    state: State<'_, AppState>,
+    contact: StudentContact,
+) -> Result<StudentContact, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    NotificationService::set_student_contact(&db, contact)
+        .map_err(state.context("Failed to update student contact"))
+}
+
+#[tauri::command]
+async fn get_notification_templates(
+    state: State<'_, AppState>,
+) -> Result<Vec<NotificationTemplate>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    NotificationService::get_templates(&db)
+        .map_err(state.context("Failed to get notification templates"))
+}
+
+#[tauri::command]
+async fn update_notification_template(
+    state: State<'_, AppState>,
+    kind: String,
+    language: String,
+    subject: String,
+    body: String,
+) -> Result<NotificationTemplate, AppError> {
+    let kind = ReminderKind::from_str(&kind).map_err(|e| AppError::validation("kind", e))?;
+    let locale = Locale::parse(&language).ok_or_else(|| {
+        AppError::validation("language", format!("Unsupported language: {}", language))
+    })?;
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    NotificationService::update_template(&db, kind, locale, &subject, &body)
+        .map_err(state.context("Failed to update notification template"))
+}
+
+#[tauri::command]
+async fn enqueue_payment_reminders(
+    state: State<'_, AppState>,
+    language: Option<String>,
+) -> Result<EnqueueSummary, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    let locale = Locale::resolve(&db, language.as_deref())
+        .map_err(state.context("Failed to queue payment reminders"))?;
+    NotificationService::enqueue_payment_reminders(&db, locale)
+        .map_err(state.context("Failed to queue payment reminders"))
+}
+
+#[tauri::command]
+async fn get_notification_outbox(
+    state: State<'_, AppState>,
+    status: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<OutboxMessage>, AppError> {
+    let status = status
+        .map(|s| NotificationStatus::from_str(&s))
+        .transpose()
+        .map_err(|e| AppError::validation("status", e))?;
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    NotificationService::get_outbox(&db, status, limit)
+        .map_err(state.context("Failed to get notification outbox"))
+}
+
+/// Send whatever is due now instead of waiting for the background pass
+#[tauri::command]
+async fn process_notification_outbox(app: tauri::AppHandle) -> Result<DeliveryReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<AppState>();
+        let config = state
+            .db
+            .reader()
+            .and_then(|db| NotificationService::get_config(&db))
+            .map_err(state.context("Failed to send notifications"))?;
+        NotificationService::process_outbox(
+            &state.db,
+            &Providers::from_config(&config),
+            OUTBOX_BATCH_SIZE,
+        )
+        .map_err(state.context("Failed to send notifications"))
+    })
+    .await
+    .map_err(|e| AppError::storage(format!("Background task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn retry_notification(
+    state: State<'_, AppState>,
+    id: i64,
+) -> Result<OutboxMessage, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    NotificationService::retry_message(&db, id)
+        .map_err(state.context("Failed to retry notification"))
+}
+
+#[tauri::command]
+async fn cancel_notification(
+    state: State<'_, AppState>,
+    id: i64,
+) -> Result<OutboxMessage, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    NotificationService::cancel_message(&db, id)
+        .map_err(state.context("Failed to cancel notification"))
+}
+
+// Dunning-related IPC commands
+#[tauri::command]
+async fn run_dunning_escalation(
+    state: State<'_, AppState>,
+) -> Result<DunningRunSummary, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    StudentService::update_payment_statuses(&db)
+        .map_err(state.context("Failed to update payment statuses"))?;
+    DunningService::run_escalation(&db, chrono::Utc::now().date_naive())
+        .map_err(state.context("Failed to run dunning escalation"))
+}
+
+/// Today's chase list, at the levels of the last escalation run
+#[tauri::command]
+async fn get_overdue_worklist(
+    state: State<'_, AppState>,
+) -> Result<Vec<DunningWorklistEntry>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    DunningService::get_worklist(&db, chrono::Utc::now().date_naive())
+        .map_err(state.context("Failed to get overdue worklist"))
+}
+
+#[tauri::command]
+async fn record_payment_promise(
+    state: State<'_, AppState>,
+    request: RecordPromiseRequest,
+) -> Result<PaymentPromise, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    DunningService::record_promise(&db, request, chrono::Utc::now().date_naive())
+        .map_err(state.context("Failed to record payment promise"))
+}
+
+#[tauri::command]
+async fn add_dunning_note(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] studentId: String,
+    note: String,
+    #[allow(non_snake_case)] createdBy: Option<String>,
+) -> Result<DunningAction, AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    DunningService::add_note(&db, &studentId, &note, createdBy.as_deref())
+        .map_err(state.context("Failed to add dunning note"))
+}
+
+#[tauri::command]
+async fn get_dunning_history(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] studentId: String,
+) -> Result<Vec<DunningAction>, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    DunningService::get_history(&db, &studentId)
+        .map_err(state.context("Failed to get dunning history"))
+}
+
+// Analytics-related IPC commands
+#[tauri::command]
+async fn get_revenue_series(
+    state: State<'_, AppState>,
+    request: RevenueSeriesRequest,
+) -> Result<RevenueSeries, AppError> {
+    run_blocking_read(&state, move |db| {
+        AnalyticsService::get_revenue_series(db, &request)
+            .map_err(context("Failed to get revenue series"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_cash_flow_forecast(
+    state: State<'_, AppState>,
+    request: CashFlowForecastRequest,
+) -> Result<CashFlowForecast, AppError> {
+    let today = chrono::Utc::now().date_naive();
+    run_blocking_read(&state, move |db| {
+        AnalyticsService::get_cash_flow_forecast(db, &request, today)
+            .map_err(context("Failed to get cash-flow forecast"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn export_cash_flow_forecast(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] filePath: String,
+    request: CashFlowForecastRequest,
+    format: String,
+    language: Option<String>,
+) -> Result<CashFlowForecast, AppError> {
+    let format = ExportFormat::from_str(&format).map_err(|e| AppError::validation("format", e))?;
+    let today = chrono::Utc::now().date_naive();
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to export cash-flow forecast"))?;
+        ExportService::export_cash_flow_forecast(db, &filePath, &request, today, format, locale)
+            .map_err(context("Failed to export cash-flow forecast"))
+    })
+    .await
+}
+
+#[tauri::command]
+async fn get_risk_config(state: State<'_, AppState>) -> Result<RiskConfig, AppError> {
+    let db = state
+        .db
+        .reader()
+        .map_err(state.context("Failed to get database connection"))?;
+    RiskService::get_config(&db).map_err(state.context("Failed to get risk scoring settings"))
+}
+
+#[tauri::command]
+async fn update_risk_config(
+    state: State<'_, AppState>,
+    config: RiskConfig,
+) -> Result<(), AppError> {
+    let db = state
+        .db
+        .writer()
+        .map_err(state.context("Failed to lock database"))?;
+    RiskService::update_config(&db, &config)
+        .map_err(state.context("Failed to update risk scoring settings"))
+}
+
+#[tauri::command]
+async fn get_at_risk_students(
+    state: State<'_, AppState>,
+    #[allow(non_snake_case)] groupName: Option<String>,
+    #[allow(non_snake_case)] minScore: Option<f64>,
+    language: Option<String>,
+) -> Result<Vec<AtRiskStudent>, AppError> {
+    let today = chrono::Utc::now().date_naive();
+    run_blocking_read(&state, move |db| {
+        let locale = Locale::resolve(db, language.as_deref())
+            .map_err(context("Failed to get at-risk students"))?;
+        RiskService::get_at_risk_students(db, groupName.as_deref(), minScore, today, locale)
+            .map_err(context("Failed to get at-risk students"))
+    })
+    .await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1310,18 +2279,117 @@ pub fn run() {
                 .app_data_dir()
                 .expect("Failed to get app data directory");
 
-            let database = Database::new(app_data_dir).expect("Failed to initialize database");
+            // Set when support has reviewed a migration checksum mismatch and wants the app to start anyway
+            let allow_checksum_mismatch =
+                std::env::var_os(ALLOW_MIGRATION_CHECKSUM_MISMATCH_ENV).is_some();
+            let writer = Database::open(app_data_dir, allow_checksum_mismatch)
+                .expect("Failed to initialize database");
+
+            // Installment students from before per-student schedules get their default one
+            match InstallmentService::backfill_schedules(&writer) {
+                Ok(0) => {}
+                Ok(count) => log::info!("Created installment schedules for {} students", count),
+                Err(e) => log::warn!("Failed to create missing installment schedules: {}", e),
+            }
+
+            let database = DatabasePool::from_writer(writer, READER_POOL_SIZE)
+                .expect("Failed to initialize database");
 
             // Perform health check
-            if !database.health_check().unwrap_or(false) {
+            let healthy = database
+                .reader()
+                .and_then(|db| db.health_check())
+                .unwrap_or(false);
+            if !healthy {
                 panic!("Database health check failed");
             }
 
-            log::info!("Database initialized successfully");
+            // Error messages follow the language setting from the start
+            let locale = database
+                .reader()
+                .and_then(|db| Locale::from_settings(&db))
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to read the language setting: {}", e);
+                    Locale::default()
+                });
+
+            let startup = database.startup_report();
+            log::info!(
+                "Database initialized successfully in {} ms ({} migrations applied)",
+                startup.total_ms,
+                startup.migrations_applied
+            );
 
             // Store database in app state
             app.manage(AppState {
-                db: Mutex::new(database),
+                db: database,
+                locale: RwLock::new(locale),
+            });
+
+            // Refresh query planner statistics at the first quiet moment after launch, then daily
+            let handle = app.handle().clone();
+            std::thread::spawn(move || {
+                let mut last_run: Option<Instant> = None;
+                loop {
+                    std::thread::sleep(IDLE_CHECK_INTERVAL);
+                    let state = handle.state::<AppState>();
+                    if state.db.is_closed() {
+                        break;
+                    }
+                    if last_run.map_or(false, |at| at.elapsed() < MAINTENANCE_INTERVAL) {
+                        continue;
+                    }
+                    match state.db.run_idle_maintenance(IDLE_PERIOD) {
+                        Ok(true) => last_run = Some(Instant::now()),
+                        Ok(false) => {}
+                        Err(e) => {
+                            // Try again tomorrow rather than on every check
+                            log::warn!("Idle database maintenance failed: {}", e);
+                            last_run = Some(Instant::now());
+                        }
+                    }
+                }
+            });
+
+            // Deliver queued notifications in the background; settings are re-read each pass
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(OUTBOX_INTERVAL);
+                let state = handle.state::<AppState>();
+                if state.db.is_closed() {
+                    break;
+                }
+                let result = state
+                    .db
+                    .reader()
+                    .and_then(|db| NotificationService::get_config(&db))
+                    .and_then(|config| {
+                        NotificationService::process_outbox(
+                            &state.db,
+                            &Providers::from_config(&config),
+                            OUTBOX_BATCH_SIZE,
+                        )
+                    });
+                if let Err(e) = result {
+                    log::warn!("Notification delivery failed: {}", e);
+                }
+            });
+
+            // Keep dunning levels current for the worklist; repeat runs on one day change nothing
+            let handle = app.handle().clone();
+            std::thread::spawn(move || loop {
+                std::thread::sleep(DUNNING_INTERVAL);
+                let state = handle.state::<AppState>();
+                if state.db.is_closed() {
+                    break;
+                }
+                let result = state.db.writer().and_then(|db| {
+                    StudentService::update_payment_statuses(&db)?;
+                    DunningService::run_escalation(&db, chrono::Utc::now().date_naive())
+                });
+                if let Err(e) = result {
+                    log::warn!("Dunning escalation failed: {}", e);
+                }
             });
 
             Ok(())
@@ -1333,6 +2401,8 @@ pub fn run() {
             add_student,
             update_student,
             delete_student,
+            transfer_student,
+            get_student_group_history,
             get_student_by_id,
             get_students_by_group,
             get_students_by_payment_status,
@@ -1350,6 +2420,8 @@ pub fn run() {
             get_student_attendance_stats,
             get_daily_attendance_summary,
             get_attendance_summary_range,
+            set_attendance_status,
+            get_attendance_matrix,
             delete_attendance,
             get_current_date,
             format_date,
@@ -1362,6 +2434,17 @@ pub fn run() {
             update_all_payment_statuses,
             delete_payment,
             get_payment_statistics,
+            // Installment schedule commands
+            get_installment_schedule,
+            update_installment_schedule,
+            // Enrollment commands
+            get_student_enrollments,
+            create_enrollment,
+            update_enrollment,
+            end_enrollment,
+            delete_enrollment,
+            get_enrollment_payment_history,
+            get_group_waitlist,
             // Settings commands
             get_settings,
             update_settings,
@@ -1379,6 +2462,10 @@ pub fn run() {
             get_recent_audit_activity,
             get_audit_statistics,
             cleanup_old_audit_entries,
+            verify_audit_chain,
+            get_record_changes,
+            reconstruct_record,
+            revert_audit_entry,
             // Groups commands
             get_all_groups,
             get_all_groups_with_counts,
@@ -1386,6 +2473,7 @@ pub fn run() {
             get_group_by_name,
             add_group,
             update_group,
+            update_group_settings,
             delete_group,
             force_delete_group_with_reassignment,
             get_students_count_by_group_id,
@@ -1393,6 +2481,15 @@ pub fn run() {
             ensure_default_groups_exist,
             get_group_statistics,
             validate_group_name,
+            // Term commands
+            get_all_terms,
+            get_active_term,
+            create_term,
+            update_term,
+            delete_term,
+            set_active_term,
+            get_student_term_balances,
+            rollover_term,
             // Payment Settings commands
             get_payment_settings,
             update_payment_settings,
@@ -1406,6 +2503,7 @@ pub fn run() {
             // Migration commands
             get_migration_history,
             get_schema_info,
+            get_startup_report,
             validate_migrations,
             force_apply_migration,
             mark_migration_applied,
@@ -1413,6 +2511,9 @@ pub fn run() {
             get_all_migrations,
             check_migration_integrity,
             get_rollback_info,
+            rollback_to,
+            // Integrity check commands
+            run_integrity_check,
             // QR Code commands
             generate_qr_code_for_student_id,
             generate_qr_code_for_student,
@@ -1428,12 +2529,41 @@ pub fn run() {
             export_payment_summary_csv,
             export_payment_history_csv,
             export_overdue_students_csv,
+            export_workbook_xlsx,
+            export_attendance_matrix,
+            get_aging_report,
+            export_aging_report,
             validate_export_path,
             // Backup commands
             create_backup,
             validate_backup,
             restore_backup,
-            get_backup_metadata
+            get_backup_metadata,
+            // Notification commands
+            get_notification_config,
+            update_notification_config,
+            get_student_contact,
+            set_student_contact,
+            get_notification_templates,
+            update_notification_template,
+            enqueue_payment_reminders,
+            get_notification_outbox,
+            process_notification_outbox,
+            retry_notification,
+            cancel_notification,
+            // Dunning commands
+            run_dunning_escalation,
+            get_overdue_worklist,
+            record_payment_promise,
+            add_dunning_note,
+            get_dunning_history,
+            // Analytics commands
+            get_revenue_series,
+            get_cash_flow_forecast,
+            export_cash_flow_forecast,
+            get_risk_config,
+            update_risk_config,
+            get_at_risk_students
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,359 @@
+use crate::audit_service::AuditService;
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::groups_service::{Group, GroupsService};
+use crate::student_service::{Student, StudentService, UpdateStudentRequest};
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+/// A period during which a student belonged to a group; `end_date` is the last day, inclusive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupMembership {
+    pub id: i32,
+    pub student_id: String,
+    pub group_id: Option<i32>,
+    pub group_name: String,
+    pub start_date: String,
+    pub end_date: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferStudentRequest {
+    pub student_id: String,
+    pub to_group: String,
+    pub effective_date: String,
+    pub reason: Option<String>,
+}
+
+const MEMBERSHIP_COLUMNS: &str = "m.id, m.student_id, m.group_id, COALESCE(g.name, m.group_name), m.start_date, m.end_date, m.reason, m.created_at";
+
+pub struct MembershipService;
+
+impl MembershipService {
+    /// Helper function to map database row to GroupMembership struct
+    fn map_row_to_membership(row: &rusqlite::Row) -> rusqlite::Result<GroupMembership> {
+        Ok(GroupMembership {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            group_id: row.get(2)?,
+            group_name: row.get(3)?,
+            start_date: row.get(4)?,
+            end_date: row.get(5)?,
+            reason: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+
+    /// Get the group history of a student, oldest first
+    pub fn get_student_group_history(
+        db: &Database,
+        student_id: &str,
+    ) -> DatabaseResult<Vec<GroupMembership>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM group_memberships m LEFT JOIN groups g ON g.id = m.group_id
+             WHERE m.student_id = ?1 ORDER BY m.start_date, m.id",
+            MEMBERSHIP_COLUMNS
+        ))?;
+
+        let membership_iter = stmt.query_map([student_id], Self::map_row_to_membership)?;
+
+        membership_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// Get the membership a student held on a date, if their history reaches back that far
+    pub fn get_group_on_date(
+        db: &Database,
+        student_id: &str,
+        date: &str,
+    ) -> DatabaseResult<Option<GroupMembership>> {
+        let result = db.connection().query_row(
+            &format!(
+                "SELECT {} FROM group_memberships m LEFT JOIN groups g ON g.id = m.group_id
+                 WHERE m.student_id = ?1 AND m.start_date <= ?2
+                 ORDER BY m.start_date DESC, m.id DESC
+                 LIMIT 1",
+                MEMBERSHIP_COLUMNS
+            ),
+            params![student_id, date],
+            Self::map_row_to_membership,
+        );
+
+        match result {
+            Ok(membership) => Ok(Some(membership)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(DatabaseError::Sqlite(e)),
+        }
+    }
+
+    /// Close the student's current membership the day before `effective_date` and open one in `group`.
+    /// Does nothing when the latest membership is already in `group`.
+    pub(crate) fn record_group_change(
+        db: &Database,
+        student_id: &str,
+        group: &Group,
+        effective_date: &str,
+        reason: Option<&str>,
+    ) -> DatabaseResult<()> {
+        let effective = NaiveDate::parse_from_str(effective_date, "%Y-%m-%d").map_err(|_| {
+            DatabaseError::validation(
+                "effective_date",
+                "Invalid effective date format. Use YYYY-MM-DD",
+            )
+        })?;
+
+        let history = Self::get_student_group_history(db, student_id)?;
+        let latest = history
+            .iter()
+            .max_by(|a, b| a.start_date.cmp(&b.start_date).then(a.id.cmp(&b.id)));
+
+        match latest {
+            Some(current) if current.group_id == Some(group.id) => return Ok(()),
+            Some(current) if effective_date < current.start_date.as_str() => {
+                return Err(DatabaseError::validation(
+                    "effective_date",
+                    format!(
+                        "Effective date {} is before the student joined '{}' on {}",
+                        effective_date, current.group_name, current.start_date
+                    ),
+                ));
+            }
+            // Same day as the current membership started: it was never really held
+            Some(current) if effective_date == current.start_date => {
+                db.connection().execute(
+                    "UPDATE group_memberships SET group_id = ?1, group_name = ?2, reason = ?3 WHERE id = ?4",
+                    params![group.id, group.name, reason, current.id],
+                )?;
+                return Ok(());
+            }
+            Some(current) => {
+                let last_day = (effective - Duration::days(1))
+                    .format("%Y-%m-%d")
+                    .to_string();
+                db.connection().execute(
+                    "UPDATE group_memberships SET end_date = ?1 WHERE id = ?2",
+                    params![last_day, current.id],
+                )?;
+            }
+            None => {}
+        }
+
+        db.connection().execute(
+            "INSERT INTO group_memberships (student_id, group_id, group_name, start_date, reason) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![student_id, group.id, group.name, effective_date, reason],
+        )?;
+
+        Ok(())
+    }
+
+    /// Move a student to another group from `effective_date` on. Attendance and payments
+    /// before that date stay attributed to the previous group.
+    pub fn transfer_student(
+        db: &Database,
+        request: TransferStudentRequest,
+    ) -> DatabaseResult<Student> {
+        AuditService::audited(db, |audit| {
+            let effective =
+                NaiveDate::parse_from_str(&request.effective_date, "%Y-%m-%d").map_err(|_| {
+                    DatabaseError::validation(
+                        "effective_date",
+                        "Invalid effective date format. Use YYYY-MM-DD",
+                    )
+                })?;
+
+            if effective > Utc::now().date_naive() {
+                return Err(DatabaseError::validation(
+                    "effective_date",
+                    "Transfers cannot take effect in the future",
+                ));
+            }
+
+            let student = StudentService::get_student_by_id(db, &request.student_id)?
+                .ok_or_else(|| DatabaseError::not_found("Student", &request.student_id))?;
+
+            let group = GroupsService::resolve_group(db, &request.to_group)?;
+            if student.group_id == Some(group.id) {
+                return Err(DatabaseError::conflict(
+                    "already_in_group",
+                    format!(
+                        "Student '{}' is already in group '{}'",
+                        student.id, group.name
+                    ),
+                ));
+            }
+            GroupsService::ensure_seat_available(db, &group)?;
+
+            let reason = request
+                .reason
+                .as_deref()
+                .map(str::trim)
+                .filter(|r| !r.is_empty());
+
+            Self::record_group_change(db, &student.id, &group, &request.effective_date, reason)?;
+
+            // The move is audited as one TRANSFER entry rather than an UPDATE of the group
+            let (before, after) = StudentService::apply_update(
+                db,
+                &student.id,
+                UpdateStudentRequest {
+                    name: student.name.clone(),
+                    group_name: group.name.clone(),
+                    payment_plan: student.payment_plan.clone(),
+                    plan_amount: student.plan_amount,
+                    installment_count: student.installment_count,
+                    paid_amount: None,
+                    enrollment_date: None,
+                },
+            )?;
+
+            let mut new_values = serde_json::to_value(&after).map_err(|e| {
+                DatabaseError::Storage(format!("Failed to serialize student: {}", e))
+            })?;
+            new_values["effective_date"] = serde_json::json!(request.effective_date);
+            new_values["reason"] = serde_json::json!(reason);
+
+            audit.change(
+                "TRANSFER",
+                "students",
+                &student.id,
+                &before,
+                &new_values,
+                None,
+            );
+
+            StudentService::get_student_by_id(db, &student.id)?
+                .ok_or_else(|| DatabaseError::not_found("Student", &student.id))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attendance_service::{AttendanceHistoryFilter, AttendanceService};
+    use crate::payment_service::{PaymentMethod, PaymentService, RecordPaymentRequest};
+    use crate::student_service::{CreateStudentRequest, PaymentPlan};
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(db: &Database, group: &str) -> Student {
+        let request = CreateStudentRequest {
+            name: "Moving Student".to_string(),
+            group_name: group.to_string(),
+            payment_plan: PaymentPlan::OneTime,
+            plan_amount: 6000,
+            installment_count: None,
+            paid_amount: Some(0),
+            enrollment_date: Some("2024-01-01".to_string()),
+        };
+        StudentService::create_student(db, request).unwrap()
+    }
+
+    fn transfer(student_id: &str, to_group: &str, effective_date: &str) -> TransferStudentRequest {
+        TransferStudentRequest {
+            student_id: student_id.to_string(),
+            to_group: to_group.to_string(),
+            effective_date: effective_date.to_string(),
+            reason: Some("Schedule change".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_transfer_keeps_history() {
+        let (db, _temp_dir) = create_test_db();
+        let student = create_student(&db, "Group A");
+
+        let moved = MembershipService::transfer_student(
+            &db,
+            transfer(&student.id, "Group B", "2024-03-01"),
+        )
+        .unwrap();
+        assert_eq!(moved.group_name, "Group B");
+
+        let history = MembershipService::get_student_group_history(&db, &student.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].group_name, "Group A");
+        assert_eq!(history[0].end_date.as_deref(), Some("2024-02-29"));
+        assert_eq!(history[1].group_name, "Group B");
+        assert_eq!(history[1].start_date, "2024-03-01");
+        assert_eq!(history[1].reason.as_deref(), Some("Schedule change"));
+
+        let before = MembershipService::get_group_on_date(&db, &student.id, "2024-02-15")
+            .unwrap()
+            .unwrap();
+        assert_eq!(before.group_name, "Group A");
+
+        // Back-dating before the current membership began is rejected
+        assert!(MembershipService::transfer_student(
+            &db,
+            transfer(&student.id, "Group C", "2024-02-01")
+        )
+        .is_err());
+        assert!(MembershipService::transfer_student(
+            &db,
+            transfer(&student.id, "Group B", "2024-04-01")
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_reports_attribute_dates_to_group_at_the_time() {
+        let (db, _temp_dir) = create_test_db();
+        let student = create_student(&db, "Group A");
+
+        AttendanceService::mark_attendance(&db, &student.id, None, "2024-02-10").unwrap();
+        PaymentService::record_payment(
+            &db,
+            RecordPaymentRequest {
+                student_id: student.id.clone(),
+                enrollment_id: None,
+                amount: 1000,
+                payment_date: "2024-02-10".to_string(),
+                payment_method: PaymentMethod::Cash,
+                notes: None,
+            },
+        )
+        .unwrap();
+
+        MembershipService::transfer_student(&db, transfer(&student.id, "Group B", "2024-03-01"))
+            .unwrap();
+        AttendanceService::mark_attendance(&db, &student.id, None, "2024-03-05").unwrap();
+
+        let group_a = AttendanceService::get_attendance_history(
+            &db,
+            Some(AttendanceHistoryFilter {
+                student_id: None,
+                start_date: None,
+                end_date: None,
+                group_name: Some("Group A".to_string()),
+            }),
+        )
+        .unwrap();
+        assert_eq!(group_a.len(), 1);
+        assert_eq!(group_a[0].date, "2024-02-10");
+
+        let february =
+            AttendanceService::get_daily_attendance_summary(&db, "2024-02-10", Some("Group B"))
+                .unwrap();
+        assert_eq!(february.present_students, 0);
+        assert_eq!(february.total_students, 0);
+
+        let march =
+            AttendanceService::get_daily_attendance_summary(&db, "2024-03-05", Some("Group B"))
+                .unwrap();
+        assert_eq!(march.present_students, 1);
+        assert_eq!(march.total_students, 1);
+
+        let stats = PaymentService::get_payment_statistics(&db, None, None, None).unwrap();
+        assert_eq!(stats.revenue_by_group.get("Group A"), Some(&1000));
+        assert_eq!(stats.revenue_by_group.get("Group B"), None);
+    }
+}
@@ -0,0 +1,300 @@
+use crate::notification_service::{NotificationConfig, OutboxMessage};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long a provider may take to accept one message
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where a message is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Email,
+    Sms,
+    #[serde(rename = "whatsapp")]
+    WhatsApp,
+}
+
+impl Channel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Email => "email",
+            Channel::Sms => "sms",
+            Channel::WhatsApp => "whatsapp",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "email" => Ok(Channel::Email),
+            "sms" => Ok(Channel::Sms),
+            "whatsapp" => Ok(Channel::WhatsApp),
+            _ => Err(format!("Invalid notification channel: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeliveryError {
+    /// Worth trying again later, e.g. a timeout or a gateway that is down
+    #[error("{0}")]
+    Transient(String),
+    /// Trying again will not help, e.g. a rejected address or wrong credentials
+    #[error("{0}")]
+    Permanent(String),
+}
+
+/// Something that can deliver outbox messages on one channel
+pub trait NotificationProvider: Send + Sync {
+    /// Deliver one message, returning the provider's id for it when it has one
+    fn send(&self, message: &OutboxMessage) -> Result<Option<String>, DeliveryError>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// TLS from the first byte, usually port 465
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually port 587
+    #[default]
+    StartTls,
+    /// Unencrypted, for a relay on the local network
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    /// Never sent back to the frontend; leaving it blank on save keeps the stored one
+    pub password: Option<String>,
+    /// Set when the settings are read back: whether a password is stored
+    #[serde(default)]
+    pub password_set: bool,
+    /// Sender address, optionally with a name: `School <office@example.com>`
+    pub from: String,
+}
+
+pub struct SmtpProvider {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpProvider {
+    pub fn new(config: &SmtpConfig) -> Result<Self, DeliveryError> {
+        let from = config.from.parse::<Mailbox>().map_err(|e| {
+            DeliveryError::Permanent(format!("Invalid sender address '{}': {}", config.from, e))
+        })?;
+
+        let builder = match config.security {
+            SmtpSecurity::Tls => SmtpTransport::relay(&config.host),
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&config.host),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(&config.host)),
+        }
+        .map_err(|e| DeliveryError::Permanent(format!("Invalid SMTP server: {}", e)))?;
+
+        let mut builder = builder.timeout(Some(SEND_TIMEOUT));
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpProvider {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl NotificationProvider for SmtpProvider {
+    fn send(&self, message: &OutboxMessage) -> Result<Option<String>, DeliveryError> {
+        let to = message.recipient.parse::<Mailbox>().map_err(|e| {
+            DeliveryError::Permanent(format!(
+                "Invalid email address '{}': {}",
+                message.recipient, e
+            ))
+        })?;
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject.clone().unwrap_or_default())
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|e| DeliveryError::Permanent(format!("Invalid email: {}", e)))?;
+
+        match self.transport.send(&email) {
+            Ok(response) => Ok(response.message().next().map(str::to_string)),
+            Err(e) if e.is_permanent() => Err(DeliveryError::Permanent(e.to_string())),
+            Err(e) => Err(DeliveryError::Transient(e.to_string())),
+        }
+    }
+}
+
+/// An SMS or WhatsApp gateway that accepts a JSON POST per message:
+/// `{"channel", "to", "from", "message"}`, authorised with a bearer token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpGatewayConfig {
+    pub url: String,
+    /// Never sent back to the frontend; leaving it blank on save keeps the stored one
+    pub token: Option<String>,
+    /// Set when the settings are read back: whether a token is stored
+    #[serde(default)]
+    pub token_set: bool,
+    /// Sender id or number, when the gateway needs one
+    pub sender: Option<String>,
+}
+
+pub struct HttpGatewayProvider {
+    channel: Channel,
+    config: HttpGatewayConfig,
+    agent: ureq::Agent,
+}
+
+impl HttpGatewayProvider {
+    pub fn new(channel: Channel, config: HttpGatewayConfig) -> Self {
+        HttpGatewayProvider {
+            channel,
+            config,
+            agent: ureq::AgentBuilder::new().timeout(SEND_TIMEOUT).build(),
+        }
+    }
+}
+
+impl NotificationProvider for HttpGatewayProvider {
+    fn send(&self, message: &OutboxMessage) -> Result<Option<String>, DeliveryError> {
+        let mut request = self.agent.post(&self.config.url);
+        if let Some(token) = &self.config.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        let payload = serde_json::json!({
+            "channel": self.channel.as_str(),
+            "to": message.recipient,
+            "from": self.config.sender,
+            "message": message.body,
+        });
+
+        match request.send_json(payload) {
+            Ok(response) => {
+                // Gateways differ in what they return; keep an id when there is one
+                let body: serde_json::Value = response.into_json().unwrap_or_default();
+                let id = ["id", "message_id", "sid"]
+                    .iter()
+                    .find_map(|key| match &body[*key] {
+                        serde_json::Value::String(id) => Some(id.clone()),
+                        serde_json::Value::Number(id) => Some(id.to_string()),
+                        _ => None,
+                    });
+                Ok(id)
+            }
+            // Rate limits and timeouts pass; other client errors mean the request itself is wrong
+            Err(ureq::Error::Status(code, response)) => {
+                let detail = response.into_string().unwrap_or_default();
+                let error = format!("Gateway answered {}: {}", code, detail.trim());
+                if (400..500).contains(&code) && code != 408 && code != 429 {
+                    Err(DeliveryError::Permanent(error))
+                } else {
+                    Err(DeliveryError::Transient(error))
+                }
+            }
+            Err(ureq::Error::Transport(e)) => Err(DeliveryError::Transient(e.to_string())),
+        }
+    }
+}
+
+/// Appends each message to a JSON Lines file instead of sending it, for trying templates
+/// out and for tests
+pub struct FileProvider {
+    path: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileProvider { path: path.into() }
+    }
+}
+
+impl NotificationProvider for FileProvider {
+    fn send(&self, message: &OutboxMessage) -> Result<Option<String>, DeliveryError> {
+        let line = serde_json::json!({
+            "id": message.id,
+            "channel": message.channel.as_str(),
+            "to": message.recipient,
+            "subject": message.subject,
+            "message": message.body,
+        });
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| DeliveryError::Transient(format!("Failed to open outbox file: {}", e)))?;
+        writeln!(file, "{}", line)
+            .map_err(|e| DeliveryError::Transient(format!("Failed to write outbox file: {}", e)))?;
+
+        Ok(Some(format!("file-{}", message.id)))
+    }
+}
+
+/// The provider to use for each channel
+#[derive(Default)]
+pub struct Providers {
+    providers: HashMap<Channel, Box<dyn NotificationProvider>>,
+}
+
+impl Providers {
+    /// Providers for the enabled channels. An outbox file takes every channel, so reminders
+    /// can be checked without sending anything.
+    pub fn from_config(config: &NotificationConfig) -> Self {
+        let mut providers = Providers::default();
+        for &channel in &config.channels {
+            if let Some(path) = &config.outbox_file {
+                providers = providers.with(channel, FileProvider::new(path));
+                continue;
+            }
+
+            match channel {
+                Channel::Email => match config.smtp.as_ref().map(SmtpProvider::new) {
+                    Some(Ok(provider)) => providers = providers.with(channel, provider),
+                    Some(Err(e)) => log::warn!("Email notifications are disabled: {}", e),
+                    None => {}
+                },
+                Channel::Sms | Channel::WhatsApp => {
+                    let gateway = match channel {
+                        Channel::Sms => &config.sms,
+                        _ => &config.whatsapp,
+                    };
+                    if let Some(gateway) = gateway {
+                        providers = providers
+                            .with(channel, HttpGatewayProvider::new(channel, gateway.clone()));
+                    }
+                }
+            }
+        }
+        providers
+    }
+
+    pub fn with(mut self, channel: Channel, provider: impl NotificationProvider + 'static) -> Self {
+        self.providers.insert(channel, Box::new(provider));
+        self
+    }
+
+    pub fn get(&self, channel: Channel) -> Option<&dyn NotificationProvider> {
+        self.providers
+            .get(&channel)
+            .map(|provider| provider.as_ref())
+    }
+}
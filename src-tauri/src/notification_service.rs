@@ -0,0 +1,1131 @@
+use crate::database::{Database, DatabaseError, DatabasePool, DatabaseResult};
+use crate::i18n::Locale;
+use crate::installment_service::InstallmentService;
+use crate::notification_providers::{
+    Channel, DeliveryError, HttpGatewayConfig, Providers, SmtpConfig,
+};
+use crate::settings_service::SettingsService;
+use crate::student_service::{PaymentPlan, Student, StudentService};
+use crate::term_service::TermService;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Setting holding `NotificationConfig` as JSON
+const CONFIG_SETTING: &str = "notifications";
+
+/// Placeholders a template may use
+const TEMPLATE_PLACEHOLDERS: &[&str] = &["student", "amount", "due_date", "group"];
+
+/// Delay before the first retry; it doubles with each failed attempt up to `MAX_BACKOFF_MINUTES`
+const BASE_BACKOFF_MINUTES: i64 = 1;
+const MAX_BACKOFF_MINUTES: i64 = 6 * 60;
+
+/// A message left in `sending` this long was claimed by a run that never finished
+const STALE_CLAIM_MINUTES: i64 = 15;
+
+const OUTBOX_COLUMNS: &str = "id, student_id, kind, channel, recipient, subject, body, status, attempts, max_attempts, next_attempt_at, last_error, provider_message_id, created_at, sent_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReminderKind {
+    DueSoon,
+    Overdue,
+}
+
+impl ReminderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReminderKind::DueSoon => "due_soon",
+            ReminderKind::Overdue => "overdue",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "due_soon" => Ok(ReminderKind::DueSoon),
+            "overdue" => Ok(ReminderKind::Overdue),
+            _ => Err(format!("Invalid reminder kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationStatus {
+    Pending,
+    /// Claimed by a delivery run
+    Sending,
+    Sent,
+    /// Gave up after a permanent error or the last attempt
+    Failed,
+    Cancelled,
+}
+
+impl NotificationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationStatus::Pending => "pending",
+            NotificationStatus::Sending => "sending",
+            NotificationStatus::Sent => "sent",
+            NotificationStatus::Failed => "failed",
+            NotificationStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "pending" => Ok(NotificationStatus::Pending),
+            "sending" => Ok(NotificationStatus::Sending),
+            "sent" => Ok(NotificationStatus::Sent),
+            "failed" => Ok(NotificationStatus::Failed),
+            "cancelled" => Ok(NotificationStatus::Cancelled),
+            _ => Err(format!("Invalid notification status: {}", s)),
+        }
+    }
+}
+
+/// Where a student (or their guardian) can be reached
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StudentContact {
+    pub student_id: String,
+    pub phone: Option<String>,
+    pub whatsapp: Option<String>,
+    pub email: Option<String>,
+}
+
+impl StudentContact {
+    /// The address to use on `channel`; WhatsApp falls back to the phone number
+    pub fn address(&self, channel: Channel) -> Option<&str> {
+        match channel {
+            Channel::Email => self.email.as_deref(),
+            Channel::Sms => self.phone.as_deref(),
+            Channel::WhatsApp => self.whatsapp.as_deref().or(self.phone.as_deref()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationTemplate {
+    pub id: i32,
+    pub kind: ReminderKind,
+    pub locale: Locale,
+    /// Email subject; SMS and WhatsApp only use the body
+    pub subject: String,
+    /// Text with `{student}`, `{amount}`, `{due_date}` and `{group}` placeholders
+    pub body: String,
+    pub updated_at: String,
+}
+
+/// Which channels reminders go out on and how each is delivered
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Channels a reminder is queued on, for each one the student has an address for
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+    pub smtp: Option<SmtpConfig>,
+    pub sms: Option<HttpGatewayConfig>,
+    pub whatsapp: Option<HttpGatewayConfig>,
+    /// Write every message to this file instead of sending it
+    pub outbox_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxMessage {
+    pub id: i64,
+    pub student_id: String,
+    pub kind: ReminderKind,
+    pub channel: Channel,
+    pub recipient: String,
+    pub subject: Option<String>,
+    pub body: String,
+    pub status: NotificationStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub next_attempt_at: String,
+    pub last_error: Option<String>,
+    pub provider_message_id: Option<String>,
+    pub created_at: String,
+    pub sent_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnqueueSummary {
+    pub queued: usize,
+    /// Reminders already in the outbox for the same due date
+    pub duplicates: usize,
+    /// Students with no address on any enabled channel
+    pub missing_contact: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryReport {
+    pub sent: usize,
+    /// Failed for now and scheduled for another attempt
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+impl DeliveryReport {
+    pub fn record(&mut self, status: NotificationStatus) {
+        match status {
+            NotificationStatus::Sent => self.sent += 1,
+            NotificationStatus::Failed => self.failed += 1,
+            _ => self.retrying += 1,
+        }
+    }
+}
+
+pub struct NotificationService;
+
+impl NotificationService {
+    fn map_row_to_message(row: &rusqlite::Row) -> rusqlite::Result<OutboxMessage> {
+        let parse_error = |index: usize, e: String| {
+            rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, e.into())
+        };
+        Ok(OutboxMessage {
+            id: row.get(0)?,
+            student_id: row.get(1)?,
+            kind: ReminderKind::from_str(&row.get::<_, String>(2)?)
+                .map_err(|e| parse_error(2, e))?,
+            channel: Channel::from_str(&row.get::<_, String>(3)?).map_err(|e| parse_error(3, e))?,
+            recipient: row.get(4)?,
+            subject: row.get(5)?,
+            body: row.get(6)?,
+            status: NotificationStatus::from_str(&row.get::<_, String>(7)?)
+                .map_err(|e| parse_error(7, e))?,
+            attempts: row.get(8)?,
+            max_attempts: row.get(9)?,
+            next_attempt_at: row.get(10)?,
+            last_error: row.get(11)?,
+            provider_message_id: row.get(12)?,
+            created_at: row.get(13)?,
+            sent_at: row.get(14)?,
+        })
+    }
+
+    /// Timestamps are stored like `CURRENT_TIMESTAMP` so they compare as text
+    fn timestamp(time: DateTime<Utc>) -> String {
+        time.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+
+    pub fn get_config(db: &Database) -> DatabaseResult<NotificationConfig> {
+        match SettingsService::get_setting(db, CONFIG_SETTING)? {
+            Some(json) => serde_json::from_str(&json).map_err(|e| {
+                DatabaseError::validation(
+                    "notifications",
+                    format!("Invalid notification settings: {}", e),
+                )
+            }),
+            None => Ok(NotificationConfig::default()),
+        }
+    }
+
+    /// The settings as shown to the user: stored passwords and tokens are replaced by a flag
+    pub fn get_redacted_config(db: &Database) -> DatabaseResult<NotificationConfig> {
+        let mut config = Self::get_config(db)?;
+        if let Some(smtp) = &mut config.smtp {
+            smtp.password_set = smtp.password.take().is_some();
+        }
+        for gateway in [&mut config.sms, &mut config.whatsapp]
+            .into_iter()
+            .flatten()
+        {
+            gateway.token_set = gateway.token.take().is_some();
+        }
+        Ok(config)
+    }
+
+    /// Save the settings. A blank password or token keeps the stored one, since the frontend
+    /// only ever sees them redacted.
+    pub fn update_config(db: &Database, config: &NotificationConfig) -> DatabaseResult<()> {
+        // Settings that no longer parse are replaced outright
+        let stored = Self::get_config(db).unwrap_or_default();
+        let mut config = config.clone();
+        if let Some(smtp) = &mut config.smtp {
+            let stored_password = stored.smtp.and_then(|smtp| smtp.password);
+            smtp.password = Self::keep_secret(smtp.password.take(), stored_password);
+            smtp.password_set = false;
+        }
+        for (gateway, stored) in [
+            (&mut config.sms, stored.sms),
+            (&mut config.whatsapp, stored.whatsapp),
+        ] {
+            if let Some(gateway) = gateway {
+                let stored_token = stored.and_then(|gateway| gateway.token);
+                gateway.token = Self::keep_secret(gateway.token.take(), stored_token);
+                gateway.token_set = false;
+            }
+        }
+
+        if let Some(smtp) = &config.smtp {
+            if smtp.host.trim().is_empty() || !smtp.from.contains('@') {
+                return Err(DatabaseError::validation(
+                    "smtp",
+                    "SMTP settings need a server and a sender address",
+                ));
+            }
+        }
+        for gateway in [&config.sms, &config.whatsapp].into_iter().flatten() {
+            if !gateway.url.starts_with("https://") && !gateway.url.starts_with("http://") {
+                return Err(DatabaseError::validation(
+                    "gateway_url",
+                    format!(
+                        "Gateway URL must start with http:// or https://: {}",
+                        gateway.url
+                    ),
+                ));
+            }
+        }
+
+        let json = serde_json::to_string(&config).map_err(|e| {
+            DatabaseError::validation(
+                "notifications",
+                format!("Invalid notification settings: {}", e),
+            )
+        })?;
+        SettingsService::set_setting(db, CONFIG_SETTING, &json)
+    }
+
+    fn keep_secret(secret: Option<String>, stored: Option<String>) -> Option<String> {
+        match secret {
+            Some(secret) if !secret.trim().is_empty() => Some(secret),
+            _ => stored,
+        }
+    }
+
+    pub fn get_student_contact(db: &Database, student_id: &str) -> DatabaseResult<StudentContact> {
+        let contact = db
+            .connection()
+            .query_row(
+                "SELECT student_id, phone, whatsapp, email FROM student_contacts WHERE student_id = ?1",
+                params![student_id],
+                |row| {
+                    Ok(StudentContact {
+                        student_id: row.get(0)?,
+                        phone: row.get(1)?,
+                        whatsapp: row.get(2)?,
+                        email: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(contact.unwrap_or_else(|| StudentContact {
+            student_id: student_id.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    /// Save a student's addresses; empty values are cleared
+    pub fn set_student_contact(
+        db: &Database,
+        contact: StudentContact,
+    ) -> DatabaseResult<StudentContact> {
+        if StudentService::get_student_by_id(db, &contact.student_id)?.is_none() {
+            return Err(DatabaseError::not_found("Student", &contact.student_id));
+        }
+
+        let clean = |value: Option<String>| {
+            value
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let contact = StudentContact {
+            student_id: contact.student_id,
+            phone: clean(contact.phone),
+            whatsapp: clean(contact.whatsapp),
+            email: clean(contact.email),
+        };
+
+        for (field, number) in [("phone", &contact.phone), ("whatsapp", &contact.whatsapp)] {
+            if let Some(number) = number {
+                let digits = number.strip_prefix('+').unwrap_or(number);
+                let valid = digits.len() >= 6
+                    && digits
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || c == ' ' || c == '-');
+                if !valid {
+                    return Err(DatabaseError::validation(
+                        field,
+                        format!("Invalid phone number: {}", number),
+                    ));
+                }
+            }
+        }
+        if let Some(email) = &contact.email {
+            let valid = email.split_once('@').map_or(false, |(user, domain)| {
+                !user.is_empty() && domain.contains('.')
+            });
+            if !valid {
+                return Err(DatabaseError::validation(
+                    "email",
+                    format!("Invalid email address: {}", email),
+                ));
+            }
+        }
+
+        db.connection().execute(
+            "INSERT INTO student_contacts (student_id, phone, whatsapp, email, updated_at)
+             VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)
+             ON CONFLICT (student_id) DO UPDATE SET
+                phone = excluded.phone, whatsapp = excluded.whatsapp, email = excluded.email,
+                updated_at = excluded.updated_at",
+            params![
+                contact.student_id,
+                contact.phone,
+                contact.whatsapp,
+                contact.email
+            ],
+        )?;
+
+        Ok(contact)
+    }
+
+    pub fn get_templates(db: &Database) -> DatabaseResult<Vec<NotificationTemplate>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT id, kind, locale, subject, body, updated_at FROM notification_templates ORDER BY kind, locale",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut templates = Vec::new();
+        for row in rows {
+            let (id, kind, locale, subject, body, updated_at) = row?;
+            templates.push(NotificationTemplate {
+                id,
+                kind: ReminderKind::from_str(&kind)
+                    .map_err(|e| DatabaseError::validation("kind", e))?,
+                locale: Locale::parse(&locale).unwrap_or_default(),
+                subject,
+                body,
+                updated_at,
+            });
+        }
+        Ok(templates)
+    }
+
+    fn get_template(
+        db: &Database,
+        kind: ReminderKind,
+        locale: Locale,
+    ) -> DatabaseResult<NotificationTemplate> {
+        Self::get_templates(db)?
+            .into_iter()
+            .find(|t| t.kind == kind && t.locale == locale)
+            .ok_or_else(|| {
+                DatabaseError::not_found(
+                    "Notification template",
+                    format!("{}/{}", kind.as_str(), locale.code()),
+                )
+            })
+    }
+
+    pub fn update_template(
+        db: &Database,
+        kind: ReminderKind,
+        locale: Locale,
+        subject: &str,
+        body: &str,
+    ) -> DatabaseResult<NotificationTemplate> {
+        if body.trim().is_empty() {
+            return Err(DatabaseError::validation(
+                "template",
+                "Template text cannot be empty",
+            ));
+        }
+        for text in [subject, body] {
+            if let Some(unknown) = Self::placeholders(text)
+                .into_iter()
+                .find(|name| !TEMPLATE_PLACEHOLDERS.contains(&name.as_str()))
+            {
+                return Err(DatabaseError::validation(
+                    "template",
+                    format!(
+                        "Unknown placeholder {{{}}}; use {}",
+                        unknown,
+                        TEMPLATE_PLACEHOLDERS
+                            .iter()
+                            .map(|p| format!("{{{}}}", p))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                ));
+            }
+        }
+
+        db.connection().execute(
+            "INSERT INTO notification_templates (kind, locale, subject, body, updated_at)
+             VALUES (?1, ?2, ?3, ?4, CURRENT_TIMESTAMP)
+             ON CONFLICT (kind, locale) DO UPDATE SET
+                subject = excluded.subject, body = excluded.body, updated_at = excluded.updated_at",
+            params![kind.as_str(), locale.code(), subject.trim(), body.trim()],
+        )?;
+
+        Self::get_template(db, kind, locale)
+    }
+
+    /// Names between braces, e.g. `student` in `Hello {student}`
+    fn placeholders(text: &str) -> Vec<String> {
+        text.split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_string()))
+            .collect()
+    }
+
+    fn render(text: &str, values: &BTreeMap<&str, String>) -> String {
+        values.iter().fold(text.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+    }
+
+    /// What the reminder asks for: the open installments up to the due date, one month of a
+    /// monthly plan, or the rest of the plan amount
+    pub(crate) fn amount_due(db: &Database, student: &Student) -> DatabaseResult<i32> {
+        let installments = InstallmentService::get_installments(db, &student.id)?;
+        if !installments.is_empty() {
+            let due_date = student.next_due_date.as_deref().unwrap_or("");
+            return Ok(installments
+                .iter()
+                .filter(|i| i.due_date.as_str() <= due_date)
+                .map(|i| (i.amount_due - i.amount_allocated).max(0))
+                .sum());
+        }
+
+        Ok(match student.payment_plan {
+            PaymentPlan::Monthly => student.plan_amount,
+            _ => {
+                let paid = TermService::paid_toward_term(
+                    db,
+                    &student.id,
+                    None,
+                    student.paid_amount as i64,
+                )?;
+                (student.plan_amount as i64 - paid).clamp(0, i32::MAX as i64) as i32
+            }
+        })
+    }
+
+    /// Queue a reminder for every due-soon and overdue student on each enabled channel they
+    /// have an address for. Reminders already queued for the same due date are skipped.
+    pub fn enqueue_payment_reminders(
+        db: &Database,
+        locale: Locale,
+    ) -> DatabaseResult<EnqueueSummary> {
+        let config = Self::get_config(db)?;
+        if config.channels.is_empty() {
+            return Err(DatabaseError::validation(
+                "channels",
+                "No notification channel is enabled",
+            ));
+        }
+
+        let mut summary = EnqueueSummary::default();
+        let batches = [
+            (
+                ReminderKind::DueSoon,
+                StudentService::get_due_soon_students(db)?,
+            ),
+            (
+                ReminderKind::Overdue,
+                StudentService::get_overdue_students(db)?,
+            ),
+        ];
+
+        for (kind, students) in batches {
+            let template = Self::get_template(db, kind, locale)?;
+
+            for student in students {
+                let contact = Self::get_student_contact(db, &student.id)?;
+                let due_date = student.next_due_date.clone().unwrap_or_default();
+                let values = BTreeMap::from([
+                    ("student", student.name.clone()),
+                    ("amount", Self::amount_due(db, &student)?.to_string()),
+                    ("due_date", due_date.clone()),
+                    ("group", student.group_name.clone()),
+                ]);
+
+                let mut reachable = false;
+                for channel in &config.channels {
+                    let Some(recipient) = contact.address(*channel) else {
+                        continue;
+                    };
+                    reachable = true;
+
+                    let subject = match channel {
+                        Channel::Email => Some(Self::render(&template.subject, &values)),
+                        _ => None,
+                    };
+                    let dedupe_key = format!(
+                        "{}:{}:{}:{}",
+                        kind.as_str(),
+                        student.id,
+                        due_date,
+                        channel.as_str()
+                    );
+                    let inserted = db.connection().execute(
+                        "INSERT OR IGNORE INTO notification_outbox
+                            (student_id, kind, channel, recipient, subject, body, dedupe_key)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                        params![
+                            student.id,
+                            kind.as_str(),
+                            channel.as_str(),
+                            recipient,
+                            subject,
+                            Self::render(&template.body, &values),
+                            dedupe_key
+                        ],
+                    )?;
+                    if inserted > 0 {
+                        summary.queued += 1;
+                    } else {
+                        summary.duplicates += 1;
+                    }
+                }
+
+                if !reachable {
+                    summary.missing_contact.push(student.id.clone());
+                }
+            }
+        }
+
+        log::info!(
+            "Queued {} payment reminders ({} already queued, {} students without contact details)",
+            summary.queued,
+            summary.duplicates,
+            summary.missing_contact.len()
+        );
+        Ok(summary)
+    }
+
+    /// Outbox messages, newest first
+    pub fn get_outbox(
+        db: &Database,
+        status: Option<NotificationStatus>,
+        limit: Option<i64>,
+    ) -> DatabaseResult<Vec<OutboxMessage>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM notification_outbox
+             WHERE ?1 IS NULL OR status = ?1
+             ORDER BY created_at DESC, id DESC
+             LIMIT ?2",
+            OUTBOX_COLUMNS
+        ))?;
+        let messages = stmt
+            .query_map(
+                params![status.map(|s| s.as_str()), limit.unwrap_or(-1)],
+                Self::map_row_to_message,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(messages)
+    }
+
+    /// Mark up to `limit` messages that are due as `sending` and return them. Claims left
+    /// behind by an interrupted run are taken over.
+    pub fn claim_due_messages(
+        db: &Database,
+        now: DateTime<Utc>,
+        limit: i64,
+    ) -> DatabaseResult<Vec<OutboxMessage>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "UPDATE notification_outbox SET status = 'sending', updated_at = ?1
+             WHERE id IN (
+                SELECT id FROM notification_outbox
+                WHERE (status = 'pending' AND next_attempt_at <= ?1)
+                   OR (status = 'sending' AND updated_at <= ?2)
+                ORDER BY next_attempt_at, id
+                LIMIT ?3)
+             RETURNING {}",
+            OUTBOX_COLUMNS
+        ))?;
+        let mut messages = stmt
+            .query_map(
+                params![
+                    Self::timestamp(now),
+                    Self::timestamp(now - Duration::minutes(STALE_CLAIM_MINUTES)),
+                    limit
+                ],
+                Self::map_row_to_message,
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+        messages.sort_by_key(|m| m.id);
+        Ok(messages)
+    }
+
+    /// Hand a claimed message to the provider for its channel
+    pub fn deliver(
+        providers: &Providers,
+        message: &OutboxMessage,
+    ) -> Result<Option<String>, DeliveryError> {
+        match providers.get(message.channel) {
+            Some(provider) => provider.send(message),
+            None => Err(DeliveryError::Transient(format!(
+                "No provider is configured for {}",
+                message.channel.as_str()
+            ))),
+        }
+    }
+
+    fn backoff(attempts: i32) -> Duration {
+        let exponent = attempts.clamp(1, 16) as u32 - 1;
+        Duration::minutes((BASE_BACKOFF_MINUTES << exponent).min(MAX_BACKOFF_MINUTES))
+    }
+
+    /// Store the outcome of a delivery attempt and return the message's new status
+    pub fn record_delivery(
+        db: &Database,
+        message: &OutboxMessage,
+        result: &Result<Option<String>, DeliveryError>,
+        now: DateTime<Utc>,
+    ) -> DatabaseResult<NotificationStatus> {
+        let attempts = message.attempts + 1;
+        let (status, error, next_attempt_at) = match result {
+            Ok(_) => (
+                NotificationStatus::Sent,
+                None,
+                message.next_attempt_at.clone(),
+            ),
+            Err(DeliveryError::Transient(e)) if attempts < message.max_attempts => (
+                NotificationStatus::Pending,
+                Some(e.clone()),
+                Self::timestamp(now + Self::backoff(attempts)),
+            ),
+            Err(e) => (
+                NotificationStatus::Failed,
+                Some(e.to_string()),
+                message.next_attempt_at.clone(),
+            ),
+        };
+        let provider_message_id = result.as_ref().ok().cloned().flatten();
+        let now = Self::timestamp(now);
+
+        db.connection().execute(
+            "UPDATE notification_outbox
+             SET status = ?1, attempts = ?2, last_error = ?3, next_attempt_at = ?4,
+                 provider_message_id = COALESCE(?5, provider_message_id),
+                 sent_at = CASE WHEN ?1 = 'sent' THEN ?6 ELSE sent_at END,
+                 updated_at = ?6
+             WHERE id = ?7",
+            params![
+                status.as_str(),
+                attempts,
+                error,
+                next_attempt_at,
+                provider_message_id,
+                now,
+                message.id
+            ],
+        )?;
+
+        if let Some(error) = &error {
+            log::warn!(
+                "Notification {} to {} failed (attempt {}): {}",
+                message.id,
+                message.recipient,
+                attempts,
+                error
+            );
+        }
+        Ok(status)
+    }
+
+    /// Claim due messages, send them without holding the writer, then record each outcome.
+    /// Sending can take a while, so other commands keep working in between.
+    pub fn process_outbox(
+        pool: &DatabasePool,
+        providers: &Providers,
+        limit: i64,
+    ) -> DatabaseResult<DeliveryReport> {
+        let messages = Self::claim_due_messages(&*pool.writer()?, Utc::now(), limit)?;
+
+        let mut report = DeliveryReport::default();
+        for message in &messages {
+            let result = Self::deliver(providers, message);
+            let status = Self::record_delivery(&*pool.writer()?, message, &result, Utc::now())?;
+            report.record(status);
+        }
+        Ok(report)
+    }
+
+    fn get_message(db: &Database, id: i64) -> DatabaseResult<OutboxMessage> {
+        db.connection()
+            .query_row(
+                &format!(
+                    "SELECT {} FROM notification_outbox WHERE id = ?1",
+                    OUTBOX_COLUMNS
+                ),
+                params![id],
+                Self::map_row_to_message,
+            )
+            .optional()?
+            .ok_or_else(|| DatabaseError::not_found("Notification", id))
+    }
+
+    /// Send a failed or cancelled message again, with a fresh set of attempts
+    pub fn retry_message(db: &Database, id: i64) -> DatabaseResult<OutboxMessage> {
+        let message = Self::get_message(db, id)?;
+        if !matches!(
+            message.status,
+            NotificationStatus::Failed | NotificationStatus::Cancelled
+        ) {
+            return Err(DatabaseError::forbidden(
+                "notification_state",
+                format!(
+                    "Only failed or cancelled messages can be retried, not {}",
+                    message.status.as_str()
+                ),
+            ));
+        }
+
+        db.connection().execute(
+            "UPDATE notification_outbox
+             SET status = 'pending', attempts = 0, last_error = NULL,
+                 next_attempt_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?1",
+            params![id],
+        )?;
+        Self::get_message(db, id)
+    }
+
+    pub fn cancel_message(db: &Database, id: i64) -> DatabaseResult<OutboxMessage> {
+        let message = Self::get_message(db, id)?;
+        if !matches!(
+            message.status,
+            NotificationStatus::Pending | NotificationStatus::Failed
+        ) {
+            return Err(DatabaseError::forbidden(
+                "notification_state",
+                format!(
+                    "Only pending or failed messages can be cancelled, not {}",
+                    message.status.as_str()
+                ),
+            ));
+        }
+
+        db.connection().execute(
+            "UPDATE notification_outbox SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            params![id],
+        )?;
+        Self::get_message(db, id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notification_providers::{FileProvider, NotificationProvider};
+    use crate::student_service::CreateStudentRequest;
+    use tempfile::TempDir;
+
+    fn setup_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_overdue_student(db: &Database, name: &str) -> Student {
+        let student = StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: name.to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::Monthly,
+                plan_amount: 850,
+                installment_count: None,
+                paid_amount: None,
+                enrollment_date: Some("2024-01-01".to_string()),
+            },
+        )
+        .unwrap();
+        db.connection()
+            .execute(
+                "UPDATE students SET payment_status = 'overdue', next_due_date = '2024-02-01' WHERE id = ?1",
+                params![student.id],
+            )
+            .unwrap();
+        student
+    }
+
+    fn enable_channels(db: &Database, channels: Vec<Channel>) {
+        let config = NotificationConfig {
+            channels,
+            ..Default::default()
+        };
+        NotificationService::update_config(db, &config).unwrap();
+    }
+
+    struct RejectingProvider(DeliveryError);
+
+    impl NotificationProvider for RejectingProvider {
+        fn send(&self, _message: &OutboxMessage) -> Result<Option<String>, DeliveryError> {
+            Err(match &self.0 {
+                DeliveryError::Transient(e) => DeliveryError::Transient(e.clone()),
+                DeliveryError::Permanent(e) => DeliveryError::Permanent(e.clone()),
+            })
+        }
+    }
+
+    #[test]
+    fn test_enqueue_payment_reminders() {
+        let (db, _temp_dir) = setup_test_db();
+        let reachable = create_overdue_student(&db, "Sara");
+        let unreachable = create_overdue_student(&db, "Omar");
+        NotificationService::set_student_contact(
+            &db,
+            StudentContact {
+                student_id: reachable.id.clone(),
+                phone: Some("+966 500 000 001".to_string()),
+                whatsapp: None,
+                email: Some("sara@example.com".to_string()),
+            },
+        )
+        .unwrap();
+
+        // Nothing can be queued before a channel is enabled
+        assert!(NotificationService::enqueue_payment_reminders(&db, Locale::En).is_err());
+
+        enable_channels(&db, vec![Channel::Email, Channel::WhatsApp]);
+        let summary = NotificationService::enqueue_payment_reminders(&db, Locale::En).unwrap();
+        assert_eq!(summary.queued, 2);
+        assert_eq!(summary.missing_contact, vec![unreachable.id]);
+
+        let outbox = NotificationService::get_outbox(&db, None, None).unwrap();
+        let email = outbox.iter().find(|m| m.channel == Channel::Email).unwrap();
+        assert_eq!(email.recipient, "sara@example.com");
+        assert_eq!(email.subject.as_deref(), Some("Overdue payment"));
+        assert!(
+            email.body.contains("Sara")
+                && email.body.contains("850")
+                && email.body.contains("2024-02-01")
+        );
+        // WhatsApp falls back to the phone number and has no subject
+        let whatsapp = outbox
+            .iter()
+            .find(|m| m.channel == Channel::WhatsApp)
+            .unwrap();
+        assert_eq!(whatsapp.recipient, "+966 500 000 001");
+        assert_eq!(whatsapp.subject, None);
+
+        // The same due date is not reminded twice
+        let again = NotificationService::enqueue_payment_reminders(&db, Locale::En).unwrap();
+        assert_eq!(again.queued, 0);
+        assert_eq!(again.duplicates, 2);
+    }
+
+    #[test]
+    fn test_delivery_retries_and_gives_up() {
+        let (db, temp_dir) = setup_test_db();
+        let student = create_overdue_student(&db, "Sara");
+        NotificationService::set_student_contact(
+            &db,
+            StudentContact {
+                student_id: student.id.clone(),
+                phone: Some("0500000001".to_string()),
+                whatsapp: None,
+                email: Some("sara@example.com".to_string()),
+            },
+        )
+        .unwrap();
+        enable_channels(&db, vec![Channel::Email, Channel::Sms]);
+        NotificationService::enqueue_payment_reminders(&db, Locale::Ar).unwrap();
+
+        let outbox_file = temp_dir.path().join("outbox.jsonl");
+        let providers = Providers::default()
+            .with(Channel::Email, FileProvider::new(&outbox_file))
+            .with(
+                Channel::Sms,
+                RejectingProvider(DeliveryError::Transient("gateway down".to_string())),
+            );
+
+        let now = Utc::now();
+        let claimed = NotificationService::claim_due_messages(&db, now, 10).unwrap();
+        assert_eq!(claimed.len(), 2);
+        // Claimed messages are not handed out twice
+        assert!(NotificationService::claim_due_messages(&db, now, 10)
+            .unwrap()
+            .is_empty());
+
+        let mut report = DeliveryReport::default();
+        for message in &claimed {
+            let result = NotificationService::deliver(&providers, message);
+            report
+                .record(NotificationService::record_delivery(&db, message, &result, now).unwrap());
+        }
+        assert_eq!(
+            report,
+            DeliveryReport {
+                sent: 1,
+                retrying: 1,
+                failed: 0
+            }
+        );
+        assert_eq!(
+            std::fs::read_to_string(&outbox_file)
+                .unwrap()
+                .lines()
+                .count(),
+            1
+        );
+
+        let sent =
+            NotificationService::get_outbox(&db, Some(NotificationStatus::Sent), None).unwrap();
+        assert_eq!(
+            sent[0].provider_message_id,
+            Some(format!("file-{}", sent[0].id))
+        );
+
+        // The retry waits for its backoff, then fails for good on the last attempt
+        let mut time = now;
+        for attempt in 2..=5 {
+            assert!(NotificationService::claim_due_messages(&db, time, 10)
+                .unwrap()
+                .is_empty());
+            time += NotificationService::backoff(attempt - 1);
+            let claimed = NotificationService::claim_due_messages(&db, time, 10).unwrap();
+            assert_eq!(claimed.len(), 1);
+            let result = NotificationService::deliver(&providers, &claimed[0]);
+            let status =
+                NotificationService::record_delivery(&db, &claimed[0], &result, time).unwrap();
+            let expected = if attempt == 5 {
+                NotificationStatus::Failed
+            } else {
+                NotificationStatus::Pending
+            };
+            assert_eq!(status, expected);
+        }
+
+        let failed =
+            NotificationService::get_outbox(&db, Some(NotificationStatus::Failed), None).unwrap();
+        assert_eq!(failed[0].attempts, 5);
+        assert_eq!(failed[0].last_error.as_deref(), Some("gateway down"));
+
+        // Sent messages stay sent; failed ones can be queued again
+        assert!(NotificationService::retry_message(&db, sent[0].id).is_err());
+        let retried = NotificationService::retry_message(&db, failed[0].id).unwrap();
+        assert_eq!(retried.status, NotificationStatus::Pending);
+        assert_eq!(retried.attempts, 0);
+        let cancelled = NotificationService::cancel_message(&db, retried.id).unwrap();
+        assert_eq!(cancelled.status, NotificationStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_permanent_errors_are_not_retried() {
+        let (db, _temp_dir) = setup_test_db();
+        let student = create_overdue_student(&db, "Sara");
+        NotificationService::set_student_contact(
+            &db,
+            StudentContact {
+                student_id: student.id,
+                email: Some("sara@example.com".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        enable_channels(&db, vec![Channel::Email]);
+        NotificationService::enqueue_payment_reminders(&db, Locale::Ar).unwrap();
+
+        let providers = Providers::default().with(
+            Channel::Email,
+            RejectingProvider(DeliveryError::Permanent(
+                "mailbox does not exist".to_string(),
+            )),
+        );
+        let now = Utc::now();
+        let claimed = NotificationService::claim_due_messages(&db, now, 10).unwrap();
+        let result = NotificationService::deliver(&providers, &claimed[0]);
+        let status = NotificationService::record_delivery(&db, &claimed[0], &result, now).unwrap();
+        assert_eq!(status, NotificationStatus::Failed);
+    }
+
+    #[test]
+    fn test_contact_and_template_validation() {
+        let (db, _temp_dir) = setup_test_db();
+        let student = create_overdue_student(&db, "Sara");
+
+        let invalid_email = StudentContact {
+            student_id: student.id.clone(),
+            email: Some("not-an-address".to_string()),
+            ..Default::default()
+        };
+        assert!(NotificationService::set_student_contact(&db, invalid_email).is_err());
+        let invalid_phone = StudentContact {
+            student_id: student.id.clone(),
+            phone: Some("call me".to_string()),
+            ..Default::default()
+        };
+        assert!(NotificationService::set_student_contact(&db, invalid_phone).is_err());
+
+        // Blank values clear the address
+        let contact = NotificationService::set_student_contact(
+            &db,
+            StudentContact {
+                student_id: student.id.clone(),
+                phone: Some("  ".to_string()),
+                whatsapp: None,
+                email: Some(" sara@example.com ".to_string()),
+            },
+        )
+        .unwrap();
+        assert_eq!(contact.phone, None);
+        assert_eq!(
+            NotificationService::get_student_contact(&db, &student.id).unwrap(),
+            contact
+        );
+
+        assert!(NotificationService::update_template(
+            &db,
+            ReminderKind::Overdue,
+            Locale::En,
+            "Reminder",
+            "Dear {parent}, please pay"
+        )
+        .is_err());
+        let template = NotificationService::update_template(
+            &db,
+            ReminderKind::Overdue,
+            Locale::En,
+            "Reminder for {student}",
+            "{student} ({group}) owes {amount}",
+        )
+        .unwrap();
+        assert_eq!(template.body, "{student} ({group}) owes {amount}");
+        assert_eq!(NotificationService::get_templates(&db).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_secrets_are_redacted_and_kept_when_blank() {
+        let (db, _temp_dir) = setup_test_db();
+        let config = |password: Option<&str>, token: Option<&str>| -> NotificationConfig {
+            serde_json::from_value(serde_json::json!({
+                "channels": ["email", "sms"],
+                "smtp": { "host": "smtp.example.com", "password": password, "from": "office@example.com" },
+                "sms": { "url": "https://sms.example.com", "token": token },
+            }))
+            .unwrap()
+        };
+        NotificationService::update_config(&db, &config(Some("secret"), Some("abc"))).unwrap();
+
+        let shown = NotificationService::get_redacted_config(&db).unwrap();
+        let smtp = shown.smtp.clone().unwrap();
+        let sms = shown.sms.clone().unwrap();
+        assert_eq!((smtp.password, smtp.password_set), (None, true));
+        assert_eq!((sms.token, sms.token_set), (None, true));
+
+        // Saving what was shown keeps the secrets; a new value replaces them
+        NotificationService::update_config(&db, &shown).unwrap();
+        NotificationService::update_config(&db, &config(Some(""), Some("xyz"))).unwrap();
+        let stored = NotificationService::get_config(&db).unwrap();
+        assert_eq!(stored.smtp.unwrap().password.as_deref(), Some("secret"));
+        assert_eq!(stored.sms.unwrap().token.as_deref(), Some("xyz"));
+    }
+}
@@ -1,15 +1,19 @@
 use crate::database::{Database, DatabaseError, DatabaseResult};
 use crate::audit_service::AuditService;
-use crate::student_service::{StudentService, PaymentPlan, PaymentStatus};
+use crate::enrollment_service::EnrollmentService;
+use crate::installment_service::InstallmentService;
+use crate::student_service::{Student, StudentService, PaymentPlan, PaymentStatus};
+use crate::term_service::{TermService, TERM_STUDENTS_SQL};
 use chrono::{Utc, NaiveDate};
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 // Business logic constants - consider making these configurable
 const MAX_PAYMENT_AMOUNT: i32 = 1_000_000; // Maximum allowed payment amount in cents/smallest currency unit
 const DEFAULT_RECENT_PAYMENTS_LIMIT: usize = 10; // Number of recent payments to show in summary
 const DAYS_PER_MONTH: f64 = 30.44; // Average days per month for monthly payment calculations
-const DEFAULT_INSTALLMENT_COUNT: i32 = 3; // Default number of installments when not specified
+pub(crate) const DEFAULT_INSTALLMENT_COUNT: i32 = 3; // Default number of installments when not specified
 
 // Custom error types for better error handling
 #[derive(Debug)]
@@ -55,6 +59,7 @@ pub struct BatchUpdateResult {
 pub struct PaymentTransaction {
     pub id: i32,
     pub student_id: String,
+    pub enrollment_id: Option<i32>,
     pub amount: i32,
     pub payment_date: String,
     pub payment_method: PaymentMethod,
@@ -96,6 +101,9 @@ impl PaymentMethod {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordPaymentRequest {
     pub student_id: String,
+    /// Enrollment the payment is billed to; the primary enrollment when omitted
+    #[serde(default)]
+    pub enrollment_id: Option<i32>,
     pub amount: i32,
     pub payment_date: String,
     pub payment_method: PaymentMethod,
@@ -179,7 +187,7 @@ impl PaymentService {
     fn build_payment_query(
         filter: &Option<PaymentHistoryFilter>,
     ) -> (String, String, Vec<Box<dyn rusqlite::ToSql>>) {
-        let mut query = "SELECT id, student_id, amount, payment_date, payment_method, notes, created_at FROM payment_transactions".to_string();
+        let mut query = "SELECT id, student_id, amount, payment_date, payment_method, notes, created_at, enrollment_id FROM payment_transactions".to_string();
         let mut count_query = "SELECT COUNT(*) FROM payment_transactions".to_string();
         let mut conditions = Vec::new();
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -240,14 +248,30 @@ impl PaymentService {
         plan_amount: i32,
         enrollment_date: &str,
         installment_count: Option<i32>,
+    ) -> Result<i64, DatabaseError> {
+        Self::calculate_expected_amount_until(
+            payment_plan,
+            plan_amount,
+            enrollment_date,
+            installment_count,
+            Utc::now().date_naive(),
+        )
+    }
+
+    /// Expected amount as of `until` rather than today; monthly plans accrue up to that date
+    pub(crate) fn calculate_expected_amount_until(
+        payment_plan: &PaymentPlan,
+        plan_amount: i32,
+        enrollment_date: &str,
+        installment_count: Option<i32>,
+        until: NaiveDate,
     ) -> Result<i64, DatabaseError> {
         match payment_plan {
             PaymentPlan::OneTime => Ok(plan_amount as i64),
             PaymentPlan::Monthly => {
                 let enrollment = NaiveDate::parse_from_str(enrollment_date, "%Y-%m-%d")
-                    .map_err(|_| DatabaseError::Migration("Invalid enrollment date".to_string()))?;
-                let today = Utc::now().date_naive();
-                let days_enrolled = (today - enrollment).num_days() as f64;
+                    .map_err(|_| DatabaseError::validation("enrollment_date", "Invalid enrollment date"))?;
+                let days_enrolled = (until - enrollment).num_days() as f64;
                 let months_enrolled = ((days_enrolled / DAYS_PER_MONTH) as i32) + 1;
                 Ok((plan_amount as i64) * (months_enrolled.max(1) as i64))
             }
@@ -257,12 +281,32 @@ impl PaymentService {
         }
     }
 
+    /// Expected amount for a student, preferring the explicit installment schedule when present
+    fn calculate_student_expected_amount(
+        db: &Database,
+        student: &crate::student_service::Student,
+    ) -> Result<i64, DatabaseError> {
+        if student.payment_plan == PaymentPlan::Installment {
+            if let Some(total) = InstallmentService::get_schedule_total(db, &student.id)? {
+                return Ok(total);
+            }
+        }
+
+        Self::calculate_expected_amount(
+            &student.payment_plan,
+            student.plan_amount,
+            &student.enrollment_date,
+            student.installment_count,
+        )
+    }
+
     /// Parse payment transaction from database row
     fn parse_payment_transaction(row: &rusqlite::Row) -> Result<PaymentTransaction, rusqlite::Error> {
         let payment_method_str: String = row.get(4)?;
         Ok(PaymentTransaction {
             id: row.get(0)?,
             student_id: row.get(1)?,
+            enrollment_id: row.get(7)?,
             amount: row.get(2)?,
             payment_date: row.get(3)?,
             payment_method: PaymentMethod::from_str(&payment_method_str)
@@ -344,57 +388,82 @@ impl PaymentService {
             .map_err(PaymentError::DatabaseError)?
             .ok_or_else(|| PaymentError::StudentNotFound(request.student_id.clone()))?;
 
-        // Start transaction
-        let tx = db.connection().unchecked_transaction()?;
+        // Resolve the enrollment being billed
+        let enrollment = EnrollmentService::resolve_enrollment(
+            db,
+            &request.student_id,
+            request.enrollment_id,
+            false,
+        )
+        .map_err(PaymentError::DatabaseError)?;
+
+        // The payment, the balances it moves and its audit entry are written together
+        AuditService::audited(db, |audit| {
+            // Insert payment transaction
+            let now = Utc::now().to_rfc3339();
+            db.connection().execute(
+                "INSERT INTO payment_transactions (student_id, enrollment_id, amount, payment_date, payment_method, notes, created_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    request.student_id,
+                    enrollment.id,
+                    request.amount,
+                    request.payment_date,
+                    request.payment_method.as_str(),
+                    request.notes,
+                    now
+                ],
+            )?;
 
-        // Insert payment transaction
-        let now = Utc::now().to_rfc3339();
-        tx.execute(
-            "INSERT INTO payment_transactions (student_id, amount, payment_date, payment_method, notes, created_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                request.student_id,
-                request.amount,
-                request.payment_date,
-                request.payment_method.as_str(),
-                request.notes,
-                now
-            ],
-        )?;
+            // Get the inserted payment ID
+            let payment_id = db.connection().last_insert_rowid() as i32;
+
+            if enrollment.is_primary {
+                // Update student's paid amount
+                let new_paid_amount = student.paid_amount + request.amount;
+                db.connection().execute(
+                    "UPDATE students SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![new_paid_amount, now, request.student_id],
+                )?;
+            } else {
+                db.connection().execute(
+                    "UPDATE enrollments SET paid_amount = paid_amount + ?1, updated_at = ?2 WHERE id = ?3",
+                    params![request.amount, now, enrollment.id],
+                )?;
+            }
 
-        // Get the inserted payment ID
-        let payment_id = tx.last_insert_rowid() as i32;
+            if enrollment.is_primary {
+                // Allocate the new total to the installment schedule (no-op for other plans)
+                InstallmentService::allocate_payments(db, &request.student_id)?;
 
-        // Update student's paid amount
-        let new_paid_amount = student.paid_amount + request.amount;
-        tx.execute(
-            "UPDATE students SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
-            params![new_paid_amount, now, request.student_id],
-        )?;
+                // Recalculate payment status and due dates for the student
+                Self::update_student_payment_status(db, &request.student_id)?;
+            } else {
+                EnrollmentService::update_enrollment_payment_status(db, enrollment.id)?;
+            }
 
-        // Commit transaction
-        tx.commit()?;
-
-        // Recalculate payment status and due dates for the student
-        Self::update_student_payment_status(db, &request.student_id)?;
-
-        // Create the payment transaction object to return
-        let payment_transaction = PaymentTransaction {
-            id: payment_id,
-            student_id: request.student_id.clone(),
-            amount: request.amount,
-            payment_date: request.payment_date,
-            payment_method: request.payment_method,
-            notes: request.notes,
-            created_at: now,
-        };
+            // Create the payment transaction object to return
+            let payment_transaction = PaymentTransaction {
+                id: payment_id,
+                student_id: request.student_id.clone(),
+                enrollment_id: Some(enrollment.id),
+                amount: request.amount,
+                payment_date: request.payment_date,
+                payment_method: request.payment_method,
+                notes: request.notes,
+                created_at: now,
+            };
 
-        // Log audit entry for payment creation
-        if let Ok(serialized_data) = AuditService::serialize_data(&payment_transaction) {
-            let _ = AuditService::log_create(db, "payment_transactions", &payment_id.to_string(), &serialized_data, None);
-        }
+            audit.create(
+                "payment_transactions",
+                &payment_id.to_string(),
+                &payment_transaction,
+                None,
+            );
 
-        Ok(payment_transaction)
+            Ok(payment_transaction)
+        })
+        .map_err(PaymentError::from)
     }
 
 
@@ -433,11 +502,16 @@ impl PaymentService {
         Self::get_payment_history(db, Some(filter))
     }
 
-    /// Get comprehensive payment summary using optimized calculations
-    pub fn get_payment_summary(db: &Database) -> DatabaseResult<PaymentSummary> {
-        let overall_stats = Self::get_overall_payment_stats(db)?;
-        let plan_breakdown = Self::get_payment_plan_breakdown(db)?;
-        let recent_payments = Self::get_recent_payments(db)?;
+    /// Get comprehensive payment summary using optimized calculations. With a term, only
+    /// students who took part in it are counted and paid amounts are the payments dated in it.
+    pub fn get_payment_summary(
+        db: &Database,
+        term_id: Option<i32>,
+    ) -> DatabaseResult<PaymentSummary> {
+        let students = Self::get_summary_students(db, term_id)?;
+        let overall_stats = Self::get_overall_payment_stats(db, &students)?;
+        let plan_breakdown = Self::get_payment_plan_breakdown(db, &students)?;
+        let recent_payments = Self::get_recent_payments(db, term_id)?;
 
         Ok(PaymentSummary {
             total_students: overall_stats.0,
@@ -452,9 +526,41 @@ impl PaymentService {
         })
     }
 
+    /// Students counted in the payment summary. Within a term, each student's paid amount is
+    /// replaced by what they paid during it.
+    fn get_summary_students(db: &Database, term_id: Option<i32>) -> DatabaseResult<Vec<Student>> {
+        let mut students = StudentService::get_all_students(db)?;
+        let Some(term_id) = term_id else {
+            return Ok(students);
+        };
+
+        let term_students = {
+            let mut stmt = db.connection().prepare(TERM_STUDENTS_SQL)?;
+            let rows = stmt.query_map([term_id], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<HashSet<_>, _>>()?
+        };
+        let term_paid = {
+            let mut stmt = db.connection().prepare(
+                "SELECT student_id, SUM(amount) FROM payment_transactions WHERE term_id = ?1 GROUP BY student_id",
+            )?;
+            let rows = stmt.query_map([term_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?))
+            })?;
+            rows.collect::<Result<HashMap<_, _>, _>>()?
+        };
+
+        students.retain(|student| term_students.contains(&student.id));
+        for student in &mut students {
+            student.paid_amount = term_paid.get(&student.id).copied().unwrap_or(0);
+        }
+        Ok(students)
+    }
+
     /// Get overall payment statistics
-    fn get_overall_payment_stats(db: &Database) -> DatabaseResult<(i32, i64, i64, i32, i32, i32, i32)> {
-        let students = StudentService::get_all_students(db)?;
+    fn get_overall_payment_stats(
+        db: &Database,
+        students: &[Student],
+    ) -> DatabaseResult<(i32, i64, i64, i32, i32, i32, i32)> {
         let mut total_expected = 0i64;
         let mut total_paid = 0i64;
         let mut students_paid = 0;
@@ -462,13 +568,8 @@ impl PaymentService {
         let mut students_overdue = 0;
         let mut students_due_soon = 0;
 
-        for student in &students {
-            let expected = Self::calculate_expected_amount(
-                &student.payment_plan,
-                student.plan_amount,
-                &student.enrollment_date,
-                student.installment_count,
-            )?;
+        for student in students {
+            let expected = Self::calculate_student_expected_amount(db, student)?;
             total_expected += expected;
             total_paid += student.paid_amount as i64;
 
@@ -492,19 +593,16 @@ impl PaymentService {
     }
 
     /// Get payment plan breakdown statistics
-    fn get_payment_plan_breakdown(db: &Database) -> DatabaseResult<PaymentPlanBreakdown> {
-        let students = StudentService::get_all_students(db)?;
+    fn get_payment_plan_breakdown(
+        db: &Database,
+        students: &[Student],
+    ) -> DatabaseResult<PaymentPlanBreakdown> {
         let mut one_time_stats = PaymentPlanStats::default();
         let mut monthly_stats = PaymentPlanStats::default();
         let mut installment_stats = PaymentPlanStats::default();
 
-        for student in &students {
-            let expected = Self::calculate_expected_amount(
-                &student.payment_plan,
-                student.plan_amount,
-                &student.enrollment_date,
-                student.installment_count,
-            )?;
+        for student in students {
+            let expected = Self::calculate_student_expected_amount(db, student)?;
 
             let stats = match student.payment_plan {
                 PaymentPlan::OneTime => &mut one_time_stats,
@@ -522,10 +620,25 @@ impl PaymentService {
         })
     }
 
-    /// Get recent payments
-    fn get_recent_payments(db: &Database) -> DatabaseResult<Vec<PaymentTransaction>> {
+    /// Get recent payments, optionally only those made during a term
+    fn get_recent_payments(
+        db: &Database,
+        term_id: Option<i32>,
+    ) -> DatabaseResult<Vec<PaymentTransaction>> {
+        let term_payments = match term_id {
+            Some(term_id) => {
+                let mut stmt = db
+                    .connection()
+                    .prepare("SELECT id FROM payment_transactions WHERE term_id = ?1")?;
+                let rows = stmt.query_map([term_id], |row| row.get::<_, i32>(0))?;
+                Some(rows.collect::<Result<HashSet<_>, _>>()?)
+            }
+            None => None,
+        };
+
         Ok(Self::get_payment_history(db, None)?
             .into_iter()
+            .filter(|payment| term_payments.as_ref().map_or(true, |ids| ids.contains(&payment.id)))
             .take(DEFAULT_RECENT_PAYMENTS_LIMIT)
             .collect())
     }
@@ -535,32 +648,7 @@ impl PaymentService {
         db: &Database,
         student_id: &str,
     ) -> DatabaseResult<()> {
-        let student = StudentService::get_student_by_id(db, student_id)?
-            .ok_or_else(|| DatabaseError::Migration(format!("Student with ID {} not found", student_id)))?;
-
-        let config = StudentService::get_payment_plan_config(db)?;
-
-        // Get the most recent payment date for due date calculation
-        let last_payment_date = Self::get_last_payment_date(db, student_id)?;
-
-        // Calculate new due date
-        let next_due_date = StudentService::calculate_next_due_date(
-            &student.payment_plan,
-            &student.enrollment_date,
-            last_payment_date.as_deref(),
-            config.installment_interval,
-        )?;
-
-        // Calculate new payment status
-        let payment_status = StudentService::calculate_payment_status(
-            &student.payment_plan,
-            student.plan_amount,
-            student.paid_amount,
-            student.installment_count,
-            next_due_date.as_deref(),
-            &student.enrollment_date,
-            config.reminder_days,
-        )?;
+        let (next_due_date, payment_status) = Self::calculate_student_payment_state(db, student_id)?;
 
         // Update student record
         let now = Utc::now().to_rfc3339();
@@ -569,16 +657,74 @@ impl PaymentService {
             params![next_due_date, payment_status.as_str(), now, student_id],
         )?;
 
+        // Keep the primary enrollment in step with the student record
+        EnrollmentService::sync_primary_enrollment(db, student_id)?;
+
         Ok(())
     }
 
-    /// Get the most recent payment date for a student
+    /// Work out a student's next due date and payment status from their plan and payments
+    pub(crate) fn calculate_student_payment_state(
+        db: &Database,
+        student_id: &str,
+    ) -> DatabaseResult<(Option<String>, PaymentStatus)> {
+        let student = StudentService::get_student_by_id(db, student_id)?
+            .ok_or_else(|| DatabaseError::not_found("Student", &student_id))?;
+
+        let config = StudentService::get_payment_plan_config(db)?;
+
+        // Students with an installment schedule follow its first open installment
+        let schedule_state =
+            InstallmentService::get_schedule_payment_state(db, student_id, config.reminder_days)?;
+
+        let (next_due_date, payment_status) = match schedule_state {
+            Some(state) => state,
+            None => {
+                // Get the most recent payment date for due date calculation
+                let last_payment_date = Self::get_last_payment_date(db, student_id)?;
+
+                // Calculate new due date
+                let next_due_date = StudentService::calculate_next_due_date(
+                    &student.payment_plan,
+                    &student.enrollment_date,
+                    last_payment_date.as_deref(),
+                    config.installment_interval,
+                )?;
+
+                // Calculate new payment status
+                let paid_toward_term = TermService::paid_toward_term(
+                    db,
+                    student_id,
+                    None,
+                    student.paid_amount as i64,
+                )?;
+                let payment_status = StudentService::calculate_payment_status(
+                    &student.payment_plan,
+                    student.plan_amount,
+                    paid_toward_term as i32,
+                    student.installment_count,
+                    next_due_date.as_deref(),
+                    &student.enrollment_date,
+                    config.reminder_days,
+                )?;
+
+                (next_due_date, payment_status)
+            }
+        };
+
+        Ok((next_due_date, payment_status))
+    }
+
+    /// Get the most recent payment date for a student's primary enrollment
     fn get_last_payment_date(
         db: &Database,
         student_id: &str,
     ) -> DatabaseResult<Option<String>> {
         let result = db.connection().query_row(
-            "SELECT payment_date FROM payment_transactions WHERE student_id = ?1 ORDER BY payment_date DESC LIMIT 1",
+            "SELECT payment_date FROM payment_transactions
+             WHERE student_id = ?1
+               AND (enrollment_id IS NULL OR enrollment_id IN (SELECT id FROM enrollments WHERE is_primary = 1))
+             ORDER BY payment_date DESC LIMIT 1",
             [student_id],
             |row| row.get::<_, String>(0),
         );
@@ -620,78 +766,93 @@ impl PaymentService {
         db: &Database,
         payment_id: i32,
     ) -> DatabaseResult<bool> {
-        // Get the full payment transaction first for audit log
-        let payment_record = db.connection().query_row(
-            "SELECT id, student_id, amount, payment_date, payment_method, notes, created_at FROM payment_transactions WHERE id = ?1",
-            [payment_id],
-            |row| {
-                let payment_method_str: String = row.get(4)?;
-                let payment_method = PaymentMethod::from_str(&payment_method_str)
-                    .map_err(|_e| rusqlite::Error::InvalidColumnType(4, "payment_method".to_string(), rusqlite::types::Type::Text))?;
+        AuditService::audited(db, |audit| {
+            // Get the full payment transaction first for audit log
+            let payment_record = db.connection().query_row(
+                "SELECT id, student_id, amount, payment_date, payment_method, notes, created_at, enrollment_id FROM payment_transactions WHERE id = ?1",
+                [payment_id],
+                |row| {
+                    let payment_method_str: String = row.get(4)?;
+                    let payment_method = PaymentMethod::from_str(&payment_method_str)
+                        .map_err(|_e| rusqlite::Error::InvalidColumnType(4, "payment_method".to_string(), rusqlite::types::Type::Text))?;
                 
-                Ok(PaymentTransaction {
-                    id: row.get(0)?,
-                    student_id: row.get(1)?,
-                    amount: row.get(2)?,
-                    payment_date: row.get(3)?,
-                    payment_method,
-                    notes: row.get(5)?,
-                    created_at: row.get(6)?,
-                })
-            },
-        );
-
-        let payment_transaction = match payment_record {
-            Ok(transaction) => Some(transaction),
-            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
-            Err(e) => return Err(DatabaseError::Sqlite(e)),
-        };
-
-        let (student_id, amount) = if let Some(ref transaction) = payment_transaction {
-            (transaction.student_id.clone(), transaction.amount)
-        } else {
-            return Ok(false);
-        };
+                    Ok(PaymentTransaction {
+                        id: row.get(0)?,
+                        student_id: row.get(1)?,
+                        enrollment_id: row.get(7)?,
+                        amount: row.get(2)?,
+                        payment_date: row.get(3)?,
+                        payment_method,
+                        notes: row.get(5)?,
+                        created_at: row.get(6)?,
+                    })
+                },
+            );
 
-        // Start transaction
-        let tx = db.connection().unchecked_transaction()?;
+            let payment_transaction = match payment_record {
+                Ok(transaction) => Some(transaction),
+                Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(false),
+                Err(e) => return Err(DatabaseError::Sqlite(e)),
+            };
 
-        // Delete the payment transaction
-        let rows_affected = tx.execute(
-            "DELETE FROM payment_transactions WHERE id = ?1",
-            [payment_id],
-        )?;
+            let (student_id, amount, enrollment_id) = if let Some(ref transaction) = payment_transaction {
+                (transaction.student_id.clone(), transaction.amount, transaction.enrollment_id)
+            } else {
+                return Ok(false);
+            };
 
-        if rows_affected == 0 {
-            return Ok(false);
-        }
+            // Payments billed to a secondary enrollment only touch that enrollment's balance
+            let secondary_enrollment = match enrollment_id {
+                Some(id) => EnrollmentService::get_enrollment_by_id(db, id)?.filter(|e| !e.is_primary),
+                None => None,
+            };
 
-        // Update student's paid amount (subtract the deleted payment)
-        let student = StudentService::get_student_by_id(db, &student_id)?
-            .ok_or_else(|| DatabaseError::Migration(format!("Student with ID {} not found", student_id)))?;
+            // Delete the payment transaction
+            let rows_affected = db.connection().execute(
+                "DELETE FROM payment_transactions WHERE id = ?1",
+                [payment_id],
+            )?;
 
-        let new_paid_amount = (student.paid_amount - amount).max(0);
-        let now = Utc::now().to_rfc3339();
-        
-        tx.execute(
-            "UPDATE students SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
-            params![new_paid_amount, now, student_id],
-        )?;
+            if rows_affected == 0 {
+                return Ok(false);
+            }
 
-        // Commit transaction
-        tx.commit()?;
+            let now = Utc::now().to_rfc3339();
+
+            if let Some(ref enrollment) = secondary_enrollment {
+                // A balance carried over at term rollover can leave this below zero
+                let new_paid_amount = enrollment.paid_amount - amount;
+                db.connection().execute(
+                    "UPDATE enrollments SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![new_paid_amount, now, enrollment.id],
+                )?;
+            } else {
+                // Update student's paid amount (subtract the deleted payment)
+                let student = StudentService::get_student_by_id(db, &student_id)?
+                    .ok_or_else(|| DatabaseError::not_found("Student", &student_id))?;
+
+                let new_paid_amount = student.paid_amount - amount;
+
+                db.connection().execute(
+                    "UPDATE students SET paid_amount = ?1, updated_at = ?2 WHERE id = ?3",
+                    params![new_paid_amount, now, student_id],
+                )?;
+            }
 
-        // Log audit entry for payment deletion
-        if let Some(transaction) = payment_transaction {
-            if let Ok(serialized_data) = AuditService::serialize_data(&transaction) {
-                let _ = AuditService::log_delete(db, "payment_transactions", &payment_id.to_string(), &serialized_data, None);
+            if let Some(transaction) = payment_transaction {
+                audit.delete("payment_transactions", &payment_id.to_string(), &transaction, None);
             }
-        }
 
-        // Recalculate payment status for the student
-        Self::update_student_payment_status(db, &student_id)?;
+            if let Some(enrollment) = secondary_enrollment {
+                EnrollmentService::update_enrollment_payment_status(db, enrollment.id)?;
+            } else {
+                // Reallocate the schedule and recalculate payment status for the student
+                InstallmentService::allocate_payments(db, &student_id)?;
+                Self::update_student_payment_status(db, &student_id)?;
+            }
 
-        Ok(true)
+            Ok(true)
+        })
     }
 
 
@@ -700,11 +861,12 @@ impl PaymentService {
 
 
 
-    /// Get payment statistics for a specific date range
+    /// Get payment statistics for a specific date range and/or term
     pub fn get_payment_statistics(
         db: &Database,
         start_date: Option<&str>,
         end_date: Option<&str>,
+        term_id: Option<i32>,
     ) -> DatabaseResult<PaymentStatistics> {
         let mut query = "SELECT COUNT(*) as transaction_count, SUM(amount) as total_amount, AVG(amount) as average_amount FROM payment_transactions".to_string();
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -720,6 +882,12 @@ impl PaymentService {
             params.push(Box::new(end.to_string()));
         }
 
+        // Written against the id so it also applies to payment_groups_view below
+        if let Some(term_id) = term_id {
+            conditions.push("id IN (SELECT id FROM payment_transactions WHERE term_id = ?)".to_string());
+            params.push(Box::new(term_id));
+        }
+
         if !conditions.is_empty() {
             query.push_str(" WHERE ");
             query.push_str(&conditions.join(" AND "));
@@ -782,10 +950,30 @@ impl PaymentService {
             }
         }
 
+        // Revenue by the group each payment's student belonged to on the payment date
+        let mut group_query = "SELECT group_name, SUM(amount) FROM payment_groups_view".to_string();
+        if !conditions.is_empty() {
+            group_query.push_str(" WHERE ");
+            group_query.push_str(&conditions.join(" AND "));
+        }
+        group_query.push_str(" GROUP BY group_name");
+
+        let mut group_stmt = db.connection().prepare(&group_query)?;
+        let group_iter = group_stmt.query_map(&param_refs[..], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut revenue_by_group = HashMap::new();
+        for group_result in group_iter {
+            let (group_name, total) = group_result.map_err(DatabaseError::from)?;
+            revenue_by_group.insert(group_name, total);
+        }
+
         Ok(PaymentStatistics {
             transaction_count,
             total_amount,
             average_amount,
+            revenue_by_group,
             payment_method_breakdown: PaymentMethodBreakdown {
                 cash: PaymentMethodStats {
                     count: cash_count,
@@ -810,6 +998,7 @@ pub struct PaymentStatistics {
     pub total_amount: i64,
     pub average_amount: f64,
     pub payment_method_breakdown: PaymentMethodBreakdown,
+    pub revenue_by_group: HashMap<String, i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -855,3 +1044,149 @@ pub struct OverduePaymentInfo {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrollment_service::CreateEnrollmentRequest;
+    use crate::student_service::CreateStudentRequest;
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(db: &Database, name: &str) -> String {
+        let request = CreateStudentRequest {
+            name: name.to_string(),
+            group_name: "Math".to_string(),
+            payment_plan: PaymentPlan::OneTime,
+            plan_amount: 6000,
+            installment_count: None,
+            paid_amount: Some(0),
+            enrollment_date: Some("2024-01-01".to_string()),
+        };
+        StudentService::create_student(db, request).unwrap().id
+    }
+
+    fn enroll_in_physics(db: &Database, student_id: &str) -> i32 {
+        let request = CreateEnrollmentRequest {
+            student_id: student_id.to_string(),
+            group_name: "Physics".to_string(),
+            course: Some("Physics 101".to_string()),
+            start_date: Some("2024-02-01".to_string()),
+            end_date: None,
+            payment_plan: PaymentPlan::Monthly,
+            plan_amount: 850,
+            installment_count: None,
+            paid_amount: None,
+        };
+        EnrollmentService::create_enrollment(db, request)
+            .unwrap()
+            .id
+    }
+
+    fn payment(student_id: &str, enrollment_id: Option<i32>, amount: i32) -> RecordPaymentRequest {
+        RecordPaymentRequest {
+            student_id: student_id.to_string(),
+            enrollment_id,
+            amount,
+            payment_date: "2024-02-01".to_string(),
+            payment_method: PaymentMethod::Cash,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn test_record_and_delete_secondary_enrollment_payment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Two Course Student");
+        let physics_id = enroll_in_physics(&db, &student_id);
+
+        let transaction =
+            PaymentService::record_payment(&db, payment(&student_id, Some(physics_id), 850))
+                .unwrap();
+        assert_eq!(transaction.enrollment_id, Some(physics_id));
+
+        let physics = EnrollmentService::get_enrollment_by_id(&db, physics_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(physics.paid_amount, 850);
+        assert_ne!(physics.payment_status, PaymentStatus::Overdue);
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 0);
+
+        assert!(PaymentService::delete_payment(&db, transaction.id).unwrap());
+        let physics = EnrollmentService::get_enrollment_by_id(&db, physics_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(physics.paid_amount, 0);
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 0);
+    }
+
+    #[test]
+    fn test_record_and_delete_primary_enrollment_payment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "Two Course Student");
+        let physics_id = enroll_in_physics(&db, &student_id);
+        let primary_id = EnrollmentService::get_primary_enrollment_id(&db, &student_id).unwrap();
+
+        let transaction =
+            PaymentService::record_payment(&db, payment(&student_id, Some(primary_id), 6000))
+                .unwrap();
+        assert_eq!(transaction.enrollment_id, Some(primary_id));
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 6000);
+        assert_eq!(student.payment_status, PaymentStatus::Paid);
+
+        let physics = EnrollmentService::get_enrollment_by_id(&db, physics_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(physics.paid_amount, 0);
+
+        assert!(PaymentService::delete_payment(&db, transaction.id).unwrap());
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 0);
+        assert_ne!(student.payment_status, PaymentStatus::Paid);
+    }
+
+    #[test]
+    fn test_record_payment_rejects_other_students_enrollment() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db, "First Student");
+        let other_id = create_student(&db, "Second Student");
+        let physics_id = enroll_in_physics(&db, &other_id);
+
+        let result =
+            PaymentService::record_payment(&db, payment(&student_id, Some(physics_id), 850));
+        assert!(matches!(
+            result,
+            Err(PaymentError::DatabaseError(
+                DatabaseError::Validation { .. }
+            ))
+        ));
+
+        let physics = EnrollmentService::get_enrollment_by_id(&db, physics_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(physics.paid_amount, 0);
+        assert!(
+            PaymentService::get_student_payment_history(&db, &student_id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::database::{Database, DatabaseResult};
+use crate::database::{Database, DatabaseError, DatabaseResult};
 use crate::audit_service::AuditService;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
@@ -61,158 +61,157 @@ impl PaymentSettingsService {
     
     /// Create default payment settings
     fn create_default_settings(db: &Database) -> DatabaseResult<PaymentSettings> {
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        
-        db.connection().execute(
-            "INSERT INTO payment_settings (id, one_time_amount, monthly_amount, installment_amount, 
-                                         installment_interval_months, reminder_days, payment_threshold, updated_at) 
-             VALUES (1, 6000, 850, 2850, 3, 7, 6000, ?1)",
-            params![now],
-        )?;
-        
-        let settings = PaymentSettings {
-            id: 1,
-            one_time_amount: 6000,
-            monthly_amount: 850,
-            installment_amount: 2850,
-            installment_interval_months: 3,
-            reminder_days: 7,
-            payment_threshold: 6000,
-            updated_at: now.clone(),
-        };
-        
-        // Create audit log entry
-        let new_values = serde_json::json!({
-            "id": 1,
-            "one_time_amount": 6000,
-            "monthly_amount": 850,
-            "installment_amount": 2850,
-            "installment_interval_months": 3,
-            "reminder_days": 7,
-            "payment_threshold": 6000,
-            "updated_at": now
-        });
-        
-        AuditService::log_action(
-            db,
-            "CREATE",
-            "payment_settings",
-            "1",
-            None,
-            Some(&new_values.to_string()),
-            Some("Created default payment settings"),
-        )?;
-        
-        Ok(settings)
+        AuditService::audited(db, |audit| {
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        
+            db.connection().execute(
+                "INSERT INTO payment_settings (id, one_time_amount, monthly_amount, installment_amount, 
+                                             installment_interval_months, reminder_days, payment_threshold, updated_at) 
+                 VALUES (1, 6000, 850, 2850, 3, 7, 6000, ?1)",
+                params![now],
+            )?;
+        
+            let settings = PaymentSettings {
+                id: 1,
+                one_time_amount: 6000,
+                monthly_amount: 850,
+                installment_amount: 2850,
+                installment_interval_months: 3,
+                reminder_days: 7,
+                payment_threshold: 6000,
+                updated_at: now.clone(),
+            };
+        
+            // Create audit log entry
+            let new_values = serde_json::json!({
+                "id": 1,
+                "one_time_amount": 6000,
+                "monthly_amount": 850,
+                "installment_amount": 2850,
+                "installment_interval_months": 3,
+                "reminder_days": 7,
+                "payment_threshold": 6000,
+                "updated_at": now
+            });
+        
+            audit.record(
+                "CREATE",
+                "payment_settings",
+                "1",
+                None,
+                Some(new_values.to_string()),
+                Some("Created default payment settings"),
+            );
+        
+            Ok(settings)
+        })
     }
     
     /// Update payment settings
     pub fn update_payment_settings(db: &Database, request: UpdatePaymentSettingsRequest) -> DatabaseResult<()> {
-        // Validate the settings
-        Self::validate_payment_settings(&request)?;
-        
-        // Get existing settings for audit logging
-        let existing_settings = Self::get_payment_settings(db)?;
-        
-        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        
-        // Update the settings
-        let rows_affected = db.connection().execute(
-            "UPDATE payment_settings 
-             SET one_time_amount = ?1, monthly_amount = ?2, installment_amount = ?3,
-                 installment_interval_months = ?4, reminder_days = ?5, payment_threshold = ?6,
-                 updated_at = ?7
-             WHERE id = 1",
-            params![
-                request.one_time_amount,
-                request.monthly_amount,
-                request.installment_amount,
-                request.installment_interval_months,
-                request.reminder_days,
-                request.payment_threshold,
-                now
-            ],
-        )?;
-        
-        if rows_affected == 0 {
-            return Err(crate::database::DatabaseError::Migration(
-                "Payment settings not found".to_string()
-            ));
-        }
-        
-        // Create audit log entry
-        let old_values = serde_json::json!({
-            "id": existing_settings.id,
-            "one_time_amount": existing_settings.one_time_amount,
-            "monthly_amount": existing_settings.monthly_amount,
-            "installment_amount": existing_settings.installment_amount,
-            "installment_interval_months": existing_settings.installment_interval_months,
-            "reminder_days": existing_settings.reminder_days,
-            "payment_threshold": existing_settings.payment_threshold,
-            "updated_at": existing_settings.updated_at
-        });
-        
-        let new_values = serde_json::json!({
-            "id": 1,
-            "one_time_amount": request.one_time_amount,
-            "monthly_amount": request.monthly_amount,
-            "installment_amount": request.installment_amount,
-            "installment_interval_months": request.installment_interval_months,
-            "reminder_days": request.reminder_days,
-            "payment_threshold": request.payment_threshold,
-            "updated_at": now
-        });
-        
-        AuditService::log_action(
-            db,
-            "UPDATE",
-            "payment_settings",
-            "1",
-            Some(&old_values.to_string()),
-            Some(&new_values.to_string()),
-            Some("Updated payment settings"),
-        )?;
+        AuditService::audited(db, |audit| {
+            // Validate the settings
+            Self::validate_payment_settings(&request)?;
+        
+            // Get existing settings for audit logging
+            let existing_settings = Self::get_payment_settings(db)?;
+        
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        
+            // Update the settings
+            let rows_affected = db.connection().execute(
+                "UPDATE payment_settings 
+                 SET one_time_amount = ?1, monthly_amount = ?2, installment_amount = ?3,
+                     installment_interval_months = ?4, reminder_days = ?5, payment_threshold = ?6,
+                     updated_at = ?7
+                 WHERE id = 1",
+                params![
+                    request.one_time_amount,
+                    request.monthly_amount,
+                    request.installment_amount,
+                    request.installment_interval_months,
+                    request.reminder_days,
+                    request.payment_threshold,
+                    now
+                ],
+            )?;
+        
+            if rows_affected == 0 {
+                return Err(DatabaseError::not_found("Payment settings", 1));
+            }
         
-        Ok(())
+            // Create audit log entry
+            let old_values = serde_json::json!({
+                "id": existing_settings.id,
+                "one_time_amount": existing_settings.one_time_amount,
+                "monthly_amount": existing_settings.monthly_amount,
+                "installment_amount": existing_settings.installment_amount,
+                "installment_interval_months": existing_settings.installment_interval_months,
+                "reminder_days": existing_settings.reminder_days,
+                "payment_threshold": existing_settings.payment_threshold,
+                "updated_at": existing_settings.updated_at
+            });
+        
+            let new_values = serde_json::json!({
+                "id": 1,
+                "one_time_amount": request.one_time_amount,
+                "monthly_amount": request.monthly_amount,
+                "installment_amount": request.installment_amount,
+                "installment_interval_months": request.installment_interval_months,
+                "reminder_days": request.reminder_days,
+                "payment_threshold": request.payment_threshold,
+                "updated_at": now
+            });
+        
+            audit.update(
+                "payment_settings",
+                "1",
+                &old_values,
+                &new_values,
+                Some("Updated payment settings"),
+            );
+        
+            Ok(())
+        })
     }
     
     /// Validate payment settings
     pub fn validate_payment_settings(request: &UpdatePaymentSettingsRequest) -> DatabaseResult<()> {
         // Validate amounts are positive
         if request.one_time_amount <= 0 {
-            return Err(crate::database::DatabaseError::Migration(
-                "One-time amount must be greater than 0".to_string()
+            return Err(DatabaseError::validation(
+                "one_time_amount", "One-time amount must be greater than 0"
             ));
         }
         
         if request.monthly_amount <= 0 {
-            return Err(crate::database::DatabaseError::Migration(
-                "Monthly amount must be greater than 0".to_string()
+            return Err(DatabaseError::validation(
+                "monthly_amount", "Monthly amount must be greater than 0"
             ));
         }
         
         if request.installment_amount <= 0 {
-            return Err(crate::database::DatabaseError::Migration(
-                "Installment amount must be greater than 0".to_string()
+            return Err(DatabaseError::validation(
+                "installment_amount", "Installment amount must be greater than 0"
             ));
         }
         
         if request.payment_threshold <= 0 {
-            return Err(crate::database::DatabaseError::Migration(
-                "Payment threshold must be greater than 0".to_string()
+            return Err(DatabaseError::validation(
+                "payment_threshold", "Payment threshold must be greater than 0"
             ));
         }
         
         // Validate intervals are reasonable
         if request.installment_interval_months < 1 || request.installment_interval_months > 12 {
-            return Err(crate::database::DatabaseError::Migration(
-                "Installment interval must be between 1 and 12 months".to_string()
+            return Err(DatabaseError::validation(
+                "installment_interval_months", "Installment interval must be between 1 and 12 months"
             ));
         }
         
         if request.reminder_days < 0 || request.reminder_days > 365 {
-            return Err(crate::database::DatabaseError::Migration(
-                "Reminder days must be between 0 and 365".to_string()
+            return Err(DatabaseError::validation(
+                "reminder_days", "Reminder days must be between 0 and 365"
             ));
         }
         
@@ -220,26 +219,26 @@ impl PaymentSettingsService {
         const MAX_AMOUNT: i32 = 1_000_000; // 1 million EGP max
         
         if request.one_time_amount > MAX_AMOUNT {
-            return Err(crate::database::DatabaseError::Migration(
-                format!("One-time amount cannot exceed {} EGP", MAX_AMOUNT)
+            return Err(DatabaseError::validation(
+                "one_time_amount", format!("One-time amount cannot exceed {} EGP", MAX_AMOUNT)
             ));
         }
         
         if request.monthly_amount > MAX_AMOUNT {
-            return Err(crate::database::DatabaseError::Migration(
-                format!("Monthly amount cannot exceed {} EGP", MAX_AMOUNT)
+            return Err(DatabaseError::validation(
+                "monthly_amount", format!("Monthly amount cannot exceed {} EGP", MAX_AMOUNT)
             ));
         }
         
         if request.installment_amount > MAX_AMOUNT {
-            return Err(crate::database::DatabaseError::Migration(
-                format!("Installment amount cannot exceed {} EGP", MAX_AMOUNT)
+            return Err(DatabaseError::validation(
+                "installment_amount", format!("Installment amount cannot exceed {} EGP", MAX_AMOUNT)
             ));
         }
         
         if request.payment_threshold > MAX_AMOUNT {
-            return Err(crate::database::DatabaseError::Migration(
-                format!("Payment threshold cannot exceed {} EGP", MAX_AMOUNT)
+            return Err(DatabaseError::validation(
+                "payment_threshold", format!("Payment threshold cannot exceed {} EGP", MAX_AMOUNT)
             ));
         }
         
@@ -328,8 +327,8 @@ impl PaymentSettingsService {
             "one-time" => Ok(settings.one_time_amount),
             "monthly" => Ok(settings.monthly_amount),
             "installment" => Ok(settings.installment_amount),
-            _ => Err(crate::database::DatabaseError::Migration(
-                format!("Unknown payment plan type: {}", plan_type)
+            _ => Err(DatabaseError::validation(
+                "plan_type", format!("Unknown payment plan type: {}", plan_type)
             )),
         }
     }
@@ -387,8 +386,8 @@ impl PaymentSettingsService {
                 reminder_days: current_settings.reminder_days,
                 payment_threshold: value,
             },
-            _ => return Err(crate::database::DatabaseError::Migration(
-                format!("Unknown setting name: {}", setting_name)
+            _ => return Err(DatabaseError::validation(
+                "setting_name", format!("Unknown setting name: {}", setting_name)
             )),
         };
         
@@ -1,4 +1,5 @@
 use crate::database::Database;
+use crate::i18n::{self, Locale};
 use base64::{engine::general_purpose, Engine as _};
 use image::{ImageBuffer, Luma};
 use qrcode::QrCode;
@@ -195,14 +196,19 @@ impl QRService {
         qr_codes: &[QRCodeData],
         file_path: &str,
         title: Option<&str>,
+        locale: Locale,
     ) -> Result<(), QRServiceError> {
+        let default_title = i18n::t(locale, "qr.title");
         let mut content = String::new();
-        content.push_str(&format!("{}\n\n", title.unwrap_or("Student QR Codes")));
+        content.push_str(&format!("{}\n\n", title.unwrap_or(&default_title)));
 
+        let student = i18n::t(locale, "qr.student");
+        let id = i18n::t(locale, "qr.id");
+        let group = i18n::t(locale, "qr.group");
         for qr_code in qr_codes {
             content.push_str(&format!(
-                "Student: {}\nID: {}\nGroup: {}\n\n",
-                qr_code.student_name, qr_code.student_id, qr_code.group_name
+                "{}: {}\n{}: {}\n{}: {}\n\n",
+                student, qr_code.student_name, id, qr_code.student_id, group, qr_code.group_name
             ));
         }
 
@@ -215,6 +221,7 @@ impl QRService {
         db: &Database,
         file_path: &str,
         group_name: Option<&str>,
+        locale: Locale,
     ) -> Result<(), QRServiceError> {
         let qr_codes = if let Some(group) = group_name {
             let batch = Self::generate_qr_codes_for_group(db, group)?;
@@ -224,12 +231,12 @@ impl QRService {
         };
 
         let title = if let Some(group) = group_name {
-            format!("QR Codes - {}", group)
+            i18n::t(locale, "qr.title_group").replace("{group}", group)
         } else {
-            "All Student QR Codes".to_string()
+            i18n::t(locale, "qr.title_all")
         };
 
-        Self::export_qr_codes_to_pdf(&qr_codes, file_path, Some(&title))
+        Self::export_qr_codes_to_pdf(&qr_codes, file_path, Some(&title), locale)
     }
 
     /// Validate QR code by attempting to decode it
@@ -272,6 +279,7 @@ pub struct QRCodeStatistics {
 mod tests {
     use super::*;
     use crate::database::Database;
+    use crate::i18n::{self, Locale};
     use tempfile::NamedTempFile;
 
     fn setup_test_db() -> Database {
@@ -480,12 +488,25 @@ mod tests {
             },
         ];
 
-        let result = QRService::export_qr_codes_to_pdf(&qr_codes, file_path, Some("Test QR Codes"));
+        let result = QRService::export_qr_codes_to_pdf(
+            &qr_codes,
+            file_path,
+            Some("Test QR Codes"),
+            Locale::Ar,
+        );
         assert!(result.is_ok());
 
         // Verify file was created and has content
         let metadata = std::fs::metadata(file_path).expect("PDF file should exist");
         assert!(metadata.len() > 0);
+
+        let content = std::fs::read_to_string(file_path).unwrap();
+        assert!(content.contains("الطالب: Test Student 1"));
+
+        QRService::export_qr_codes_to_pdf(&qr_codes, file_path, None, Locale::En).unwrap();
+        let content = std::fs::read_to_string(file_path).unwrap();
+        assert!(content.starts_with("Student QR Codes"));
+        assert!(content.contains("Student: Test Student 1"));
     }
 
     #[test]
@@ -0,0 +1,424 @@
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::installment_service::InstallmentService;
+use crate::student_service::{PaymentPlan, PaymentPlanConfig, Student, StudentService};
+use crate::term_service::TermService;
+use chrono::{Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Upper bound, in days past due, of each bucket but the last
+const BUCKET_LIMITS: [i64; 3] = [30, 60, 90];
+
+/// Outstanding amounts by how long they have been due
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgingBuckets {
+    pub days_0_30: i64,
+    pub days_31_60: i64,
+    pub days_61_90: i64,
+    pub days_over_90: i64,
+    pub total: i64,
+}
+
+impl AgingBuckets {
+    pub fn add(&mut self, days_past_due: i64, amount: i64) {
+        let bucket = if days_past_due <= BUCKET_LIMITS[0] {
+            &mut self.days_0_30
+        } else if days_past_due <= BUCKET_LIMITS[1] {
+            &mut self.days_31_60
+        } else if days_past_due <= BUCKET_LIMITS[2] {
+            &mut self.days_61_90
+        } else {
+            &mut self.days_over_90
+        };
+        *bucket += amount;
+        self.total += amount;
+    }
+
+    pub fn merge(&mut self, other: &AgingBuckets) {
+        self.days_0_30 += other.days_0_30;
+        self.days_31_60 += other.days_31_60;
+        self.days_61_90 += other.days_61_90;
+        self.days_over_90 += other.days_over_90;
+        self.total += other.total;
+    }
+
+    /// Amounts in column order: the four buckets, then the total
+    pub fn values(&self) -> [i64; 5] {
+        [
+            self.days_0_30,
+            self.days_31_60,
+            self.days_61_90,
+            self.days_over_90,
+            self.total,
+        ]
+    }
+}
+
+/// The unpaid part of one due date in a student's plan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutstandingDue {
+    pub due_date: String,
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingItem {
+    pub due_date: String,
+    pub days_past_due: i64,
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentAging {
+    pub student_id: String,
+    pub student_name: String,
+    pub group_name: String,
+    pub payment_plan: PaymentPlan,
+    pub buckets: AgingBuckets,
+    pub oldest_due_date: Option<String>,
+    /// Each unpaid due date, oldest first
+    pub items: Vec<AgingItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupAging {
+    pub group_name: String,
+    pub student_count: i32,
+    pub buckets: AgingBuckets,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingReport {
+    pub as_of: String,
+    pub group_name: Option<String>,
+    pub groups: Vec<GroupAging>,
+    /// Students who owe something, by group and then largest total first
+    pub students: Vec<StudentAging>,
+    pub totals: AgingBuckets,
+}
+
+pub struct ReceivablesService;
+
+impl ReceivablesService {
+    fn parse_date(field: &'static str, value: &str) -> DatabaseResult<NaiveDate> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+            DatabaseError::validation(
+                field,
+                format!(
+                    "Invalid date format '{}'. Expected format: YYYY-MM-DD",
+                    value
+                ),
+            )
+        })
+    }
+
+    /// Every date a student's plan falls due on up to `until`, with the amount due then.
+    /// Installment schedules are used as stored; monthly plans fall due on the enrollment day
+    /// of each month and one-time plans on their due date.
+    pub(crate) fn plan_dues(
+        db: &Database,
+        student: &Student,
+        config: &PaymentPlanConfig,
+        until: NaiveDate,
+    ) -> DatabaseResult<Vec<(NaiveDate, i64)>> {
+        let enrollment = Self::parse_date("enrollment_date", &student.enrollment_date)?;
+
+        let dues = match student.payment_plan {
+            PaymentPlan::OneTime => {
+                let due_date = match &student.next_due_date {
+                    Some(date) => Self::parse_date("due_date", date)?,
+                    None => enrollment,
+                };
+                vec![(due_date, student.plan_amount as i64)]
+            }
+            PaymentPlan::Monthly => (0..)
+                .map_while(|month| enrollment.checked_add_months(Months::new(month)))
+                .take_while(|due_date| *due_date <= until)
+                .map(|due_date| (due_date, student.plan_amount as i64))
+                .collect(),
+            PaymentPlan::Installment => {
+                let installments = InstallmentService::get_installments(db, &student.id)?;
+                let schedule: Vec<(String, i32)> = if installments.is_empty() {
+                    InstallmentService::build_default_installments(
+                        &student.enrollment_date,
+                        student.plan_amount,
+                        student.installment_count,
+                        config.installment_interval,
+                    )?
+                    .into_iter()
+                    .map(|i| (i.due_date, i.amount_due))
+                    .collect()
+                } else {
+                    installments
+                        .into_iter()
+                        .map(|i| (i.due_date, i.amount_due))
+                        .collect()
+                };
+                schedule
+                    .into_iter()
+                    .map(|(due_date, amount)| {
+                        Ok((Self::parse_date("due_date", &due_date)?, amount as i64))
+                    })
+                    .collect::<DatabaseResult<Vec<_>>>()?
+            }
+        };
+
+        Ok(dues
+            .into_iter()
+            .filter(|(due_date, _)| *due_date <= until)
+            .collect())
+    }
+
+    /// What is still unpaid of each due date up to `until`, with the student's paid amount
+    /// spread over the oldest dues first. A balance owed from earlier terms is due from the
+    /// start of the term it was carried into; payments made before that went to earlier terms.
+    pub fn outstanding_dues(
+        db: &Database,
+        student: &Student,
+        config: &PaymentPlanConfig,
+        until: NaiveDate,
+    ) -> DatabaseResult<Vec<OutstandingDue>> {
+        let mut paid = student.paid_amount as i64;
+        let mut dues = Vec::new();
+        if let Some(carried) = TermService::get_carried_balance(db, &student.id, None)? {
+            paid -= carried.paid_before;
+            if carried.amount > 0 {
+                let carried_on = Self::parse_date("carried_on", &carried.carried_on)?;
+                if carried_on <= until {
+                    dues.push((carried_on, carried.amount));
+                }
+            } else {
+                paid -= carried.amount;
+            }
+        }
+        dues.extend(Self::plan_dues(db, student, config, until)?);
+
+        let mut remaining_paid = paid.max(0);
+        let mut outstanding = Vec::new();
+        for (due_date, amount) in dues {
+            let covered = remaining_paid.min(amount);
+            remaining_paid -= covered;
+            if amount > covered {
+                outstanding.push(OutstandingDue {
+                    due_date: due_date.format("%Y-%m-%d").to_string(),
+                    amount: amount - covered,
+                });
+            }
+        }
+
+        Ok(outstanding)
+    }
+
+    /// Outstanding amounts on `as_of` by how many days each due date has passed, per student,
+    /// per group and in total. Amounts falling due on `as_of` count as 0 days past due.
+    pub fn get_aging_report(
+        db: &Database,
+        as_of: &str,
+        group_name: Option<&str>,
+    ) -> DatabaseResult<AgingReport> {
+        let as_of_date = Self::parse_date("as_of", as_of)?;
+        let config = StudentService::get_payment_plan_config(db)?;
+
+        let students = match group_name {
+            Some(group) => StudentService::get_students_by_group(db, group)?,
+            None => StudentService::get_all_students(db)?,
+        };
+
+        let mut student_rows = Vec::new();
+        for student in students {
+            let dues = Self::outstanding_dues(db, &student, &config, as_of_date)?;
+            if dues.is_empty() {
+                continue;
+            }
+
+            let mut buckets = AgingBuckets::default();
+            let items: Vec<AgingItem> = dues
+                .into_iter()
+                .map(|due| {
+                    let days_past_due = NaiveDate::parse_from_str(&due.due_date, "%Y-%m-%d")
+                        .map_or(0, |date| (as_of_date - date).num_days());
+                    buckets.add(days_past_due, due.amount);
+                    AgingItem {
+                        due_date: due.due_date,
+                        days_past_due,
+                        amount: due.amount,
+                    }
+                })
+                .collect();
+
+            student_rows.push(StudentAging {
+                oldest_due_date: items.first().map(|item| item.due_date.clone()),
+                student_id: student.id,
+                student_name: student.name,
+                group_name: student.group_name,
+                payment_plan: student.payment_plan,
+                buckets,
+                items,
+            });
+        }
+
+        student_rows.sort_by(|a, b| {
+            a.group_name
+                .cmp(&b.group_name)
+                .then(b.buckets.total.cmp(&a.buckets.total))
+                .then_with(|| a.student_name.cmp(&b.student_name))
+        });
+
+        let mut groups: BTreeMap<&str, GroupAging> = BTreeMap::new();
+        let mut totals = AgingBuckets::default();
+        for student in &student_rows {
+            let group = groups
+                .entry(student.group_name.as_str())
+                .or_insert_with(|| GroupAging {
+                    group_name: student.group_name.clone(),
+                    student_count: 0,
+                    buckets: AgingBuckets::default(),
+                });
+            group.student_count += 1;
+            group.buckets.merge(&student.buckets);
+            totals.merge(&student.buckets);
+        }
+        let groups = groups.into_values().collect();
+
+        Ok(AgingReport {
+            as_of: as_of.to_string(),
+            group_name: group_name.map(str::to_string),
+            groups,
+            students: student_rows,
+            totals,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::student_service::CreateStudentRequest;
+    use crate::term_service::{CreateTermRequest, RolloverRequest, TermService};
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn setup_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(
+        db: &Database,
+        name: &str,
+        group: &str,
+        payment_plan: PaymentPlan,
+        plan_amount: i32,
+        paid_amount: i32,
+    ) -> Student {
+        StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: name.to_string(),
+                group_name: group.to_string(),
+                payment_plan,
+                plan_amount,
+                installment_count: None,
+                paid_amount: Some(paid_amount),
+                enrollment_date: Some("2024-01-10".to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bucket_boundaries() {
+        let mut buckets = AgingBuckets::default();
+        for days in [0, 30, 31, 60, 61, 90, 91] {
+            buckets.add(days, 1);
+        }
+        assert_eq!(buckets.values(), [2, 2, 2, 1, 7]);
+    }
+
+    #[test]
+    fn test_aging_report_spreads_payments_over_oldest_dues() {
+        let (db, _temp_dir) = setup_test_db();
+        // Due on Jan 10, Feb 10, Mar 10 and Apr 10; 700 pays January and part of February
+        let monthly = create_student(&db, "Monthly", "Group A", PaymentPlan::Monthly, 500, 700);
+        create_student(&db, "One-time", "Group B", PaymentPlan::OneTime, 3000, 1000);
+        create_student(&db, "Paid up", "Group A", PaymentPlan::OneTime, 3000, 3000);
+
+        let report = ReceivablesService::get_aging_report(&db, "2024-04-15", None).unwrap();
+        assert_eq!(report.students.len(), 2);
+
+        let student = &report.students[0];
+        assert_eq!(student.student_id, monthly.id);
+        assert_eq!(student.oldest_due_date.as_deref(), Some("2024-02-10"));
+        let items: Vec<_> = student
+            .items
+            .iter()
+            .map(|i| (i.days_past_due, i.amount))
+            .collect();
+        assert_eq!(items, vec![(65, 300), (36, 500), (5, 500)]);
+        assert_eq!(student.buckets.values(), [500, 500, 300, 0, 1300]);
+
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].group_name, "Group A");
+        assert_eq!(report.groups[0].student_count, 1);
+        assert_eq!(report.groups[1].buckets.values(), [0, 0, 0, 2000, 2000]);
+        assert_eq!(report.totals.values(), [500, 500, 300, 2000, 3300]);
+
+        let group_b =
+            ReceivablesService::get_aging_report(&db, "2024-04-15", Some("Group B")).unwrap();
+        assert_eq!(group_b.students.len(), 1);
+        assert_eq!(group_b.totals.total, 2000);
+
+        assert!(ReceivablesService::get_aging_report(&db, "15/04/2024", None).is_err());
+    }
+
+    #[test]
+    fn test_aging_report_includes_balance_carried_at_rollover() {
+        let (db, _temp_dir) = setup_test_db();
+        let spring = TermService::create_term(
+            &db,
+            CreateTermRequest {
+                name: "Spring 2024".to_string(),
+                start_date: "2024-01-01".to_string(),
+                end_date: "2024-06-30".to_string(),
+            },
+        )
+        .unwrap();
+        let fall = TermService::create_term(
+            &db,
+            CreateTermRequest {
+                name: "Fall 2024".to_string(),
+                start_date: "2024-09-01".to_string(),
+                end_date: "2024-12-20".to_string(),
+            },
+        )
+        .unwrap();
+        let student = create_student(&db, "Owing", "Group A", PaymentPlan::OneTime, 3000, 1000);
+
+        TermService::rollover_term(
+            &db,
+            RolloverRequest {
+                from_term_id: spring.id,
+                to_term_id: fall.id,
+                group_mapping: HashMap::new(),
+                continuing_student_ids: None,
+            },
+        )
+        .unwrap();
+
+        // The 2000 left unpaid in spring is due from the start of fall, ahead of fall's own fee
+        let report = ReceivablesService::get_aging_report(&db, "2024-10-01", None).unwrap();
+        assert_eq!(report.students.len(), 1);
+        assert_eq!(report.students[0].student_id, student.id);
+        let items: Vec<_> = report.students[0]
+            .items
+            .iter()
+            .map(|i| (i.due_date.as_str(), i.amount))
+            .collect();
+        assert_eq!(items, vec![("2024-09-01", 2000), ("2024-09-01", 3000)]);
+        assert_eq!(report.totals.total, 5000);
+
+        let before = ReceivablesService::get_aging_report(&db, "2024-08-31", None).unwrap();
+        assert_eq!(before.totals.total, 0);
+    }
+}
@@ -0,0 +1,1176 @@
+use crate::attendance_service::ENROLLMENT_DAY_FILTER;
+use crate::audit_service::{AuditLogEntry, AuditService};
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::enrollment_service::EnrollmentService;
+use crate::groups_service::{Group, GroupsService};
+use crate::installment_service::InstallmentService;
+use crate::membership_service::MembershipService;
+use crate::payment_service::PaymentService;
+use crate::student_service::{PaymentPlan, StudentService};
+use chrono::Utc;
+use rusqlite::{params, params_from_iter, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+pub const REVERT_ACTION: &str = "REVERT";
+/// Field of a REVERT entry's new values naming the entry it undid
+pub const REVERTED_ENTRY_FIELD: &str = "reverted_entry_id";
+
+/// Columns restored from a snapshot; snapshot fields outside these are ignored
+const STUDENT_COLUMNS: &[&str] = &[
+    "id",
+    "name",
+    "group_name",
+    "group_id",
+    "payment_plan",
+    "plan_amount",
+    "installment_count",
+    "paid_amount",
+    "enrollment_date",
+    "next_due_date",
+    "payment_status",
+    "created_at",
+    "updated_at",
+];
+const PAYMENT_COLUMNS: &[&str] = &[
+    "id",
+    "student_id",
+    "enrollment_id",
+    "amount",
+    "payment_date",
+    "payment_method",
+    "notes",
+    "created_at",
+];
+const ATTENDANCE_COLUMNS: &[&str] = &[
+    "id",
+    "student_id",
+    "enrollment_id",
+    "date",
+    "created_at",
+    "status",
+];
+const GROUP_COLUMNS: &[&str] = &[
+    "id",
+    "name",
+    "capacity",
+    "room",
+    "level",
+    "subject",
+    "is_active",
+    "created_at",
+    "updated_at",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevertResult {
+    pub reverted_entry_id: i32,
+    /// The REVERT entry recording the undo; `None` while audit logging is disabled
+    pub revert_entry: Option<AuditLogEntry>,
+    /// Payments and attendance brought back along with a deleted student
+    pub related_restored: usize,
+}
+
+/// Derived state recalculated after the undo, before the revert is committed
+enum Refresh {
+    Student {
+        id: String,
+        regenerate_schedule: bool,
+    },
+    Enrollment(i32),
+    Waitlist(i32),
+}
+
+pub struct RevertService;
+
+impl RevertService {
+    /// Undo one audited create, update or delete of a student, payment, attendance mark or group,
+    /// or a student's transfer. Changes made to the record since must have been reverted first.
+    /// The undo, the balances, schedules and waitlists it affects and its REVERT entry are all
+    /// written in one transaction.
+    pub fn revert_audit_entry(db: &Database, entry_id: i32) -> DatabaseResult<RevertResult> {
+        let entry = AuditService::get_entry(db, entry_id)?
+            .ok_or_else(|| DatabaseError::not_found("Audit entry", entry_id))?;
+
+        let history = AuditService::get_record_history(db, &entry.table_name, &entry.record_id)?;
+        let reverted: HashSet<i64> = history
+            .iter()
+            .filter(|other| other.action_type == REVERT_ACTION)
+            .filter_map(|other| Self::values(other.new_values.as_deref(), "new").ok())
+            .filter_map(|values| Self::int(&values, REVERTED_ENTRY_FIELD))
+            .collect();
+        if reverted.contains(&(entry.id as i64)) {
+            return Err(DatabaseError::conflict(
+                "already_reverted",
+                format!("Audit entry {} has already been reverted", entry.id),
+            ));
+        }
+
+        // Reverted changes and the REVERT entries undoing them no longer stand in the way
+        let later = history
+            .into_iter()
+            .filter(|other| other.id > entry.id && other.action_type != REVERT_ACTION)
+            .filter(|other| !reverted.contains(&(other.id as i64)))
+            .min_by_key(|other| other.id);
+        if let Some(later) = later {
+            return Err(DatabaseError::conflict(
+                "changed_since",
+                format!(
+                    "Record {} in {} was changed after entry {} ({} in entry {} at {}). Revert the later change first",
+                    entry.record_id, entry.table_name, entry.id, later.action_type, later.id, later.timestamp
+                ),
+            ));
+        }
+
+        let old_values = || Self::values(entry.old_values.as_deref(), "previous");
+        let new_values = || Self::values(entry.new_values.as_deref(), "new");
+        let record_id = entry.record_id.as_str();
+
+        let related_restored = AuditService::audited(db, |audit| {
+            let mut related_restored = 0;
+            let refresh = match (entry.table_name.as_str(), entry.action_type.as_str()) {
+                ("students", "CREATE") => Self::remove_student(db, record_id)?,
+                ("students", "UPDATE") => {
+                    Self::restore_student_fields(db, record_id, &old_values()?, &new_values()?)?
+                }
+                ("students", "DELETE") => {
+                    let (refresh, restored) = Self::restore_student(db, record_id, old_values()?)?;
+                    related_restored = restored;
+                    refresh
+                }
+                ("students", "TRANSFER") => {
+                    Self::undo_transfer(db, record_id, &old_values()?, &new_values()?)?
+                }
+                ("payment_transactions", "CREATE") => Self::remove_payment(db, record_id)?,
+                ("payment_transactions", "DELETE") => {
+                    Self::restore_payment(db, record_id, old_values()?)?
+                }
+                ("attendance", "CREATE") => Self::remove_attendance(db, record_id)?,
+                ("attendance", "UPDATE") => {
+                    Self::restore_attendance_status(db, record_id, &old_values()?)?
+                }
+                ("attendance", "DELETE") => Self::restore_attendance(db, record_id, old_values()?)?,
+                ("groups", "CREATE") => Self::remove_group(db, record_id)?,
+                ("groups", "UPDATE") => Self::rename_group(db, record_id, &old_values()?)?,
+                ("groups", "DELETE") => Self::restore_group(db, record_id, &old_values()?)?,
+                (table_name, action_type) => {
+                    return Err(DatabaseError::validation(
+                        "entry_id",
+                        format!(
+                            "{} entries on {} cannot be reverted",
+                            action_type, table_name
+                        ),
+                    ))
+                }
+            };
+
+            for step in refresh {
+                Self::refresh(db, step)?;
+            }
+
+            // The REVERT entry is the inverse of the one undone, and names it among its new values
+            let mut restored = match entry.old_values.as_deref() {
+                Some(_) => old_values()?,
+                None => Map::new(),
+            };
+            restored.insert(REVERTED_ENTRY_FIELD.to_string(), Value::from(entry.id));
+            audit.record(
+                REVERT_ACTION,
+                &entry.table_name,
+                record_id,
+                entry.new_values.clone(),
+                Some(Value::Object(restored).to_string()),
+                None,
+            );
+
+            Ok(related_restored)
+        })?;
+
+        let revert_entry = AuditService::get_record_history(db, &entry.table_name, record_id)?
+            .into_iter()
+            .filter(|other| other.id > entry.id && other.action_type == REVERT_ACTION)
+            .max_by_key(|other| other.id);
+
+        log::info!(
+            "Reverted audit entry {} ({} on {} {})",
+            entry.id,
+            entry.action_type,
+            entry.table_name,
+            record_id
+        );
+
+        Ok(RevertResult {
+            reverted_entry_id: entry.id,
+            revert_entry,
+            related_restored,
+        })
+    }
+
+    fn values(values: Option<&str>, which: &str) -> DatabaseResult<Map<String, Value>> {
+        values
+            .and_then(|text| serde_json::from_str::<Value>(text).ok())
+            .and_then(|value| match value {
+                Value::Object(fields) => Some(fields),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                DatabaseError::validation(
+                    "entry_id",
+                    format!("The audit entry has no {} values to restore", which),
+                )
+            })
+    }
+
+    fn text(values: &Map<String, Value>, field: &str) -> Option<String> {
+        values
+            .get(field)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    }
+
+    fn int(values: &Map<String, Value>, field: &str) -> Option<i64> {
+        values.get(field).and_then(Value::as_i64)
+    }
+
+    fn sql_value(value: &Value) -> rusqlite::types::Value {
+        use rusqlite::types::Value as SqlValue;
+        match value {
+            Value::Null => SqlValue::Null,
+            Value::Bool(flag) => SqlValue::Integer(*flag as i64),
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => SqlValue::Integer(integer),
+                None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(text) => SqlValue::Text(text.clone()),
+            other => SqlValue::Text(other.to_string()),
+        }
+    }
+
+    /// Insert the snapshot fields that are columns of `table_name`, keeping the original ID
+    fn insert_row(
+        db: &Database,
+        table_name: &str,
+        columns: &[&str],
+        values: &Map<String, Value>,
+    ) -> DatabaseResult<()> {
+        let present: Vec<&str> = columns
+            .iter()
+            .copied()
+            .filter(|column| values.contains_key(*column))
+            .collect();
+        let placeholders: Vec<String> = (1..=present.len()).map(|i| format!("?{}", i)).collect();
+
+        db.connection().execute(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table_name,
+                present.join(", "),
+                placeholders.join(", ")
+            ),
+            params_from_iter(
+                present
+                    .iter()
+                    .map(|column| Self::sql_value(&values[*column])),
+            ),
+        )?;
+        Ok(())
+    }
+
+    fn row_exists(db: &Database, table_name: &str, id: &str) -> DatabaseResult<bool> {
+        let exists = db.connection().query_row(
+            &format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = ?1)", table_name),
+            [id],
+            |row| row.get(0),
+        )?;
+        Ok(exists)
+    }
+
+    fn missing(table_name: &str, id: &str) -> DatabaseError {
+        DatabaseError::conflict(
+            "record_missing",
+            format!("Record {} no longer exists in {}", id, table_name),
+        )
+    }
+
+    fn already_exists(table_name: &str, id: &str) -> DatabaseError {
+        DatabaseError::conflict(
+            "record_exists",
+            format!("Record {} already exists in {}", id, table_name),
+        )
+    }
+
+    /// The group a student snapshot belonged to, by ID or else by name
+    fn snapshot_group(db: &Database, values: &Map<String, Value>) -> DatabaseResult<Group> {
+        let group = match Self::int(values, "group_id") {
+            Some(id) => GroupsService::get_group_by_id(db, id as i32)?,
+            None => match Self::text(values, "group_name") {
+                Some(name) => GroupsService::get_group_by_name(db, &name)?,
+                None => None,
+            },
+        };
+        group.ok_or_else(|| {
+            DatabaseError::conflict(
+                "group_unavailable",
+                format!(
+                    "Group '{}' no longer exists. Restore it first",
+                    Self::text(values, "group_name").unwrap_or_default()
+                ),
+            )
+        })
+    }
+
+    fn require_student(db: &Database, student_id: &str) -> DatabaseResult<()> {
+        match StudentService::get_student_by_id(db, student_id)? {
+            Some(_) => Ok(()),
+            None => Err(DatabaseError::conflict(
+                "student_missing",
+                format!(
+                    "Student '{}' no longer exists. Reverting their deletion also restores their payments and attendance",
+                    student_id
+                ),
+            )),
+        }
+    }
+
+    /// The snapshot's enrollment when it still exists, otherwise the student's primary one
+    fn existing_enrollment(
+        db: &Database,
+        enrollment_id: Option<i64>,
+        student_id: &str,
+    ) -> DatabaseResult<i32> {
+        if let Some(id) = enrollment_id {
+            if EnrollmentService::get_enrollment_by_id(db, id as i32)?.is_some() {
+                return Ok(id as i32);
+            }
+        }
+        EnrollmentService::sync_primary_enrollment(db, student_id)
+    }
+
+    fn remove_student(db: &Database, student_id: &str) -> DatabaseResult<Vec<Refresh>> {
+        if StudentService::get_student_by_id(db, student_id)?.is_none() {
+            return Err(Self::missing("students", student_id));
+        }
+
+        let (payments, marks): (i64, i64) = db.connection().query_row(
+            "SELECT (SELECT COUNT(*) FROM payment_transactions WHERE student_id = ?1),
+                    (SELECT COUNT(*) FROM attendance WHERE student_id = ?1)",
+            [student_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if payments + marks > 0 {
+            return Err(DatabaseError::conflict(
+                "has_activity",
+                format!(
+                    "Student '{}' has {} payments and {} attendance marks recorded since being added",
+                    student_id, payments, marks
+                ),
+            ));
+        }
+
+        let group_ids: Vec<i32> = {
+            let mut stmt = db.connection().prepare(
+                "SELECT group_id FROM students WHERE id = ?1 AND group_id IS NOT NULL
+                 UNION
+                 SELECT group_id FROM enrollments WHERE student_id = ?1 AND status = 'active' AND group_id IS NOT NULL",
+            )?;
+            let ids = stmt
+                .query_map([student_id], |row| row.get(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            ids
+        };
+
+        db.connection()
+            .execute("DELETE FROM students WHERE id = ?1", [student_id])?;
+
+        Ok(group_ids.into_iter().map(Refresh::Waitlist).collect())
+    }
+
+    /// Put back the fields an update changed. Paid amounts follow the payment records, so only
+    /// a change the update made to the paid amount by hand is undone.
+    fn restore_student_fields(
+        db: &Database,
+        student_id: &str,
+        old: &Map<String, Value>,
+        new: &Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        let current = StudentService::get_student_by_id(db, student_id)?
+            .ok_or_else(|| Self::missing("students", student_id))?;
+        let group = Self::snapshot_group(db, old)?;
+
+        let payment_plan = match Self::text(old, "payment_plan") {
+            Some(plan) => PaymentPlan::from_str(&plan)
+                .map_err(|e| DatabaseError::validation("payment_plan", e))?,
+            None => current.payment_plan.clone(),
+        };
+        let plan_amount = Self::int(old, "plan_amount").map_or(current.plan_amount, |v| v as i32);
+        let installment_count = Self::int(old, "installment_count").map(|v| v as i32);
+        let enrollment_date =
+            Self::text(old, "enrollment_date").unwrap_or_else(|| current.enrollment_date.clone());
+        let paid_change = Self::int(old, "paid_amount").unwrap_or_default()
+            - Self::int(new, "paid_amount").unwrap_or_default();
+
+        let now = Utc::now().to_rfc3339();
+        db.connection().execute(
+            "UPDATE students
+             SET name = COALESCE(?1, name), group_name = ?2, group_id = ?3, payment_plan = ?4, plan_amount = ?5,
+                 installment_count = ?6, enrollment_date = ?7, paid_amount = paid_amount + ?8, updated_at = ?9
+             WHERE id = ?10",
+            params![
+                Self::text(old, "name"),
+                group.name,
+                group.id,
+                payment_plan.as_str(),
+                plan_amount,
+                installment_count,
+                enrollment_date,
+                paid_change,
+                now,
+                student_id
+            ],
+        )?;
+
+        let mut refresh = vec![Refresh::Student {
+            id: student_id.to_string(),
+            regenerate_schedule: current.payment_plan != payment_plan
+                || current.enrollment_date != enrollment_date
+                || current.plan_amount != plan_amount
+                || current.installment_count != installment_count,
+        }];
+
+        if current.group_id != Some(group.id) {
+            let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+            let effective_date = std::cmp::max(today, enrollment_date);
+            MembershipService::record_group_change(db, student_id, &group, &effective_date, None)?;
+            refresh.extend(current.group_id.map(Refresh::Waitlist));
+        }
+
+        Ok(refresh)
+    }
+
+    /// Move a student back to the group a transfer took them from. The membership the transfer
+    /// opened is removed and the one it closed is reopened, so the group history reads as if
+    /// the transfer never happened.
+    fn undo_transfer(
+        db: &Database,
+        student_id: &str,
+        old: &Map<String, Value>,
+        new: &Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        let current = StudentService::get_student_by_id(db, student_id)?
+            .ok_or_else(|| Self::missing("students", student_id))?;
+        let group = Self::snapshot_group(db, old)?;
+        GroupsService::ensure_seat_available(db, &group)?;
+
+        let effective_date = Self::text(new, "effective_date").unwrap_or_default();
+        let mut history = MembershipService::get_student_group_history(db, student_id)?;
+        let opened = history.pop().filter(|latest| {
+            latest.start_date == effective_date && latest.group_id == current.group_id
+        });
+        if let Some(opened) = opened {
+            match history.pop() {
+                Some(closed) if closed.group_id == Some(group.id) && closed.end_date.is_some() => {
+                    db.connection()
+                        .execute("DELETE FROM group_memberships WHERE id = ?1", [opened.id])?;
+                    db.connection().execute(
+                        "UPDATE group_memberships SET end_date = NULL WHERE id = ?1",
+                        [closed.id],
+                    )?;
+                }
+                // A transfer on the day a membership started changed that membership's group
+                _ => {
+                    db.connection().execute(
+                        "UPDATE group_memberships SET group_id = ?1, group_name = ?2, reason = NULL WHERE id = ?3",
+                        params![group.id, group.name, opened.id],
+                    )?;
+                }
+            }
+        }
+
+        let now = Utc::now().to_rfc3339();
+        db.connection().execute(
+            "UPDATE students SET group_name = ?1, group_id = ?2, updated_at = ?3 WHERE id = ?4",
+            params![group.name, group.id, now, student_id],
+        )?;
+
+        let mut refresh = vec![Refresh::Student {
+            id: student_id.to_string(),
+            regenerate_schedule: false,
+        }];
+        refresh.extend(current.group_id.map(Refresh::Waitlist));
+        Ok(refresh)
+    }
+
+    /// Re-insert a deleted student, then the payments and attendance deleted along with them
+    /// that the audit log still shows
+    fn restore_student(
+        db: &Database,
+        student_id: &str,
+        mut old: Map<String, Value>,
+    ) -> DatabaseResult<(Vec<Refresh>, usize)> {
+        if Self::row_exists(db, "students", student_id)? {
+            return Err(Self::already_exists("students", student_id));
+        }
+
+        let group = Self::snapshot_group(db, &old)?;
+        old.insert("group_name".to_string(), Value::from(group.name));
+        old.insert("group_id".to_string(), Value::from(group.id));
+        Self::insert_row(db, "students", STUDENT_COLUMNS, &old)?;
+
+        let primary_id = EnrollmentService::sync_primary_enrollment(db, student_id)?;
+        let restored = Self::restore_related(db, student_id, primary_id)?;
+
+        Ok((
+            vec![Refresh::Student {
+                id: student_id.to_string(),
+                regenerate_schedule: true,
+            }],
+            restored,
+        ))
+    }
+
+    fn restore_related(db: &Database, student_id: &str, primary_id: i32) -> DatabaseResult<usize> {
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut restored = 0;
+
+        for (table_name, columns) in [
+            ("payment_transactions", PAYMENT_COLUMNS),
+            ("attendance", ATTENDANCE_COLUMNS),
+        ] {
+            let record_ids: Vec<String> = {
+                let mut stmt = db.connection().prepare(
+                    "SELECT record_id FROM audit_log
+                     WHERE table_name = ?1
+                       AND json_valid(COALESCE(new_values, old_values))
+                       AND json_extract(COALESCE(new_values, old_values), '$.student_id') = ?2
+                     GROUP BY record_id
+                     ORDER BY MIN(id)",
+                )?;
+                let ids = stmt
+                    .query_map(params![table_name, student_id], |row| row.get(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                ids
+            };
+
+            for record_id in record_ids {
+                let snapshot = AuditService::reconstruct_record(db, table_name, &record_id, &now)?;
+                let Some(Value::Object(mut values)) = snapshot.values else {
+                    continue;
+                };
+                if Self::row_exists(db, table_name, &record_id)? {
+                    continue;
+                }
+
+                // Secondary enrollments went with the student; their rows move to the primary one
+                let enrollment_id = match Self::int(&values, "enrollment_id") {
+                    Some(id)
+                        if EnrollmentService::get_enrollment_by_id(db, id as i32)?.is_some() =>
+                    {
+                        id as i32
+                    }
+                    _ => primary_id,
+                };
+                values.insert("enrollment_id".to_string(), Value::from(enrollment_id));
+                Self::insert_row(db, table_name, columns, &values)?;
+                restored += 1;
+            }
+        }
+
+        Ok(restored)
+    }
+
+    /// Move `amount` into or out of the balance a payment was billed to
+    fn adjust_paid_amount(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: Option<i32>,
+        amount: i32,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        let secondary = match enrollment_id {
+            Some(id) => EnrollmentService::get_enrollment_by_id(db, id)?.filter(|e| !e.is_primary),
+            None => None,
+        };
+        let now = Utc::now().to_rfc3339();
+
+        match secondary {
+            Some(enrollment) => {
+                db.connection().execute(
+                    "UPDATE enrollments SET paid_amount = paid_amount + ?1, updated_at = ?2 WHERE id = ?3",
+                    params![amount, now, enrollment.id],
+                )?;
+                Ok(vec![Refresh::Enrollment(enrollment.id)])
+            }
+            None => {
+                db.connection().execute(
+                    "UPDATE students SET paid_amount = paid_amount + ?1, updated_at = ?2 WHERE id = ?3",
+                    params![amount, now, student_id],
+                )?;
+                Ok(vec![Refresh::Student {
+                    id: student_id.to_string(),
+                    regenerate_schedule: false,
+                }])
+            }
+        }
+    }
+
+    fn remove_payment(db: &Database, payment_id: &str) -> DatabaseResult<Vec<Refresh>> {
+        let payment: Option<(String, Option<i32>, i32)> = db
+            .connection()
+            .query_row(
+                "SELECT student_id, enrollment_id, amount FROM payment_transactions WHERE id = ?1",
+                [payment_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let (student_id, enrollment_id, amount) =
+            payment.ok_or_else(|| Self::missing("payment_transactions", payment_id))?;
+
+        db.connection().execute(
+            "DELETE FROM payment_transactions WHERE id = ?1",
+            [payment_id],
+        )?;
+
+        Self::adjust_paid_amount(db, &student_id, enrollment_id, -amount)
+    }
+
+    fn restore_payment(
+        db: &Database,
+        payment_id: &str,
+        mut old: Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        if Self::row_exists(db, "payment_transactions", payment_id)? {
+            return Err(Self::already_exists("payment_transactions", payment_id));
+        }
+
+        let student_id = Self::text(&old, "student_id").unwrap_or_default();
+        Self::require_student(db, &student_id)?;
+        let enrollment_id =
+            Self::existing_enrollment(db, Self::int(&old, "enrollment_id"), &student_id)?;
+        let amount = Self::int(&old, "amount").unwrap_or_default() as i32;
+
+        old.insert("enrollment_id".to_string(), Value::from(enrollment_id));
+        Self::insert_row(db, "payment_transactions", PAYMENT_COLUMNS, &old)?;
+
+        Self::adjust_paid_amount(db, &student_id, Some(enrollment_id), amount)
+    }
+
+    fn remove_attendance(db: &Database, attendance_id: &str) -> DatabaseResult<Vec<Refresh>> {
+        let rows_affected = db
+            .connection()
+            .execute("DELETE FROM attendance WHERE id = ?1", [attendance_id])?;
+        if rows_affected == 0 {
+            return Err(Self::missing("attendance", attendance_id));
+        }
+        Ok(Vec::new())
+    }
+
+    fn restore_attendance_status(
+        db: &Database,
+        attendance_id: &str,
+        old: &Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        let rows_affected = db.connection().execute(
+            "UPDATE attendance SET status = COALESCE(?1, status) WHERE id = ?2",
+            params![Self::text(old, "status"), attendance_id],
+        )?;
+        if rows_affected == 0 {
+            return Err(Self::missing("attendance", attendance_id));
+        }
+        Ok(Vec::new())
+    }
+
+    fn restore_attendance(
+        db: &Database,
+        attendance_id: &str,
+        mut old: Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        if Self::row_exists(db, "attendance", attendance_id)? {
+            return Err(Self::already_exists("attendance", attendance_id));
+        }
+
+        let student_id = Self::text(&old, "student_id").unwrap_or_default();
+        Self::require_student(db, &student_id)?;
+        let enrollment_id =
+            Self::existing_enrollment(db, Self::int(&old, "enrollment_id"), &student_id)?;
+        let date = Self::text(&old, "date").unwrap_or_default();
+
+        let existing: i32 = db.connection().query_row(
+            &format!(
+                "SELECT COUNT(*) FROM attendance WHERE {}",
+                ENROLLMENT_DAY_FILTER
+            ),
+            params![student_id, enrollment_id, date],
+            |row| row.get(0),
+        )?;
+        if existing > 0 {
+            return Err(DatabaseError::conflict(
+                "attendance_already_recorded",
+                format!(
+                    "Attendance has been recorded again for student '{}' on date '{}'",
+                    student_id, date
+                ),
+            ));
+        }
+
+        old.insert("enrollment_id".to_string(), Value::from(enrollment_id));
+        Self::insert_row(db, "attendance", ATTENDANCE_COLUMNS, &old)?;
+        Ok(Vec::new())
+    }
+
+    fn remove_group(db: &Database, group_id: &str) -> DatabaseResult<Vec<Refresh>> {
+        let active: bool = db.connection().query_row(
+            "SELECT EXISTS(SELECT 1 FROM groups WHERE id = ?1 AND deleted_at IS NULL)",
+            [group_id],
+            |row| row.get(0),
+        )?;
+        if !active {
+            return Err(Self::missing("groups", group_id));
+        }
+
+        let members: i64 = db.connection().query_row(
+            "SELECT (SELECT COUNT(*) FROM students WHERE group_id = ?1)
+                  + (SELECT COUNT(*) FROM enrollments WHERE group_id = ?1)",
+            [group_id],
+            |row| row.get(0),
+        )?;
+        if members > 0 {
+            return Err(DatabaseError::conflict(
+                "group_in_use",
+                format!(
+                    "Group {} has students or enrollments since being created",
+                    group_id
+                ),
+            ));
+        }
+
+        db.connection()
+            .execute("DELETE FROM groups WHERE id = ?1", [group_id])?;
+        Ok(Vec::new())
+    }
+
+    fn check_group_name(db: &Database, group_id: &str, name: &str) -> DatabaseResult<()> {
+        let count: i32 = db.connection().query_row(
+            "SELECT COUNT(*) FROM groups WHERE name = ?1 AND id != ?2",
+            params![name, group_id],
+            |row| row.get(0),
+        )?;
+        if count > 0 {
+            return Err(DatabaseError::conflict(
+                "group_exists",
+                format!("Group '{}' already exists", name),
+            ));
+        }
+        Ok(())
+    }
+
+    fn rename_group(
+        db: &Database,
+        group_id: &str,
+        old: &Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        let name = Self::text(old, "name").unwrap_or_default();
+        Self::check_group_name(db, group_id, &name)?;
+
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let rows_affected = db.connection().execute(
+            "UPDATE groups SET name = ?1, updated_at = ?2 WHERE id = ?3 AND deleted_at IS NULL",
+            params![name, now, group_id],
+        )?;
+        if rows_affected == 0 {
+            return Err(Self::missing("groups", group_id));
+        }
+        Ok(Vec::new())
+    }
+
+    /// Undo a soft delete together with the members it removed, or re-insert a force-deleted
+    /// group. Students a force delete moved to another group stay there.
+    fn restore_group(
+        db: &Database,
+        group_id: &str,
+        old: &Map<String, Value>,
+    ) -> DatabaseResult<Vec<Refresh>> {
+        let name = Self::text(old, "name").unwrap_or_default();
+        Self::check_group_name(db, group_id, &name)?;
+
+        let deleted_at: Option<Option<String>> = db
+            .connection()
+            .query_row(
+                "SELECT deleted_at FROM groups WHERE id = ?1",
+                [group_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match deleted_at {
+            None => {
+                Self::insert_row(db, "groups", GROUP_COLUMNS, old)?;
+                Ok(Vec::new())
+            }
+            Some(None) => Err(Self::already_exists("groups", group_id)),
+            Some(Some(deleted_at)) => {
+                let now = Utc::now().to_rfc3339();
+
+                // Members removed with the group share its deletion time
+                let student_ids: Vec<String> = {
+                    let mut stmt = db.connection().prepare(
+                        "SELECT id FROM students
+                         WHERE deleted_at = ?1
+                           AND id IN (SELECT student_id FROM enrollments
+                                      WHERE group_id = ?2 AND is_primary = 1 AND status = 'withdrawn' AND updated_at = ?1)",
+                    )?;
+                    let ids = stmt
+                        .query_map(params![deleted_at, group_id], |row| row.get(0))?
+                        .collect::<Result<Vec<_>, _>>()?;
+                    ids
+                };
+
+                db.connection().execute(
+                    "UPDATE groups SET deleted_at = NULL, updated_at = ?1 WHERE id = ?2",
+                    params![now, group_id],
+                )?;
+                for student_id in &student_ids {
+                    db.connection().execute(
+                        "UPDATE students SET deleted_at = NULL, group_name = ?1, group_id = ?2, updated_at = ?3 WHERE id = ?4",
+                        params![name, group_id, now, student_id],
+                    )?;
+                }
+                db.connection().execute(
+                    "UPDATE enrollments SET status = 'active', end_date = NULL, updated_at = ?1
+                     WHERE group_id = ?2 AND status = 'withdrawn' AND updated_at = ?3",
+                    params![now, group_id, deleted_at],
+                )?;
+
+                Ok(student_ids
+                    .into_iter()
+                    .map(|id| Refresh::Student {
+                        id,
+                        regenerate_schedule: false,
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    fn refresh(db: &Database, step: Refresh) -> DatabaseResult<()> {
+        match step {
+            Refresh::Student {
+                id,
+                regenerate_schedule,
+            } => {
+                let Some(student) = StudentService::get_student_by_id(db, &id)? else {
+                    return Ok(());
+                };
+                if student.payment_plan == PaymentPlan::Installment {
+                    if regenerate_schedule {
+                        let config = StudentService::get_payment_plan_config(db)?;
+                        InstallmentService::generate_schedule(
+                            db,
+                            &id,
+                            &student.enrollment_date,
+                            student.plan_amount,
+                            student.installment_count,
+                            config.installment_interval,
+                        )?;
+                    } else {
+                        InstallmentService::allocate_payments(db, &id)?;
+                    }
+                } else {
+                    InstallmentService::delete_schedule(db, &id)?;
+                }
+                PaymentService::update_student_payment_status(db, &id)
+            }
+            Refresh::Enrollment(id) => EnrollmentService::update_enrollment_payment_status(db, id),
+            Refresh::Waitlist(group_id) => {
+                EnrollmentService::promote_waitlist(db, group_id).map(|_| ())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attendance_service::AttendanceService;
+    use crate::membership_service::TransferStudentRequest;
+    use crate::payment_service::{PaymentMethod, RecordPaymentRequest};
+    use crate::settings_service::SettingsService;
+    use crate::student_service::{CreateStudentRequest, UpdateStudentRequest};
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(db: &Database) -> String {
+        StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: "Reverted Student".to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::OneTime,
+                plan_amount: 6000,
+                installment_count: None,
+                paid_amount: Some(0),
+                enrollment_date: Some("2024-01-01".to_string()),
+            },
+        )
+        .unwrap()
+        .id
+    }
+
+    fn record_payment(db: &Database, student_id: &str, amount: i32) -> i32 {
+        PaymentService::record_payment(
+            db,
+            RecordPaymentRequest {
+                student_id: student_id.to_string(),
+                enrollment_id: None,
+                amount,
+                payment_date: "2024-01-05".to_string(),
+                payment_method: PaymentMethod::Cash,
+                notes: None,
+            },
+        )
+        .unwrap()
+        .id
+    }
+
+    fn entry_id(db: &Database, table_name: &str, record_id: &str, action_type: &str) -> i32 {
+        AuditService::get_record_history(db, table_name, record_id)
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.action_type == action_type)
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn test_revert_student_deletion_restores_payments_and_attendance() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db);
+        record_payment(&db, &student_id, 2000);
+        AttendanceService::mark_attendance(&db, &student_id, None, "2024-01-08").unwrap();
+
+        StudentService::delete_student(&db, &student_id).unwrap();
+        let deleted = entry_id(&db, "students", &student_id, "DELETE");
+
+        let result = RevertService::revert_audit_entry(&db, deleted).unwrap();
+        assert_eq!(result.related_restored, 2);
+        let revert_entry = result.revert_entry.unwrap();
+        assert_eq!(revert_entry.action_type, REVERT_ACTION);
+        assert_eq!(revert_entry.user_id, None);
+        let restored: Value =
+            serde_json::from_str(revert_entry.new_values.as_deref().unwrap()).unwrap();
+        assert_eq!(restored[REVERTED_ENTRY_FIELD], deleted);
+        assert_eq!(restored["id"], student_id.as_str());
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 2000);
+        assert_eq!(
+            PaymentService::get_student_payment_history(&db, &student_id)
+                .unwrap()
+                .len(),
+            1
+        );
+        let marks: i32 = db
+            .connection()
+            .query_row(
+                "SELECT COUNT(*) FROM attendance WHERE student_id = ?1",
+                [&student_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(marks, 1);
+
+        // The deletion cannot be undone twice
+        assert!(matches!(
+            RevertService::revert_audit_entry(&db, deleted),
+            Err(DatabaseError::Conflict {
+                reason: "already_reverted",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_revert_update_requires_no_later_changes() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db);
+        for name in ["First Rename", "Second Rename"] {
+            StudentService::update_student(
+                &db,
+                &student_id,
+                UpdateStudentRequest {
+                    name: name.to_string(),
+                    group_name: "Group A".to_string(),
+                    payment_plan: PaymentPlan::OneTime,
+                    plan_amount: 6000,
+                    installment_count: None,
+                    paid_amount: None,
+                    enrollment_date: None,
+                },
+            )
+            .unwrap();
+        }
+
+        let mut updates: Vec<i32> = AuditService::get_record_history(&db, "students", &student_id)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.action_type == "UPDATE")
+            .map(|entry| entry.id)
+            .collect();
+        updates.sort();
+
+        assert!(matches!(
+            RevertService::revert_audit_entry(&db, updates[0]),
+            Err(DatabaseError::Conflict {
+                reason: "changed_since",
+                ..
+            })
+        ));
+
+        RevertService::revert_audit_entry(&db, updates[1]).unwrap();
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.name, "First Rename");
+    }
+
+    #[test]
+    fn test_revert_across_transfer() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db);
+        StudentService::update_student(
+            &db,
+            &student_id,
+            UpdateStudentRequest {
+                name: "Renamed Student".to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::OneTime,
+                plan_amount: 6000,
+                installment_count: None,
+                paid_amount: None,
+                enrollment_date: None,
+            },
+        )
+        .unwrap();
+        MembershipService::transfer_student(
+            &db,
+            TransferStudentRequest {
+                student_id: student_id.clone(),
+                to_group: "Group B".to_string(),
+                effective_date: "2024-03-01".to_string(),
+                reason: Some("Schedule change".to_string()),
+            },
+        )
+        .unwrap();
+
+        // The transfer is a single entry, and the rename waits until it is undone
+        let history = AuditService::get_record_history(&db, "students", &student_id).unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .filter(|entry| entry.action_type == "UPDATE")
+                .count(),
+            1
+        );
+        let renamed = entry_id(&db, "students", &student_id, "UPDATE");
+        assert!(matches!(
+            RevertService::revert_audit_entry(&db, renamed),
+            Err(DatabaseError::Conflict {
+                reason: "changed_since",
+                ..
+            })
+        ));
+
+        let transferred = entry_id(&db, "students", &student_id, "TRANSFER");
+        RevertService::revert_audit_entry(&db, transferred).unwrap();
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.group_name, "Group A");
+        let memberships = MembershipService::get_student_group_history(&db, &student_id).unwrap();
+        assert_eq!(memberships.len(), 1);
+        assert_eq!(memberships[0].group_name, "Group A");
+        assert_eq!(memberships[0].end_date, None);
+
+        RevertService::revert_audit_entry(&db, renamed).unwrap();
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.name, "Reverted Student");
+        assert_eq!(student.group_name, "Group A");
+    }
+
+    #[test]
+    fn test_revert_payment_creation() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db);
+        let payment_id = record_payment(&db, &student_id, 1500);
+
+        let created = entry_id(
+            &db,
+            "payment_transactions",
+            &payment_id.to_string(),
+            "CREATE",
+        );
+        RevertService::revert_audit_entry(&db, created).unwrap();
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 0);
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let snapshot = AuditService::reconstruct_record(
+            &db,
+            "payment_transactions",
+            &payment_id.to_string(),
+            &now,
+        )
+        .unwrap();
+        assert!(!snapshot.exists);
+        assert!(
+            PaymentService::get_student_payment_history(&db, &student_id)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Reversing the creation of a student who has since paid would lose the payment
+        let other = create_student(&db);
+        record_payment(&db, &other, 500);
+        let other_created = entry_id(&db, "students", &other, "CREATE");
+        assert!(matches!(
+            RevertService::revert_audit_entry(&db, other_created),
+            Err(DatabaseError::Conflict {
+                reason: "has_activity",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_revert_without_audit_log() {
+        let (db, _temp_dir) = create_test_db();
+        let student_id = create_student(&db);
+        record_payment(&db, &student_id, 1000);
+        let payment_id = record_payment(&db, &student_id, 500);
+        let created = entry_id(
+            &db,
+            "payment_transactions",
+            &payment_id.to_string(),
+            "CREATE",
+        );
+
+        SettingsService::set_setting(&db, "enable_audit_log", "false").unwrap();
+        let result = RevertService::revert_audit_entry(&db, created).unwrap();
+        assert!(result.revert_entry.is_none());
+
+        let student = StudentService::get_student_by_id(&db, &student_id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(student.paid_amount, 1000);
+    }
+}
@@ -0,0 +1,815 @@
+use crate::attendance_service::AttendanceStatus;
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::i18n::{self, Locale};
+use crate::payment_service::DEFAULT_INSTALLMENT_COUNT;
+use crate::receivables_service::ReceivablesService;
+use crate::settings_service::SettingsService;
+use crate::student_service::{PaymentPlan, PaymentPlanConfig, Student, StudentService};
+use chrono::{Datelike, Duration, NaiveDate};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Setting holding `RiskConfig` as JSON
+const CONFIG_SETTING: &str = "risk_scoring";
+
+/// Something that makes a student more likely to drop out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskSignal {
+    /// Attendance in the recent window is well below the window before it
+    AttendanceDecline,
+    /// The latest sessions were all missed
+    ConsecutiveAbsences,
+    /// A due date has been unpaid for a while
+    OverdueBalance,
+    /// The plan amount was lowered recently
+    PlanDowngrade,
+}
+
+impl RiskSignal {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskSignal::AttendanceDecline => "attendance_decline",
+            RiskSignal::ConsecutiveAbsences => "consecutive_absences",
+            RiskSignal::OverdueBalance => "overdue_balance",
+            RiskSignal::PlanDowngrade => "plan_downgrade",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "attendance_decline" => Ok(RiskSignal::AttendanceDecline),
+            "consecutive_absences" => Ok(RiskSignal::ConsecutiveAbsences),
+            "overdue_balance" => Ok(RiskSignal::OverdueBalance),
+            "plan_downgrade" => Ok(RiskSignal::PlanDowngrade),
+            _ => Err(format!("Invalid risk signal: {}", s)),
+        }
+    }
+}
+
+/// How much each signal adds to the score; only the ratios matter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskWeights {
+    pub attendance_decline: f64,
+    pub consecutive_absences: f64,
+    pub overdue_balance: f64,
+    pub plan_downgrade: f64,
+}
+
+impl Default for RiskWeights {
+    fn default() -> Self {
+        RiskWeights {
+            attendance_decline: 25.0,
+            consecutive_absences: 30.0,
+            overdue_balance: 30.0,
+            plan_downgrade: 15.0,
+        }
+    }
+}
+
+impl RiskWeights {
+    pub fn get(&self, signal: RiskSignal) -> f64 {
+        match signal {
+            RiskSignal::AttendanceDecline => self.attendance_decline,
+            RiskSignal::ConsecutiveAbsences => self.consecutive_absences,
+            RiskSignal::OverdueBalance => self.overdue_balance,
+            RiskSignal::PlanDowngrade => self.plan_downgrade,
+        }
+    }
+
+    fn total(&self) -> f64 {
+        self.attendance_decline
+            + self.consecutive_absences
+            + self.overdue_balance
+            + self.plan_downgrade
+    }
+}
+
+/// Weights and thresholds of the risk score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub weights: RiskWeights,
+    /// Days in the recent attendance window, compared with as many days before it
+    pub trend_window_days: i64,
+    /// Fall in attendance rate, in percentage points, that counts as a decline
+    pub attendance_drop_points: f64,
+    /// Sessions each window needs before a trend is judged
+    pub min_sessions: usize,
+    /// Missed sessions in a row that raise the alarm
+    pub consecutive_absences: usize,
+    /// Days the oldest unpaid due date must be past
+    pub overdue_days: i64,
+    /// How far back a lowered plan amount still counts
+    pub downgrade_window_days: i64,
+    /// Score, out of 100, from which a student is listed
+    pub min_score: f64,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        RiskConfig {
+            weights: RiskWeights::default(),
+            trend_window_days: 28,
+            attendance_drop_points: 20.0,
+            min_sessions: 3,
+            consecutive_absences: 3,
+            overdue_days: 14,
+            downgrade_window_days: 60,
+            min_score: 40.0,
+        }
+    }
+}
+
+/// One reason behind a student's score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskFactor {
+    pub signal: RiskSignal,
+    /// What this factor adds to the score
+    pub points: f64,
+    /// The measured value: percentage points lost, absences in a row, amount overdue or
+    /// amount the plan was lowered by
+    pub value: f64,
+    /// The factor in words, in the requested language
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtRiskStudent {
+    pub student_id: String,
+    pub student_name: String,
+    pub group_name: String,
+    /// 0 to 100
+    pub score: f64,
+    /// Largest contribution first
+    pub factors: Vec<RiskFactor>,
+}
+
+/// How a student took part in one of the group's sessions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SessionOutcome {
+    Attended,
+    Excused,
+    Missed,
+}
+
+/// A plan change that lowered what a whole course costs
+struct Downgrade {
+    old_amount: i64,
+    new_amount: i64,
+    date: String,
+}
+
+pub struct RiskService;
+
+impl RiskService {
+    pub fn get_config(db: &Database) -> DatabaseResult<RiskConfig> {
+        match SettingsService::get_setting(db, CONFIG_SETTING)? {
+            Some(json) => serde_json::from_str(&json).map_err(|e| {
+                DatabaseError::validation(
+                    "risk_scoring",
+                    format!("Invalid risk scoring settings: {}", e),
+                )
+            }),
+            None => Ok(RiskConfig::default()),
+        }
+    }
+
+    pub fn update_config(db: &Database, config: &RiskConfig) -> DatabaseResult<()> {
+        let weights = &config.weights;
+        let all_weights = [
+            weights.attendance_decline,
+            weights.consecutive_absences,
+            weights.overdue_balance,
+            weights.plan_downgrade,
+        ];
+        if all_weights.iter().any(|w| !w.is_finite() || *w < 0.0) || weights.total() <= 0.0 {
+            return Err(DatabaseError::validation(
+                "weights",
+                "Weights must not be negative and at least one must be above zero",
+            ));
+        }
+        if config.trend_window_days < 1
+            || config.overdue_days < 0
+            || config.downgrade_window_days < 1
+            || config.min_sessions < 1
+            || config.consecutive_absences < 1
+        {
+            return Err(DatabaseError::validation(
+                "risk_scoring",
+                "Windows, session counts and absence counts must be at least 1, overdue days at least 0",
+            ));
+        }
+        if !(0.0..=100.0).contains(&config.attendance_drop_points) {
+            return Err(DatabaseError::validation(
+                "attendance_drop_points",
+                "Attendance drop must be between 0 and 100 percentage points",
+            ));
+        }
+        if !(0.0..=100.0).contains(&config.min_score) {
+            return Err(DatabaseError::validation(
+                "min_score",
+                "Minimum score must be between 0 and 100",
+            ));
+        }
+
+        let json = serde_json::to_string(config).map_err(|e| {
+            DatabaseError::validation(
+                "risk_scoring",
+                format!("Invalid risk scoring settings: {}", e),
+            )
+        })?;
+        SettingsService::set_setting(db, CONFIG_SETTING, &json)
+    }
+
+    /// Dates on which anyone in each group has a mark, i.e. the sessions the group held
+    fn group_sessions(
+        db: &Database,
+        from: &str,
+        to: &str,
+    ) -> DatabaseResult<HashMap<String, BTreeSet<String>>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT DISTINCT v.group_name, a.date
+             FROM attendance a
+             JOIN attendance_groups_view v ON v.id = a.id
+             WHERE a.date BETWEEN ?1 AND ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![from, to], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut sessions: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for (group, date) in rows {
+            sessions.entry(group).or_default().insert(date);
+        }
+        Ok(sessions)
+    }
+
+    /// Each student's marks by date; a present or late mark beats an excused one
+    fn student_marks(
+        db: &Database,
+        from: &str,
+        to: &str,
+    ) -> DatabaseResult<HashMap<String, HashMap<String, SessionOutcome>>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT student_id, date, status FROM attendance WHERE date BETWEEN ?1 AND ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![from, to], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut marks: HashMap<String, HashMap<String, SessionOutcome>> = HashMap::new();
+        for (student_id, date, status) in rows {
+            let status = AttendanceStatus::from_str(&status)
+                .map_err(|e| DatabaseError::validation("status", e))?;
+            let outcome = match status {
+                AttendanceStatus::Present | AttendanceStatus::Late => SessionOutcome::Attended,
+                AttendanceStatus::Excused => SessionOutcome::Excused,
+                AttendanceStatus::Absent => SessionOutcome::Missed,
+            };
+            let mark = marks
+                .entry(student_id)
+                .or_default()
+                .entry(date)
+                .or_insert(outcome);
+            if outcome == SessionOutcome::Attended || *mark == SessionOutcome::Missed {
+                *mark = outcome;
+            }
+        }
+        Ok(marks)
+    }
+
+    /// Months one course runs for on a monthly plan: the term a change fell in, or without
+    /// one, as many months as the one-time price buys at the monthly price
+    fn course_months(
+        term: Option<(String, String)>,
+        plan_config: &PaymentPlanConfig,
+    ) -> DatabaseResult<i64> {
+        let Some((start_date, end_date)) = term else {
+            let months =
+                plan_config.one_time_amount as f64 / plan_config.monthly_amount.max(1) as f64;
+            return Ok((months.round() as i64).max(1));
+        };
+        let parse = |field: &'static str, value: &str| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| DatabaseError::validation(field, "Invalid term date"))
+        };
+        let (start, end) = (
+            parse("start_date", &start_date)?,
+            parse("end_date", &end_date)?,
+        );
+        let months =
+            (end.year() - start.year()) as i64 * 12 + end.month() as i64 - start.month() as i64 + 1;
+        Ok(months.max(1))
+    }
+
+    /// What a whole course costs on a plan, so amounts on different plans compare
+    fn course_total(
+        plan: &str,
+        amount: i64,
+        installment_count: Option<i64>,
+        course_months: i64,
+    ) -> DatabaseResult<i64> {
+        let plan = PaymentPlan::from_str(plan)
+            .map_err(|e| DatabaseError::validation("payment_plan", e))?;
+        Ok(match plan {
+            PaymentPlan::OneTime => amount,
+            PaymentPlan::Monthly => amount * course_months,
+            PaymentPlan::Installment => {
+                amount * installment_count.unwrap_or(DEFAULT_INSTALLMENT_COUNT as i64)
+            }
+        })
+    }
+
+    /// The latest plan change since `since` that lowered each student's course total
+    fn plan_downgrades(
+        db: &Database,
+        since: &str,
+        plan_config: &PaymentPlanConfig,
+    ) -> DatabaseResult<HashMap<String, Downgrade>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT c.student_id, c.old_plan, c.old_amount, c.old_installment_count,
+                    c.new_plan, c.new_amount, c.new_installment_count, c.changed_at,
+                    t.start_date, t.end_date
+             FROM plan_changes c
+             LEFT JOIN terms t ON date(c.changed_at) BETWEEN t.start_date AND t.end_date
+             WHERE c.changed_at >= ?1
+             ORDER BY c.changed_at, c.id",
+        )?;
+        let rows = stmt
+            .query_map(params![since], |row| {
+                let term = match (row.get::<_, Option<String>>(8)?, row.get(9)?) {
+                    (Some(start_date), Some(end_date)) => Some((start_date, end_date)),
+                    _ => None,
+                };
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (
+                        row.get::<_, String>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, Option<i64>>(3)?,
+                    ),
+                    (
+                        row.get::<_, String>(4)?,
+                        row.get::<_, i64>(5)?,
+                        row.get::<_, Option<i64>>(6)?,
+                    ),
+                    row.get::<_, String>(7)?,
+                    term,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut downgrades = HashMap::new();
+        for (
+            student_id,
+            (old_plan, old_amount, old_count),
+            (new_plan, new_amount, new_count),
+            changed_at,
+            term,
+        ) in rows
+        {
+            let months = Self::course_months(term, plan_config)?;
+            let old_amount = Self::course_total(&old_plan, old_amount, old_count, months)?;
+            let new_amount = Self::course_total(&new_plan, new_amount, new_count, months)?;
+            if new_amount < old_amount {
+                downgrades.insert(
+                    student_id,
+                    Downgrade {
+                        old_amount,
+                        new_amount,
+                        date: changed_at.chars().take(10).collect(),
+                    },
+                );
+            }
+        }
+        Ok(downgrades)
+    }
+
+    fn rate(outcomes: &[SessionOutcome]) -> Option<(f64, usize)> {
+        let counted = outcomes
+            .iter()
+            .filter(|o| **o != SessionOutcome::Excused)
+            .count();
+        let attended = outcomes
+            .iter()
+            .filter(|o| **o == SessionOutcome::Attended)
+            .count();
+        (counted > 0).then(|| (attended as f64 / counted as f64 * 100.0, counted))
+    }
+
+    fn detail(locale: Locale, signal: RiskSignal, params: &[(&str, String)]) -> String {
+        let params: BTreeMap<String, String> = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        i18n::format(locale, &format!("risk.{}", signal.as_str()), &params).unwrap_or_default()
+    }
+
+    /// Every signal that fires for one student, before weighting
+    #[allow(clippy::too_many_arguments)]
+    fn student_signals(
+        db: &Database,
+        student: &Student,
+        plan_config: &PaymentPlanConfig,
+        config: &RiskConfig,
+        sessions: Option<&BTreeSet<String>>,
+        marks: Option<&HashMap<String, SessionOutcome>>,
+        downgrade: Option<&Downgrade>,
+        today: NaiveDate,
+        locale: Locale,
+    ) -> DatabaseResult<Vec<(RiskSignal, f64, String)>> {
+        let mut signals = Vec::new();
+        let format_date = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+        let recent_start = format_date(today - Duration::days(config.trend_window_days - 1));
+
+        // Sessions the student was enrolled for, oldest first; no mark means missed
+        let outcomes: Vec<(&str, SessionOutcome)> = sessions
+            .into_iter()
+            .flatten()
+            .filter(|date| date.as_str() >= student.enrollment_date.as_str())
+            .map(|date| {
+                let outcome = marks
+                    .and_then(|marks| marks.get(date))
+                    .copied()
+                    .unwrap_or(SessionOutcome::Missed);
+                (date.as_str(), outcome)
+            })
+            .collect();
+
+        let (previous, recent): (Vec<_>, Vec<_>) = outcomes
+            .iter()
+            .partition(|(date, _)| *date < recent_start.as_str());
+        let previous: Vec<_> = previous.into_iter().map(|(_, o)| *o).collect();
+        let recent: Vec<_> = recent.into_iter().map(|(_, o)| *o).collect();
+        if let (Some((previous_rate, previous_count)), Some((recent_rate, recent_count))) =
+            (Self::rate(&previous), Self::rate(&recent))
+        {
+            let drop = previous_rate - recent_rate;
+            if previous_count >= config.min_sessions
+                && recent_count >= config.min_sessions
+                && drop >= config.attendance_drop_points
+            {
+                signals.push((
+                    RiskSignal::AttendanceDecline,
+                    drop.round(),
+                    Self::detail(
+                        locale,
+                        RiskSignal::AttendanceDecline,
+                        &[
+                            ("previous", format!("{:.0}", previous_rate)),
+                            ("recent", format!("{:.0}", recent_rate)),
+                        ],
+                    ),
+                ));
+            }
+        }
+
+        let missed_in_a_row = outcomes
+            .iter()
+            .rev()
+            .filter(|(_, o)| *o != SessionOutcome::Excused)
+            .take_while(|(_, o)| *o == SessionOutcome::Missed)
+            .count();
+        if missed_in_a_row >= config.consecutive_absences {
+            signals.push((
+                RiskSignal::ConsecutiveAbsences,
+                missed_in_a_row as f64,
+                Self::detail(
+                    locale,
+                    RiskSignal::ConsecutiveAbsences,
+                    &[("count", missed_in_a_row.to_string())],
+                ),
+            ));
+        }
+
+        let overdue: Vec<_> =
+            ReceivablesService::outstanding_dues(db, student, plan_config, today)?
+                .into_iter()
+                .filter(|due| due.due_date < format_date(today))
+                .collect();
+        let oldest_overdue_days = overdue.first().and_then(|due| {
+            NaiveDate::parse_from_str(&due.due_date, "%Y-%m-%d")
+                .ok()
+                .map(|date| (today - date).num_days())
+        });
+        if let Some(days) = oldest_overdue_days.filter(|days| *days >= config.overdue_days) {
+            let amount: i64 = overdue.iter().map(|due| due.amount).sum();
+            signals.push((
+                RiskSignal::OverdueBalance,
+                amount as f64,
+                Self::detail(
+                    locale,
+                    RiskSignal::OverdueBalance,
+                    &[("amount", amount.to_string()), ("days", days.to_string())],
+                ),
+            ));
+        }
+
+        if let Some(downgrade) = downgrade {
+            signals.push((
+                RiskSignal::PlanDowngrade,
+                (downgrade.old_amount - downgrade.new_amount) as f64,
+                Self::detail(
+                    locale,
+                    RiskSignal::PlanDowngrade,
+                    &[
+                        ("old", downgrade.old_amount.to_string()),
+                        ("new", downgrade.new_amount.to_string()),
+                        ("date", downgrade.date.clone()),
+                    ],
+                ),
+            ));
+        }
+
+        Ok(signals)
+    }
+
+    /// Students whose risk score reaches `min_score` (the configured one when omitted), highest
+    /// score first. Sessions are the dates on which anyone in the student's current group has a
+    /// mark; a session without a mark from the student counts as missed and excused ones are
+    /// left out of rates and runs.
+    pub fn get_at_risk_students(
+        db: &Database,
+        group_name: Option<&str>,
+        min_score: Option<f64>,
+        today: NaiveDate,
+        locale: Locale,
+    ) -> DatabaseResult<Vec<AtRiskStudent>> {
+        let config = Self::get_config(db)?;
+        let min_score = min_score.unwrap_or(config.min_score);
+        let plan_config = StudentService::get_payment_plan_config(db)?;
+        let total_weight = config.weights.total();
+        if total_weight <= 0.0 {
+            return Ok(Vec::new());
+        }
+
+        let format_date = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+        let window_start = format_date(today - Duration::days(2 * config.trend_window_days - 1));
+        let today_str = format_date(today);
+        let sessions = Self::group_sessions(db, &window_start, &today_str)?;
+        let marks = Self::student_marks(db, &window_start, &today_str)?;
+        let downgrades = Self::plan_downgrades(
+            db,
+            &format_date(today - Duration::days(config.downgrade_window_days)),
+            &plan_config,
+        )?;
+
+        let students = match group_name {
+            Some(group) => StudentService::get_students_by_group(db, group)?,
+            None => StudentService::get_all_students(db)?,
+        };
+
+        let mut at_risk = Vec::new();
+        for student in students {
+            let signals = Self::student_signals(
+                db,
+                &student,
+                &plan_config,
+                &config,
+                sessions.get(&student.group_name),
+                marks.get(&student.id),
+                downgrades.get(&student.id),
+                today,
+                locale,
+            )?;
+
+            let mut factors: Vec<RiskFactor> = signals
+                .into_iter()
+                .map(|(signal, value, detail)| RiskFactor {
+                    signal,
+                    points: (config.weights.get(signal) / total_weight * 1000.0).round() / 10.0,
+                    value,
+                    detail,
+                })
+                .filter(|factor| factor.points > 0.0)
+                .collect();
+            let score = factors
+                .iter()
+                .map(|factor| factor.points)
+                .sum::<f64>()
+                .min(100.0);
+            if factors.is_empty() || score < min_score {
+                continue;
+            }
+            factors.sort_by(|a, b| b.points.total_cmp(&a.points));
+
+            at_risk.push(AtRiskStudent {
+                student_id: student.id,
+                student_name: student.name,
+                group_name: student.group_name,
+                score: (score * 10.0).round() / 10.0,
+                factors,
+            });
+        }
+
+        at_risk.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.student_name.cmp(&b.student_name))
+        });
+        Ok(at_risk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attendance_service::AttendanceService;
+    use crate::student_service::{CreateStudentRequest, UpdateStudentRequest};
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    fn setup_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn create_student(db: &Database, name: &str, paid_amount: i32, enrollment: &str) -> Student {
+        StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: name.to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::Monthly,
+                plan_amount: 500,
+                installment_count: None,
+                paid_amount: Some(paid_amount),
+                enrollment_date: Some(enrollment.to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_config_defaults_and_validation() {
+        let (db, _temp_dir) = setup_test_db();
+        assert_eq!(RiskService::get_config(&db).unwrap(), RiskConfig::default());
+
+        let mut config = RiskConfig {
+            min_score: 60.0,
+            ..RiskConfig::default()
+        };
+        RiskService::update_config(&db, &config).unwrap();
+        assert_eq!(RiskService::get_config(&db).unwrap().min_score, 60.0);
+
+        config.min_score = 150.0;
+        assert!(RiskService::update_config(&db, &config).is_err());
+        config.min_score = 60.0;
+        config.weights = RiskWeights {
+            attendance_decline: 0.0,
+            consecutive_absences: 0.0,
+            overdue_balance: 0.0,
+            plan_downgrade: 0.0,
+        };
+        assert!(RiskService::update_config(&db, &config).is_err());
+    }
+
+    #[test]
+    fn test_at_risk_students_combine_signals() {
+        let (db, _temp_dir) = setup_test_db();
+        // Plan changes are stamped in real time, so the scenario is built around today
+        let today = Utc::now().date_naive();
+        let day = |days_ago: i64| {
+            (today - Duration::days(days_ago))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+
+        let fading = create_student(&db, "Fading", 0, &day(120));
+        let steady = create_student(&db, "Steady", 5000, &day(120));
+        for days_ago in [50, 45, 40, 35, 20, 15, 10, 5] {
+            let date = day(days_ago);
+            AttendanceService::set_attendance_status(
+                &db,
+                &steady.id,
+                None,
+                &date,
+                AttendanceStatus::Present,
+            )
+            .unwrap();
+            let status = if days_ago > 15 {
+                AttendanceStatus::Present
+            } else {
+                AttendanceStatus::Absent
+            };
+            AttendanceService::set_attendance_status(&db, &fading.id, None, &date, status).unwrap();
+        }
+        StudentService::update_student(
+            &db,
+            &fading.id,
+            UpdateStudentRequest {
+                name: "Fading".to_string(),
+                group_name: "Group A".to_string(),
+                payment_plan: PaymentPlan::Monthly,
+                plan_amount: 300,
+                installment_count: None,
+                paid_amount: None,
+                enrollment_date: Some(day(120)),
+            },
+        )
+        .unwrap();
+
+        let at_risk =
+            RiskService::get_at_risk_students(&db, None, None, today, Locale::En).unwrap();
+        assert_eq!(at_risk.len(), 1);
+        let student = &at_risk[0];
+        assert_eq!(student.student_id, fading.id);
+        assert_eq!(student.score, 100.0);
+        let signals: Vec<_> = student.factors.iter().map(|f| f.signal).collect();
+        assert_eq!(
+            signals,
+            vec![
+                RiskSignal::ConsecutiveAbsences,
+                RiskSignal::OverdueBalance,
+                RiskSignal::AttendanceDecline,
+                RiskSignal::PlanDowngrade,
+            ]
+        );
+        assert_eq!(student.factors[0].value, 3.0);
+        assert_eq!(student.factors[0].detail, "3 absences in a row");
+        // Four sessions of 100% before the window, one of four attended in it
+        assert_eq!(student.factors[2].value, 75.0);
+        // 200 a month less over the seven months 6000 buys at the default 850 a month
+        assert_eq!(student.factors[3].value, 1400.0);
+
+        // Without the attendance trend the remaining signals add up to 75
+        RiskService::update_config(
+            &db,
+            &RiskConfig {
+                attendance_drop_points: 80.0,
+                ..RiskConfig::default()
+            },
+        )
+        .unwrap();
+        let at_risk =
+            RiskService::get_at_risk_students(&db, None, None, today, Locale::En).unwrap();
+        assert_eq!(at_risk[0].score, 75.0);
+        assert!(
+            RiskService::get_at_risk_students(&db, None, Some(80.0), today, Locale::En)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(RiskService::get_at_risk_students(
+            &db,
+            Some("Group B"),
+            Some(0.0),
+            today,
+            Locale::En
+        )
+        .unwrap()
+        .is_empty());
+    }
+
+    #[test]
+    fn test_plan_downgrades_compare_course_totals_without_audit_log() {
+        let (db, _temp_dir) = setup_test_db();
+        SettingsService::set_setting(&db, "enable_audit_log", "false").unwrap();
+        let today = Utc::now().date_naive();
+        let plan_config = StudentService::get_payment_plan_config(&db).unwrap();
+        let change_plan = |student: &Student, payment_plan: PaymentPlan, plan_amount: i32| {
+            StudentService::update_student(
+                &db,
+                &student.id,
+                UpdateStudentRequest {
+                    name: student.name.clone(),
+                    group_name: student.group_name.clone(),
+                    payment_plan,
+                    plan_amount,
+                    installment_count: Some(3),
+                    paid_amount: None,
+                    enrollment_date: Some(student.enrollment_date.clone()),
+                },
+            )
+            .unwrap();
+        };
+
+        // 500 a month over seven months is 3500; a one-time 3000 is less despite the larger amount
+        let lowered = create_student(&db, "Lowered", 0, "2024-01-10");
+        change_plan(&lowered, PaymentPlan::OneTime, 3000);
+        // 900 a month over seven months is 6300, more than the 6000 paid at once before
+        let spread = create_student(&db, "Spread", 0, "2024-01-10");
+        change_plan(&spread, PaymentPlan::OneTime, 6000);
+        let spread = StudentService::get_student_by_id(&db, &spread.id)
+            .unwrap()
+            .unwrap();
+        change_plan(&spread, PaymentPlan::Monthly, 900);
+
+        let since = (today - Duration::days(1)).format("%Y-%m-%d").to_string();
+        let downgrades = RiskService::plan_downgrades(&db, &since, &plan_config).unwrap();
+        let lowered = &downgrades[&lowered.id];
+        assert_eq!((lowered.old_amount, lowered.new_amount), (3500, 3000));
+        assert!(!downgrades.contains_key(&spread.id));
+    }
+}
@@ -9,6 +9,9 @@ pub struct AppSettings {
     pub payment_threshold: i32,
     pub default_groups: Vec<String>,
     pub enable_audit_log: bool,
+    /// Undo a change whose audit entry cannot be written instead of keeping it unlogged
+    #[serde(default)]
+    pub audit_strict_mode: bool,
     pub language: String,
     pub theme: String,
     pub enable_multi_user: bool,
@@ -39,7 +42,7 @@ impl SettingsService {
     pub fn get_settings(db: &Database) -> DatabaseResult<AppSettings> {
         let mut stmt = db.connection().prepare(
             "SELECT key, value FROM settings WHERE key IN (
-                'payment_threshold', 'default_groups', 'enable_audit_log', 
+                'payment_threshold', 'default_groups', 'enable_audit_log', 'audit_strict_mode',
                 'language', 'theme', 'enable_multi_user', 'backup_encryption', 
                 'accessibility_mode'
             )"
@@ -71,6 +74,11 @@ impl SettingsService {
             .map(|v| v == "true")
             .unwrap_or(true);
 
+        let audit_strict_mode = settings_map
+            .get("audit_strict_mode")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+
         let language = settings_map
             .get("language")
             .cloned()
@@ -100,6 +108,7 @@ impl SettingsService {
             payment_threshold,
             default_groups,
             enable_audit_log,
+            audit_strict_mode,
             language,
             theme,
             enable_multi_user,
@@ -131,6 +140,11 @@ impl SettingsService {
             params!["enable_audit_log", if settings.enable_audit_log { "true" } else { "false" }],
         )?;
 
+        tx.execute(
+            "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
+            params!["audit_strict_mode", if settings.audit_strict_mode { "true" } else { "false" }],
+        )?;
+
         tx.execute(
             "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, CURRENT_TIMESTAMP)",
             params!["language", settings.language],
@@ -312,6 +326,7 @@ impl SettingsService {
             ("payment_threshold", "6000"),
             ("default_groups", r#"["Group A", "Group B", "Group C"]"#),
             ("enable_audit_log", "true"),
+            ("audit_strict_mode", "false"),
             ("language", "ar"),
             ("theme", "light"),
             ("enable_multi_user", "false"),
@@ -386,6 +401,7 @@ mod tests {
         assert_eq!(settings.payment_threshold, 6000);
         assert_eq!(settings.default_groups, vec!["Group A", "Group B", "Group C"]);
         assert_eq!(settings.enable_audit_log, true);
+        assert_eq!(settings.audit_strict_mode, false);
         assert_eq!(settings.language, "ar");
         assert_eq!(settings.theme, "light");
         assert_eq!(settings.enable_multi_user, false);
@@ -401,6 +417,7 @@ mod tests {
             payment_threshold: 7000,
             default_groups: vec!["Group X".to_string(), "Group Y".to_string()],
             enable_audit_log: false,
+            audit_strict_mode: true,
             language: "en".to_string(),
             theme: "dark".to_string(),
             enable_multi_user: true,
@@ -414,6 +431,7 @@ mod tests {
         assert_eq!(retrieved_settings.payment_threshold, new_settings.payment_threshold);
         assert_eq!(retrieved_settings.default_groups, new_settings.default_groups);
         assert_eq!(retrieved_settings.enable_audit_log, new_settings.enable_audit_log);
+        assert_eq!(retrieved_settings.audit_strict_mode, new_settings.audit_strict_mode);
         assert_eq!(retrieved_settings.language, new_settings.language);
         assert_eq!(retrieved_settings.theme, new_settings.theme);
         assert_eq!(retrieved_settings.enable_multi_user, new_settings.enable_multi_user);
@@ -522,6 +540,7 @@ mod tests {
             payment_threshold: 9999,
             default_groups: vec!["Modified Group".to_string()],
             enable_audit_log: false,
+            audit_strict_mode: true,
             language: "en".to_string(),
             theme: "dark".to_string(),
             enable_multi_user: true,
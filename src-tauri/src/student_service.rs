@@ -1,5 +1,10 @@
 use crate::audit_service::AuditService;
 use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::enrollment_service::EnrollmentService;
+use crate::groups_service::GroupsService;
+use crate::installment_service::InstallmentService;
+use crate::membership_service::MembershipService;
+use crate::term_service::{TermService, TERM_GROUP_MEMBERS_SQL, TERM_STUDENTS_SQL};
 use chrono::{Datelike, NaiveDate, Utc};
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
@@ -10,6 +15,7 @@ pub struct Student {
     pub id: String,
     pub name: String,
     pub group_name: String,
+    pub group_id: Option<i32>,
     pub payment_plan: PaymentPlan,
     pub plan_amount: i32,
     pub installment_count: Option<i32>,
@@ -87,6 +93,7 @@ pub struct StudentWithAttendance {
     pub id: String,
     pub name: String,
     pub group_name: String,
+    pub group_id: Option<i32>,
     pub payment_plan: PaymentPlan,
     pub plan_amount: i32,
     pub installment_count: Option<i32>,
@@ -104,6 +111,7 @@ pub struct StudentWithAttendance {
 pub struct AttendanceRecord {
     pub id: i32,
     pub student_id: String,
+    pub enrollment_id: Option<i32>,
     pub date: String,
     pub created_at: String,
 }
@@ -112,6 +120,7 @@ pub struct AttendanceRecord {
 pub struct PaymentTransaction {
     pub id: i32,
     pub student_id: String,
+    pub enrollment_id: Option<i32>,
     pub amount: i32,
     pub payment_date: String,
     pub payment_method: String,
@@ -162,6 +171,7 @@ impl StudentService {
             id: row.get(0)?,
             name: row.get(1)?,
             group_name: row.get(2)?,
+            group_id: row.get(12)?,
             payment_plan: PaymentPlan::from_str(&payment_plan_str).unwrap_or(PaymentPlan::OneTime),
             plan_amount: row.get(4)?,
             installment_count: row.get(5)?,
@@ -243,42 +253,69 @@ impl StudentService {
         payment_plan: &PaymentPlan,
         plan_amount: i32,
         installment_count: Option<i32>,
-    ) -> Result<(), String> {
+    ) -> DatabaseResult<()> {
         if name.trim().is_empty() {
-            return Err("Student name cannot be empty".to_string());
+            return Err(DatabaseError::validation(
+                "name",
+                "Student name cannot be empty",
+            ));
         }
 
         if name.len() > 255 {
-            return Err("Student name cannot exceed 255 characters".to_string());
+            return Err(DatabaseError::validation(
+                "name",
+                "Student name cannot exceed 255 characters",
+            ));
         }
 
         if group_name.trim().is_empty() {
-            return Err("Group name cannot be empty".to_string());
+            return Err(DatabaseError::validation(
+                "group_name",
+                "Group name cannot be empty",
+            ));
         }
 
         if group_name.len() > 100 {
-            return Err("Group name cannot exceed 100 characters".to_string());
+            return Err(DatabaseError::validation(
+                "group_name",
+                "Group name cannot exceed 100 characters",
+            ));
         }
 
         if plan_amount <= 0 {
-            return Err("Plan amount must be positive".to_string());
+            return Err(DatabaseError::validation(
+                "plan_amount",
+                "Plan amount must be positive",
+            ));
         }
 
         if plan_amount > 1_000_000 {
-            return Err("Plan amount cannot exceed 1,000,000".to_string());
+            return Err(DatabaseError::validation(
+                "plan_amount",
+                "Plan amount cannot exceed 1,000,000",
+            ));
         }
 
         // Validate installment count for installment plans
         if *payment_plan == PaymentPlan::Installment {
             match installment_count {
                 Some(count) if count <= 0 => {
-                    return Err("Installment count must be positive".to_string());
+                    return Err(DatabaseError::validation(
+                        "installment_count",
+                        "Installment count must be positive",
+                    ));
                 }
                 Some(count) if count > 12 => {
-                    return Err("Installment count cannot exceed 12".to_string());
+                    return Err(DatabaseError::validation(
+                        "installment_count",
+                        "Installment count cannot exceed 12",
+                    ));
                 }
                 None => {
-                    return Err("Installment count is required for installment plans".to_string());
+                    return Err(DatabaseError::validation(
+                        "installment_count",
+                        "Installment count is required for installment plans",
+                    ));
                 }
                 _ => {}
             }
@@ -294,12 +331,13 @@ impl StudentService {
         last_payment_date: Option<&str>,
         installment_interval: i32,
     ) -> DatabaseResult<Option<String>> {
-        let enrollment = NaiveDate::parse_from_str(enrollment_date, "%Y-%m-%d")
-            .map_err(|_| DatabaseError::Migration("Invalid enrollment date format".to_string()))?;
+        let enrollment = NaiveDate::parse_from_str(enrollment_date, "%Y-%m-%d").map_err(|_| {
+            DatabaseError::validation("enrollment_date", "Invalid enrollment date format")
+        })?;
 
         let reference_date = if let Some(last_payment) = last_payment_date {
             NaiveDate::parse_from_str(last_payment, "%Y-%m-%d").map_err(|_| {
-                DatabaseError::Migration("Invalid last payment date format".to_string())
+                DatabaseError::validation("payment_date", "Invalid last payment date format")
             })?
         } else {
             enrollment
@@ -362,7 +400,10 @@ impl StudentService {
                 } else {
                     let enrollment = NaiveDate::parse_from_str(enrollment_date, "%Y-%m-%d")
                         .map_err(|_| {
-                            DatabaseError::Migration("Invalid enrollment date format".to_string())
+                            DatabaseError::validation(
+                                "enrollment_date",
+                                "Invalid enrollment date format",
+                            )
                         })?;
 
                     let days_since_enrollment = (today - enrollment).num_days();
@@ -377,7 +418,7 @@ impl StudentService {
                 if let Some(due_date_str) = next_due_date {
                     let due_date =
                         NaiveDate::parse_from_str(due_date_str, "%Y-%m-%d").map_err(|_| {
-                            DatabaseError::Migration("Invalid due date format".to_string())
+                            DatabaseError::validation("due_date", "Invalid due date format")
                         })?;
 
                     let days_until_due = (due_date - today).num_days();
@@ -401,7 +442,7 @@ impl StudentService {
                 } else if let Some(due_date_str) = next_due_date {
                     let due_date =
                         NaiveDate::parse_from_str(due_date_str, "%Y-%m-%d").map_err(|_| {
-                            DatabaseError::Migration("Invalid due date format".to_string())
+                            DatabaseError::validation("due_date", "Invalid due date format")
                         })?;
 
                     let days_until_due = (due_date - today).num_days();
@@ -420,101 +461,145 @@ impl StudentService {
         }
     }
 
+    /// Store the due date and status dictated by a student's installment schedule, if any
+    fn apply_schedule_payment_state(
+        db: &Database,
+        student_id: &str,
+        reminder_days: i32,
+    ) -> DatabaseResult<Option<(Option<String>, PaymentStatus)>> {
+        let state = InstallmentService::get_schedule_payment_state(db, student_id, reminder_days)?;
+
+        if let Some((next_due_date, payment_status)) = &state {
+            db.connection().execute(
+                "UPDATE students SET next_due_date = ?1, payment_status = ?2 WHERE id = ?3",
+                params![next_due_date, payment_status.as_str(), student_id],
+            )?;
+        }
+
+        Ok(state)
+    }
+
     /// Create a new student
     pub fn create_student(db: &Database, request: CreateStudentRequest) -> DatabaseResult<Student> {
-        // Validate input data
-        Self::validate_student_data(
-            &request.name,
-            &request.group_name,
-            &request.payment_plan,
-            request.plan_amount,
-            request.installment_count,
-        )
-        .map_err(|e| DatabaseError::Migration(e))?;
+        AuditService::audited(db, |audit| {
+            // Validate input data
+            Self::validate_student_data(
+                &request.name,
+                &request.group_name,
+                &request.payment_plan,
+                request.plan_amount,
+                request.installment_count,
+            )?;
 
-        // Generate unique student ID
-        let student_id = Self::generate_student_id(db)?;
+            // Generate unique student ID
+            let student_id = Self::generate_student_id(db)?;
 
-        // Get payment plan configuration
-        let config = Self::get_payment_plan_config(db)?;
+            // Students reference their group by ID; the name is matched loosely so typos don't fork groups
+            let group = GroupsService::resolve_group(db, &request.group_name)?;
+            GroupsService::ensure_seat_available(db, &group)?;
 
-        // Set enrollment date to provided value or today
-        let enrollment_date = request
-            .enrollment_date
-            .unwrap_or_else(|| Utc::now().date_naive().format("%Y-%m-%d").to_string());
+            // Get payment plan configuration
+            let config = Self::get_payment_plan_config(db)?;
 
-        // Calculate next due date
-        let next_due_date = Self::calculate_next_due_date(
-            &request.payment_plan,
-            &enrollment_date,
-            None,
-            config.installment_interval,
-        )?;
+            // Set enrollment date to provided value or today
+            let enrollment_date = request
+                .enrollment_date
+                .unwrap_or_else(|| Utc::now().date_naive().format("%Y-%m-%d").to_string());
 
-        // Set paid amount to provided value or 0
-        let paid_amount = request.paid_amount.unwrap_or(0);
+            // Calculate next due date
+            let next_due_date = Self::calculate_next_due_date(
+                &request.payment_plan,
+                &enrollment_date,
+                None,
+                config.installment_interval,
+            )?;
 
-        // Calculate initial payment status
-        let payment_status = Self::calculate_payment_status(
-            &request.payment_plan,
-            request.plan_amount,
-            paid_amount,
-            request.installment_count,
-            next_due_date.as_deref(),
-            &enrollment_date,
-            config.reminder_days,
-        )?;
+            // Set paid amount to provided value or 0
+            let paid_amount = request.paid_amount.unwrap_or(0);
 
-        // Insert student into database
-        let now = Utc::now().to_rfc3339();
-        db.connection().execute(
-            "INSERT INTO students (id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-            params![
-                student_id,
-                request.name.trim(),
-                request.group_name.trim(),
-                request.payment_plan.as_str(),
+            // Calculate initial payment status
+            let payment_status = Self::calculate_payment_status(
+                &request.payment_plan,
                 request.plan_amount,
+                paid_amount,
                 request.installment_count,
+                next_due_date.as_deref(),
+                &enrollment_date,
+                config.reminder_days,
+            )?;
+
+            // Insert student into database
+            let now = Utc::now().to_rfc3339();
+            db.connection().execute(
+                "INSERT INTO students (id, name, group_name, group_id, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                params![
+                    student_id,
+                    request.name.trim(),
+                    group.name,
+                    group.id,
+                    request.payment_plan.as_str(),
+                    request.plan_amount,
+                    request.installment_count,
+                    paid_amount,
+                    enrollment_date,
+                    next_due_date,
+                    payment_status.as_str(),
+                    now,
+                    now
+                ],
+            )?;
+
+            // Group history starts on the enrollment date
+            MembershipService::record_group_change(db, &student_id, &group, &enrollment_date, None)?;
+
+            // Installment plans get an explicit schedule; its first open installment drives the due date
+            let (next_due_date, payment_status) = if request.payment_plan == PaymentPlan::Installment {
+                InstallmentService::generate_schedule(
+                    db,
+                    &student_id,
+                    &enrollment_date,
+                    request.plan_amount,
+                    request.installment_count,
+                    config.installment_interval,
+                )?;
+                Self::apply_schedule_payment_state(db, &student_id, config.reminder_days)?
+                    .unwrap_or((next_due_date, payment_status))
+            } else {
+                (next_due_date, payment_status)
+            };
+
+            // The student's own group and plan form their primary enrollment
+            EnrollmentService::sync_primary_enrollment(db, &student_id)?;
+
+            // Create the student object to return
+            let created_student = Student {
+                id: student_id.clone(),
+                name: request.name.trim().to_string(),
+                group_name: group.name,
+                group_id: Some(group.id),
+                payment_plan: request.payment_plan,
+                plan_amount: request.plan_amount,
+                installment_count: request.installment_count,
                 paid_amount,
                 enrollment_date,
                 next_due_date,
-                payment_status.as_str(),
-                now,
-                now
-            ],
-        )?;
-
-        // Create the student object to return
-        let created_student = Student {
-            id: student_id.clone(),
-            name: request.name.trim().to_string(),
-            group_name: request.group_name.trim().to_string(),
-            payment_plan: request.payment_plan,
-            plan_amount: request.plan_amount,
-            installment_count: request.installment_count,
-            paid_amount,
-            enrollment_date,
-            next_due_date,
-            payment_status,
-            created_at: now.clone(),
-            updated_at: now,
-        };
+                payment_status,
+                created_at: now.clone(),
+                updated_at: now,
+            };
 
-        // Log audit entry for student creation
-        if let Ok(serialized_data) = AuditService::serialize_data(&created_student) {
-            let _ = AuditService::log_create(db, "students", &student_id, &serialized_data, None);
-        }
+            audit.create("students", &student_id, &created_student, None);
 
-        Ok(created_student)
+            Ok(created_student)
+        })
     }
 
     /// Get all students
     pub fn get_all_students(db: &Database) -> DatabaseResult<Vec<Student>> {
         let mut stmt = db.connection().prepare(
-            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at 
-             FROM students 
+            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at, group_id 
+             FROM students_view 
              ORDER BY created_at DESC",
         )?;
 
@@ -536,7 +621,7 @@ impl StudentService {
         for student in students {
             // Get attendance records for this student
             let mut attendance_stmt = db.connection().prepare(
-                "SELECT id, student_id, date, created_at 
+                "SELECT id, student_id, enrollment_id, date, created_at 
                  FROM attendance 
                  WHERE student_id = ?1 
                  ORDER BY date DESC",
@@ -546,8 +631,9 @@ impl StudentService {
                 Ok(AttendanceRecord {
                     id: row.get(0)?,
                     student_id: row.get(1)?,
-                    date: row.get(2)?,
-                    created_at: row.get(3)?,
+                    enrollment_id: row.get(2)?,
+                    date: row.get(3)?,
+                    created_at: row.get(4)?,
                 })
             })?;
 
@@ -556,7 +642,7 @@ impl StudentService {
 
             // Get payment history for this student
             let mut payment_stmt = db.connection().prepare(
-                "SELECT id, student_id, amount, payment_date, payment_method, notes, created_at 
+                "SELECT id, student_id, amount, payment_date, payment_method, notes, created_at, enrollment_id 
                  FROM payment_transactions 
                  WHERE student_id = ?1 
                  ORDER BY payment_date DESC",
@@ -566,6 +652,7 @@ impl StudentService {
                 Ok(PaymentTransaction {
                     id: row.get(0)?,
                     student_id: row.get(1)?,
+                    enrollment_id: row.get(7)?,
                     amount: row.get(2)?,
                     payment_date: row.get(3)?,
                     payment_method: row.get(4)?,
@@ -581,6 +668,7 @@ impl StudentService {
                 id: student.id,
                 name: student.name,
                 group_name: student.group_name,
+                group_id: student.group_id,
                 payment_plan: student.payment_plan,
                 plan_amount: student.plan_amount,
                 installment_count: student.installment_count,
@@ -601,8 +689,8 @@ impl StudentService {
     /// Get a student by ID
     pub fn get_student_by_id(db: &Database, student_id: &str) -> DatabaseResult<Option<Student>> {
         let result = db.connection().query_row(
-            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at 
-             FROM students 
+            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at, group_id 
+             FROM students_view 
              WHERE id = ?1",
             [student_id],
             Self::map_row_to_student,
@@ -621,6 +709,21 @@ impl StudentService {
         student_id: &str,
         request: UpdateStudentRequest,
     ) -> DatabaseResult<()> {
+        AuditService::audited(db, |audit| {
+            let (current_student, updated_student) =
+                Self::apply_update(db, student_id, request)?;
+            audit.update("students", student_id, &current_student, &updated_student, None);
+            Ok(())
+        })
+    }
+
+    /// Update a student without auditing it, returning the student before and after. The caller
+    /// records the change, under its own action when the update is part of a larger one.
+    pub(crate) fn apply_update(
+        db: &Database,
+        student_id: &str,
+        request: UpdateStudentRequest,
+    ) -> DatabaseResult<(Student, Student)> {
         // Validate input data
         Self::validate_student_data(
             &request.name,
@@ -628,17 +731,20 @@ impl StudentService {
             &request.payment_plan,
             request.plan_amount,
             request.installment_count,
-        )
-        .map_err(|e| DatabaseError::Migration(e))?;
+        )?;
 
         // Check if student exists and get current data
-        let current_student = Self::get_student_by_id(db, student_id)?.ok_or_else(|| {
-            DatabaseError::Migration(format!("Student with ID {} not found", student_id))
-        })?;
+        let current_student = Self::get_student_by_id(db, student_id)?
+            .ok_or_else(|| DatabaseError::not_found("Student", student_id))?;
 
         // Get payment plan configuration
         let config = Self::get_payment_plan_config(db)?;
 
+        let group = GroupsService::resolve_group(db, &request.group_name)?;
+        if current_student.group_id != Some(group.id) {
+            GroupsService::ensure_seat_available(db, &group)?;
+        }
+
         // Derive new values for optionally updatable fields
         let new_paid_amount = request.paid_amount.unwrap_or(current_student.paid_amount);
         let new_enrollment_date = request
@@ -661,19 +767,18 @@ impl StudentService {
         };
 
         // Recalculate payment status
+        let paid_toward_term =
+            TermService::paid_toward_term(db, student_id, None, new_paid_amount as i64)?;
         let payment_status = Self::calculate_payment_status(
             &request.payment_plan,
             request.plan_amount,
-            new_paid_amount,
+            paid_toward_term as i32,
             request.installment_count,
             next_due_date.as_deref(),
             &new_enrollment_date,
             config.reminder_days,
         )?;
 
-        // Serialize old data for audit log
-        let old_data = AuditService::serialize_data(&current_student).ok();
-
         // Store values we need after the move
         let paid_amount = new_paid_amount;
         let enrollment_date = new_enrollment_date.clone();
@@ -683,11 +788,12 @@ impl StudentService {
         let now = Utc::now().to_rfc3339();
         let rows_affected = db.connection().execute(
             "UPDATE students 
-             SET name = ?1, group_name = ?2, payment_plan = ?3, plan_amount = ?4, installment_count = ?5, paid_amount = ?6, enrollment_date = ?7, next_due_date = ?8, payment_status = ?9, updated_at = ?10 
-             WHERE id = ?11",
+             SET name = ?1, group_name = ?2, group_id = ?3, payment_plan = ?4, plan_amount = ?5, installment_count = ?6, paid_amount = ?7, enrollment_date = ?8, next_due_date = ?9, payment_status = ?10, updated_at = ?11 
+             WHERE id = ?12",
             params![
                 request.name.trim(),
-                request.group_name.trim(),
+                group.name,
+                group.id,
                 request.payment_plan.as_str(),
                 request.plan_amount,
                 request.installment_count,
@@ -701,17 +807,56 @@ impl StudentService {
         )?;
 
         if rows_affected == 0 {
-            return Err(DatabaseError::Migration(format!(
-                "Failed to update student with ID {}",
-                student_id
-            )));
+            return Err(DatabaseError::not_found("Student", student_id));
+        }
+
+        // A group change made here takes effect today; back-dated moves go through transfer_student
+        if current_student.group_id != Some(group.id) {
+            let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+            let effective_date = std::cmp::max(today, enrollment_date.clone());
+            MembershipService::record_group_change(db, student_id, &group, &effective_date, None)?;
+        }
+
+        // Keep the installment schedule in line with the plan terms
+        let (next_due_date, payment_status) = if request.payment_plan == PaymentPlan::Installment {
+            let terms_changed = current_student.payment_plan != request.payment_plan
+                || current_student.enrollment_date != enrollment_date
+                || current_student.plan_amount != request.plan_amount
+                || current_student.installment_count != request.installment_count;
+
+            if terms_changed {
+                InstallmentService::generate_schedule(
+                    db,
+                    student_id,
+                    &enrollment_date,
+                    request.plan_amount,
+                    request.installment_count,
+                    config.installment_interval,
+                )?;
+            } else {
+                InstallmentService::allocate_payments(db, student_id)?;
+            }
+
+            Self::apply_schedule_payment_state(db, student_id, config.reminder_days)?
+                .unwrap_or((next_due_date, payment_status))
+        } else {
+            InstallmentService::delete_schedule(db, student_id)?;
+            (next_due_date, payment_status)
+        };
+
+        EnrollmentService::sync_primary_enrollment(db, student_id)?;
+
+        // Moving out of a group frees a seat for its waitlist
+        if let Some(old_group_id) = current_student.group_id.filter(|&id| id != group.id) {
+            EnrollmentService::promote_waitlist(db, old_group_id)?;
         }
 
         // Create updated student object for audit log
         let updated_student = Student {
             id: student_id.to_string(),
             name: request.name.trim().to_string(),
-            group_name: request.group_name.trim().to_string(),
+            group_name: group.name,
+            group_id: Some(group.id),
             payment_plan: request.payment_plan,
             plan_amount: request.plan_amount,
             installment_count: request.installment_count,
@@ -723,53 +868,55 @@ impl StudentService {
             updated_at: now,
         };
 
-        // Log audit entry for student update
-        if let (Some(old_data), Ok(new_data)) =
-            (old_data, AuditService::serialize_data(&updated_student))
-        {
-            let _ =
-                AuditService::log_update(db, "students", student_id, &old_data, &new_data, None);
-        }
-
-        Ok(())
+        Ok((current_student, updated_student))
     }
 
     /// Delete a student
     pub fn delete_student(db: &Database, student_id: &str) -> DatabaseResult<()> {
-        // Check if student exists and get current data for audit log
-        let current_student = Self::get_student_by_id(db, student_id)?.ok_or_else(|| {
-            DatabaseError::Migration(format!("Student with ID {} not found", student_id))
-        })?;
-
-        // Serialize student data for audit log
-        let old_data = AuditService::serialize_data(&current_student).ok();
-
-        // Delete student (attendance records will be deleted automatically due to foreign key constraint)
-        let rows_affected = db
-            .connection()
-            .execute("DELETE FROM students WHERE id = ?1", [student_id])?;
+        AuditService::audited(db, |audit| {
+            // Check if student exists and get current data for audit log
+            let current_student = Self::get_student_by_id(db, student_id)?
+                .ok_or_else(|| DatabaseError::not_found("Student", student_id))?;
+
+            // Groups the student held a seat in, to refill from their waitlists afterwards
+            let group_ids: Vec<i32> = {
+                let mut stmt = db.connection().prepare(
+                    "SELECT group_id FROM students WHERE id = ?1 AND group_id IS NOT NULL
+                     UNION
+                     SELECT group_id FROM enrollments WHERE student_id = ?1 AND status = 'active' AND group_id IS NOT NULL",
+                )?;
+                let ids = stmt
+                    .query_map([student_id], |row| row.get(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                ids
+            };
+
+            // Delete student (attendance records will be deleted automatically due to foreign key constraint)
+            let rows_affected = db
+                .connection()
+                .execute("DELETE FROM students WHERE id = ?1", [student_id])?;
+
+            if rows_affected == 0 {
+                return Err(DatabaseError::not_found("Student", student_id));
+            }
 
-        if rows_affected == 0 {
-            return Err(DatabaseError::Migration(format!(
-                "Failed to delete student with ID {}",
-                student_id
-            )));
-        }
+            audit.delete("students", student_id, &current_student, None);
 
-        // Log audit entry for student deletion
-        if let Some(old_data) = old_data {
-            let _ = AuditService::log_delete(db, "students", student_id, &old_data, None);
-        }
+            for group_id in group_ids {
+                EnrollmentService::promote_waitlist(db, group_id)?;
+            }
 
-        Ok(())
+            Ok(())
+        })
     }
 
-    /// Get students by group
+    /// Get students by group, including students with an active additional enrollment in it
     pub fn get_students_by_group(db: &Database, group_name: &str) -> DatabaseResult<Vec<Student>> {
         let mut stmt = db.connection().prepare(
-            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at 
-             FROM students 
+            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at, group_id 
+             FROM students_view 
              WHERE group_name = ?1 
+                OR id IN (SELECT student_id FROM enrollments_view WHERE group_name = ?1 AND status = 'active') 
              ORDER BY name",
         )?;
 
@@ -786,8 +933,8 @@ impl StudentService {
         status: &str,
     ) -> DatabaseResult<Vec<Student>> {
         let mut stmt = db.connection().prepare(
-            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at 
-             FROM students 
+            "SELECT id, name, group_name, payment_plan, plan_amount, installment_count, paid_amount, enrollment_date, next_due_date, payment_status, created_at, updated_at, group_id 
+             FROM students_view 
              WHERE payment_status = ?1 
              ORDER BY next_due_date ASC, name",
         )?;
@@ -800,6 +947,7 @@ impl StudentService {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 group_name: row.get(2)?,
+                group_id: row.get(12)?,
                 payment_plan: PaymentPlan::from_str(&payment_plan_str)
                     .unwrap_or(PaymentPlan::OneTime),
                 plan_amount: row.get(4)?,
@@ -835,65 +983,79 @@ impl StudentService {
         let config = Self::get_payment_plan_config(db)?;
 
         for student in students {
-            let new_status = Self::calculate_payment_status(
-                &student.payment_plan,
-                student.plan_amount,
-                student.paid_amount,
-                student.installment_count,
-                student.next_due_date.as_deref(),
-                &student.enrollment_date,
+            let schedule_state = InstallmentService::get_schedule_payment_state(
+                db,
+                &student.id,
                 config.reminder_days,
             )?;
 
+            let new_status = match schedule_state {
+                Some((_, status)) => status,
+                None => Self::calculate_payment_status(
+                    &student.payment_plan,
+                    student.plan_amount,
+                    TermService::paid_toward_term(
+                        db,
+                        &student.id,
+                        None,
+                        student.paid_amount as i64,
+                    )? as i32,
+                    student.installment_count,
+                    student.next_due_date.as_deref(),
+                    &student.enrollment_date,
+                    config.reminder_days,
+                )?,
+            };
+
             if new_status != student.payment_status {
                 db.connection().execute(
                     "UPDATE students SET payment_status = ?1, updated_at = ?2 WHERE id = ?3",
                     params![new_status.as_str(), Utc::now().to_rfc3339(), student.id],
                 )?;
+                EnrollmentService::sync_primary_enrollment(db, &student.id)?;
             }
         }
 
         Ok(())
     }
 
-    /// Get student statistics
-    pub fn get_student_statistics(db: &Database) -> DatabaseResult<StudentStatistics> {
+    /// Get student statistics, optionally limited to the students and revenue of one term
+    pub fn get_student_statistics(
+        db: &Database,
+        term_id: Option<i32>,
+    ) -> DatabaseResult<StudentStatistics> {
+        let scope = format!("(?1 IS NULL OR id IN ({}))", TERM_STUDENTS_SQL);
+        let count_where = |condition: &str| -> DatabaseResult<i32> {
+            Ok(db.connection().query_row(
+                &format!("SELECT COUNT(*) FROM students WHERE {}{}", scope, condition),
+                [term_id],
+                |row| row.get(0),
+            )?)
+        };
+
         // Total students
-        let total_students: i32 =
-            db.connection()
-                .query_row("SELECT COUNT(*) FROM students", [], |row| row.get(0))?;
+        let total_students = count_where("")?;
 
         // Students by payment status
-        let paid_students: i32 = db.connection().query_row(
-            "SELECT COUNT(*) FROM students WHERE payment_status = 'paid'",
-            [],
-            |row| row.get(0),
-        )?;
-
-        let overdue_students: i32 = db.connection().query_row(
-            "SELECT COUNT(*) FROM students WHERE payment_status = 'overdue'",
-            [],
-            |row| row.get(0),
-        )?;
-
-        let due_soon_students: i32 = db.connection().query_row(
-            "SELECT COUNT(*) FROM students WHERE payment_status = 'due_soon'",
-            [],
-            |row| row.get(0),
-        )?;
-
-        let pending_students: i32 = db.connection().query_row(
-            "SELECT COUNT(*) FROM students WHERE payment_status = 'pending'",
-            [],
-            |row| row.get(0),
-        )?;
-
-        // Students by group
-        let mut group_stmt = db.connection().prepare(
-            "SELECT group_name, COUNT(*) FROM students GROUP BY group_name ORDER BY group_name",
-        )?;
-
-        let group_iter = group_stmt.query_map([], |row| {
+        let paid_students = count_where(" AND payment_status = 'paid'")?;
+        let overdue_students = count_where(" AND payment_status = 'overdue'")?;
+        let due_soon_students = count_where(" AND payment_status = 'due_soon'")?;
+        let pending_students = count_where(" AND payment_status = 'pending'")?;
+
+        // Students by group (a student enrolled in several groups counts in each); within a
+        // term, by the groups they belonged to during it
+        let mut group_stmt = db.connection().prepare(&format!(
+            "SELECT group_name, COUNT(DISTINCT student_id) FROM (
+                 SELECT id AS student_id, group_name FROM students_view WHERE ?1 IS NULL
+                 UNION
+                 SELECT student_id, group_name FROM enrollments_view WHERE status = 'active' AND ?1 IS NULL
+                 UNION
+                 SELECT student_id, group_name FROM ({})
+             ) GROUP BY group_name ORDER BY group_name",
+            TERM_GROUP_MEMBERS_SQL
+        ))?;
+
+        let group_iter = group_stmt.query_map([term_id], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?))
         })?;
 
@@ -904,11 +1066,12 @@ impl StudentService {
         }
 
         // Students by payment plan
-        let mut plan_stmt = db.connection().prepare(
-            "SELECT payment_plan, COUNT(*) FROM students GROUP BY payment_plan ORDER BY payment_plan",
-        )?;
+        let mut plan_stmt = db.connection().prepare(&format!(
+            "SELECT payment_plan, COUNT(*) FROM students WHERE {} GROUP BY payment_plan ORDER BY payment_plan",
+            scope
+        ))?;
 
-        let plan_iter = plan_stmt.query_map([], |row| {
+        let plan_iter = plan_stmt.query_map([term_id], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?))
         })?;
 
@@ -922,18 +1085,27 @@ impl StudentService {
         let avg_payment: f64 = db
             .connection()
             .query_row(
-                "SELECT AVG(CAST(paid_amount AS REAL)) FROM students",
-                [],
+                &format!(
+                    "SELECT AVG(CAST(paid_amount AS REAL)) FROM students WHERE {}",
+                    scope
+                ),
+                [term_id],
                 |row| row.get(0),
             )
             .unwrap_or(0.0);
 
-        // Total revenue
+        // Total revenue, including payments billed to additional enrollments; within a term,
+        // the payments dated in it
         let total_revenue: i64 = db
             .connection()
             .query_row(
-                "SELECT SUM(CAST(paid_amount AS INTEGER)) FROM students",
-                [],
+                "SELECT CASE WHEN ?1 IS NULL THEN
+                     (SELECT IFNULL(SUM(CAST(paid_amount AS INTEGER)), 0) FROM students)
+                     + (SELECT IFNULL(SUM(paid_amount), 0) FROM enrollments WHERE is_primary = 0)
+                 ELSE
+                     (SELECT IFNULL(SUM(amount), 0) FROM payment_transactions WHERE term_id = ?1)
+                 END",
+                [term_id],
                 |row| row.get(0),
             )
             .unwrap_or(0);
@@ -1297,7 +1469,7 @@ mod tests {
         };
         StudentService::create_student(&db, request2).unwrap();
 
-        let stats = StudentService::get_student_statistics(&db).unwrap();
+        let stats = StudentService::get_student_statistics(&db, None).unwrap();
 
         assert_eq!(stats.total_students, 2);
         assert_eq!(stats.students_by_group.get("Group A"), Some(&1));
@@ -0,0 +1,1000 @@
+use crate::audit_service::AuditService;
+use crate::database::{Database, DatabaseError, DatabaseResult};
+use crate::enrollment_service::{
+    Enrollment, EnrollmentService, EnrollmentStatus, UpdateEnrollmentRequest,
+};
+use crate::groups_service::GroupsService;
+use crate::installment_service::InstallmentService;
+use crate::membership_service::MembershipService;
+use crate::payment_service::PaymentService;
+use crate::student_service::{PaymentPlan, Student, StudentService, UpdateStudentRequest};
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An academic term; `end_date` is the last day, inclusive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Term {
+    pub id: i32,
+    pub name: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub is_active: bool,
+    pub closed_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTermRequest {
+    pub name: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateTermRequest {
+    pub name: String,
+    pub start_date: String,
+    pub end_date: String,
+}
+
+/// Balance carried from one term into the next; positive is owed, negative is credit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TermBalance {
+    pub id: i32,
+    pub student_id: String,
+    pub enrollment_id: Option<i32>,
+    pub from_term_id: i32,
+    pub to_term_id: i32,
+    pub amount: i64,
+    pub created_at: String,
+}
+
+/// The balance carried into an enrollment at its latest rollover
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarriedBalance {
+    /// Owed from earlier terms when positive, credit when negative
+    pub amount: i64,
+    /// What had been paid at the rollover; those payments went to earlier terms
+    pub paid_before: i64,
+    /// Start date of the term it was carried into
+    pub carried_on: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloverRequest {
+    pub from_term_id: i32,
+    pub to_term_id: i32,
+    /// Next-term group for each current group name; unmapped groups carry over unchanged
+    #[serde(default)]
+    pub group_mapping: HashMap<String, String>,
+    /// Students continuing into the next term; `None` continues everyone in the closing term
+    pub continuing_student_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RolloverSummary {
+    pub closed_term: Term,
+    pub active_term: Term,
+    pub continuing_students: i32,
+    pub ended_students: i32,
+    pub enrollments_rolled: i32,
+    pub balances: Vec<TermBalance>,
+}
+
+const TERM_COLUMNS: &str =
+    "id, name, start_date, end_date, is_active, closed_at, created_at, updated_at";
+
+/// Students taking part in term `?1`: group memberships overlapping its dates, plus enrollments
+/// assigned to it. Yields nothing when `?1` is NULL.
+pub(crate) const TERM_STUDENTS_SQL: &str =
+    "SELECT m.student_id FROM group_memberships m JOIN terms t ON t.id = ?1
+     WHERE m.start_date <= t.end_date AND (m.end_date IS NULL OR m.end_date >= t.start_date)
+     UNION
+     SELECT student_id FROM enrollments WHERE term_id = ?1 AND status IN ('active', 'completed')";
+
+/// Same scope as [`TERM_STUDENTS_SQL`] with the group each student was in during the term
+pub(crate) const TERM_GROUP_MEMBERS_SQL: &str =
+    "SELECT m.student_id, COALESCE(g.name, m.group_name) AS group_name, m.group_id
+     FROM group_memberships m JOIN terms t ON t.id = ?1
+     LEFT JOIN groups g ON g.id = m.group_id
+     WHERE m.start_date <= t.end_date AND (m.end_date IS NULL OR m.end_date >= t.start_date)
+     UNION
+     SELECT student_id, group_name, group_id FROM enrollments_view
+     WHERE term_id = ?1 AND status IN ('active', 'completed')";
+
+pub struct TermService;
+
+impl TermService {
+    /// Helper function to map database row to Term struct
+    fn map_row_to_term(row: &rusqlite::Row) -> rusqlite::Result<Term> {
+        Ok(Term {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            start_date: row.get(2)?,
+            end_date: row.get(3)?,
+            is_active: row.get(4)?,
+            closed_at: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }
+
+    /// Validate term name and dates
+    fn validate_term_data(name: &str, start_date: &str, end_date: &str) -> DatabaseResult<()> {
+        if name.trim().is_empty() {
+            return Err(DatabaseError::validation(
+                "name",
+                "Term name cannot be empty",
+            ));
+        }
+
+        if name.trim().len() > 100 {
+            return Err(DatabaseError::validation(
+                "name",
+                "Term name cannot exceed 100 characters",
+            ));
+        }
+
+        if NaiveDate::parse_from_str(start_date, "%Y-%m-%d").is_err() {
+            return Err(DatabaseError::validation(
+                "start_date",
+                "Invalid start date format. Use YYYY-MM-DD",
+            ));
+        }
+
+        if NaiveDate::parse_from_str(end_date, "%Y-%m-%d").is_err() {
+            return Err(DatabaseError::validation(
+                "end_date",
+                "Invalid end date format. Use YYYY-MM-DD",
+            ));
+        }
+
+        if end_date < start_date {
+            return Err(DatabaseError::validation(
+                "end_date",
+                "End date cannot be before start date",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reject dates that overlap another term, so every date belongs to at most one term
+    fn ensure_no_overlap(
+        db: &Database,
+        start_date: &str,
+        end_date: &str,
+        exclude_id: Option<i32>,
+    ) -> DatabaseResult<()> {
+        let overlapping = db.connection().query_row(
+            "SELECT name FROM terms
+             WHERE start_date <= ?2 AND end_date >= ?1 AND id != IFNULL(?3, -1)
+             ORDER BY start_date LIMIT 1",
+            params![start_date, end_date, exclude_id],
+            |row| row.get::<_, String>(0),
+        );
+
+        match overlapping {
+            Ok(name) => Err(DatabaseError::conflict(
+                "term_overlap",
+                format!("Term dates overlap with term '{}'", name),
+            )),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(()),
+            Err(e) => Err(DatabaseError::Sqlite(e)),
+        }
+    }
+
+    /// Attach unassigned enrollments, attendance and payments dated within the term
+    fn assign_term_rows(db: &Database, term: &Term) -> DatabaseResult<()> {
+        let args = params![term.id, term.start_date, term.end_date];
+
+        db.connection().execute(
+            "UPDATE enrollments SET term_id = ?1 WHERE term_id IS NULL AND start_date BETWEEN ?2 AND ?3",
+            args,
+        )?;
+        db.connection().execute(
+            "UPDATE attendance SET term_id = ?1 WHERE term_id IS NULL AND date BETWEEN ?2 AND ?3",
+            args,
+        )?;
+        db.connection().execute(
+            "UPDATE payment_transactions SET term_id = ?1 WHERE term_id IS NULL AND payment_date BETWEEN ?2 AND ?3",
+            args,
+        )?;
+
+        Ok(())
+    }
+
+    /// Get all terms, oldest first
+    pub fn get_all_terms(db: &Database) -> DatabaseResult<Vec<Term>> {
+        let mut stmt = db.connection().prepare(&format!(
+            "SELECT {} FROM terms ORDER BY start_date",
+            TERM_COLUMNS
+        ))?;
+
+        let term_iter = stmt.query_map([], Self::map_row_to_term)?;
+
+        term_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// Get a term by ID
+    pub fn get_term_by_id(db: &Database, id: i32) -> DatabaseResult<Option<Term>> {
+        let result = db.connection().query_row(
+            &format!("SELECT {} FROM terms WHERE id = ?1", TERM_COLUMNS),
+            [id],
+            Self::map_row_to_term,
+        );
+
+        match result {
+            Ok(term) => Ok(Some(term)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(DatabaseError::Sqlite(e)),
+        }
+    }
+
+    /// Get the term currently marked active, if any
+    pub fn get_active_term(db: &Database) -> DatabaseResult<Option<Term>> {
+        let result = db.connection().query_row(
+            &format!("SELECT {} FROM terms WHERE is_active = 1", TERM_COLUMNS),
+            [],
+            Self::map_row_to_term,
+        );
+
+        match result {
+            Ok(term) => Ok(Some(term)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(DatabaseError::Sqlite(e)),
+        }
+    }
+
+    fn require_term(db: &Database, id: i32) -> DatabaseResult<Term> {
+        Self::get_term_by_id(db, id)?.ok_or_else(|| DatabaseError::not_found("Term", id))
+    }
+
+    /// Create a term and attach existing records dated within it
+    pub fn create_term(db: &Database, request: CreateTermRequest) -> DatabaseResult<Term> {
+        AuditService::audited(db, |audit| {
+            Self::validate_term_data(&request.name, &request.start_date, &request.end_date)?;
+            Self::ensure_no_overlap(db, &request.start_date, &request.end_date, None)?;
+
+            let now = Utc::now().to_rfc3339();
+            db.connection().execute(
+                "INSERT INTO terms (name, start_date, end_date, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![request.name.trim(), request.start_date, request.end_date, now, now],
+            )?;
+
+            let term = Self::require_term(db, db.connection().last_insert_rowid() as i32)?;
+            Self::assign_term_rows(db, &term)?;
+
+            audit.create("terms", &term.id.to_string(), &term, None);
+
+            Ok(term)
+        })
+    }
+
+    /// Rename a term or move its dates; records are re-attached to match the new dates
+    pub fn update_term(db: &Database, id: i32, request: UpdateTermRequest) -> DatabaseResult<Term> {
+        AuditService::audited(db, |audit| {
+            Self::validate_term_data(&request.name, &request.start_date, &request.end_date)?;
+
+            let current = Self::require_term(db, id)?;
+            let dates_changed =
+                current.start_date != request.start_date || current.end_date != request.end_date;
+
+            if dates_changed {
+                if current.closed_at.is_some() {
+                    return Err(DatabaseError::forbidden(
+                        "term_closed",
+                        format!(
+                            "Term '{}' is closed; its dates can no longer change",
+                            current.name
+                        ),
+                    ));
+                }
+                Self::ensure_no_overlap(db, &request.start_date, &request.end_date, Some(id))?;
+            }
+
+            db.connection().execute(
+                "UPDATE terms SET name = ?1, start_date = ?2, end_date = ?3, updated_at = ?4 WHERE id = ?5",
+                params![
+                    request.name.trim(),
+                    request.start_date,
+                    request.end_date,
+                    Utc::now().to_rfc3339(),
+                    id
+                ],
+            )?;
+
+            let term = Self::require_term(db, id)?;
+
+            if dates_changed {
+                for table in ["enrollments", "attendance", "payment_transactions"] {
+                    db.connection().execute(
+                        &format!("UPDATE {} SET term_id = NULL WHERE term_id = ?1", table),
+                        [id],
+                    )?;
+                }
+                Self::assign_term_rows(db, &term)?;
+            }
+
+            audit.update("terms", &id.to_string(), &current, &term, None);
+
+            Ok(term)
+        })
+    }
+
+    /// Delete a term that has not been rolled over; its records become unassigned
+    pub fn delete_term(db: &Database, id: i32) -> DatabaseResult<bool> {
+        AuditService::audited(db, |audit| {
+            let term = match Self::get_term_by_id(db, id)? {
+                Some(term) => term,
+                None => return Ok(false),
+            };
+
+            let carried: i32 = db.connection().query_row(
+                "SELECT COUNT(*) FROM term_balances WHERE from_term_id = ?1 OR to_term_id = ?1",
+                [id],
+                |row| row.get(0),
+            )?;
+
+            if term.closed_at.is_some() || carried > 0 {
+                return Err(DatabaseError::forbidden(
+                    "term_rolled_over",
+                    format!(
+                        "Term '{}' is part of a rollover and cannot be deleted",
+                        term.name
+                    ),
+                ));
+            }
+
+            db.connection()
+                .execute("DELETE FROM terms WHERE id = ?1", [id])?;
+
+            audit.delete("terms", &id.to_string(), &term, None);
+
+            Ok(true)
+        })
+    }
+
+    /// Make a term the active one; any previously active term is deactivated
+    pub fn set_active_term(db: &Database, id: i32) -> DatabaseResult<Term> {
+        let term = Self::require_term(db, id)?;
+
+        if term.closed_at.is_some() {
+            return Err(DatabaseError::forbidden(
+                "term_closed",
+                format!("Term '{}' is closed and cannot be made active", term.name),
+            ));
+        }
+
+        db.connection().execute(
+            "UPDATE terms SET is_active = (id = ?1), updated_at = CASE WHEN is_active != (id = ?1) THEN ?2 ELSE updated_at END",
+            params![id, Utc::now().to_rfc3339()],
+        )?;
+
+        Self::require_term(db, id)
+    }
+
+    /// Narrow an optional date range to a term's dates. Without a term the range is returned as is.
+    pub(crate) fn clamp_to_term(
+        db: &Database,
+        term_id: Option<i32>,
+        start_date: Option<&str>,
+        end_date: Option<&str>,
+    ) -> DatabaseResult<(Option<String>, Option<String>)> {
+        let term = match term_id {
+            Some(id) => Self::require_term(db, id)?,
+            None => return Ok((start_date.map(str::to_string), end_date.map(str::to_string))),
+        };
+
+        let start = match start_date {
+            Some(start) if start > term.start_date.as_str() => start.to_string(),
+            _ => term.start_date.clone(),
+        };
+        let end = match end_date {
+            Some(end) if end < term.end_date.as_str() => end.to_string(),
+            _ => term.end_date.clone(),
+        };
+
+        Ok((Some(start), Some(end)))
+    }
+
+    /// Get balances carried over for a student, oldest first
+    pub fn get_student_term_balances(
+        db: &Database,
+        student_id: &str,
+    ) -> DatabaseResult<Vec<TermBalance>> {
+        let mut stmt = db.connection().prepare(
+            "SELECT b.id, b.student_id, b.enrollment_id, b.from_term_id, b.to_term_id, b.amount, b.created_at
+             FROM term_balances b JOIN terms t ON t.id = b.to_term_id
+             WHERE b.student_id = ?1 AND b.amount <> 0 ORDER BY t.start_date, b.id",
+        )?;
+
+        let balance_iter = stmt.query_map([student_id], |row| {
+            Ok(TermBalance {
+                id: row.get(0)?,
+                student_id: row.get(1)?,
+                enrollment_id: row.get(2)?,
+                from_term_id: row.get(3)?,
+                to_term_id: row.get(4)?,
+                amount: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?;
+
+        balance_iter
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DatabaseError::from)
+    }
+
+    /// The balance carried into `enrollment_id`, or the student's primary enrollment, at its
+    /// latest rollover
+    pub fn get_carried_balance(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: Option<i32>,
+    ) -> DatabaseResult<Option<CarriedBalance>> {
+        db.connection()
+            .query_row(
+                "SELECT b.amount, b.paid_before, t.start_date FROM term_balances b
+                 JOIN terms t ON t.id = b.to_term_id
+                 WHERE b.student_id = ?1
+                   AND b.enrollment_id = COALESCE(?2, (SELECT id FROM enrollments WHERE student_id = ?1 AND is_primary = 1))
+                 ORDER BY t.start_date DESC, b.id DESC
+                 LIMIT 1",
+                params![student_id, enrollment_id],
+                |row| {
+                    Ok(CarriedBalance {
+                        amount: row.get(0)?,
+                        paid_before: row.get(1)?,
+                        carried_on: row.get(2)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(DatabaseError::from)
+    }
+
+    /// What a paid amount leaves for the current term: payments made before the latest rollover
+    /// went to earlier terms, and a balance carried from them is owed first
+    pub fn paid_toward_term(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: Option<i32>,
+        paid_amount: i64,
+    ) -> DatabaseResult<i64> {
+        let carried = Self::get_carried_balance(db, student_id, enrollment_id)?;
+        Ok(carried.map_or(paid_amount, |carried| {
+            paid_amount - carried.paid_before - carried.amount
+        }))
+    }
+
+    /// Outstanding balance of a primary enrollment at the end of a term
+    fn student_balance_at(
+        db: &Database,
+        student: &Student,
+        until: NaiveDate,
+    ) -> DatabaseResult<i64> {
+        let schedule_total = if student.payment_plan == PaymentPlan::Installment {
+            InstallmentService::get_schedule_total(db, &student.id)?
+        } else {
+            None
+        };
+
+        let expected = match schedule_total {
+            Some(total) => total,
+            None => PaymentService::calculate_expected_amount_until(
+                &student.payment_plan,
+                student.plan_amount,
+                &student.enrollment_date,
+                student.installment_count,
+                until,
+            )?,
+        };
+
+        let paid = Self::paid_toward_term(db, &student.id, None, student.paid_amount as i64)?;
+        Ok(expected - paid)
+    }
+
+    /// Outstanding balance of a secondary enrollment at the end of a term
+    fn enrollment_balance_at(
+        db: &Database,
+        enrollment: &Enrollment,
+        until: NaiveDate,
+    ) -> DatabaseResult<i64> {
+        let expected = PaymentService::calculate_expected_amount_until(
+            &enrollment.payment_plan,
+            enrollment.plan_amount,
+            &enrollment.start_date,
+            enrollment.installment_count,
+            until,
+        )?;
+
+        let paid = Self::paid_toward_term(
+            db,
+            &enrollment.student_id,
+            Some(enrollment.id),
+            enrollment.paid_amount as i64,
+        )?;
+        Ok(expected - paid)
+    }
+
+    /// Record what an enrollment carries into the next term. A row is kept even for a zero
+    /// balance, since it also marks which payments belong to earlier terms.
+    fn record_balance(
+        db: &Database,
+        student_id: &str,
+        enrollment_id: i32,
+        from: &Term,
+        to: &Term,
+        amount: i64,
+        paid_before: i32,
+    ) -> DatabaseResult<()> {
+        db.connection().execute(
+            "INSERT INTO term_balances (student_id, enrollment_id, from_term_id, to_term_id, amount, paid_before) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![student_id, enrollment_id, from.id, to.id, amount, paid_before],
+        )?;
+        Ok(())
+    }
+
+    /// Close a term and open the next one. Continuing students move to their next-term groups
+    /// (per `group_mapping`) and start paying from the new term's start date, with whatever they
+    /// still owed or had overpaid carried over as a term balance; their paid amounts are left as
+    /// they are. Everyone else has their enrollments completed at the end of the closing term.
+    pub fn rollover_term(
+        db: &Database,
+        request: RolloverRequest,
+    ) -> DatabaseResult<RolloverSummary> {
+        AuditService::audited(db, |audit| {
+            let from = Self::require_term(db, request.from_term_id)?;
+            let to = Self::require_term(db, request.to_term_id)?;
+
+            if from.closed_at.is_some() {
+                return Err(DatabaseError::forbidden(
+                    "term_closed",
+                    format!("Term '{}' has already been closed", from.name),
+                ));
+            }
+            if to.closed_at.is_some() || to.start_date <= from.end_date {
+                return Err(DatabaseError::validation(
+                    "to_term_id",
+                    format!(
+                        "Term '{}' must be an open term starting after '{}' ends",
+                        to.name, from.name
+                    ),
+                ));
+            }
+
+            let from_end = NaiveDate::parse_from_str(&from.end_date, "%Y-%m-%d")
+                .map_err(|_| DatabaseError::validation("end_date", "Invalid term end date"))?;
+
+            let candidates: Vec<String> = {
+                let mut stmt = db.connection().prepare(&format!(
+                    "SELECT id FROM students WHERE deleted_at IS NULL AND id IN ({}) ORDER BY id",
+                    TERM_STUDENTS_SQL
+                ))?;
+                let id_iter = stmt.query_map([from.id], |row| row.get::<_, String>(0))?;
+                id_iter.collect::<Result<Vec<_>, _>>()?
+            };
+
+            let continuing: Vec<String> = match &request.continuing_student_ids {
+                Some(ids) => {
+                    if let Some(unknown) = ids.iter().find(|id| !candidates.contains(id)) {
+                        return Err(DatabaseError::validation(
+                            "student_ids",
+                            format!("Student '{}' is not part of term '{}'", unknown, from.name),
+                        ));
+                    }
+                    ids.clone()
+                }
+                None => candidates.clone(),
+            };
+
+            // Resolve every target group before anything moves
+            let mut target_groups = HashMap::new();
+            for (from_group, to_group) in &request.group_mapping {
+                target_groups.insert(
+                    from_group.trim().to_lowercase(),
+                    GroupsService::resolve_group(db, to_group)?,
+                );
+            }
+            let target_group = |name: &str| -> DatabaseResult<crate::groups_service::Group> {
+                match target_groups.get(&name.trim().to_lowercase()) {
+                    Some(group) => Ok(group.clone()),
+                    None => GroupsService::resolve_group(db, name),
+                }
+            };
+
+            let mut continuing_students = 0;
+            let mut ended_students = 0;
+            let mut enrollments_rolled = 0;
+
+            for student_id in &candidates {
+                let student = StudentService::get_student_by_id(db, student_id)?
+                    .ok_or_else(|| DatabaseError::not_found("Student", student_id))?;
+
+                let secondary: Vec<Enrollment> =
+                    EnrollmentService::get_student_enrollments(db, student_id)?
+                        .into_iter()
+                        .filter(|e| {
+                            !e.is_primary
+                                && e.status == EnrollmentStatus::Active
+                                && e.start_date <= from.end_date
+                        })
+                        .collect();
+
+                let primary_id = EnrollmentService::get_primary_enrollment_id(db, student_id)?;
+
+                if !continuing.contains(student_id) {
+                    for enrollment in secondary {
+                        EnrollmentService::end_enrollment(
+                            db,
+                            enrollment.id,
+                            &from.end_date,
+                            EnrollmentStatus::Completed,
+                        )?;
+                    }
+
+                    db.connection().execute(
+                        "UPDATE enrollments SET status = 'completed', end_date = ?1, updated_at = ?2 WHERE id = ?3",
+                        params![from.end_date, Utc::now().to_rfc3339(), primary_id],
+                    )?;
+                    db.connection().execute(
+                        "UPDATE group_memberships SET end_date = ?1 WHERE student_id = ?2 AND end_date IS NULL",
+                        params![from.end_date, student_id],
+                    )?;
+
+                    ended_students += 1;
+                    continue;
+                }
+
+                // Primary enrollment: follows the student record into the new term
+                let balance = Self::student_balance_at(db, &student, from_end)?;
+                let group = target_group(&student.group_name)?;
+
+                if student.group_id != Some(group.id) {
+                    GroupsService::ensure_seat_available(db, &group)?;
+                }
+                MembershipService::record_group_change(
+                    db,
+                    student_id,
+                    &group,
+                    &to.start_date,
+                    Some("Term rollover"),
+                )?;
+                Self::record_balance(
+                    db,
+                    student_id,
+                    primary_id,
+                    &from,
+                    &to,
+                    balance,
+                    student.paid_amount,
+                )?;
+
+                StudentService::update_student(
+                    db,
+                    student_id,
+                    UpdateStudentRequest {
+                        name: student.name.clone(),
+                        group_name: group.name.clone(),
+                        payment_plan: student.payment_plan.clone(),
+                        plan_amount: student.plan_amount,
+                        installment_count: student.installment_count,
+                        paid_amount: None,
+                        enrollment_date: Some(to.start_date.clone()),
+                    },
+                )?;
+                PaymentService::update_student_payment_status(db, student_id)?;
+
+                // Additional enrollments roll over in place so they keep their seat
+                for enrollment in secondary {
+                    let balance = Self::enrollment_balance_at(db, &enrollment, from_end)?;
+                    let group = target_group(&enrollment.group_name)?;
+                    Self::record_balance(
+                        db,
+                        student_id,
+                        enrollment.id,
+                        &from,
+                        &to,
+                        balance,
+                        enrollment.paid_amount,
+                    )?;
+
+                    EnrollmentService::update_enrollment(
+                        db,
+                        enrollment.id,
+                        UpdateEnrollmentRequest {
+                            group_name: group.name.clone(),
+                            course: enrollment.course.clone(),
+                            start_date: to.start_date.clone(),
+                            end_date: None,
+                            payment_plan: enrollment.payment_plan.clone(),
+                            plan_amount: enrollment.plan_amount,
+                            installment_count: enrollment.installment_count,
+                            status: EnrollmentStatus::Active,
+                        },
+                    )?;
+                    EnrollmentService::update_enrollment_payment_status(db, enrollment.id)?;
+
+                    enrollments_rolled += 1;
+                }
+
+                continuing_students += 1;
+            }
+
+            db.connection().execute(
+                "UPDATE terms SET is_active = 0, closed_at = ?1, updated_at = ?1 WHERE id = ?2",
+                params![Utc::now().to_rfc3339(), from.id],
+            )?;
+            let active_term = Self::set_active_term(db, to.id)?;
+            let closed_term = Self::require_term(db, from.id)?;
+
+            let balances: Vec<TermBalance> = {
+                let mut stmt = db.connection().prepare(
+                    "SELECT id, student_id, enrollment_id, from_term_id, to_term_id, amount, created_at
+                     FROM term_balances WHERE from_term_id = ?1 AND amount <> 0 ORDER BY student_id, id",
+                )?;
+                let balance_iter = stmt.query_map([from.id], |row| {
+                    Ok(TermBalance {
+                        id: row.get(0)?,
+                        student_id: row.get(1)?,
+                        enrollment_id: row.get(2)?,
+                        from_term_id: row.get(3)?,
+                        to_term_id: row.get(4)?,
+                        amount: row.get(5)?,
+                        created_at: row.get(6)?,
+                    })
+                })?;
+                balance_iter.collect::<Result<Vec<_>, _>>()?
+            };
+
+            let summary = RolloverSummary {
+                closed_term,
+                active_term,
+                continuing_students,
+                ended_students,
+                enrollments_rolled,
+                balances,
+            };
+
+            let new_values = serde_json::json!({
+                "to_term_id": to.id,
+                "continuing_students": continuing_students,
+                "ended_students": ended_students,
+                "enrollments_rolled": enrollments_rolled,
+                "carried_total": summary.balances.iter().map(|b| b.amount).sum::<i64>()
+            });
+            audit.record(
+                "ROLLOVER",
+                "terms",
+                &from.id.to_string(),
+                None,
+                Some(new_values.to_string()),
+                None,
+            );
+
+            Ok(summary)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attendance_service::AttendanceService;
+    use crate::integrity_service::IntegrityService;
+    use crate::payment_service::{PaymentMethod, RecordPaymentRequest};
+    use crate::student_service::CreateStudentRequest;
+    use tempfile::TempDir;
+
+    fn create_test_db() -> (Database, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_path_buf()).unwrap();
+        (db, temp_dir)
+    }
+
+    fn term(name: &str, start_date: &str, end_date: &str) -> CreateTermRequest {
+        CreateTermRequest {
+            name: name.to_string(),
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+        }
+    }
+
+    fn create_student(db: &Database, name: &str, group: &str) -> Student {
+        StudentService::create_student(
+            db,
+            CreateStudentRequest {
+                name: name.to_string(),
+                group_name: group.to_string(),
+                payment_plan: PaymentPlan::OneTime,
+                plan_amount: 6000,
+                installment_count: None,
+                paid_amount: Some(0),
+                enrollment_date: Some("2024-01-10".to_string()),
+            },
+        )
+        .unwrap()
+    }
+
+    fn pay(db: &Database, student_id: &str, amount: i32, date: &str) {
+        PaymentService::record_payment(
+            db,
+            RecordPaymentRequest {
+                student_id: student_id.to_string(),
+                enrollment_id: None,
+                amount,
+                payment_date: date.to_string(),
+                payment_method: PaymentMethod::Cash,
+                notes: None,
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_terms_assign_records_and_reject_overlap() {
+        let (db, _temp_dir) = create_test_db();
+        let student = create_student(&db, "Early Student", "Group A");
+        AttendanceService::mark_attendance(&db, &student.id, None, "2024-02-01").unwrap();
+        pay(&db, &student.id, 1000, "2024-02-01");
+
+        // Records that predate the term are attached when it is created
+        let spring =
+            TermService::create_term(&db, term("Spring 2024", "2024-01-01", "2024-06-30")).unwrap();
+        let assigned: i32 = db
+            .connection()
+            .query_row(
+                "SELECT (SELECT COUNT(*) FROM attendance WHERE term_id = ?1)
+                      + (SELECT COUNT(*) FROM payment_transactions WHERE term_id = ?1)
+                      + (SELECT COUNT(*) FROM enrollments WHERE term_id = ?1)",
+                [spring.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(assigned, 3);
+
+        // New records are attached on insert
+        pay(&db, &student.id, 500, "2024-03-01");
+        let stats =
+            PaymentService::get_payment_statistics(&db, None, None, Some(spring.id)).unwrap();
+        assert_eq!(stats.total_amount, 1500);
+        let summary = PaymentService::get_payment_summary(&db, Some(spring.id)).unwrap();
+        assert_eq!(
+            (summary.total_students, summary.total_paid_amount),
+            (1, 1500)
+        );
+        assert_eq!(summary.recent_payments.len(), 2);
+
+        assert!(
+            TermService::create_term(&db, term("Overlap", "2024-06-01", "2024-08-31")).is_err()
+        );
+        assert!(
+            TermService::create_term(&db, term("Backwards", "2024-09-30", "2024-09-01")).is_err()
+        );
+
+        let fall =
+            TermService::create_term(&db, term("Fall 2024", "2024-09-01", "2024-12-20")).unwrap();
+        let summary = PaymentService::get_payment_summary(&db, Some(fall.id)).unwrap();
+        assert_eq!(
+            (summary.total_paid_amount, summary.recent_payments.len()),
+            (0, 0)
+        );
+        TermService::set_active_term(&db, spring.id).unwrap();
+        TermService::set_active_term(&db, fall.id).unwrap();
+        let active = TermService::get_active_term(&db).unwrap().unwrap();
+        assert_eq!(active.id, fall.id);
+        assert!(
+            !TermService::get_term_by_id(&db, spring.id)
+                .unwrap()
+                .unwrap()
+                .is_active
+        );
+    }
+
+    #[test]
+    fn test_rollover_carries_balances_and_moves_groups() {
+        let (db, _temp_dir) = create_test_db();
+        let spring =
+            TermService::create_term(&db, term("Spring 2024", "2024-01-01", "2024-06-30")).unwrap();
+        let fall =
+            TermService::create_term(&db, term("Fall 2024", "2024-09-01", "2024-12-20")).unwrap();
+
+        let owing = create_student(&db, "Owing Student", "Level 1");
+        pay(&db, &owing.id, 4000, "2024-02-01");
+        let overpaid = create_student(&db, "Overpaid Student", "Level 1");
+        pay(&db, &overpaid.id, 6500, "2024-02-01");
+        let leaving = create_student(&db, "Leaving Student", "Level 1");
+
+        let mut group_mapping = HashMap::new();
+        group_mapping.insert("Level 1".to_string(), "Level 2".to_string());
+
+        let summary = TermService::rollover_term(
+            &db,
+            RolloverRequest {
+                from_term_id: spring.id,
+                to_term_id: fall.id,
+                group_mapping,
+                continuing_student_ids: Some(vec![owing.id.clone(), overpaid.id.clone()]),
+            },
+        )
+        .unwrap();
+
+        assert_eq!(summary.continuing_students, 2);
+        assert_eq!(summary.ended_students, 1);
+        assert!(summary.closed_term.closed_at.is_some());
+        assert!(summary.active_term.is_active);
+
+        let owing = StudentService::get_student_by_id(&db, &owing.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(owing.group_name, "Level 2");
+        assert_eq!(owing.enrollment_date, "2024-09-01");
+        // Payments stay with the student; what went to spring no longer counts toward fall
+        assert_eq!(owing.paid_amount, 4000);
+        assert_eq!(
+            TermService::paid_toward_term(&db, &owing.id, None, owing.paid_amount as i64).unwrap(),
+            -2000
+        );
+
+        let balances = TermService::get_student_term_balances(&db, &owing.id).unwrap();
+        assert_eq!(balances.len(), 1);
+        assert_eq!(balances[0].amount, 2000);
+
+        let overpaid = StudentService::get_student_by_id(&db, &overpaid.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(overpaid.paid_amount, 6500);
+        assert_eq!(
+            TermService::paid_toward_term(&db, &overpaid.id, None, overpaid.paid_amount as i64)
+                .unwrap(),
+            500
+        );
+        assert_eq!(summary.balances.len(), 2);
+
+        // Paid amounts still add up to their transactions
+        let report = IntegrityService::run_integrity_check(&db, false).unwrap();
+        assert!(report
+            .issues
+            .iter()
+            .all(|issue| issue.check != "paid_amount"));
+
+        // Spring stays attributed to the old group
+        let history = MembershipService::get_student_group_history(&db, &owing.id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].group_name, "Level 1");
+        assert_eq!(history[0].end_date.as_deref(), Some("2024-08-31"));
+
+        let leaving = StudentService::get_student_by_id(&db, &leaving.id)
+            .unwrap()
+            .unwrap();
+        assert_eq!(leaving.group_name, "Level 1");
+        let leaving_enrollments =
+            EnrollmentService::get_student_enrollments(&db, &leaving.id).unwrap();
+        assert_eq!(leaving_enrollments[0].status, EnrollmentStatus::Completed);
+
+        let spring_stats = StudentService::get_student_statistics(&db, Some(spring.id)).unwrap();
+        assert_eq!(spring_stats.total_students, 3);
+        assert_eq!(spring_stats.students_by_group.get("Level 1"), Some(&3));
+        let fall_stats = StudentService::get_student_statistics(&db, Some(fall.id)).unwrap();
+        assert_eq!(fall_stats.total_students, 2);
+        assert_eq!(fall_stats.students_by_group.get("Level 2"), Some(&2));
+
+        // A closed term cannot be rolled over twice
+        assert!(TermService::rollover_term(
+            &db,
+            RolloverRequest {
+                from_term_id: spring.id,
+                to_term_id: fall.id,
+                group_mapping: HashMap::new(),
+                continuing_student_ids: None,
+            },
+        )
+        .is_err());
+    }
+}
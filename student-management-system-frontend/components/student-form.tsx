@@ -22,6 +22,7 @@ import {
 import { useGroups } from "@/hooks/useGroups";
 import { usePaymentSettings } from "@/hooks/usePaymentSettings";
 import { validateStudentData } from "@/lib/data-transform";
+import { ApiError } from "@/lib/api";
 import QRCodeDisplay from "@/components/qr-code-display";
 import { useAccessibility } from "@/components/accessibility-provider";
 import { ariaLabels, FocusManager } from "@/lib/accessibility";
@@ -47,6 +48,16 @@ const SELECT_STATES = {
   EMPTY: "__empty__",
 } as const;
 
+// Backend field names (AppError.field) mapped to the form fields they belong to
+const FORM_FIELDS: Record<string, string | undefined> = {
+  name: "name",
+  group_name: "group",
+  plan_amount: "planAmount",
+  paid_amount: "paidAmount",
+  installment_count: "installmentCount",
+  enrollment_date: "enrollmentDate",
+};
+
 // Memoized payment summary component with optimized calculations
 const PaymentSummary = React.memo(
   ({
@@ -181,16 +192,9 @@ export function StudentForm({ student, onSubmit, onClose }: StudentFormProps) {
 
     // Map validation errors to form fields
     validationErrors.forEach((error) => {
-      if (error.includes("اسم الطالب")) {
-        newErrors.name = error;
-      } else if (error.includes("المجموعة")) {
-        newErrors.group = error;
-      } else if (error.includes("مبلغ الخطة")) {
-        newErrors.planAmount = error;
-      } else if (error.includes("المبلغ المدفوع")) {
-        newErrors.paidAmount = error;
-      } else if (error.includes("عدد الأقساط")) {
-        newErrors.installmentCount = error;
+      const formField = FORM_FIELDS[error.field];
+      if (formField) {
+        newErrors[formField] = error.message;
       }
     });
 
@@ -267,14 +271,20 @@ export function StudentForm({ student, onSubmit, onClose }: StudentFormProps) {
       }
     } catch (error) {
       console.error("Error in form submission:", error);
+      const formField =
+        error instanceof ApiError && error.field
+          ? FORM_FIELDS[error.field]
+          : undefined;
       const errorMessage =
-        error instanceof Error
+        error instanceof ApiError && error.localizedMessage
+          ? error.localizedMessage
+          : error instanceof Error
           ? `حدث خطأ: ${error.message}`
           : "حدث خطأ غير متوقع أثناء حفظ البيانات";
 
       setFormState((prev) => ({
         ...prev,
-        errors: { ...prev.errors, submit: errorMessage },
+        errors: { ...prev.errors, [formField ?? "submit"]: errorMessage },
       }));
     } finally {
       setFormState((prev) => ({ ...prev, isSubmitting: false }));
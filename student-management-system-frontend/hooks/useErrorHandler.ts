@@ -1,4 +1,5 @@
 import { useState, useCallback } from "react";
+import { ApiError } from "@/lib/api";
 
 interface ErrorState {
   message: string;
@@ -28,18 +29,27 @@ export function useErrorHandler() {
     (err: unknown, context: string) => {
       console.error(`${context}:`, err);
 
-      let errorMessage = `حدث خطأ في ${context}`;
+      if (err instanceof ApiError && err.code) {
+        const message = err.localizedMessage ?? err.message;
+        switch (err.code) {
+          case "VALIDATION":
+          case "CONFLICT":
+            // The user can correct these, so they are not shown as failures
+            showError(message, "warning");
+            return;
+          case "NOT_FOUND":
+          case "FORBIDDEN":
+            showError(message);
+            return;
+          case "STORAGE":
+            showError(`${context}: ${message}`);
+            return;
+        }
+      }
 
+      let errorMessage = `حدث خطأ في ${context}`;
       if (err instanceof Error) {
-        if (err.message.includes("Group name is required")) {
-          errorMessage = "المجموعة مطلوبة";
-        } else if (err.message.includes("Student name is required")) {
-          errorMessage = "اسم الطالب مطلوب";
-        } else if (err.message.includes("Plan amount must be positive")) {
-          errorMessage = "مبلغ الخطة يجب أن يكون أكبر من صفر";
-        } else {
-          errorMessage = `${context}: ${err.message}`;
-        }
+        errorMessage = `${context}: ${err.message}`;
       }
 
       showError(errorMessage);
@@ -7,7 +7,10 @@ import type {
   QRCodeData,
   QRCodeBatch,
   QRCodeStatistics,
+  AppError,
+  ErrorCode,
 } from "@/types";
+import { isAppError } from "@/lib/type-guards";
 
 // Note: Backend uses snake_case, frontend types handle both snake_case and camelCase for compatibility
 
@@ -63,13 +66,25 @@ interface DailyAttendanceSummary {
  * Custom error class for API operations
  */
 export class ApiError extends Error {
+  public readonly code?: ErrorCode;
+  public readonly field?: string;
+  public readonly messageKey?: string;
+  public readonly localizedMessage?: string;
+  public readonly params: Record<string, string>;
+
   constructor(
     message: string,
     public readonly operation: string,
-    public readonly originalError?: unknown
+    public readonly originalError?: unknown,
+    appError?: AppError
   ) {
     super(message);
     this.name = "ApiError";
+    this.code = appError?.code;
+    this.field = appError?.field ?? undefined;
+    this.messageKey = appError?.message_key;
+    this.localizedMessage = appError?.localized_message || undefined;
+    this.params = appError?.params ?? {};
   }
 }
 
@@ -218,7 +233,12 @@ export class ApiService {
         }
 
         const errorMessage = this.extractErrorMessage(error, command);
-        throw new ApiError(errorMessage, command, error);
+        throw new ApiError(
+          errorMessage,
+          command,
+          error,
+          isAppError(error) ? error : undefined
+        );
       }
     };
 
@@ -273,6 +293,10 @@ export class ApiService {
       return `${baseMessage}: ${error}`;
     }
 
+    if (isAppError(error)) {
+      return `${baseMessage}: ${error.message}`;
+    }
+
     if (typeof error === "object") {
       const errorObj = error as Record<string, unknown>;
 
@@ -298,6 +322,26 @@ export class ApiService {
     return input.trim().replace(/[<>]/g, "");
   }
 
+  /**
+   * Builds a VALIDATION error shaped like the backend's, so callers can
+   * branch on `code`/`field` whichever side rejected the input
+   */
+  private static validationError(
+    field: string,
+    message: string,
+    localizedMessage: string,
+    operation: string
+  ): ApiError {
+    return new ApiError(message, operation, undefined, {
+      code: "VALIDATION",
+      message_key: `errors.validation.${field}`,
+      message,
+      localized_message: localizedMessage,
+      field,
+      params: {},
+    });
+  }
+
   /**
    * Validates student data for add/update operations
    */
@@ -310,20 +354,37 @@ export class ApiService {
     operation: string
   ): void {
     if (!name?.trim()) {
-      throw new ApiError("Student name is required", operation);
+      throw this.validationError(
+        "name",
+        "Student name is required",
+        "اسم الطالب مطلوب",
+        operation
+      );
     }
     if (!groupName?.trim()) {
-      throw new ApiError("Group name is required", operation);
+      throw this.validationError(
+        "group_name",
+        "Group name is required",
+        "المجموعة مطلوبة",
+        operation
+      );
     }
     if (planAmount <= 0) {
-      throw new ApiError("Plan amount must be positive", operation);
+      throw this.validationError(
+        "plan_amount",
+        "Plan amount must be positive",
+        "مبلغ الخطة يجب أن يكون أكبر من صفر",
+        operation
+      );
     }
     if (
       paymentPlan === "installment" &&
       (!installmentCount || installmentCount <= 0)
     ) {
-      throw new ApiError(
+      throw this.validationError(
+        "installment_count",
         "Installment count is required for installment plans",
+        "عدد الأقساط مطلوب للخطط المقسطة",
         operation
       );
     }
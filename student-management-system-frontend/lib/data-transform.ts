@@ -161,25 +161,39 @@ export function createSettingsForBackend(settingsData: {
 }
 
 // Validation helpers
-export function validateStudentData(student: Partial<Student>): string[] {
-  const errors: string[] = [];
+// A validation failure keyed by the backend field name, as in AppError.field
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
+export function validateStudentData(
+  student: Partial<Student>
+): FieldError[] {
+  const errors: FieldError[] = [];
 
   if (!student.name?.trim()) {
-    errors.push("اسم الطالب مطلوب");
+    errors.push({ field: "name", message: "اسم الطالب مطلوب" });
   }
 
   if (!student.group_name?.trim() && !student.group?.trim()) {
-    errors.push("المجموعة مطلوبة");
+    errors.push({ field: "group_name", message: "المجموعة مطلوبة" });
   }
 
   const planAmount = student.plan_amount || student.planAmount;
   if (!planAmount || planAmount <= 0) {
-    errors.push("مبلغ الخطة يجب أن يكون أكبر من صفر");
+    errors.push({
+      field: "plan_amount",
+      message: "مبلغ الخطة يجب أن يكون أكبر من صفر",
+    });
   }
 
   const paidAmount = student.paid_amount || student.paidAmount;
   if (paidAmount !== undefined && paidAmount < 0) {
-    errors.push("المبلغ المدفوع لا يمكن أن يكون سالباً");
+    errors.push({
+      field: "paid_amount",
+      message: "المبلغ المدفوع لا يمكن أن يكون سالباً",
+    });
   }
 
   const paymentPlan = student.payment_plan || student.paymentPlan;
@@ -189,7 +203,10 @@ export function validateStudentData(student: Partial<Student>): string[] {
     paymentPlan === "installment" &&
     (!installmentCount || installmentCount <= 0)
   ) {
-    errors.push("عدد الأقساط مطلوب للخطط المقسطة");
+    errors.push({
+      field: "installment_count",
+      message: "عدد الأقساط مطلوب للخطط المقسطة",
+    });
   }
 
   return errors;
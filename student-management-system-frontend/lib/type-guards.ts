@@ -1,4 +1,4 @@
-import type { Student, PaymentPlan, PaymentStatus } from "@/types";
+import type { Student, PaymentPlan, PaymentStatus, AppError } from "@/types";
 
 export function isValidPaymentPlan(value: string): value is PaymentPlan {
   return ["one-time", "monthly", "installment"].includes(value);
@@ -18,6 +18,16 @@ export function isStudent(obj: any): obj is Student {
   );
 }
 
+export function isAppError(obj: unknown): obj is AppError {
+  if (!obj || typeof obj !== "object") return false;
+  const error = obj as Record<string, unknown>;
+  return (
+    typeof error.code === "string" &&
+    typeof error.message_key === "string" &&
+    typeof error.message === "string"
+  );
+}
+
 export function validateStudentData(data: Partial<Student>): string[] {
   const errors: string[] = [];
 
@@ -90,6 +90,7 @@ export interface AppSettings {
   payment_threshold: number; // Backend uses snake_case
   default_groups: string[]; // Backend uses snake_case
   enable_audit_log: boolean; // Backend uses snake_case
+  audit_strict_mode?: boolean; // Backend uses snake_case
   language: "en" | "ar";
   theme: "light" | "dark";
   enable_multi_user: boolean; // Backend uses snake_case
@@ -167,3 +168,21 @@ export interface RestoreResult {
   payments_restored: number;
   errors: string[];
 }
+
+// Error types
+export type ErrorCode =
+  | "NOT_FOUND"
+  | "VALIDATION"
+  | "CONFLICT"
+  | "FORBIDDEN"
+  | "STORAGE";
+
+// Structured error returned by every backend command
+export interface AppError {
+  code: ErrorCode;
+  message_key: string;
+  message: string;
+  localized_message: string;
+  field: string | null;
+  params: Record<string, string>;
+}